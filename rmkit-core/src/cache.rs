@@ -0,0 +1,178 @@
+use crate::error::{Classify, FailureKind};
+use crate::interrupt::TempPath;
+use crate::report::{Event, Reporter};
+use crate::version::TemplateSource;
+use futures::stream::StreamExt;
+use reqwest::Client;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+
+/// Cached archives of branches older than this are refreshed in the background
+const BRANCH_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Template archive in the local cache
+pub(crate) struct TemplateArchive {
+    /// Path of the zip archive
+    pub(crate) path: PathBuf,
+    /// Snapshot of the cached archive, which is used while the cached one is refreshed
+    snapshot: Option<TempPath>,
+    /// Background refresh of a stale cached archive
+    refresh: Option<JoinHandle<Result<(), String>>>,
+}
+
+impl TemplateArchive {
+    /// Wait for the background refresh, if any, so that the next run uses fresh content
+    pub(crate) async fn finish(self, reporter: &mut dyn Reporter) {
+        drop(self.snapshot);
+        if let Some(refresh) = self.refresh {
+            reporter.report(match refresh.await {
+                Ok(Ok(())) => {
+                    Event::Download("Refreshed the cached template in the background".to_string())
+                }
+                Ok(Err(e)) => {
+                    Event::Warning(format!("Failed to refresh the cached template: {}", e))
+                }
+                Err(e) => Event::Warning(format!("Failed to refresh the cached template: {}", e)),
+            });
+        }
+    }
+}
+
+/// Directory of cached template archives
+pub fn template_cache_dir() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(dirs::cache_dir()
+        .ok_or("Failed to find the cache directory")?
+        .join("rmkit")
+        .join("templates"))
+}
+
+/// Get the template archive of `source`, downloading it if it's not cached
+///
+/// Archives of commits never change and are cached forever. Archives of branches and tags are served from
+/// the cache immediately and refreshed in the background when they are older than an hour.
+/// `offline` only uses the cache, without refreshing it.
+#[tracing::instrument(name = "download", skip_all, fields(source = %source.name()))]
+pub(crate) async fn fetch_template_archive(
+    source: &TemplateSource,
+    offline: bool,
+    client: &Client,
+    reporter: &mut dyn Reporter,
+) -> Result<TemplateArchive, Box<dyn Error>> {
+    let cache_dir = template_cache_dir()?;
+    fs::create_dir_all(&cache_dir)?;
+    let name = source.name();
+    let key: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let path = cache_dir.join(format!("{}.zip", key));
+    if offline {
+        if !path.exists() {
+            return Err(format!(
+                "Template {} isn't cached, run once without --offline to download it",
+                name
+            ))
+            .or_fail(FailureKind::Network);
+        }
+        reporter.report(Event::Download(format!(
+            "Using cached template of {} (offline)",
+            name
+        )));
+        return Ok(TemplateArchive {
+            path,
+            snapshot: None,
+            refresh: None,
+        });
+    }
+    let token = source.token();
+    let url = source.archive_url(token.is_some());
+
+    let age = fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    let Some(age) = age else {
+        reporter.report(Event::Download(format!(
+            "Downloading project template from {}",
+            url
+        )));
+        download_to(&url, &path, token.as_deref(), client).await?;
+        return Ok(TemplateArchive {
+            path,
+            snapshot: None,
+            refresh: None,
+        });
+    };
+
+    if source.is_immutable() || age < BRANCH_CACHE_TTL {
+        reporter.report(Event::Download(format!(
+            "Using cached template of {} ({} min old)",
+            name,
+            age.as_secs() / 60
+        )));
+        return Ok(TemplateArchive {
+            path,
+            snapshot: None,
+            refresh: None,
+        });
+    }
+
+    reporter.report(Event::Download(format!(
+        "Using cached template of {} ({} min old), refreshing it in the background",
+        name,
+        age.as_secs() / 60
+    )));
+    // The cached archive is copied, so the refresh can't replace it while it's being extracted
+    let snapshot = TempPath::new(cache_dir.join(format!("{}.{}.zip", key, std::process::id())));
+    fs::copy(&path, snapshot.path())?;
+    let (client, refresh_path) = (client.clone(), path);
+    let refresh = tokio::spawn(async move {
+        download_to(&url, &refresh_path, token.as_deref(), &client)
+            .await
+            .map_err(|e| e.to_string())
+    });
+    Ok(TemplateArchive {
+        path: snapshot.path().to_path_buf(),
+        snapshot: Some(snapshot),
+        refresh: Some(refresh),
+    })
+}
+
+/// Download `url` to `path`, the file is replaced only when the download completes
+async fn download_to(
+    url: &str,
+    path: &Path,
+    token: Option<&str>,
+    client: &Client,
+) -> Result<(), Box<dyn Error>> {
+    let mut request = client.get(url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(format!(
+            "Download failed: {}, check the template repository and branch or tag. Private repositories need a GitHub token in RMKIT_TEMPLATE_TOKEN or GITHUB_TOKEN",
+            response.status()
+        ))
+        .or_fail(FailureKind::Network);
+    }
+    if !response.status().is_success() {
+        return Err(format!("Download failed: {}", response.status()))
+            .or_fail(FailureKind::Network);
+    }
+
+    // The partial file is removed if the download fails or is interrupted
+    let partial = TempPath::new(path.with_extension(format!("{}.partial", std::process::id())));
+    let mut file = File::create(partial.path())?;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        file.write_all(&chunk?)?;
+    }
+    fs::rename(partial.path(), path)?;
+    Ok(())
+}
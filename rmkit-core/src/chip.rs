@@ -0,0 +1,862 @@
+use crate::keyboard_toml::uf2_key;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Built-in chip database
+const BUILTIN_CHIPS: &str = include_str!("chips.toml");
+
+/// Chip and board database, either the built-in one or a user extension
+#[derive(Debug, Default, Deserialize)]
+struct ChipDatabase {
+    /// Boards and the chip they use
+    #[serde(default)]
+    boards: HashMap<String, String>,
+    /// Alternative names of chips and boards
+    #[serde(default)]
+    aliases: HashMap<String, String>,
+    /// UF2 family ids, by the uf2 key of the chip
+    #[serde(default)]
+    uf2_families: HashMap<String, u32>,
+    /// Split transports supported by chips, keyed by chip name or chip name prefix
+    #[serde(default)]
+    split: HashMap<String, Vec<String>>,
+    /// Build targets, firmware formats and memory layouts of chips, by chip name
+    #[serde(default)]
+    targets: HashMap<String, ChipTarget>,
+    /// Bootloaders, flash sizes and pinouts of boards, by board name
+    #[serde(default)]
+    board_info: HashMap<String, BoardInfo>,
+    /// Pins of the packages of chips, keyed by chip name or chip name prefix, then by package name
+    #[serde(default)]
+    packages: HashMap<String, HashMap<String, ChipPackage>>,
+    /// Chips and boards which can be chosen in `rmkit init`
+    #[serde(default)]
+    options: ChipOptions,
+    /// Why the user's chips.toml was ignored
+    #[serde(skip)]
+    user_error: Option<String>,
+}
+
+/// How to build and flash firmware of a chip
+#[derive(Debug, Deserialize)]
+pub struct ChipTarget {
+    /// Rust target triple
+    pub target: String,
+    /// Firmware formats, e.g. `uf2` or `hex`
+    #[serde(default)]
+    pub formats: Vec<String>,
+    /// Format the bare chip is usually flashed from, one of `formats`
+    pub default_format: Option<String>,
+    /// Chip name used by probe-rs
+    pub probe_rs_chip: String,
+    /// Regions of memory.x, without a bootloader, memory.x isn't generated if there are none
+    #[serde(default)]
+    pub memory: Vec<MemoryRegion>,
+}
+
+/// Region in the `MEMORY` command of memory.x
+#[derive(Debug, Deserialize)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub origin: u32,
+    /// Length as in the linker script, e.g. `1024K` or `2048K - 0x100`
+    pub length: String,
+}
+
+/// Metadata of a board, every field is optional
+#[derive(Debug, Default, Deserialize)]
+pub struct BoardInfo {
+    /// Other names of the board in `[boards]` which share the metadata
+    #[serde(default)]
+    pub other_names: Vec<String>,
+    pub bootloader: Option<Bootloader>,
+    /// Size of the flash, as in the linker script, e.g. `2048K`
+    pub flash_size: Option<String>,
+    /// USB vendor id of the board in bootloader mode
+    pub usb_vid: Option<u16>,
+    /// USB product id of the board in bootloader mode
+    pub usb_pid: Option<u16>,
+    /// Pins which can be used for the matrix, in the order of the pinout
+    #[serde(default)]
+    pub pins: Vec<String>,
+}
+
+/// Package of a chip, e.g. UFQFPN48 or LQFP100
+#[derive(Debug, Default, Deserialize)]
+pub struct ChipPackage {
+    /// Pins which are bonded out, single pins or ranges like `PA0-PA15`
+    #[serde(default)]
+    pins: Vec<String>,
+}
+
+impl ChipPackage {
+    /// Pins which are bonded out, with the ranges expanded, invalid ranges are skipped
+    pub fn pins(&self) -> Vec<String> {
+        self.pins
+            .iter()
+            .filter_map(|pins| expand_pins(pins))
+            .flatten()
+            .collect()
+    }
+}
+
+/// Expand a pin or a range of pins, e.g. `PA0-PA3` to `PA0`, `PA1`, `PA2`, `PA3`, `None` if the range is invalid
+fn expand_pins(pins: &str) -> Option<Vec<String>> {
+    let Some((first, last)) = pins.split_once('-') else {
+        return Some(vec![pins.to_string()]);
+    };
+    let ((prefix, start), (last_prefix, end)) = (split_pin(first)?, split_pin(last)?);
+    if prefix != last_prefix || start > end {
+        return None;
+    }
+    Some((start..=end).map(|n| format!("{}{}", prefix, n)).collect())
+}
+
+/// Split a pin into its prefix and number, e.g. `PA12` into `PA` and 12
+fn split_pin(pin: &str) -> Option<(&str, u32)> {
+    let number = pin.trim_start_matches(|c: char| !c.is_ascii_digit());
+    Some((&pin[..pin.len() - number.len()], number.parse().ok()?))
+}
+
+/// Bootloader of a board, which decides how firmware is uploaded without a debug probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bootloader {
+    /// Shows up as a USB drive, firmware is copied to it as a UF2 file
+    Uf2,
+    /// Arduino bootloader of Pro Micro boards, uploaded with avrdude
+    Caterina,
+    /// USB DFU, uploaded with dfu-util
+    Dfu,
+    /// No bootloader, flashed with a debug probe
+    None,
+}
+
+impl fmt::Display for Bootloader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Bootloader::Uf2 => "uf2",
+            Bootloader::Caterina => "caterina",
+            Bootloader::Dfu => "dfu",
+            Bootloader::None => "none",
+        })
+    }
+}
+
+impl Bootloader {
+    /// How to upload firmware with the bootloader
+    pub fn upload_hint(self) -> &'static str {
+        match self {
+            Bootloader::Uf2 => {
+                "put it into bootloader mode and copy the .uf2 firmware to its drive"
+            }
+            Bootloader::Caterina => "reset it and upload the .hex firmware with avrdude",
+            Bootloader::Dfu => "reset it into DFU mode and upload the .bin firmware with dfu-util",
+            Bootloader::None => "flash it with a debug probe",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChipOptions {
+    #[serde(default)]
+    chips: Vec<String>,
+}
+
+/// Firmware formats which can be listed for a chip
+const FIRMWARE_FORMATS: [&str; 4] = ["elf", "hex", "bin", "uf2"];
+
+impl ChipDatabase {
+    /// Find an entry which makes the database invalid
+    fn invalid_entry(&self) -> Option<String> {
+        for (chip, target) in &self.targets {
+            if target.target.split('-').count() < 3 {
+                return Some(format!(
+                    "target {} of {} isn't a Rust target triple",
+                    target.target, chip
+                ));
+            }
+            if let Some(format) = target
+                .formats
+                .iter()
+                .find(|f| !FIRMWARE_FORMATS.contains(&f.as_str()))
+            {
+                return Some(format!(
+                    "format {} of {} is unknown, it should be one of {}",
+                    format,
+                    chip,
+                    FIRMWARE_FORMATS.join(", ")
+                ));
+            }
+            if let Some(format) = target
+                .default_format
+                .as_ref()
+                .filter(|f| !target.formats.contains(f))
+            {
+                return Some(format!(
+                    "default format {} of {} isn't one of its formats",
+                    format, chip
+                ));
+            }
+        }
+        for (chip, packages) in &self.packages {
+            for (package, info) in packages {
+                if let Some(pins) = info.pins.iter().find(|p| expand_pins(p).is_none()) {
+                    return Some(format!(
+                        "pins {} of package {} of {} isn't a pin or a range like PA0-PA15",
+                        pins, package, chip
+                    ));
+                }
+            }
+        }
+        for (board, info) in &self.board_info {
+            if info.usb_vid.is_some() != info.usb_pid.is_some() {
+                return Some(format!(
+                    "board info of {} needs both usb_vid and usb_pid",
+                    board
+                ));
+            }
+            if let Some(size) = info.flash_size.as_ref().filter(|s| !is_memory_size(s)) {
+                return Some(format!(
+                    "flash size {} of {} should be a size like 2048K or 2M",
+                    size, board
+                ));
+            }
+        }
+        None
+    }
+
+    /// Get the metadata of a board by its name, one of its other names or an alias
+    fn board_info(&self, board: &str) -> Option<&BoardInfo> {
+        let board = self.aliases.get(board).map_or(board, |b| b.as_str());
+        self.board_info.get(board).or_else(|| {
+            self.board_info
+                .values()
+                .find(|info| info.other_names.iter().any(|n| n == board))
+        })
+    }
+
+    /// Get the split transports of a chip or board, `None` if there's no entry for it
+    fn split_entry(&self, chip_or_board: &str) -> Option<&Vec<String>> {
+        let chip = self
+            .boards
+            .get(chip_or_board)
+            .map(|c| c.as_str())
+            .unwrap_or(chip_or_board)
+            .to_lowercase();
+        self.split
+            .iter()
+            .filter(|(key, _)| chip.starts_with(key.to_lowercase().as_str()))
+            .max_by_key(|(key, _)| key.len())
+            .map(|(_, transports)| transports)
+    }
+
+    /// Find UF2 family ids which aren't in `official`, the uf2families.json of microsoft/uf2
+    fn audit_uf2_families(&self, official: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let official: Vec<OfficialUf2Family> = serde_json::from_str(official)
+            .map_err(|e| format!("Invalid uf2families.json: {}", e))?;
+        let official_ids: Vec<u32> = official
+            .iter()
+            .filter_map(|family| u32::from_str_radix(family.id.trim_start_matches("0x"), 16).ok())
+            .collect();
+        let mut families: Vec<(&String, &u32)> = self.uf2_families.iter().collect();
+        families.sort();
+        Ok(families
+            .into_iter()
+            .filter(|(_, id)| !official_ids.contains(id))
+            .map(|(key, id)| {
+                format!(
+                    "UF2 family id {:#010x} of {} isn't in uf2families.json",
+                    id, key
+                )
+            })
+            .collect())
+    }
+
+    /// Find inconsistent entries
+    fn audit(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let is_known_chip = |chip: &str| {
+            self.options.chips.iter().any(|o| o == chip)
+                || self.split_entry(chip).is_some()
+                || self.uf2_families.contains_key(&uf2_key(chip))
+        };
+
+        let mut options: Vec<String> = Vec::new();
+        for option in &self.options.chips {
+            if options.contains(&option.to_lowercase()) {
+                errors.push(format!("Option {} is listed more than once", option));
+            }
+            options.push(option.to_lowercase());
+
+            if self.split_entry(option).is_none() {
+                errors.push(format!(
+                    "Option {} has no [split] entry, use an empty list if it can't be split",
+                    option
+                ));
+            }
+        }
+
+        for (board, chip) in &self.boards {
+            if !is_known_chip(chip) {
+                errors.push(format!("Board {} uses unknown chip {}", board, chip));
+            }
+        }
+
+        for (alias, target) in &self.aliases {
+            if !self.boards.contains_key(target) && !self.options.chips.contains(target) {
+                errors.push(format!(
+                    "Alias {} points to unknown chip or board {}",
+                    alias, target
+                ));
+            }
+            if alias != target
+                && (self.boards.contains_key(alias) || self.options.chips.contains(alias))
+            {
+                errors.push(format!(
+                    "Alias {} shadows the chip or board with the same name",
+                    alias
+                ));
+            }
+        }
+
+        let mut families: Vec<(&String, &u32)> = self.uf2_families.iter().collect();
+        families.sort();
+        for (i, (key, family)) in families.iter().enumerate() {
+            if let Some((other, _)) = families[..i].iter().find(|(_, f)| f == family) {
+                errors.push(format!(
+                    "UF2 family id {:#010x} is used by both {} and {}",
+                    family, other, key
+                ));
+            }
+        }
+
+        for (chip, target) in &self.targets {
+            if !is_known_chip(chip) {
+                errors.push(format!("Target of unknown chip {}", chip));
+            }
+            for region in ["FLASH", "RAM"] {
+                if !target.memory.is_empty() && !target.memory.iter().any(|r| r.name == region) {
+                    errors.push(format!("Memory of {} has no {} region", chip, region));
+                }
+            }
+        }
+
+        let mut named: Vec<&String> = Vec::new();
+        for (board, info) in &self.board_info {
+            for name in std::iter::once(board).chain(&info.other_names) {
+                if !self.boards.contains_key(name) {
+                    errors.push(format!("Board info of unknown board {}", name));
+                }
+                if named.contains(&name) {
+                    errors.push(format!("Board {} has more than one board info", name));
+                }
+                named.push(name);
+            }
+            if info.usb_vid.is_some() != info.usb_pid.is_some() {
+                errors.push(format!(
+                    "Board info of {} needs both usb_vid and usb_pid",
+                    board
+                ));
+            }
+            for (i, pin) in info.pins.iter().enumerate() {
+                if info.pins[..i].contains(pin) {
+                    errors.push(format!("Pin {} of {} is listed more than once", pin, board));
+                }
+            }
+        }
+
+        for (chip, packages) in &self.packages {
+            for (package, info) in packages {
+                let pins = info.pins();
+                for (i, pin) in pins.iter().enumerate() {
+                    if pins[..i].contains(pin) {
+                        errors.push(format!(
+                            "Pin {} of package {} of {} is listed more than once",
+                            pin, package, chip
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (chip, transports) in &self.split {
+            for transport in transports {
+                if transport != "ble" && transport != "serial" {
+                    errors.push(format!(
+                        "Split transport {} of {} is unknown, it should be ble or serial",
+                        transport, chip
+                    ));
+                }
+            }
+        }
+
+        errors.sort();
+        errors
+    }
+}
+
+/// Check a memory size in the format of linker scripts, e.g. `1024K` or `2M`
+fn is_memory_size(size: &str) -> bool {
+    size.strip_suffix(['K', 'M'])
+        .unwrap_or(size)
+        .parse::<u32>()
+        .is_ok()
+}
+
+/// Path of the user chip database, which extends or overrides the built-in one
+fn user_chips_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config").join("rmkit").join("chips.toml"))
+}
+
+/// Get the chip database, the built-in database merged with the user's `~/.config/rmkit/chips.toml`
+fn database() -> &'static ChipDatabase {
+    static DATABASE: OnceLock<ChipDatabase> = OnceLock::new();
+    DATABASE.get_or_init(|| {
+        let mut database: ChipDatabase =
+            toml::from_str(BUILTIN_CHIPS).expect("built-in chips.toml should be valid");
+
+        let Some(path) = user_chips_path().filter(|p| p.exists()) else {
+            return database;
+        };
+        let user: ChipDatabase = match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| toml::from_str(&content).map_err(|e| e.to_string()))
+            .and_then(|user: ChipDatabase| match user.invalid_entry() {
+                Some(e) => Err(e),
+                None => Ok(user),
+            }) {
+            Ok(user) => user,
+            Err(e) => {
+                database.user_error = Some(format!("Ignoring invalid {}: {}", path.display(), e));
+                return database;
+            }
+        };
+
+        // User boards override the built-in ones, user options are listed first
+        database.boards.extend(user.boards);
+        database.aliases.extend(user.aliases);
+        database.uf2_families.extend(user.uf2_families);
+        database.split.extend(user.split);
+        database.targets.extend(user.targets);
+        database.board_info.extend(user.board_info);
+        for (chip, packages) in user.packages {
+            database.packages.entry(chip).or_default().extend(packages);
+        }
+        let options = &mut database.options.chips;
+        options.retain(|o| !user.options.chips.contains(o));
+        options.splice(0..0, user.options.chips);
+        database
+    })
+}
+
+/// Boards and the chip they use
+pub fn get_board_chip_map() -> HashMap<&'static str, &'static str> {
+    database()
+        .boards
+        .iter()
+        .map(|(board, chip)| (board.as_str(), chip.as_str()))
+        .collect()
+}
+
+/// All supported chips, only the ones which support split if `split` is set
+pub fn get_chip_options(split: bool) -> Vec<&'static str> {
+    database()
+        .options
+        .chips
+        .iter()
+        .map(|o| o.as_str())
+        .filter(|o| !split || !split_transports(o).is_empty())
+        .collect()
+}
+
+/// All known chips followed by all known boards
+pub fn get_chip_and_board_names() -> Vec<&'static str> {
+    let mut names = get_chip_options(false);
+    let mut boards: Vec<&str> = database().boards.keys().map(|b| b.as_str()).collect();
+    boards.retain(|b| !names.contains(b));
+    boards.sort();
+    names.extend(boards);
+    names
+}
+
+/// Get the transports (`ble`, `serial`) which a chip or board supports for split keyboards
+///
+/// Empty if the chip can't be used for split keyboards
+pub fn split_transports(chip_or_board: &str) -> &'static [String] {
+    database()
+        .split_entry(chip_or_board)
+        .map(|transports| transports.as_slice())
+        .unwrap_or(&[])
+}
+
+/// Get the packages of a chip or board by their name, `None` if there's no entry for the chip
+///
+/// The longest chip name prefix matching the chip is used.
+pub fn get_chip_packages(chip_or_board: &str) -> Option<&'static HashMap<String, ChipPackage>> {
+    let chip = get_board_chip_map()
+        .get(chip_or_board)
+        .copied()
+        .unwrap_or(chip_or_board)
+        .to_lowercase();
+    database()
+        .packages
+        .iter()
+        .filter(|(key, _)| chip.starts_with(key.to_lowercase().as_str()))
+        .max_by_key(|(key, _)| key.len())
+        .map(|(_, packages)| packages)
+}
+
+/// Get the bootloader, flash size, USB ids and pinout of a board, if they're known
+pub fn get_board_info(board: &str) -> Option<&'static BoardInfo> {
+    database().board_info(board)
+}
+
+/// Resolve a chip or board name given by the user to a known chip or board
+///
+/// Besides exact names, aliases, case and punctuation differences (`nRF52840`, `nice_nano_v2`) and
+/// full part numbers (`stm32f411ceu6`) are accepted. A suggestion is given when the name can't be resolved.
+pub fn resolve_chip(name: &str) -> Result<String, Box<dyn Error>> {
+    let database = database();
+    let known = get_chip_and_board_names();
+    if known.contains(&name) {
+        return Ok(name.to_string());
+    }
+
+    let normalize = |s: &str| -> String {
+        s.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    };
+    let normalized = normalize(name);
+    let resolved = database
+        .aliases
+        .iter()
+        .find(|(alias, _)| normalize(alias) == normalized)
+        .map(|(_, target)| target.as_str())
+        .or_else(|| known.iter().copied().find(|k| normalize(k) == normalized))
+        // Full part numbers of chips have package and temperature suffixes, e.g. `stm32f411ceu6`
+        .or_else(|| {
+            known
+                .iter()
+                .copied()
+                .filter(|k| !database.boards.contains_key(*k))
+                .filter(|k| k.len() >= 7 && normalized.starts_with(&normalize(k)))
+                .max_by_key(|k| k.len())
+        });
+    if let Some(resolved) = resolved {
+        return Ok(resolved.to_string());
+    }
+
+    let suggestion = known
+        .iter()
+        .copied()
+        .chain(database.aliases.keys().map(|a| a.as_str()))
+        .map(|k| (strsim::jaro_winkler(&normalize(k), &normalized), k))
+        .filter(|(score, _)| *score > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0));
+    match suggestion {
+        Some((_, k)) => Err(format!(
+            "Unknown chip or board '{}', did you mean '{}'? Run `rmkit chips list` to see the known chips",
+            name, k
+        )
+        .into()),
+        None => Err(format!(
+            "Unknown chip or board '{}', run `rmkit chips list` to see the known chips",
+            name
+        )
+        .into()),
+    }
+}
+
+/// Check a `chip` or `board` of keyboard.toml against the chip database
+///
+/// rmk reads the name as it's written, so the other spellings which [`resolve_chip`] accepts are errors,
+/// with the known name as a suggestion.
+pub fn check_chip_name(name: &str, is_board: bool) -> Result<(), Box<dyn Error>> {
+    let database = database();
+    let known = if is_board {
+        database.boards.contains_key(name)
+    } else {
+        database.targets.contains_key(name)
+            || (database.options.chips.iter().any(|c| c == name)
+                && !database.boards.contains_key(name))
+    };
+    if known {
+        return Ok(());
+    }
+    let kind = if is_board { "board" } else { "chip" };
+    match resolve_chip(name) {
+        Ok(resolved) => Err(format!(
+            "Unknown {} '{}' in keyboard.toml, did you mean '{}'?",
+            kind, name, resolved
+        )
+        .into()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Entry of uf2families.json of microsoft/uf2, e.g. `{ "id": "0xada52840", "short_name": "NRF52840", ... }`
+#[derive(Deserialize)]
+struct OfficialUf2Family {
+    id: String,
+}
+
+/// UF2 family of the chip database, so that UF2 files can be mapped back to chips
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uf2Family {
+    /// Uf2 key of the chips of the family, e.g. `nrf52840` or `stm32f4`
+    pub key: &'static str,
+    /// UF2 family id
+    pub id: u32,
+}
+
+impl Uf2Family {
+    /// Get the family of a UF2 family id, e.g. the one in the blocks of a UF2 file
+    pub fn from_family_id(id: u32) -> Option<Self> {
+        uf2_families().find(|family| family.id == id)
+    }
+
+    /// Get the family of a chip or board, or of a uf2 key
+    pub fn by_name(chip_or_board: &str) -> Option<Self> {
+        let chip = get_board_chip_map()
+            .get(chip_or_board)
+            .copied()
+            .unwrap_or(chip_or_board);
+        let (key, id) = database().uf2_families.get_key_value(&uf2_key(chip))?;
+        Some(Uf2Family { key, id: *id })
+    }
+}
+
+/// All UF2 families of the chip database, sorted by uf2 key
+pub fn uf2_families() -> impl Iterator<Item = Uf2Family> {
+    let mut families: Vec<Uf2Family> = database()
+        .uf2_families
+        .iter()
+        .map(|(key, id)| Uf2Family { key, id: *id })
+        .collect();
+    families.sort_by_key(|family| family.key);
+    families.into_iter()
+}
+
+/// Get the UF2 family id of a chip or board
+pub fn get_uf2_family(chip_or_board: &str) -> Option<u32> {
+    Uf2Family::by_name(chip_or_board).map(|family| family.id)
+}
+
+/// Get the build target, firmware formats and memory layout of a chip or board, if they're known
+pub fn get_chip_target(chip_or_board: &str) -> Option<&'static ChipTarget> {
+    let chip = get_board_chip_map()
+        .get(chip_or_board)
+        .copied()
+        .unwrap_or(chip_or_board);
+    database().targets.get(chip)
+}
+
+/// Check the chip database, including the user's chips.toml, for inconsistent entries
+pub fn audit_database() -> Vec<String> {
+    database().audit()
+}
+
+/// Check the UF2 family ids of the chip database against `official`, the uf2families.json of microsoft/uf2
+pub fn audit_uf2_families(official: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    database().audit_uf2_families(official)
+}
+
+/// Number of entries of the chip database, as a sentence part
+pub fn database_summary() -> String {
+    let database = database();
+    format!(
+        "{} chips and boards, {} boards, {} aliases, {} UF2 families, {} board infos",
+        database.options.chips.len(),
+        database.boards.len(),
+        database.aliases.len(),
+        database.uf2_families.len(),
+        database.board_info.len()
+    )
+}
+
+/// Error of the user's chips.toml, which is ignored if it's invalid
+pub fn user_database_error() -> Option<&'static str> {
+    database().user_error.as_deref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin() -> ChipDatabase {
+        toml::from_str(BUILTIN_CHIPS).unwrap()
+    }
+
+    #[test]
+    fn builtin_database_is_consistent() {
+        let errors = builtin().audit();
+        assert!(errors.is_empty(), "{:#?}", errors);
+    }
+
+    #[test]
+    fn uf2_families_are_found_by_id_and_name() {
+        assert!(uf2_families().count() > 0);
+        for family in uf2_families() {
+            assert_eq!(Uf2Family::from_family_id(family.id), Some(family));
+            assert_eq!(Uf2Family::by_name(family.key), Some(family));
+        }
+        let nrf52840 = Uf2Family::from_family_id(0xada5_2840).unwrap();
+        assert_eq!(nrf52840.key, "nrf52840");
+        assert_eq!(Uf2Family::by_name("nice!nano"), Some(nrf52840));
+        assert_eq!(Uf2Family::by_name("stm32f411ce").unwrap().key, "stm32f4");
+        assert_eq!(Uf2Family::from_family_id(0), None);
+        assert_eq!(Uf2Family::by_name("not_a_chip"), None);
+    }
+
+    #[test]
+    fn audit_finds_unknown_board_chip() {
+        let mut database = builtin();
+        database
+            .boards
+            .insert("my_board".to_string(), "not_a_chip".to_string());
+        let errors = database.audit();
+        assert_eq!(errors, ["Board my_board uses unknown chip not_a_chip"]);
+    }
+
+    #[test]
+    fn audit_finds_duplicate_uf2_family() {
+        let mut database = builtin();
+        let family = database.uf2_families["rp2040"];
+        database.uf2_families.insert("rp2354".to_string(), family);
+        let errors = database.audit();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("rp2040") && errors[0].contains("rp2354"));
+    }
+
+    #[test]
+    fn uf2_families_are_checked_against_the_official_list() {
+        let database = builtin();
+        let official: Vec<String> = database
+            .uf2_families
+            .iter()
+            .filter(|(key, _)| *key != "nrf52833")
+            .map(|(key, id)| {
+                format!(
+                    r#"{{ "id": "{:#010x}", "short_name": "{}", "description": "" }}"#,
+                    id,
+                    key.to_uppercase()
+                )
+            })
+            .collect();
+        let errors = database
+            .audit_uf2_families(&format!("[{}]", official.join(", ")))
+            .unwrap();
+        assert_eq!(
+            errors,
+            ["UF2 family id 0x621e937a of nrf52833 isn't in uf2families.json"]
+        );
+        assert!(database.audit_uf2_families("{}").is_err());
+    }
+
+    #[test]
+    fn audit_finds_duplicate_option_and_bad_alias() {
+        let mut database = builtin();
+        database.options.chips.push("NRF52840".to_string());
+        database
+            .aliases
+            .insert("nrf".to_string(), "nrf5".to_string());
+        let errors = database.audit();
+        assert_eq!(
+            errors,
+            [
+                "Alias nrf points to unknown chip or board nrf5",
+                "Option NRF52840 is listed more than once",
+            ]
+        );
+    }
+
+    #[test]
+    fn audit_finds_unknown_split_transport() {
+        let mut database = builtin();
+        database
+            .split
+            .insert("rp2040".to_string(), vec!["usb".to_string()]);
+        let errors = database.audit();
+        assert_eq!(
+            errors,
+            ["Split transport usb of rp2040 is unknown, it should be ble or serial"]
+        );
+    }
+
+    #[test]
+    fn audit_finds_incomplete_memory() {
+        let mut database = builtin();
+        database
+            .targets
+            .get_mut("nrf52840")
+            .unwrap()
+            .memory
+            .retain(|r| r.name != "RAM");
+        let errors = database.audit();
+        assert_eq!(errors, ["Memory of nrf52840 has no RAM region"]);
+    }
+
+    #[test]
+    fn board_info_by_other_name_and_alias() {
+        let database = builtin();
+        let info = database.board_info("pi_pico_w").unwrap();
+        assert_eq!(info.bootloader, Some(Bootloader::Uf2));
+        assert_eq!(info.flash_size.as_deref(), Some("2048K"));
+        assert!(std::ptr::eq(
+            info,
+            database.board_info("rpi_pico_w").unwrap()
+        ));
+        assert!(database.board_info("nrf52840").is_none());
+    }
+
+    #[test]
+    fn package_pins_are_expanded() {
+        assert_eq!(
+            expand_pins("PA0-PA2"),
+            Some(vec![
+                "PA0".to_string(),
+                "PA1".to_string(),
+                "PA2".to_string()
+            ])
+        );
+        assert_eq!(expand_pins("PIN_3"), Some(vec!["PIN_3".to_string()]));
+        assert_eq!(expand_pins("PA2-PA0"), None);
+        assert_eq!(expand_pins("PA0-PB2"), None);
+
+        let packages = builtin();
+        let stm32f411 = &packages.packages["stm32f411"];
+        // 36 I/Os, PB11 isn't bonded out
+        let pins = stm32f411["UFQFPN48"].pins();
+        assert_eq!(pins.len(), 36);
+        assert!(!pins.contains(&"PB11".to_string()));
+        assert_eq!(stm32f411["LQFP64"].pins().len(), 50);
+        assert_eq!(stm32f411["LQFP100"].pins().len(), 81);
+        assert_eq!(packages.packages["rp2350"]["QFN60"].pins().len(), 30);
+        assert!(get_chip_packages("stm32f411ce").is_some_and(|p| p.contains_key("LQFP100")));
+        assert!(get_chip_packages("nrf52840").is_none());
+    }
+
+    #[test]
+    fn audit_finds_bad_board_info() {
+        let mut database = builtin();
+        let info = database.board_info.get_mut("KB2040").unwrap();
+        info.usb_pid = None;
+        info.other_names.push("kb2041".to_string());
+        let errors = database.audit();
+        assert_eq!(
+            errors,
+            [
+                "Board info of KB2040 needs both usb_vid and usb_pid",
+                "Board info of unknown board kb2041",
+            ]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use crate::report::{Event, Reporter, Resolution};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Marker of the start of user code in a template file, which is kept when the project is generated again
+///
+/// It can be followed by a name, to tell the regions of a file apart.
+const USER_CODE_BEGIN: &str = "rmkit:user-code-begin";
+/// Marker of the end of user code in a template file
+const USER_CODE_END: &str = "rmkit:user-code-end";
+
+/// Writes template files into a project, the reporter decides what to do with existing files which differ
+pub(crate) struct Conflicts<'a> {
+    reporter: &'a mut dyn Reporter,
+    /// Overwrite all existing files without asking
+    overwrite_all: bool,
+    /// Keep all existing files without asking
+    skip_all: bool,
+    /// Existing files which were kept
+    skipped: Vec<PathBuf>,
+}
+
+impl<'a> Conflicts<'a> {
+    /// `force` overwrites existing files without asking
+    pub(crate) fn new(force: bool, reporter: &'a mut dyn Reporter) -> Self {
+        Self {
+            reporter,
+            overwrite_all: force,
+            skip_all: false,
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Write a template file, if the file exists with different content, ask the reporter whether to overwrite it
+    ///
+    /// The user code between the markers of an existing file is put into the template file first.
+    pub(crate) fn write(&mut self, path: &Path, content: &[u8]) -> Result<(), Box<dyn Error>> {
+        let existing = fs::read(path);
+        let merged = existing
+            .as_ref()
+            .ok()
+            .and_then(|existing| keep_user_code(existing, content));
+        let content = merged.as_deref().unwrap_or(content);
+        let existing = match existing {
+            Ok(existing) if existing == content => return Ok(()),
+            Ok(existing) => existing,
+            Err(_) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, content)?;
+                return Ok(());
+            }
+        };
+
+        if !self.overwrite_all && !self.skip_all {
+            match self.reporter.resolve_conflict(path, &existing, content)? {
+                Resolution::Overwrite => {}
+                Resolution::OverwriteAll => self.overwrite_all = true,
+                Resolution::SkipAll => self.skip_all = true,
+                Resolution::Skip => {
+                    self.skipped.push(path.to_path_buf());
+                    return Ok(());
+                }
+            }
+        }
+        if self.skip_all {
+            self.skipped.push(path.to_path_buf());
+            return Ok(());
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Reporter of the operation, while the conflicts borrow it
+    pub(crate) fn reporter(&mut self) -> &mut dyn Reporter {
+        self.reporter
+    }
+
+    /// Report the existing files which were kept
+    pub(crate) fn report(&mut self) {
+        if !self.skipped.is_empty() {
+            let files: Vec<String> = self
+                .skipped
+                .iter()
+                .map(|p| format!("\n    {}", p.display()))
+                .collect();
+            self.reporter.report(Event::Info(format!(
+                "Kept {} existing file(s):{}",
+                self.skipped.len(),
+                files.concat()
+            )));
+        }
+    }
+}
+
+/// A region of user code, the name after the begin marker and the lines between the markers
+type UserCode<'a> = (&'a str, Vec<&'a str>);
+
+/// Put the user code between the markers of an existing file into the same regions of a template file
+///
+/// Regions are matched by their name, and in order for regions with the same name. `None` if the template file has
+/// no markers, or either file isn't text.
+fn keep_user_code(existing: &[u8], template: &[u8]) -> Option<Vec<u8>> {
+    let template = std::str::from_utf8(template).ok()?;
+    if !template.contains(USER_CODE_BEGIN) {
+        return None;
+    }
+    let mut user_code = user_code_regions(std::str::from_utf8(existing).ok()?);
+
+    let mut merged = String::with_capacity(template.len());
+    let mut lines = template.split_inclusive('\n');
+    while let Some(line) = lines.next() {
+        merged.push_str(line);
+        let Some(name) = marker_name(line, USER_CODE_BEGIN) else {
+            continue;
+        };
+        let mut region = Vec::new();
+        let mut end = None;
+        for line in lines.by_ref() {
+            if line.contains(USER_CODE_END) {
+                end = Some(line);
+                break;
+            }
+            region.push(line);
+        }
+        let kept = user_code
+            .iter()
+            .position(|(n, _)| *n == name)
+            .map(|i| user_code.remove(i).1);
+        merged.extend(kept.unwrap_or(region));
+        merged.extend(end);
+    }
+    Some(merged.into_bytes())
+}
+
+/// Get the regions of user code of a file, unterminated regions are ignored
+fn user_code_regions(text: &str) -> Vec<UserCode<'_>> {
+    let mut regions = Vec::new();
+    let mut lines = text.split_inclusive('\n');
+    while let Some(line) = lines.next() {
+        let Some(name) = marker_name(line, USER_CODE_BEGIN) else {
+            continue;
+        };
+        let mut region = Vec::new();
+        for line in lines.by_ref() {
+            if line.contains(USER_CODE_END) {
+                regions.push((name, region));
+                break;
+            }
+            region.push(line);
+        }
+    }
+    regions
+}
+
+/// Get the name after a marker in a line, empty for markers without a name
+fn marker_name<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    let (_, name) = line.split_once(marker)?;
+    // Block comments end after the name
+    Some(
+        name.trim()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->")
+            .trim(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "use rmk::*;
+
+fn main() {
+    // rmkit:user-code-begin setup
+    // Your setup code
+    // rmkit:user-code-end
+    run();
+    /* rmkit:user-code-begin */
+    /* rmkit:user-code-end */
+}
+";
+
+    #[test]
+    fn user_code_is_kept() {
+        let existing = TEMPLATE
+            .replace(
+                "    // Your setup code\n",
+                "    init_leds();\n    init_oled();\n",
+            )
+            .replace(
+                "    /* rmkit:user-code-end */",
+                "    idle();\n    /* rmkit:user-code-end */",
+            );
+        let updated = TEMPLATE.replace("use rmk::*;", "use rmk::prelude::*;");
+        let merged = keep_user_code(existing.as_bytes(), updated.as_bytes()).unwrap();
+        assert_eq!(
+            String::from_utf8(merged).unwrap(),
+            existing.replace("use rmk::*;", "use rmk::prelude::*;")
+        );
+    }
+
+    #[test]
+    fn template_code_is_used_without_user_code() {
+        // The existing file has no markers, e.g. it was generated by an older template
+        let merged = keep_user_code(b"fn main() {}\n", TEMPLATE.as_bytes()).unwrap();
+        assert_eq!(merged, TEMPLATE.as_bytes());
+
+        let unterminated = "// rmkit:user-code-begin setup\n    lost();\n";
+        let merged = keep_user_code(unterminated.as_bytes(), TEMPLATE.as_bytes()).unwrap();
+        assert_eq!(merged, TEMPLATE.as_bytes());
+    }
+
+    #[test]
+    fn files_without_markers_are_unchanged() {
+        assert_eq!(keep_user_code(b"a", b"b"), None);
+        assert_eq!(keep_user_code(&[0xff], TEMPLATE.as_bytes()), None);
+    }
+}
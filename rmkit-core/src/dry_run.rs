@@ -0,0 +1,61 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// A file which a dry run would write to the target dir
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedFile {
+    /// Path relative to the target dir
+    pub path: PathBuf,
+    pub change: FileChange,
+}
+
+/// How a planned file differs from the one in the target dir
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+    New,
+    Changed {
+        existing: Vec<u8>,
+        generated: Vec<u8>,
+    },
+    Unchanged,
+}
+
+/// Get an empty temporary dir, where the project is generated instead of its target dir in a dry run
+pub(crate) fn staging_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("rmkit-dry-run-{}", process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Compare the files generated in `staging_dir` with `target_dir`, then remove `staging_dir`
+pub(crate) fn compare(
+    staging_dir: &Path,
+    target_dir: &Path,
+) -> Result<Vec<PlannedFile>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let walker = walkdir::WalkDir::new(staging_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file());
+    for entry in walker {
+        let path = entry.path().strip_prefix(staging_dir)?.to_path_buf();
+        let generated = fs::read(entry.path())?;
+        let change = match fs::read(target_dir.join(&path)) {
+            Ok(existing) if existing == generated => FileChange::Unchanged,
+            Ok(existing) => FileChange::Changed {
+                existing,
+                generated,
+            },
+            Err(_) => FileChange::New,
+        };
+        files.push(PlannedFile { path, change });
+    }
+    fs::remove_dir_all(staging_dir)?;
+    Ok(files)
+}
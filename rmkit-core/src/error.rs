@@ -0,0 +1,82 @@
+use std::error::Error;
+use std::fmt;
+
+/// Class of a failure, so that callers can tell a broken config from a network or build problem
+///
+/// The rmkit CLI has a stable exit code for every class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Invalid keyboard.toml, vial.json, netlist or template manifest
+    Config,
+    /// Failed to reach GitHub or download a template
+    Network,
+    /// cargo, rustup or a compilation target is missing
+    Toolchain,
+    /// cargo failed to build the project
+    Build,
+    /// Failed to convert a config into another format
+    Conversion,
+    /// Failed to flash the firmware to the keyboard
+    Flash,
+}
+
+/// Error with a known failure class
+#[derive(Debug)]
+pub struct ClassifiedError {
+    kind: FailureKind,
+    source: Box<dyn Error>,
+}
+
+impl ClassifiedError {
+    pub fn kind(&self) -> FailureKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for ClassifiedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl Error for ClassifiedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.source()
+    }
+}
+
+/// Attach a failure class to the error of a result
+pub trait Classify<T> {
+    /// Mark the error as `kind`, errors which are already classified keep their class
+    fn or_fail(self, kind: FailureKind) -> Result<T, Box<dyn Error>>;
+}
+
+impl<T, E: Into<Box<dyn Error>>> Classify<T> for Result<T, E> {
+    fn or_fail(self, kind: FailureKind) -> Result<T, Box<dyn Error>> {
+        self.map_err(|e| {
+            let source = e.into();
+            if source.is::<ClassifiedError>() {
+                source
+            } else {
+                Box::new(ClassifiedError { kind, source }) as Box<dyn Error>
+            }
+        })
+    }
+}
+
+/// Get the failure class of an error, `None` if it isn't classified
+///
+/// Network errors are classified wherever they come from, since downloads happen in many places.
+pub fn failure_kind(error: &(dyn Error + 'static)) -> Option<FailureKind> {
+    if let Some(e) = error.downcast_ref::<ClassifiedError>() {
+        return Some(e.kind);
+    }
+    let mut current = Some(error);
+    while let Some(e) = current {
+        if e.is::<reqwest::Error>() {
+            return Some(FailureKind::Network);
+        }
+        current = e.source();
+    }
+    None
+}
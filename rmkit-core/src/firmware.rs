@@ -0,0 +1,890 @@
+use crate::chip::{get_board_chip_map, get_chip_target};
+use crate::error::{Classify, FailureKind};
+use crate::interrupt;
+use crate::keyboard_toml::{config_feature_switches, derive_rmk_features, read_keyboard_config};
+use crate::report::{Event, Reporter};
+use cargo_metadata::MetadataCommand;
+use object::elf::PT_LOAD;
+use object::read::elf::{ElfFile, FileHeader, ProgramHeader};
+use object::{Endianness, FileKind};
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Largest flash image, segments further apart are most likely not all in flash
+const MAX_IMAGE_SIZE: u64 = 64 * 1024 * 1024;
+/// Number of UF2 blocks between progress events
+const UF2_PROGRESS_BLOCKS: u32 = 256;
+/// Payload of a UF2 block, the usual 256 bytes of bootloaders
+pub const UF2_PAYLOAD: usize = 256;
+/// Size of a UF2 block, a 32 byte header, 476 bytes of data and the final magic number
+pub const UF2_BLOCK: usize = 512;
+/// Magic numbers at the start of every UF2 block
+pub const UF2_MAGIC_START: [u32; 2] = [0x0A32_4655, 0x9E5D_5157];
+/// Magic number at the end of every UF2 block
+pub const UF2_MAGIC_END: u32 = 0x0AB1_6F30;
+/// Flag of UF2 blocks with a family id instead of a file size
+pub const UF2_FLAG_FAMILY_ID: u32 = 0x0000_2000;
+/// Flag of UF2 blocks with extension tags after their payload
+pub const UF2_FLAG_EXTENSION_TAGS: u32 = 0x0000_8000;
+/// Extension tag of the firmware version
+const UF2_TAG_VERSION: u32 = 0x9f_c7bc;
+/// Extension tag of the description of the device the firmware is for, rmkit writes the board or chip
+const UF2_TAG_DESCRIPTION: u32 = 0x65_0d9d;
+/// Extension tag of the build date, which isn't one of the standard tags of the UF2 spec
+const UF2_TAG_BUILD_DATE: u32 = 0x4d_a3e1;
+/// Longest value of an extension tag, so that all tags fit in the first block after the payload
+const UF2_MAX_TAG_VALUE: usize = 64;
+
+/// Address and content of a segment of firmware, e.g. a loaded ELF segment
+pub type Segment<'a> = (u64, &'a [u8]);
+/// Segment of firmware read from a file, e.g. Intel HEX
+pub type OwnedSegment = (u64, Vec<u8>);
+
+/// A binary of the firmware, split keyboards have one per half
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binary {
+    pub name: String,
+    /// ELF of the release build
+    pub elf: PathBuf,
+}
+
+/// Extra arguments of the cargo commands which build the firmware
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CargoArgs {
+    /// Features to enable, e.g. `rmk/storage`
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+    /// Other arguments, passed to cargo as they are
+    pub args: Vec<String>,
+}
+
+impl CargoArgs {
+    /// Add the arguments of `[build.cargo]` of the keyboard.toml of the project, which go before these
+    pub fn with_keyboard_toml(self, project_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = project_dir.join("keyboard.toml");
+        if !path.exists() {
+            return Ok(self);
+        }
+        let config = read_keyboard_config(path)?;
+        let Some(section) = config.get("build").and_then(|b| b.get("cargo")) else {
+            return Ok(self);
+        };
+        let strings = |key: &str| -> Result<Vec<String>, Box<dyn Error>> {
+            let Some(value) = section.get(key) else {
+                return Ok(Vec::new());
+            };
+            value
+                .as_array()
+                .and_then(|values| {
+                    values
+                        .iter()
+                        .map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect::<Option<Vec<String>>>()
+                })
+                .ok_or_else(|| {
+                    format!("`{}` in [build.cargo] should be an array of strings", key).into()
+                })
+        };
+        let no_default_features = match section.get("no_default_features") {
+            Some(value) => value
+                .as_bool()
+                .ok_or("`no_default_features` in [build.cargo] should be a boolean")?,
+            None => false,
+        };
+        Ok(Self {
+            features: [strings("features")?, self.features].concat(),
+            no_default_features: no_default_features || self.no_default_features,
+            args: [strings("args")?, self.args].concat(),
+        })
+    }
+
+    /// Add the rmk features which keyboard.toml of the project enables, so that they follow changes of keyboard.toml
+    ///
+    /// Cargo can't disable default features of a dependency from the command line, so features which keyboard.toml
+    /// disables must not be enabled for rmk in Cargo.toml, which is an error otherwise.
+    pub fn with_rmk_features(mut self, project_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let keyboard_toml = project_dir.join("keyboard.toml");
+        let cargo_toml = project_dir.join("Cargo.toml");
+        if !keyboard_toml.exists() || !cargo_toml.exists() {
+            return Ok(self);
+        }
+        let manifest: toml::Table = toml::from_str(&fs::read_to_string(&cargo_toml)?)?;
+        // The features of workspace dependencies are in the root Cargo.toml
+        let Some(rmk) = manifest
+            .get("dependencies")
+            .and_then(|d| d.get("rmk"))
+            .filter(|rmk| rmk.get("workspace").is_none())
+        else {
+            return Ok(self);
+        };
+        let switches = config_feature_switches(&read_keyboard_config(keyboard_toml)?)
+            .or_fail(FailureKind::Config)?;
+        let (disabled, enabled) = derive_rmk_features(switches);
+
+        let default_features = rmk
+            .get("default-features")
+            .and_then(|d| d.as_bool())
+            .unwrap_or(true);
+        let features: Vec<&str> = rmk
+            .get("features")
+            .and_then(|f| f.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|f| f.as_str())
+            .collect();
+        // Disabled features are default features, which are on unless default-features is false
+        let conflicts: Vec<&str> = disabled
+            .iter()
+            .map(|f| f.as_str())
+            .filter(|f| default_features || features.contains(f))
+            .collect();
+        if !conflicts.is_empty() {
+            let fix = if default_features {
+                "set `default-features = false` for rmk and list the other default features"
+            } else {
+                "remove them from the features of rmk"
+            };
+            return Err(format!(
+                "keyboard.toml disables the rmk features {}, but Cargo.toml enables them, {}",
+                conflicts.join(", "),
+                fix
+            ))
+            .or_fail(FailureKind::Config);
+        }
+
+        for feature in enabled {
+            if !features.contains(&feature.as_str()) {
+                self.features.push(format!("rmk/{}", feature));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Arguments of cargo, after the subcommand
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+        args.extend(self.args.iter().cloned());
+        args
+    }
+}
+
+/// Build the firmware of the project in release mode, and get its binaries
+pub fn build(
+    project_dir: &Path,
+    cargo_args: &CargoArgs,
+    reporter: &mut dyn Reporter,
+) -> Result<Vec<Binary>, Box<dyn Error>> {
+    if !project_dir.join("Cargo.toml").exists() {
+        return Err(format!("No Cargo.toml found in {}", project_dir.display()).into());
+    }
+
+    check_target(project_dir, reporter)?;
+    reporter.report(Event::Build("Building firmware...".to_string()));
+    cargo_with(project_dir, &["build", "--release"], cargo_args)?;
+
+    let metadata = tracing::info_span!("cargo", command = "metadata")
+        .in_scope(|| MetadataCommand::new().current_dir(project_dir).exec())
+        .or_fail(FailureKind::Toolchain)?;
+    let release_dir = release_dir(project_dir, metadata.target_directory.as_std_path());
+    Ok(metadata
+        .root_package()
+        .ok_or("No package found in Cargo.toml")?
+        .targets
+        .iter()
+        .filter(|t| t.is_bin())
+        .map(|t| Binary {
+            name: t.name.clone(),
+            elf: release_dir.join(&t.name),
+        })
+        .collect())
+}
+
+/// Check that the Rust target the project is built for is installed, and is the target of its chip
+///
+/// A missing target is installed with rustup if the reporter agrees. Only chips with a target in the chip database
+/// are checked, and nothing is checked without rustup.
+pub fn check_target(project_dir: &Path, reporter: &mut dyn Reporter) -> Result<(), Box<dyn Error>> {
+    let Some(chip) = project_chip(project_dir) else {
+        return Ok(());
+    };
+    let Some(chip_target) = get_chip_target(&chip) else {
+        return Ok(());
+    };
+    let config = cargo_config(project_dir).ok();
+    let target = match build_target(config.as_ref()) {
+        Some(target) if target != chip_target.target => {
+            reporter.report(Event::Warning(format!(
+                ".cargo/config.toml builds for {}, but the target of {} is {}",
+                target, chip, chip_target.target
+            )));
+            target
+        }
+        _ => &chip_target.target,
+    };
+
+    let Ok(output) = Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .current_dir(project_dir)
+        .output()
+    else {
+        return Ok(());
+    };
+    let installed = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || installed.lines().any(|t| t.trim() == target) {
+        return Ok(());
+    }
+    if !reporter.install_target(target)? {
+        return Err(format!(
+            "Compilation target {} isn't installed, install it with `rustup target add {}`",
+            target, target
+        ))
+        .or_fail(FailureKind::Toolchain);
+    }
+    reporter.report(Event::Generate(format!(
+        "Installing compilation target {}...",
+        target
+    )));
+    let status = interrupt::status(Command::new("rustup").args(["target", "add", target]))
+        .or_fail(FailureKind::Toolchain)?;
+    if !status.success() {
+        return Err(format!("Failed to install compilation target {}", target))
+            .or_fail(FailureKind::Toolchain);
+    }
+    Ok(())
+}
+
+/// Get the chip of the project from the chip, or the board, of keyboard.toml
+fn project_chip(project_dir: &Path) -> Option<String> {
+    let config = read_keyboard_config(project_dir.join("keyboard.toml")).ok()?;
+    let keyboard = config.get("keyboard")?;
+    let chip = keyboard.get("chip").and_then(|c| c.as_str()).or_else(|| {
+        let board = keyboard.get("board")?.as_str()?;
+        get_board_chip_map().get(board).copied()
+    })?;
+    Some(chip.to_string())
+}
+
+/// Flash a binary with probe-rs, the chip is taken from the runner of `.cargo/config.toml`
+pub fn flash(
+    project_dir: &Path,
+    binary: &Binary,
+    reporter: &mut dyn Reporter,
+) -> Result<(), Box<dyn Error>> {
+    let probe_rs_chip = probe_rs_chip(project_dir)?;
+    reporter.report(Event::Flash(format!("Flashing {}...", binary.name)));
+    let status = interrupt::status(
+        Command::new("probe-rs")
+            .args(["download", "--chip", &probe_rs_chip])
+            .arg(&binary.elf),
+    )
+    .map_err(|e| format!("Failed to run probe-rs, is it installed? {}", e))
+    .or_fail(FailureKind::Toolchain)?;
+    if !status.success() {
+        return Err(format!("Failed to flash {}", binary.name)).or_fail(FailureKind::Flash);
+    }
+    Ok(())
+}
+
+/// Run cargo in the project with extra arguments, failing when it fails
+pub fn cargo_with(
+    project_dir: &Path,
+    args: &[&str],
+    cargo_args: &CargoArgs,
+) -> Result<(), Box<dyn Error>> {
+    let extra = cargo_args.to_args();
+    let args: Vec<&str> = args
+        .iter()
+        .copied()
+        .chain(extra.iter().map(|a| a.as_str()))
+        .collect();
+    cargo(project_dir, &args)
+}
+
+/// Run cargo in the project, failing when it fails
+pub fn cargo(project_dir: &Path, args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let status = tracing::info_span!("cargo", command = args[0])
+        .in_scope(|| interrupt::status(Command::new("cargo").args(args).current_dir(project_dir)))
+        .or_fail(FailureKind::Toolchain)?;
+    if !status.success() {
+        return Err(format!("cargo {} failed", args[0])).or_fail(FailureKind::Build);
+    }
+    Ok(())
+}
+
+/// Read `.cargo/config.toml` of the project
+fn cargo_config(project_dir: &Path) -> Result<toml::Table, Box<dyn Error>> {
+    let path = project_dir.join(".cargo").join("config.toml");
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(toml::from_str(&content)?)
+}
+
+/// Get the chip of the probe-rs runner, `--chip` of `runner` in a `[target]` table of `.cargo/config.toml`
+pub fn probe_rs_chip(project_dir: &Path) -> Result<String, Box<dyn Error>> {
+    let config = cargo_config(project_dir)?;
+    config
+        .get("target")
+        .and_then(|t| t.as_table())
+        .into_iter()
+        .flat_map(|targets| targets.values())
+        .filter_map(|target| target.get("runner").and_then(|r| r.as_str()))
+        .filter(|runner| runner.starts_with("probe-rs"))
+        .find_map(|runner| {
+            let mut args = runner.split_whitespace();
+            args.find(|a| *a == "--chip")?;
+            args.next().map(|chip| chip.to_string())
+        })
+        .ok_or_else(|| {
+            "The runner in .cargo/config.toml isn't probe-rs, flash the halves one by one with --bin".into()
+        })
+}
+
+/// Directory of the release build, which is in a subdirectory of the build target if there is one
+pub fn release_dir(project_dir: &Path, target_dir: &Path) -> PathBuf {
+    let config = cargo_config(project_dir).ok();
+    match build_target(config.as_ref()) {
+        Some(build_target) => target_dir.join(build_target).join("release"),
+        None => target_dir.join("release"),
+    }
+}
+
+/// Get `target` of `[build]` in `.cargo/config.toml`
+fn build_target(config: Option<&toml::Table>) -> Option<&str> {
+    config?.get("build")?.get("target")?.as_str()
+}
+
+/// Get the flash image of an ELF, the loaded segments at their load addresses with gaps filled with 0xff
+///
+/// Returns the address of the image and the image.
+pub fn flash_image(elf: &[u8]) -> Result<(u64, Vec<u8>), Box<dyn Error>> {
+    let segments = match FileKind::parse(elf)? {
+        FileKind::Elf32 => load_segments::<object::elf::FileHeader32<Endianness>>(elf)?,
+        FileKind::Elf64 => load_segments::<object::elf::FileHeader64<Endianness>>(elf)?,
+        _ => return Err("The firmware is not an ELF file".into()),
+    };
+    segments_image(segments)
+}
+
+/// Get the flash image of segments, with gaps filled with 0xff
+///
+/// Returns the address of the image and the image.
+pub fn segments_image(mut segments: Vec<Segment<'_>>) -> Result<(u64, Vec<u8>), Box<dyn Error>> {
+    segments.sort_by_key(|(address, _)| *address);
+    let (Some((base, _)), Some(end)) = (
+        segments.first(),
+        segments.iter().map(|(a, d)| a + d.len() as u64).max(),
+    ) else {
+        return Err("The firmware has no loadable segments".into());
+    };
+    let base = *base;
+    if end - base > MAX_IMAGE_SIZE {
+        return Err(format!(
+            "The loadable segments span {:#x}..{:#x}, check the memory.x of the project",
+            base, end
+        )
+        .into());
+    }
+    let mut image = vec![0xff; (end - base) as usize];
+    for (address, data) in segments {
+        let offset = (address - base) as usize;
+        image[offset..offset + data.len()].copy_from_slice(data);
+    }
+    Ok((base, image))
+}
+
+/// Encode an image as Intel HEX, with extended linear address records for addresses above 64 KiB
+pub fn intel_hex(base: u64, image: &[u8]) -> Vec<u8> {
+    let record = |address: u16, kind: u8, data: &[u8]| {
+        let mut bytes = vec![data.len() as u8, (address >> 8) as u8, address as u8, kind];
+        bytes.extend_from_slice(data);
+        let checksum = bytes
+            .iter()
+            .fold(0u8, |sum, b| sum.wrapping_add(*b))
+            .wrapping_neg();
+        bytes.push(checksum);
+        let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        format!(":{}\n", hex)
+    };
+    let mut hex = String::new();
+    let mut upper = None;
+    for (i, chunk) in image.chunks(16).enumerate() {
+        let address = base + i as u64 * 16;
+        let high = (address >> 16) as u16;
+        if upper != Some(high) {
+            hex.push_str(&record(0, 0x04, &high.to_be_bytes()));
+            upper = Some(high);
+        }
+        hex.push_str(&record(address as u16, 0x00, chunk));
+    }
+    hex.push_str(&record(0, 0x01, &[]));
+    hex.into_bytes()
+}
+
+/// Read the data of Intel HEX, as contiguous segments in the order of the file
+///
+/// Extended segment and extended linear address records are supported, start address records are ignored.
+pub fn read_intel_hex(hex: &str) -> Result<Vec<OwnedSegment>, Box<dyn Error>> {
+    let mut segments: Vec<OwnedSegment> = Vec::new();
+    for_each_intel_hex(hex.as_bytes(), |address, data| {
+        match segments.last_mut() {
+            Some((start, content)) if *start + content.len() as u64 == address => {
+                content.extend_from_slice(data)
+            }
+            _ => segments.push((address, data.to_vec())),
+        }
+        Ok(())
+    })?;
+    Ok(segments)
+}
+
+/// Stream the data records of Intel HEX, with their address, to `on_data` line by line
+pub fn for_each_intel_hex(
+    hex: impl BufRead,
+    mut on_data: impl FnMut(u64, &[u8]) -> io::Result<()>,
+) -> Result<(), Box<dyn Error>> {
+    let mut offset = 0u64;
+    for (i, line) in hex.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |message: &str| format!("{} in line {} of the Intel HEX", message, i + 1);
+        let record = line
+            .trim()
+            .strip_prefix(':')
+            .filter(|r| r.len() >= 10 && r.len() % 2 == 0)
+            .ok_or_else(|| invalid("Invalid record"))?;
+        let bytes = (0..record.len())
+            .step_by(2)
+            .map(|n| u8::from_str_radix(&record[n..n + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid("Invalid hex digit"))?;
+        if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err(invalid("Invalid checksum").into());
+        }
+        let (length, kind) = (bytes[0] as usize, bytes[3]);
+        if bytes.len() != length + 5 {
+            return Err(invalid("Invalid record length").into());
+        }
+        let data = &bytes[4..4 + length];
+        match kind {
+            0x00 => on_data(
+                offset + u64::from(u16::from_be_bytes([bytes[1], bytes[2]])),
+                data,
+            )?,
+            0x01 => break,
+            0x02 if length == 2 => offset = u64::from(u16::from_be_bytes([data[0], data[1]])) << 4,
+            0x04 if length == 2 => offset = u64::from(u16::from_be_bytes([data[0], data[1]])) << 16,
+            0x03 | 0x05 => {}
+            _ => return Err(invalid("Invalid record type").into()),
+        }
+    }
+    Ok(())
+}
+
+/// Get the loaded segments of an ELF by their physical address, which is in flash for initialized RAM data
+fn load_segments<Elf: FileHeader<Endian = Endianness>>(
+    data: &[u8],
+) -> Result<Vec<Segment<'_>>, Box<dyn Error>> {
+    let elf = ElfFile::<Elf>::parse(data)?;
+    let endian = elf.endian();
+    let mut segments = Vec::new();
+    for header in elf.elf_program_headers() {
+        if header.p_type(endian) != PT_LOAD || header.p_filesz(endian).into() == 0 {
+            continue;
+        }
+        let segment = header
+            .data(endian, data)
+            .map_err(|_| "Invalid segment in the firmware")?;
+        segments.push((header.p_paddr(endian).into(), segment));
+    }
+    Ok(segments)
+}
+
+/// Extension tags of a UF2 file, which tell bootloaders and updaters what the firmware is
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Uf2Tags {
+    /// Version of the firmware
+    pub version: Option<String>,
+    /// Board, or chip, the firmware is built for
+    pub board: Option<String>,
+    /// Build date of the firmware
+    pub date: Option<String>,
+}
+
+impl Uf2Tags {
+    /// Encode the tags, each is its size, a 24-bit tag and the value padded to 4 bytes, followed by an empty tag
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for (tag, value) in self.values() {
+            let Some(value) = value else {
+                continue;
+            };
+            let mut end = value.len().min(UF2_MAX_TAG_VALUE);
+            while !value.is_char_boundary(end) {
+                end -= 1;
+            }
+            bytes.push((4 + end) as u8);
+            bytes.extend_from_slice(&tag.to_le_bytes()[..3]);
+            bytes.extend_from_slice(&value.as_bytes()[..end]);
+            bytes.resize(bytes.len().next_multiple_of(4), 0);
+        }
+        if !bytes.is_empty() {
+            bytes.extend_from_slice(&[0; 4]);
+        }
+        bytes
+    }
+
+    /// Decode the tags after the payload of a block, unknown tags are skipped
+    pub fn decode(mut data: &[u8]) -> Self {
+        let mut tags = Uf2Tags::default();
+        while data.len() >= 4 {
+            let size = data[0] as usize;
+            if size < 4 || size > data.len() {
+                break;
+            }
+            let tag = u32::from_le_bytes([data[1], data[2], data[3], 0]);
+            let value = String::from_utf8_lossy(&data[4..size]).into_owned();
+            match tag {
+                UF2_TAG_VERSION => tags.version = Some(value),
+                UF2_TAG_DESCRIPTION => tags.board = Some(value),
+                UF2_TAG_BUILD_DATE => tags.date = Some(value),
+                _ => {}
+            }
+            data = &data[size.next_multiple_of(4).min(data.len())..];
+        }
+        tags
+    }
+
+    fn values(&self) -> [(u32, &Option<String>); 3] {
+        [
+            (UF2_TAG_VERSION, &self.version),
+            (UF2_TAG_DESCRIPTION, &self.board),
+            (UF2_TAG_BUILD_DATE, &self.date),
+        ]
+    }
+}
+
+/// Encode an image as UF2, the format of drag-and-drop bootloaders
+///
+/// The extension tags are written into the first block.
+pub fn uf2(base: u64, image: &[u8], family: u32, tags: &Uf2Tags) -> Vec<u8> {
+    let mut counter = Uf2Writer::count();
+    let _ = counter.write(base, image);
+    let total = counter.finish().unwrap_or_default();
+    let mut writer = Uf2Writer::new(
+        Vec::with_capacity(total as usize * UF2_BLOCK),
+        family,
+        tags,
+        total,
+    );
+    // Writing to a Vec doesn't fail
+    let _ = writer.write(base, image);
+    let _ = writer.finish();
+    writer.into_inner()
+}
+
+/// Stream firmware data as UF2 to `writer`, reporting the written blocks as [`Event::Progress`]
+///
+/// `data` passes the data with its address to its callback, in ascending address order. It's called twice, to count
+/// the blocks and to write them, so that large images are never in memory as a whole.
+pub fn write_uf2<W: Write>(
+    writer: W,
+    family: u32,
+    tags: &Uf2Tags,
+    mut data: impl FnMut(&mut dyn FnMut(u64, &[u8]) -> io::Result<()>) -> Result<(), Box<dyn Error>>,
+    reporter: &mut dyn Reporter,
+) -> Result<W, Box<dyn Error>> {
+    let mut counter = Uf2Writer::count();
+    data(&mut |address, bytes| counter.write(address, bytes))?;
+    let total = counter.finish()?;
+
+    let mut writer = Uf2Writer::new(writer, family, tags, total);
+    let mut reported = 0;
+    data(&mut |address, bytes| {
+        writer.write(address, bytes)?;
+        if writer.written >= reported + UF2_PROGRESS_BLOCKS {
+            reported = writer.written;
+            reporter.report(Event::Progress {
+                step: "Writing UF2 blocks".to_string(),
+                done: u64::from(reported),
+                total: u64::from(total),
+            });
+        }
+        Ok(())
+    })?;
+    writer.finish()?;
+    reporter.report(Event::Progress {
+        step: "Writing UF2 blocks".to_string(),
+        done: u64::from(total),
+        total: u64::from(total),
+    });
+    Ok(writer.into_inner())
+}
+
+/// Writes data as UF2 blocks while it's streamed in, only the block being filled is kept in memory
+///
+/// Blocks are aligned to 256 bytes, like uf2conv.py does, the rest of a partly filled block is zero. Every block has
+/// the total number of blocks, which is taken from a first pass with [`Uf2Writer::count`].
+pub struct Uf2Writer<W: Write> {
+    writer: W,
+    family: u32,
+    tags: Vec<u8>,
+    total: u32,
+    /// Number of blocks written so far
+    written: u32,
+    /// Address and payload of the block being filled
+    block: Option<(u64, [u8; UF2_PAYLOAD])>,
+}
+
+impl Uf2Writer<io::Sink> {
+    /// Writer which only counts the blocks, [`Uf2Writer::finish`] returns the total
+    pub fn count() -> Self {
+        Uf2Writer::new(io::sink(), 0, &Uf2Tags::default(), 0)
+    }
+}
+
+impl<W: Write> Uf2Writer<W> {
+    pub fn new(writer: W, family: u32, tags: &Uf2Tags, total: u32) -> Self {
+        Uf2Writer {
+            writer,
+            family,
+            tags: tags.encode(),
+            total,
+            written: 0,
+            block: None,
+        }
+    }
+
+    /// Write data at `address`, which must not be below the data written before
+    pub fn write(&mut self, mut address: u64, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let block_address = address & !(UF2_PAYLOAD as u64 - 1);
+            if self.block.is_some_and(|(a, _)| a != block_address) {
+                self.write_block()?;
+            }
+            let (_, payload) = self.block.get_or_insert((block_address, [0; UF2_PAYLOAD]));
+            let offset = (address - block_address) as usize;
+            let length = data.len().min(UF2_PAYLOAD - offset);
+            payload[offset..offset + length].copy_from_slice(&data[..length]);
+            address += length as u64;
+            data = &data[length..];
+        }
+        Ok(())
+    }
+
+    /// Write the last block, returning the number of blocks
+    pub fn finish(&mut self) -> io::Result<u32> {
+        if self.block.is_some() {
+            self.write_block()?;
+        }
+        self.writer.flush()?;
+        Ok(self.written)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        let Some((address, payload)) = self.block.take() else {
+            return Ok(());
+        };
+        // The extension tags are written into the first block
+        let tags: &[u8] = if self.written == 0 { &self.tags } else { &[] };
+        let flags = if tags.is_empty() {
+            UF2_FLAG_FAMILY_ID
+        } else {
+            UF2_FLAG_FAMILY_ID | UF2_FLAG_EXTENSION_TAGS
+        };
+        let header = [
+            UF2_MAGIC_START[0],
+            UF2_MAGIC_START[1],
+            flags,
+            address as u32,
+            UF2_PAYLOAD as u32,
+            self.written,
+            self.total,
+            self.family,
+        ];
+        let mut block = Vec::with_capacity(UF2_BLOCK);
+        for word in header {
+            block.extend_from_slice(&word.to_le_bytes());
+        }
+        let mut data = [0u8; 476];
+        data[..UF2_PAYLOAD].copy_from_slice(&payload);
+        data[UF2_PAYLOAD..UF2_PAYLOAD + tags.len()].copy_from_slice(tags);
+        block.extend_from_slice(&data);
+        block.extend_from_slice(&UF2_MAGIC_END.to_le_bytes());
+        self.writer.write_all(&block)?;
+        self.written += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn cargo_args_of_keyboard_toml_come_first() {
+        let dir = env::temp_dir().join(format!("rmkit-cargo-args-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("keyboard.toml"),
+            "[keyboard]\nname = \"kb\"\n\n[build.cargo]\nfeatures = [\"rmk/storage\"]\nargs = [\"--locked\"]\n",
+        )
+        .unwrap();
+        let cli = CargoArgs {
+            features: vec!["rmk/defmt".to_string()],
+            no_default_features: true,
+            args: vec!["-v".to_string()],
+        };
+        let args = cli.with_keyboard_toml(&dir).unwrap();
+        assert_eq!(
+            args.to_args(),
+            [
+                "--features",
+                "rmk/storage,rmk/defmt",
+                "--no-default-features",
+                "--locked",
+                "-v"
+            ]
+        );
+
+        fs::write(
+            dir.join("keyboard.toml"),
+            "[build.cargo]\nfeatures = \"rmk/storage\"\n",
+        )
+        .unwrap();
+        assert!(CargoArgs::default().with_keyboard_toml(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(CargoArgs::default().to_args().is_empty());
+    }
+
+    #[test]
+    fn rmk_features_follow_keyboard_toml() {
+        let dir = env::temp_dir().join(format!("rmkit-rmk-features-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |keyboard_toml: &str, rmk: &str| {
+            fs::write(dir.join("keyboard.toml"), keyboard_toml).unwrap();
+            fs::write(
+                dir.join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"kb\"\n\n[dependencies]\nrmk = {}\n",
+                    rmk
+                ),
+            )
+            .unwrap();
+        };
+        let features = || CargoArgs::default().with_rmk_features(&dir);
+
+        // A light pin added after the project was created enables the controller feature
+        write("[light.capslock]\npin = \"PIN_0\"\n", "\"0.8\"");
+        assert_eq!(features().unwrap().features, ["rmk/controller"]);
+
+        write(
+            "[light.capslock]\npin = \"PIN_0\"\n",
+            "{ version = \"0.8\", features = [\"controller\"] }",
+        );
+        assert!(features().unwrap().features.is_empty());
+
+        // Storage is disabled, but still enabled by the default features or the features of rmk
+        write("[storage]\nenabled = false\n", "\"0.8\"");
+        let error = features().unwrap_err().to_string();
+        assert!(error.contains("storage"), "{}", error);
+        write(
+            "[storage]\nenabled = false\n",
+            "{ version = \"0.8\", default-features = false, features = [\"storage\", \"col2row\"] }",
+        );
+        assert!(features().is_err());
+        write(
+            "[storage]\nenabled = false\n[host]\nvial_enabled = false\n[dependency]\ndefmt_log = false\n",
+            "{ version = \"0.8\", default-features = false, features = [\"col2row\"] }",
+        );
+        assert!(features().unwrap().features.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn intel_hex_is_read_back() {
+        let image: Vec<u8> = (0..=255).cycle().take(0x300).collect();
+        // Crosses a 64 KiB boundary, which needs another extended linear address record
+        let base = 0x1000_ff00;
+        let hex = String::from_utf8(intel_hex(base, &image)).unwrap();
+        assert_eq!(read_intel_hex(&hex).unwrap(), [(base, image)]);
+    }
+
+    #[test]
+    fn intel_hex_gaps_are_segments() {
+        let hex = ":020000040001F9\n:0400000001020304F2\n:02001000AABB89\n:00000001FF\n";
+        let segments = read_intel_hex(hex).unwrap();
+        assert_eq!(
+            segments,
+            [(0x10000, vec![1, 2, 3, 4]), (0x10010, vec![0xaa, 0xbb])]
+        );
+        let segments = segments.iter().map(|(a, d)| (*a, d.as_slice())).collect();
+        let (base, image) = segments_image(segments).unwrap();
+        assert_eq!(base, 0x10000);
+        assert_eq!(image.len(), 0x12);
+        assert_eq!(image[4..0x10], [0xff; 12]);
+    }
+
+    #[test]
+    fn invalid_intel_hex_is_rejected() {
+        // Wrong checksum
+        assert!(read_intel_hex(":0400000001020304F3\n").is_err());
+        assert!(read_intel_hex("0400000001020304F2\n").is_err());
+        assert!(read_intel_hex(":04000000010203F2\n").is_err());
+        assert!(read_intel_hex(":0400000001020304ZZ\n").is_err());
+    }
+
+    #[test]
+    fn uf2_is_streamed_in_aligned_blocks() {
+        struct Progress(Vec<(u64, u64)>);
+        impl Reporter for Progress {
+            fn report(&mut self, event: Event) {
+                if let Event::Progress { done, total, .. } = event {
+                    self.0.push((done, total));
+                }
+            }
+        }
+
+        // Data 3 KiB apart is written as separate blocks, the gap isn't filled
+        let hex = ":020000040001F9\n:0400000001020304F2\n:02100000AABB89\n:00000001FF\n";
+        let mut progress = Progress(Vec::new());
+        let data = write_uf2(
+            Vec::new(),
+            0xada5_2840,
+            &Uf2Tags::default(),
+            |on_data| for_each_intel_hex(hex.as_bytes(), &mut *on_data),
+            &mut progress,
+        )
+        .unwrap();
+        assert_eq!(data.len(), 2 * UF2_BLOCK);
+        let word = |block: usize, n: usize| {
+            let start = block * UF2_BLOCK + n * 4;
+            u32::from_le_bytes(data[start..start + 4].try_into().unwrap())
+        };
+        assert_eq!((word(0, 3), word(0, 5), word(0, 6)), (0x10000, 0, 2));
+        assert_eq!((word(1, 3), word(1, 5), word(1, 6)), (0x11000, 1, 2));
+        assert_eq!(data[32..36], [1, 2, 3, 4]);
+        assert_eq!(progress.0.last(), Some(&(2, 2)));
+
+        // Unaligned data starts in the middle of its block
+        let data = uf2(0x1080, &[0x55; 256], 0xada5_2840, &Uf2Tags::default());
+        assert_eq!(data.len(), 2 * UF2_BLOCK);
+        assert_eq!(data[32 + 0x7f..32 + 0x81], [0, 0x55]);
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+
+/// Temporary files and directories which are removed on interrupt
+static TEMP_PATHS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+/// Running child processes which are terminated on interrupt
+static CHILDREN: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// Temporary file or directory, removed when it's dropped or on [`cleanup`]
+pub struct TempPath {
+    path: PathBuf,
+}
+
+impl TempPath {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        TEMP_PATHS.lock().unwrap().push(path.clone());
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keep the file, it's not removed anymore
+    pub fn keep(self) -> PathBuf {
+        unregister(&TEMP_PATHS, &self.path);
+        let path = self.path.clone();
+        std::mem::forget(self);
+        path
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        unregister(&TEMP_PATHS, &self.path);
+        remove_path(&self.path);
+    }
+}
+
+fn unregister<T: PartialEq>(registry: &Mutex<Vec<T>>, item: &T) {
+    registry.lock().unwrap().retain(|i| i != item);
+}
+
+fn remove_path(path: &Path) {
+    let _ = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+}
+
+/// Run a command and collect its output, like [`Command::output`], the command is terminated on interrupt
+pub fn output(cmd: &mut Command) -> io::Result<Output> {
+    let child = own_process_group(cmd)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let pid = child.id();
+    CHILDREN.lock().unwrap().push(pid);
+    let output = child.wait_with_output();
+    unregister(&CHILDREN, &pid);
+    output
+}
+
+/// Run a command, like [`Command::status`], the command is terminated on interrupt
+pub fn status(cmd: &mut Command) -> io::Result<ExitStatus> {
+    let mut child = own_process_group(cmd).spawn()?;
+    let pid = child.id();
+    CHILDREN.lock().unwrap().push(pid);
+    let status = child.wait();
+    unregister(&CHILDREN, &pid);
+    status
+}
+
+/// Run a command and pass each line of its stdout to `on_line` while it runs, the command is terminated on interrupt
+pub fn lines(cmd: &mut Command, mut on_line: impl FnMut(&str)) -> io::Result<ExitStatus> {
+    let mut child = own_process_group(cmd).stdout(Stdio::piped()).spawn()?;
+    let pid = child.id();
+    CHILDREN.lock().unwrap().push(pid);
+    let read = match child.stdout.take() {
+        Some(stdout) => BufReader::new(stdout)
+            .lines()
+            .try_for_each(|line| line.map(|line| on_line(&line))),
+        None => Ok(()),
+    };
+    let status = child.wait();
+    unregister(&CHILDREN, &pid);
+    read?;
+    status
+}
+
+/// Put the command in its own process group, so that its whole process tree can be terminated
+#[cfg(unix)]
+fn own_process_group(cmd: &mut Command) -> &mut Command {
+    use std::os::unix::process::CommandExt;
+    cmd.process_group(0)
+}
+
+#[cfg(not(unix))]
+fn own_process_group(cmd: &mut Command) -> &mut Command {
+    cmd
+}
+
+/// Terminate the running child processes and remove the temporary files
+///
+/// The library doesn't handle signals, programs call it from their Ctrl-C handler.
+pub fn cleanup() {
+    for pid in CHILDREN.lock().unwrap().drain(..) {
+        terminate(pid);
+    }
+    for path in TEMP_PATHS.lock().unwrap().drain(..) {
+        remove_path(&path);
+    }
+}
+
+#[cfg(unix)]
+fn terminate(pid: u32) {
+    // The child leads its own process group, a negative pid signals the whole group
+    // SAFETY: `kill` only sends a signal, it has no memory safety requirements
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGTERM);
+    }
+}
+
+#[cfg(not(unix))]
+fn terminate(_pid: u32) {
+    // Children share the console, so they receive the Ctrl-C themselves
+}
@@ -0,0 +1,924 @@
+use crate::chip;
+use crate::error::{Classify, FailureKind};
+use crate::interrupt::TempPath;
+use rmk_config::KeyboardTomlConfig;
+use std::{
+    env,
+    error::Error,
+    fs, panic,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Key of matrix positions without a switch
+pub const NO_KEY: &str = "No";
+
+/// All info needed to create a RMK project
+#[derive(Debug)]
+pub struct ProjectInfo {
+    /// Project name
+    pub project_name: String,
+    /// Local directory of created RMK project
+    pub target_dir: PathBuf,
+    /// Remote folder name which contains the template
+    pub remote_folder: String,
+    /// Whether the keyboard is split
+    pub split: bool,
+    /// Chip name
+    pub chip: String,
+    /// Key for uf2 generation
+    pub uf2_key: String,
+    /// Board name, if the keyboard uses a board
+    pub board: Option<String>,
+    /// List of disabled default features
+    pub disabled_default_feature: Vec<String>,
+    /// List of enabled non-default features
+    pub enabled_feature: Vec<String>,
+}
+
+/// Parse `keyboard.toml`, get all needed project info for creating a new RMK project
+///
+/// Invalid configs are errors, nothing is written except a temporary TOML copy of configs which rmk-config
+/// can't read directly.
+#[tracing::instrument(name = "parse", skip_all)]
+pub fn parse_keyboard_toml(
+    keyboard_toml: &str,
+    target_dir: Option<String>,
+) -> Result<ProjectInfo, Box<dyn std::error::Error>> {
+    let config = read_keyboard_config(keyboard_toml)?;
+    check_chip_model(&config).or_fail(FailureKind::Config)?;
+    let toml_path = keyboard_toml_path(keyboard_toml)?;
+    let keyboard_toml_config = load_rmk_config(toml_path.path()).or_fail(FailureKind::Config)?;
+    drop(toml_path);
+
+    let project_name = sanitize_project_name(&keyboard_toml_config.get_device_config().name);
+    let target_dir = if let Some(dir) = target_dir {
+        dir
+    } else {
+        project_name.clone()
+    };
+    let project_dir = env::current_dir()?.join(&target_dir);
+
+    // Check keyboard.toml
+    let light_config = keyboard_toml_config.get_light_config();
+    let row2col = diode_row2col(&config).or_fail(FailureKind::Config)?;
+    let (disabled_default_feature, enabled_feature) = derive_rmk_features(FeatureSwitches {
+        storage: keyboard_toml_config.get_storage_config().enabled,
+        defmt: keyboard_toml_config.get_dependency_config().defmt_log,
+        vial: keyboard_toml_config.get_host_config().vial_enabled,
+        light: light_config.capslock.is_some()
+            || light_config.scrolllock.is_some()
+            || light_config.numslock.is_some(),
+        row2col,
+    });
+
+    let board_config = keyboard_toml_config
+        .get_board_config()
+        .or_fail(FailureKind::Config)?;
+    let split = matches!(board_config, rmk_config::BoardConfig::Split(_));
+
+    let chip_model = keyboard_toml_config
+        .get_chip_model()
+        .or_fail(FailureKind::Config)?;
+    let uf2_key = uf2_key(&chip_model.chip);
+    let board = chip_model.board.clone();
+    let chip_or_board = if let Some(board) = chip_model.board {
+        board
+    } else {
+        chip_model.chip
+    };
+
+    Ok(ProjectInfo {
+        project_name,
+        target_dir: project_dir,
+        remote_folder: template_folder(&chip_or_board, split),
+        split,
+        chip: chip_or_board,
+        uf2_key,
+        board,
+        disabled_default_feature,
+        enabled_feature,
+    })
+}
+
+/// Check `board` or `chip` of `[keyboard]` against the chip database
+fn check_chip_model(config: &toml::Table) -> Result<(), Box<dyn Error>> {
+    let keyboard = config
+        .get("keyboard")
+        .and_then(|k| k.as_table())
+        .ok_or("keyboard.toml has no [keyboard] section")?;
+    let name = |key: &str| keyboard.get(key).and_then(|v| v.as_str());
+    match (name("board"), name("chip")) {
+        (Some(board), None) => chip::check_chip_name(board, true),
+        (None, Some(chip)) => chip::check_chip_name(chip, false),
+        _ => Err("Either `board` or `chip` should be set in [keyboard], but not both".into()),
+    }
+}
+
+/// Load a keyboard config in TOML format with rmk-config
+///
+/// rmk-config panics on configs which it can't parse or whose chip it doesn't support, so they're checked first.
+/// The panics of its remaining checks, e.g. of the number of morse taps, are caught.
+fn load_rmk_config(path: &Path) -> Result<KeyboardTomlConfig, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    let config: KeyboardTomlConfig = toml::from_str(&content)
+        .map_err(|e| format!("Invalid keyboard config: {}", e.message()))?;
+    config.get_chip_model()?.get_default_config_str()?;
+    panic::catch_unwind(|| KeyboardTomlConfig::new_from_toml_path(path)).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<String>()
+            .map(|s| s.as_str())
+            .or_else(|| payload.downcast_ref::<&str>().copied())
+            .unwrap_or("rmk-config can't read it");
+        format!("Invalid keyboard config: {}", message).into()
+    })
+}
+
+/// File format of a keyboard config
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Detect the config format by file extension, files without a known extension are treated as TOML
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("json") => ConfigFormat::Json,
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Toml,
+        }
+    }
+}
+
+/// Key which lists the configs a keyboard config is based on, e.g. `include = ["base.toml"]`
+const INCLUDE_KEY: &str = "include";
+
+/// Read a keyboard config in TOML, JSON or YAML format
+///
+/// Configs listed in `include` are read first, relative to the config, and the config is merged on top of them.
+pub fn read_keyboard_config<P: AsRef<Path>>(path: P) -> Result<toml::Table, Box<dyn Error>> {
+    read_with_includes(path.as_ref(), &mut Vec::new())
+}
+
+/// Read a keyboard config without resolving its includes
+fn read_single_config(path: &Path) -> Result<toml::Table, Box<dyn Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+        .or_fail(FailureKind::Config)?;
+    let mut config: toml::Table = match ConfigFormat::from_path(path) {
+        ConfigFormat::Toml => toml::from_str(&content).or_fail(FailureKind::Config)?,
+        ConfigFormat::Json => serde_json::from_str(&content).or_fail(FailureKind::Config)?,
+        ConfigFormat::Yaml => serde_yaml::from_str(&content).or_fail(FailureKind::Config)?,
+    };
+    for (_, value) in config.iter_mut() {
+        substitute_env_vars(value)
+            .map_err(|e| format!("{} in {}", e, path.display()))
+            .or_fail(FailureKind::Config)?;
+    }
+    Ok(config)
+}
+
+/// Replace `${VAR}` and `${VAR:-default}` in all strings of a config value with environment variables
+fn substitute_env_vars(value: &mut toml::Value) -> Result<(), String> {
+    match value {
+        toml::Value::String(s) => *s = substitute_env(s, |name| env::var(name).ok())?,
+        toml::Value::Array(array) => {
+            for item in array {
+                substitute_env_vars(item)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                substitute_env_vars(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replace `${VAR}` and `${VAR:-default}` in a string, `$${` is kept as a literal `${`
+fn substitute_env(s: &str, var: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            result.push_str(&rest[..start - 1]);
+            result.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed `${{` in \"{}\"", s))?;
+        let expr = &rest[start + 2..start + end];
+        let (name, default) = match expr.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expr, None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("Invalid environment variable name `{}`", name));
+        }
+        match (var(name), default) {
+            (Some(value), _) => result.push_str(&value),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) => return Err(format!("Environment variable {} is not set", name)),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Read a keyboard config and its includes, `stack` holds the configs being read to detect circular includes
+fn read_with_includes(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> Result<toml::Table, Box<dyn Error>> {
+    let mut config = read_single_config(path)?;
+    let includes = match config.remove(INCLUDE_KEY) {
+        None => return Ok(config),
+        Some(toml::Value::String(include)) => vec![include],
+        Some(toml::Value::Array(includes)) => includes
+            .into_iter()
+            .map(|i| match i {
+                toml::Value::String(include) => Ok(include),
+                other => Err(format!("Invalid include {} in {}", other, path.display())),
+            })
+            .collect::<Result<_, _>>()
+            .or_fail(FailureKind::Config)?,
+        Some(other) => {
+            return Err(format!("Invalid include {} in {}", other, path.display()))
+                .or_fail(FailureKind::Config)
+        }
+    };
+
+    let canonical = fs::canonicalize(path)?;
+    if stack.contains(&canonical) {
+        return Err(format!("Circular include of {}", path.display())).or_fail(FailureKind::Config);
+    }
+    stack.push(canonical);
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut merged = toml::Table::new();
+    for include in includes {
+        merge_config(&mut merged, read_with_includes(&dir.join(include), stack)?);
+    }
+    stack.pop();
+    merge_config(&mut merged, config);
+    Ok(merged)
+}
+
+/// Merge `overlay` into `base`, tables are merged recursively and other values, including arrays, are replaced
+fn merge_config(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_config(base_table, overlay_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Whether the config can be used as is by rmk-config: it's in TOML format, doesn't include other configs
+/// and doesn't use environment variables
+fn is_standalone_toml(path: &str) -> Result<bool, Box<dyn Error>> {
+    if ConfigFormat::from_path(path) != ConfigFormat::Toml {
+        return Ok(false);
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))
+        .or_fail(FailureKind::Config)?;
+    let config: toml::Table = toml::from_str(&content).or_fail(FailureKind::Config)?;
+    Ok(!config.contains_key(INCLUDE_KEY) && !config.values().any(uses_env_vars))
+}
+
+/// Whether any string of a config value uses environment variables, `${` in comments doesn't count
+fn uses_env_vars(value: &toml::Value) -> bool {
+    match value {
+        toml::Value::String(s) => s.contains("${"),
+        toml::Value::Array(array) => array.iter().any(uses_env_vars),
+        toml::Value::Table(table) => table.values().any(uses_env_vars),
+        _ => false,
+    }
+}
+
+/// Write a keyboard config, the format is decided by the extension of `path`
+pub fn write_keyboard_config<P: AsRef<Path>>(
+    path: P,
+    config: &toml::Table,
+) -> Result<(), Box<dyn Error>> {
+    let content = match ConfigFormat::from_path(&path) {
+        ConfigFormat::Toml => toml::to_string(config)?,
+        ConfigFormat::Json => serde_json::to_string_pretty(config)?,
+        ConfigFormat::Yaml => serde_yaml::to_string(config)?,
+    };
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Path of a keyboard config in TOML format, see [`keyboard_toml_path`]
+pub enum KeyboardTomlPath {
+    /// The keyboard config itself
+    Original(PathBuf),
+    /// A temporary copy of the converted config, removed when it's dropped
+    Converted(TempPath),
+}
+
+impl KeyboardTomlPath {
+    pub fn path(&self) -> &Path {
+        match self {
+            KeyboardTomlPath::Original(path) => path,
+            KeyboardTomlPath::Converted(temp) => temp.path(),
+        }
+    }
+}
+
+/// Get the path of the keyboard config in TOML format, which can be read by rmk-config
+///
+/// JSON and YAML configs, and configs with includes or environment variables, are converted to a temporary `keyboard.toml`
+pub fn keyboard_toml_path(path: &str) -> Result<KeyboardTomlPath, Box<dyn Error>> {
+    if is_standalone_toml(path)? {
+        return Ok(KeyboardTomlPath::Original(PathBuf::from(path)));
+    }
+    static CONVERTED: AtomicUsize = AtomicUsize::new(0);
+    let config = read_keyboard_config(path)?;
+    let toml_path = TempPath::new(env::temp_dir().join(format!(
+        "rmkit-{}-{}-keyboard.toml",
+        process::id(),
+        CONVERTED.fetch_add(1, Ordering::Relaxed)
+    )));
+    write_keyboard_config(toml_path.path(), &config)?;
+    Ok(KeyboardTomlPath::Converted(toml_path))
+}
+
+/// Copy the keyboard config to `dest` as `keyboard.toml`, converting it if it's not in TOML format
+///
+/// Includes are merged into the copy, so that the project doesn't depend on files outside of it
+pub fn copy_keyboard_toml<P: AsRef<Path>>(src: &str, dest: P) -> Result<(), Box<dyn Error>> {
+    if is_standalone_toml(src)? {
+        fs::copy(src, dest)?;
+    } else {
+        write_keyboard_config(dest, &read_keyboard_config(src)?)?;
+    }
+    Ok(())
+}
+
+/// Switches in `keyboard.toml` which decide the RMK feature set
+#[derive(Debug, Clone, Copy)]
+pub struct FeatureSwitches {
+    /// Whether the storage is enabled
+    pub storage: bool,
+    /// Whether defmt logging is enabled
+    pub defmt: bool,
+    /// Whether vial is enabled
+    pub vial: bool,
+    /// Whether any light pin is configured
+    pub light: bool,
+    /// Whether the diodes of the matrix are row2col
+    pub row2col: bool,
+}
+
+/// Get the disabled default features and the enabled non-default features of rmk
+pub fn derive_rmk_features(switches: FeatureSwitches) -> (Vec<String>, Vec<String>) {
+    let mut disabled_default_feature = vec![];
+    let mut enabled_feature = vec![];
+
+    if !switches.storage {
+        disabled_default_feature.push("storage".to_string());
+    }
+
+    if !switches.defmt {
+        disabled_default_feature.push("defmt".to_string());
+    }
+
+    if !switches.vial {
+        disabled_default_feature.push("vial".to_string());
+        disabled_default_feature.push("vial_lock".to_string());
+    }
+
+    // Matrix scanning is col2row by default
+    if switches.row2col {
+        disabled_default_feature.push("col2row".to_string());
+    }
+
+    // Light config requires controller feature
+    if switches.light {
+        enabled_feature.push("controller".to_string());
+    }
+
+    (disabled_default_feature, enabled_feature)
+}
+
+/// Get the feature switches of a keyboard config, settings which aren't in the config have RMK's defaults
+pub fn config_feature_switches(config: &toml::Table) -> Result<FeatureSwitches, Box<dyn Error>> {
+    let flag = |section: &str, key: &str| {
+        config
+            .get(section)
+            .and_then(|s| s.get(key))
+            .and_then(|v| v.as_bool())
+    };
+    let light = config.get("light").is_some_and(|light| {
+        ["capslock", "scrolllock", "numslock"]
+            .iter()
+            .any(|led| light.get(led).is_some())
+    });
+    Ok(FeatureSwitches {
+        storage: flag("storage", "enabled").unwrap_or(true),
+        defmt: flag("dependency", "defmt_log").unwrap_or(true),
+        vial: flag("host", "vial_enabled").unwrap_or(true),
+        light,
+        row2col: diode_row2col(config)?,
+    })
+}
+
+/// Get whether a matrix is row2col, `None` if it's not specified
+///
+/// `row2col` is the key read by rmk, without it `diode_direction` is mapped onto it.
+fn matrix_row2col(matrix: &toml::Value) -> Result<Option<bool>, Box<dyn Error>> {
+    match matrix.get("row2col") {
+        Some(row2col) => match row2col.as_bool() {
+            Some(row2col) => Ok(Some(row2col)),
+            None => Err(format!("Invalid row2col {}, it should be true or false", row2col).into()),
+        },
+        None => matrix
+            .get("diode_direction")
+            .map(diode_direction_row2col)
+            .transpose(),
+    }
+}
+
+/// Get the `row2col` which `diode_direction = "COL2ROW"` or `"ROW2COL"` maps onto
+pub fn diode_direction_row2col(direction: &toml::Value) -> Result<bool, Box<dyn Error>> {
+    match direction.as_str().map(|d| d.to_uppercase()).as_deref() {
+        Some("COL2ROW") => Ok(false),
+        Some("ROW2COL") => Ok(true),
+        _ => Err(format!(
+            "Invalid diode_direction {}, it should be \"COL2ROW\" or \"ROW2COL\"",
+            direction
+        )
+        .into()),
+    }
+}
+
+/// Get whether the diodes of the keyboard are row2col, by default they are col2row
+///
+/// All matrices of a split keyboard must have the same diode direction, because they share the rmk features.
+pub fn diode_row2col(config: &toml::Table) -> Result<bool, Box<dyn Error>> {
+    let mut row2col = None;
+    for matrix in matrices(config) {
+        match (row2col, matrix_row2col(matrix)?) {
+            (Some(a), Some(b)) if a != b => {
+                return Err("All matrices must have the same row2col".into())
+            }
+            (None, direction) => row2col = direction,
+            _ => {}
+        }
+    }
+    Ok(row2col.unwrap_or(false))
+}
+
+/// All matrix sections of a keyboard config, including the halves of a split keyboard
+fn matrices(config: &toml::Table) -> impl Iterator<Item = &toml::Value> {
+    let split = config.get("split");
+    [
+        config.get("matrix"),
+        split
+            .and_then(|s| s.get("central"))
+            .and_then(|c| c.get("matrix")),
+    ]
+    .into_iter()
+    .flatten()
+    .chain(
+        split
+            .and_then(|s| s.get("peripheral"))
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|p| p.get("matrix")),
+    )
+}
+
+/// Replace spaces in the keyboard name so that it can be used as project name
+pub fn sanitize_project_name(name: &str) -> String {
+    name.replace(" ", "_")
+}
+
+/// Get the folder name of the project template in rmk-template
+pub fn template_folder(chip_or_board: &str, split: bool) -> String {
+    if split {
+        format!("{}_split", chip_or_board)
+    } else {
+        chip_or_board.to_string()
+    }
+}
+
+/// Get the key for uf2 generation of a chip
+///
+/// STM32 chips use their series name, e.g. `stm32f4` for `stm32f411ce`. `pico_w` is a chip of its own in
+/// the chip database, used by `rmkit init`, but its firmware is flashed as `rp2040`
+pub fn uf2_key(chip: &str) -> String {
+    if chip.starts_with("stm32") {
+        chip.get(..7).unwrap_or(chip).to_string()
+    } else if chip == "pico_w" {
+        "rp2040".to_string()
+    } else {
+        chip.to_string()
+    }
+}
+
+/// Matrix pins of a keyboard, or of one half of a split keyboard
+#[derive(Debug)]
+pub struct MatrixPins {
+    /// MCU pins connected to matrix rows, in row order
+    pub row_pins: Vec<String>,
+    /// MCU pins connected to matrix columns, in column order
+    pub col_pins: Vec<String>,
+}
+
+/// Read matrix pins from `keyboard.toml`
+///
+/// For split keyboards, `peripheral` selects the peripheral half by index, `None` selects the central half
+pub fn read_matrix_pins(
+    keyboard_toml: &str,
+    peripheral: Option<usize>,
+) -> Result<MatrixPins, Box<dyn Error>> {
+    let config = read_keyboard_config(keyboard_toml)?;
+
+    let matrix = match (config.get("split"), peripheral) {
+        (Some(split), Some(i)) => split
+            .get("peripheral")
+            .and_then(|p| p.get(i))
+            .and_then(|p| p.get("matrix"))
+            .ok_or(format!("No matrix config found for split peripheral {}", i))?,
+        (Some(split), None) => split
+            .get("central")
+            .and_then(|c| c.get("matrix"))
+            .ok_or("No matrix config found for split central")?,
+        (None, Some(_)) => {
+            return Err("Peripheral is specified but the keyboard is not split".into())
+        }
+        (None, None) => config.get("matrix").ok_or("No [matrix] section found")?,
+    };
+
+    if matrix.get("matrix_type").and_then(|t| t.as_str()) == Some("direct_pin") {
+        return Err("Direct pin matrix has no row/col pins".into());
+    }
+
+    if matrix.get("row_pins").is_none() && matrix.get("input_pins").is_some() {
+        return Err("`input_pins` and `output_pins` aren't supported by rmk 0.6, run `rmkit migrate` to convert them to `row_pins` and `col_pins`".into());
+    }
+    let pins = |key: &str| -> Result<Vec<String>, Box<dyn Error>> {
+        matrix
+            .get(key)
+            .and_then(|p| p.as_array())
+            .ok_or(format!("Missing `{}` in matrix config", key))?
+            .iter()
+            .map(|p| {
+                p.as_str()
+                    .map(|s| s.to_string())
+                    .ok_or_else(|| format!("Invalid pin in `{}`: {}", key, p).into())
+            })
+            .collect()
+    };
+
+    Ok(MatrixPins {
+        row_pins: pins("row_pins")?,
+        col_pins: pins("col_pins")?,
+    })
+}
+
+/// Fill `row_pins` and `col_pins` of the `[matrix]` in `keyboard.toml` with the first pins of a board
+///
+/// The number of rows and columns of the file is kept. Returns `false` if there's no such matrix, e.g. of a split
+/// keyboard, or the board has too few pins.
+pub(crate) fn prefill_matrix_pins(
+    keyboard_toml: &Path,
+    pins: &[String],
+) -> Result<bool, Box<dyn Error>> {
+    let Ok(content) = fs::read_to_string(keyboard_toml) else {
+        return Ok(false);
+    };
+    let mut doc: toml_edit::DocumentMut = content.parse()?;
+    let Some(matrix) = doc.get_mut("matrix").and_then(|m| m.as_table_like_mut()) else {
+        return Ok(false);
+    };
+    let len = |key: &str| matrix.get(key).and_then(|p| p.as_array()).map(|p| p.len());
+    let (Some(rows), Some(cols)) = (len("row_pins"), len("col_pins")) else {
+        return Ok(false);
+    };
+    if rows + cols > pins.len() {
+        return Ok(false);
+    }
+    // Replace the values only, comments before the keys are kept
+    for (key, pins) in [
+        ("row_pins", &pins[..rows]),
+        ("col_pins", &pins[rows..rows + cols]),
+    ] {
+        if let Some(item) = matrix.get_mut(key) {
+            *item = toml_edit::value(
+                pins.iter()
+                    .map(|p| p.as_str())
+                    .collect::<toml_edit::Array>(),
+            );
+        }
+    }
+    fs::write(keyboard_toml, doc.to_string())?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::failure_kind;
+    use proptest::prelude::*;
+
+    /// Chips and boards supported by rmk-config, as (key in `[keyboard]`, name, expected uf2 key)
+    const CHIPS: &[(&str, &str, &str)] = &[
+        ("board", "nice!nano_v2", "nrf52840"),
+        ("board", "XIAO BLE", "nrf52840"),
+        ("board", "pico_w", "rp2040"),
+        ("board", "pi_pico_w", "rp2040"),
+        ("chip", "nrf52840", "nrf52840"),
+        ("chip", "nrf52832", "nrf52832"),
+        ("chip", "rp2040", "rp2040"),
+        ("chip", "stm32f411ce", "stm32f4"),
+        ("chip", "stm32h743zi", "stm32h7"),
+        ("chip", "esp32c3", "esp32c3"),
+        ("chip", "esp32s3", "esp32s3"),
+    ];
+
+    /// A generated keyboard config, with what the created project is expected to look like
+    #[derive(Debug)]
+    struct GeneratedConfig {
+        config: toml::Table,
+        format: ConfigFormat,
+        name: String,
+        key: &'static str,
+        chip: &'static str,
+        uf2_key: &'static str,
+        split: bool,
+        switches: FeatureSwitches,
+    }
+
+    fn matrix_toml(row2col: bool) -> String {
+        format!(
+            "row_pins = [\"P0_02\", \"P0_03\"]\ncol_pins = [\"P0_04\", \"P0_05\"]\nrow2col = {}\n",
+            row2col
+        )
+    }
+
+    fn split_toml(row2col: bool, serial: bool) -> String {
+        let (central_link, peripheral_link) = if serial {
+            (
+                "serial = [{ instance = \"UART0\", tx_pin = \"PIN_0\", rx_pin = \"PIN_1\" }]\n",
+                "serial = [{ instance = \"UART0\", tx_pin = \"PIN_0\", rx_pin = \"PIN_1\" }]\n",
+            )
+        } else {
+            (
+                "ble_addr = [0x18, 0xe2, 0x21, 0x80, 0xc0, 0xc7]\n",
+                "ble_addr = [0x7e, 0xfe, 0x73, 0x9e, 0x66, 0xe3]\n",
+            )
+        };
+        format!(
+            "[split]\nconnection = \"{}\"\n\n\
+             [split.central]\nrows = 2\ncols = 2\nrow_offset = 0\ncol_offset = 0\n{}\n\
+             [split.central.matrix]\n{}\n\
+             [[split.peripheral]]\nrows = 2\ncols = 2\nrow_offset = 0\ncol_offset = 2\n{}\n\
+             [split.peripheral.matrix]\n{}",
+            if serial { "serial" } else { "ble" },
+            central_link,
+            matrix_toml(row2col),
+            peripheral_link,
+            matrix_toml(row2col),
+        )
+    }
+
+    /// Keyboard configs over supported chips and boards, unibody and split keyboards, feature switches and formats
+    fn keyboard_config() -> impl Strategy<Value = GeneratedConfig> {
+        (
+            proptest::sample::select(CHIPS),
+            any::<bool>(),
+            any::<bool>(),
+            feature_switches(),
+            proptest::sample::select(vec![ConfigFormat::Toml, ConfigFormat::Json, ConfigFormat::Yaml]),
+            "[A-Za-z][A-Za-z0-9 ]{0,15}",
+        )
+            .prop_map(|((key, chip, uf2_key), split, serial, switches, format, name)| {
+                let mut content = format!(
+                    "[keyboard]\nname = \"{}\"\nvendor_id = 0x4c4b\nproduct_id = 0x4643\n{} = \"{}\"\n\n\
+                     [storage]\nenabled = {}\n\n[dependency]\ndefmt_log = {}\n\n[host]\nvial_enabled = {}\n\n",
+                    name, key, chip, switches.storage, switches.defmt, switches.vial
+                );
+                if switches.light {
+                    content.push_str("[light.capslock]\npin = \"PIN_2\"\nlow_active = true\n\n");
+                }
+                if split {
+                    content.push_str(&split_toml(switches.row2col, serial));
+                } else {
+                    content.push_str(&format!("[matrix]\n{}", matrix_toml(switches.row2col)));
+                }
+                GeneratedConfig {
+                    config: toml::from_str(&content).unwrap(),
+                    format,
+                    name,
+                    key,
+                    chip,
+                    uf2_key,
+                    split,
+                    switches,
+                }
+            })
+    }
+
+    fn feature_switches() -> impl Strategy<Value = FeatureSwitches> {
+        (
+            any::<bool>(),
+            any::<bool>(),
+            any::<bool>(),
+            any::<bool>(),
+            any::<bool>(),
+        )
+            .prop_map(|(storage, defmt, vial, light, row2col)| FeatureSwitches {
+                storage,
+                defmt,
+                vial,
+                light,
+                row2col,
+            })
+    }
+
+    #[test]
+    fn matrix_pins_are_prefilled() {
+        let path = env::temp_dir().join(format!("rmkit-prefill-{}.toml", process::id()));
+        fs::write(
+            &path,
+            "[matrix]\n# wiring\nrow_pins = [\"A\", \"B\"]\ncol_pins = [\"C\"]\n",
+        )
+        .unwrap();
+        let pins = ["P0_06", "P0_08", "P0_17", "P0_20"].map(String::from);
+        assert!(prefill_matrix_pins(&path, &pins).unwrap());
+        assert!(!prefill_matrix_pins(&path, &pins[..2]).unwrap());
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            content,
+            "[matrix]\n# wiring\nrow_pins = [\"P0_06\", \"P0_08\"]\ncol_pins = [\"P0_17\"]\n"
+        );
+    }
+
+    #[test]
+    fn env_vars_in_comments_keep_config_standalone() {
+        let path = env::temp_dir().join(format!("rmkit-standalone-{}.toml", process::id()));
+        let path_str = path.to_str().unwrap();
+        fs::write(
+            &path,
+            "# set ${NAME} to rename\n[keyboard]\nname = \"kb\"\n",
+        )
+        .unwrap();
+        assert!(is_standalone_toml(path_str).unwrap());
+        assert!(matches!(
+            keyboard_toml_path(path_str).unwrap(),
+            KeyboardTomlPath::Original(p) if p == path
+        ));
+
+        fs::write(&path, "[keyboard]\nname = \"${RMKIT_TEST_NAME:-kb}\"\n").unwrap();
+        assert!(!is_standalone_toml(path_str).unwrap());
+        let converted = keyboard_toml_path(path_str).unwrap();
+        let converted_path = converted.path().to_path_buf();
+        assert_eq!(
+            fs::read_to_string(&converted_path).unwrap(),
+            "[keyboard]\nname = \"kb\"\n"
+        );
+        drop(converted);
+        assert!(!converted_path.exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn invalid_configs_are_errors() {
+        let path = env::temp_dir().join(format!("rmkit-invalid-{}.toml", process::id()));
+        let path_str = path.to_str().unwrap();
+        for config in [
+            "[matrix]\nrow_pins = [\"PIN_0\"]\ncol_pins = [\"PIN_1\"]\n",
+            "[keyboard]\nname = \"kb\"\nchip = \"rp2040\"\n",
+            "[keyboard]\nname = \"kb\"\nvendor_id = 1\nproduct_id = 2\nchip = \"nochip\"\n",
+        ] {
+            fs::write(&path, config).unwrap();
+            let error = parse_keyboard_toml(path_str, None).unwrap_err();
+            assert_eq!(failure_kind(error.as_ref()), Some(FailureKind::Config));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn project_gets_substituted_keyboard_toml() {
+        let dir = env::temp_dir().join(format!("rmkit-substituted-{}", process::id()));
+        let project = dir.join("project");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(dir.join("base.toml"), "[keyboard]\nvendor_id = 0x4c4b\n").unwrap();
+        let keyboard_toml = dir.join("keyboard.toml");
+        fs::write(
+            &keyboard_toml,
+            "include = \"base.toml\"\n[keyboard]\nname = \"${RMKIT_TEST_KEYBOARD_NAME}\"\nchip = \"${RMKIT_TEST_KEYBOARD_CHIP:-rp2040}\"\n",
+        )
+        .unwrap();
+        env::set_var("RMKIT_TEST_KEYBOARD_NAME", "substituted");
+
+        copy_keyboard_toml(
+            keyboard_toml.to_str().unwrap(),
+            project.join("keyboard.toml"),
+        )
+        .unwrap();
+        let copied: toml::Table =
+            toml::from_str(&fs::read_to_string(project.join("keyboard.toml")).unwrap()).unwrap();
+        let keyboard = copied["keyboard"].as_table().unwrap();
+        assert_eq!(keyboard["name"].as_str(), Some("substituted"));
+        assert_eq!(keyboard["chip"].as_str(), Some("rp2040"));
+        assert_eq!(keyboard["vendor_id"].as_integer(), Some(0x4c4b));
+        assert!(!copied.contains_key(INCLUDE_KEY));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn uf2_key_of_pico_w_is_rp2040() {
+        assert_eq!(uf2_key("pico_w"), "rp2040");
+        assert_eq!(uf2_key("stm32f411ce"), "stm32f4");
+        assert_eq!(uf2_key("stm32"), "stm32");
+    }
+
+    proptest! {
+        #[test]
+        fn project_is_created_from_keyboard_config(generated in keyboard_config()) {
+            let dir = env::temp_dir().join(format!("rmkit-generated-{}", process::id()));
+            let extension = match generated.format {
+                ConfigFormat::Toml => "toml",
+                ConfigFormat::Json => "json",
+                ConfigFormat::Yaml => "yaml",
+            };
+            let path = env::temp_dir().join(format!("rmkit-generated-{}.{}", process::id(), extension));
+            write_keyboard_config(&path, &generated.config).unwrap();
+            let info = parse_keyboard_toml(path.to_str().unwrap(), Some(dir.to_string_lossy().into_owned()));
+            fs::remove_file(&path).unwrap();
+            let info = info.unwrap();
+            // Parsing has no side effects, the directory is created with the project
+            prop_assert!(!dir.exists());
+
+            prop_assert_eq!(info.target_dir, dir);
+            prop_assert_eq!(info.project_name, generated.name.replace(' ', "_"));
+            prop_assert_eq!(info.chip.as_str(), generated.chip);
+            prop_assert_eq!(info.uf2_key.as_str(), generated.uf2_key);
+            prop_assert_eq!(info.board.as_deref(), (generated.key == "board").then_some(generated.chip));
+            prop_assert_eq!(info.split, generated.split);
+            let expected_folder = if generated.split {
+                format!("{}_split", generated.chip)
+            } else {
+                generated.chip.to_string()
+            };
+            prop_assert_eq!(info.remote_folder, expected_folder);
+
+            let switches = generated.switches;
+            let disabled = |f: &str| info.disabled_default_feature.iter().any(|d| d == f);
+            let enabled = |f: &str| info.enabled_feature.iter().any(|e| e == f);
+            prop_assert_eq!(disabled("storage"), !switches.storage);
+            prop_assert_eq!(disabled("defmt"), !switches.defmt);
+            prop_assert_eq!(disabled("vial"), !switches.vial);
+            prop_assert_eq!(disabled("vial_lock"), !switches.vial);
+            prop_assert_eq!(disabled("col2row"), switches.row2col);
+            prop_assert_eq!(enabled("controller"), switches.light);
+            prop_assert!(info.disabled_default_feature.iter().all(|f| !enabled(f)));
+        }
+
+        #[test]
+        fn env_substitution_keeps_plain_strings(s in "[^$]{0,32}") {
+            prop_assert_eq!(substitute_env(&s, |_| None), Ok(s));
+        }
+
+        #[test]
+        fn env_substitution_uses_value_or_default(
+            name in "[A-Z_][A-Z0-9_]{0,8}",
+            value in proptest::option::of("[^$}]{0,8}"),
+            default in "[^$}]{0,8}",
+        ) {
+            let expected = value.clone().unwrap_or_else(|| default.clone());
+            let substituted = substitute_env(&format!("a${{{}:-{}}}b", name, default), |_| value.clone());
+            prop_assert_eq!(substituted, Ok(format!("a{}b", expected)));
+            let escaped = format!("$${{{}}}", name);
+            prop_assert_eq!(substitute_env(&escaped, |_| value.clone()), Ok(format!("${{{}}}", name)));
+        }
+
+        #[test]
+        fn project_name_has_no_spaces(name in ".{0,32}") {
+            let project_name = sanitize_project_name(&name);
+            prop_assert!(!project_name.contains(' '));
+            prop_assert_eq!(project_name.chars().count(), name.chars().count());
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Library behind rmkit: create RMK keyboard firmware projects from keyboard.toml, build and flash them.
+//!
+//! Progress is reported as [`Event`]s and questions, such as overwriting existing files, are answered by
+//! a [`Reporter`], so that frontends other than the rmkit CLI can drive the same operations. Errors are
+//! `Box<dyn Error>`, [`failure_kind`] classifies them.
+
+mod cache;
+pub mod chip;
+mod conflict;
+mod dry_run;
+mod error;
+pub mod firmware;
+pub mod interrupt;
+pub mod keyboard_toml;
+pub mod lock;
+mod project;
+mod report;
+pub mod rmk_version;
+pub mod target_config;
+pub mod template;
+mod version;
+pub mod vial;
+mod workspace;
+
+pub use cache::template_cache_dir;
+pub use dry_run::{FileChange, PlannedFile};
+pub use error::{failure_kind, ClassifiedError, Classify, FailureKind};
+pub use project::{
+    check_vial_json, create_project, init_project, CreateOptions, InitOptions, Project, VialJson,
+};
+pub use report::{Event, Reporter, Resolution};
+pub use version::TemplateOptions;
@@ -0,0 +1,101 @@
+use crate::rmk_version;
+use crate::version::{TemplateRef, TemplateSource};
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// File in the generated project which records how it was generated
+pub const LOCK_FILE: &str = "rmkit.lock";
+
+/// Where the template of a project came from
+pub(crate) enum TemplateOrigin<'a> {
+    /// Downloaded archive of a template repository
+    Remote(&'a TemplateSource, &'a Path),
+    /// Template directory given by `--local-path`
+    Local(&'a Path),
+}
+
+/// Write rmkit.lock with the rmkit version, template commit, rmk version and chip of the project
+pub(crate) fn write_lock(
+    project_dir: &Path,
+    chip: &str,
+    split: bool,
+    origin: &TemplateOrigin,
+) -> Result<(), Box<dyn Error>> {
+    let mut template = toml::Table::new();
+    match origin {
+        TemplateOrigin::Remote(source, archive) => {
+            template.insert("repo".to_string(), source.repo.clone().into());
+            let commit = match &source.reference {
+                TemplateRef::Default => None,
+                TemplateRef::Branch(branch) => {
+                    template.insert("branch".to_string(), branch.clone().into());
+                    None
+                }
+                TemplateRef::Tag(tag) => {
+                    template.insert("tag".to_string(), tag.clone().into());
+                    None
+                }
+                TemplateRef::Commit(commit) => Some(commit.clone()),
+            };
+            if let Some(commit) = commit.or_else(|| archive_commit(archive)) {
+                template.insert("commit".to_string(), commit.into());
+            }
+        }
+        TemplateOrigin::Local(path) => {
+            let path = path.canonicalize().unwrap_or(path.to_path_buf());
+            template.insert("path".to_string(), path.display().to_string().into());
+        }
+    }
+
+    let mut lock = toml::Table::new();
+    lock.insert("rmkit".to_string(), env!("CARGO_PKG_VERSION").into());
+    lock.insert("chip".to_string(), chip.into());
+    lock.insert("split".to_string(), split.into());
+    match locked_rmk_version(&project_dir.join("Cargo.lock")) {
+        Some(version) => {
+            lock.insert("rmk".to_string(), version.into());
+        }
+        None => {
+            let cargo_toml_path = project_dir.join("Cargo.toml");
+            if let Ok(Some(requirement)) = rmk_version::rmk_requirement(&cargo_toml_path) {
+                lock.insert(
+                    "rmk_requirement".to_string(),
+                    requirement.to_string().into(),
+                );
+            }
+        }
+    }
+    lock.insert("template".to_string(), template.into());
+
+    fs::write(
+        project_dir.join(LOCK_FILE),
+        format!(
+            "# Generated by rmkit, records how this project was generated\n{}",
+            toml::to_string(&lock)?
+        ),
+    )?;
+    Ok(())
+}
+
+/// Get the commit of a GitHub archive, which is stored in the zip comment
+fn archive_commit(archive: &Path) -> Option<String> {
+    let zip = ZipArchive::new(File::open(archive).ok()?).ok()?;
+    let comment = std::str::from_utf8(zip.comment()).ok()?.trim();
+    (comment.len() == 40 && comment.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| comment.to_string())
+}
+
+/// Get the version of rmk in Cargo.lock
+pub fn locked_rmk_version(cargo_lock_path: &Path) -> Option<String> {
+    let lock: toml::Table = toml::from_str(&fs::read_to_string(cargo_lock_path).ok()?).ok()?;
+    lock.get("package")?
+        .as_array()?
+        .iter()
+        .filter_map(|p| p.as_table())
+        .find(|p| p.get("name").and_then(|n| n.as_str()) == Some("rmk"))?
+        .get("version")?
+        .as_str()
+        .map(|v| v.to_string())
+}
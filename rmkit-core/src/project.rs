@@ -0,0 +1,706 @@
+use crate::cache::{self, TemplateArchive};
+use crate::chip::{self, get_board_chip_map, get_board_info};
+use crate::conflict::Conflicts;
+use crate::dry_run::{self, PlannedFile};
+use crate::error::{Classify, FailureKind};
+use crate::keyboard_toml::{
+    copy_keyboard_toml, parse_keyboard_toml, prefill_matrix_pins, read_keyboard_config,
+    sanitize_project_name, template_folder, uf2_key, ProjectInfo,
+};
+use crate::lock::{self, TemplateOrigin};
+use crate::report::{Event, Reporter};
+use crate::version::{self, TemplateOptions, TemplateSource};
+use crate::{rmk_version, target_config, template, vial, workspace};
+use cargo_metadata::{Metadata, MetadataCommand};
+use reqwest::Client;
+use std::error::Error;
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Where `vial.json` of a created project comes from
+#[derive(Debug, Clone)]
+pub enum VialJson {
+    /// Existing file, which is checked against the matrix of keyboard.toml
+    Path(PathBuf),
+    /// Generated from the layout of keyboard.toml
+    Generate,
+}
+
+/// Options of creating a project from keyboard.toml
+#[derive(Debug, Clone)]
+pub struct CreateOptions {
+    /// Path to keyboard.toml, keyboard.json and keyboard.yaml are also accepted
+    pub keyboard_toml_path: String,
+    pub vial_json: VialJson,
+    /// Directory of the project, the project name of keyboard.toml by default
+    pub target_dir: Option<String>,
+    /// Version of the official template
+    pub version: Option<String>,
+    pub template: TemplateOptions,
+}
+
+/// Options of initializing a project for a chip or board
+#[derive(Debug, Clone)]
+pub struct InitOptions {
+    /// Name of the project, which is also its directory
+    pub project_name: String,
+    /// Chip or board, aliases of the chip database are accepted
+    pub chip: String,
+    pub split: bool,
+    /// Local template directory, which is used instead of a template repository
+    pub local_template: Option<PathBuf>,
+    /// Version of the official template
+    pub version: Option<String>,
+    pub template: TemplateOptions,
+}
+
+/// A generated project
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub name: String,
+    /// Directory of the project, in a dry run nothing is written to it
+    pub dir: PathBuf,
+    /// Chip or board of the project
+    pub chip: String,
+    pub split: bool,
+    /// Files which would be written to the project directory, only in a dry run
+    pub planned_files: Option<Vec<PlannedFile>>,
+}
+
+/// Create a project from keyboard.toml and vial.json
+pub async fn create_project(
+    options: &CreateOptions,
+    client: &Client,
+    reporter: &mut dyn Reporter,
+) -> Result<Project, Box<dyn Error>> {
+    let keyboard_toml_path = &options.keyboard_toml_path;
+    let template = &options.template;
+    let config = read_keyboard_config(keyboard_toml_path)?;
+
+    // Resolve the template first for fast fail, keyboard.toml can select a custom template
+    let source = version::resolve_template_source(
+        options.version.as_deref(),
+        template,
+        Some(&config),
+        client,
+        reporter,
+    )
+    .await?;
+    // Parse keyboard.toml to get project info
+    let mut project_info = parse_keyboard_toml(keyboard_toml_path, options.target_dir.clone())?;
+    let target_dir = project_info.target_dir.clone();
+    if template.dry_run {
+        project_info.target_dir = dry_run::staging_dir()?;
+    } else {
+        fs::create_dir_all(&target_dir).map_err(|e| {
+            format!(
+                "Failed to create project directory {}: {}",
+                target_dir.display(),
+                e
+            )
+        })?;
+    }
+
+    // Download corresponding project template
+    let archive =
+        download_project_template(&project_info, &source, template, client, reporter).await?;
+
+    // Copy keyboard.toml and vial.json to project_dir
+    copy_keyboard_toml(
+        keyboard_toml_path,
+        project_info.target_dir.join("keyboard.toml"),
+    )?;
+    let vial_json_dest = project_info.target_dir.join("vial.json");
+    match &options.vial_json {
+        VialJson::Path(path) => {
+            check_vial_json(path, &config).or_fail(FailureKind::Config)?;
+            fs::copy(path, vial_json_dest)?;
+        }
+        VialJson::Generate => {
+            let vial_json = vial::generate_vial_json(&config).or_fail(FailureKind::Config)?;
+            fs::write(vial_json_dest, serde_json::to_string_pretty(&vial_json)?)?;
+            reporter.report(Event::Generate(
+                "Generated vial.json from the layout of keyboard.toml".to_string(),
+            ));
+        }
+    }
+
+    // Post-process
+    let mut project = Project {
+        name: project_info.project_name.clone(),
+        dir: target_dir,
+        chip: project_info.chip.clone(),
+        split: project_info.split,
+        planned_files: None,
+    };
+    let generated_dir = project_info.target_dir.clone();
+    post_process(
+        project_info,
+        template,
+        &TemplateOrigin::Remote(&source, &archive.path),
+        reporter,
+    )?;
+    archive.finish(reporter).await;
+    if template.dry_run {
+        project.planned_files = Some(dry_run::compare(&generated_dir, &project.dir)?);
+    }
+
+    Ok(project)
+}
+
+/// Check that vial.json matches the matrix of the keyboard config, all mismatches are in the error
+pub fn check_vial_json(path: &Path, config: &toml::Table) -> Result<(), Box<dyn Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let vial: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    let mismatches = vial::validate_vial_json(&vial, config)?;
+    if !mismatches.is_empty() {
+        return Err(format!(
+            "{} doesn't match keyboard.toml:\n  {}",
+            path.display(),
+            mismatches.join("\n  ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Postprocessing after generating project
+///
+/// In a dry run, the project is in a temporary dir, so it's not added to a workspace and the post-generation
+/// commands aren't run
+fn post_process(
+    project_info: ProjectInfo,
+    template: &TemplateOptions,
+    origin: &TemplateOrigin,
+    reporter: &mut dyn Reporter,
+) -> Result<(), Box<dyn Error>> {
+    // Make sure the template can be rendered by this rmkit
+    let manifest = template::check_template_compatibility(&project_info.target_dir, reporter)
+        .or_fail(FailureKind::Config)?;
+
+    // Render the template files with the project info and chip metadata
+    let context = template::render_context(&project_info, &manifest, &template.vars, reporter)
+        .or_fail(FailureKind::Config)?;
+    template::render_folder(
+        &project_info.target_dir,
+        &manifest.render_extensions,
+        &context,
+    )
+    .or_fail(FailureKind::Config)?;
+    template::rename_files(&project_info.target_dir, &manifest, &context)
+        .or_fail(FailureKind::Config)?;
+
+    // Chip boilerplate which the template doesn't have comes from the chip database
+    target_config::generate_target_files(
+        &project_info.target_dir,
+        &project_info.chip,
+        !project_info
+            .disabled_default_feature
+            .iter()
+            .any(|f| f == "defmt"),
+        reporter,
+    )?;
+
+    // Fail loudly instead of generating a project which doesn't build
+    template::check_placeholder_coverage(&project_info.target_dir).or_fail(FailureKind::Config)?;
+
+    // A project inside a workspace must be a member or standalone, otherwise cargo refuses to build it
+    if !template.dry_run {
+        workspace::integrate_into_workspace(&project_info.target_dir, reporter)
+            .or_fail(FailureKind::Config)?;
+    }
+
+    // Disable some default features
+    if !project_info.disabled_default_feature.is_empty() {
+        let metadata = tracing::info_span!("cargo", command = "metadata")
+            .in_scope(|| {
+                MetadataCommand::new()
+                    .current_dir(&project_info.target_dir)
+                    .exec()
+            })
+            .or_fail(FailureKind::Toolchain)?;
+        disable_rmk_default_features(
+            &project_info.target_dir,
+            &metadata,
+            project_info.disabled_default_feature,
+            reporter,
+        )?;
+    }
+
+    // Enable non-default features
+    if !project_info.enabled_feature.is_empty() {
+        enable_rmk_features(
+            &project_info.target_dir,
+            project_info.enabled_feature,
+            reporter,
+        )?;
+    }
+
+    // Warn about keyboard.toml features which the rmk version of the template doesn't support
+    let keyboard_toml_path = project_info.target_dir.join("keyboard.toml");
+    if keyboard_toml_path.exists() {
+        let config = read_keyboard_config(&keyboard_toml_path)?;
+        for problem in
+            rmk_version::check_rmk_version(&config, &project_info.target_dir.join("Cargo.toml"))?
+        {
+            reporter.report(Event::Warning(problem));
+        }
+    }
+
+    if template.dry_run {
+        for command in &manifest.post_generate {
+            reporter.report(Event::Info(format!("Would run `{}`", command)));
+        }
+    } else {
+        template::run_post_generate(&project_info.target_dir, &manifest.post_generate, reporter)?;
+    }
+
+    // Record the template and versions, after the post-generation commands which may create Cargo.lock
+    lock::write_lock(
+        &project_info.target_dir,
+        &project_info.chip,
+        project_info.split,
+        origin,
+    )?;
+
+    Ok(())
+}
+
+/// Download the template archive, or get it from the cache, and extract the project template
+async fn download_project_template(
+    project_info: &ProjectInfo,
+    source: &TemplateSource,
+    template: &TemplateOptions,
+    client: &Client,
+    reporter: &mut dyn Reporter,
+) -> Result<TemplateArchive, Box<dyn Error>> {
+    let archive = cache::fetch_template_archive(source, template.offline, client, reporter).await?;
+    let mut conflicts = Conflicts::new(template.force, reporter);
+    extract_template(
+        &archive.path,
+        &project_info.target_dir,
+        &project_info.remote_folder,
+        &mut conflicts,
+    )?;
+    conflicts.report();
+    Ok(archive)
+}
+
+/// Initialize a project for a chip or board from the template
+pub async fn init_project(
+    options: &InitOptions,
+    client: &Client,
+    reporter: &mut dyn Reporter,
+) -> Result<Project, Box<dyn Error>> {
+    let template = &options.template;
+    // Resolve the template first for fast fail (only when using remote template)
+    let source = match &options.local_template {
+        Some(_) => None,
+        None => Some(
+            version::resolve_template_source(
+                options.version.as_deref(),
+                template,
+                None,
+                client,
+                reporter,
+            )
+            .await?,
+        ),
+    };
+
+    let project_name = sanitize_project_name(&options.project_name);
+    let split = options.split;
+    let chip_or_board = chip::resolve_chip(&options.chip).or_fail(FailureKind::Config)?;
+    if split && chip::split_transports(&chip_or_board).is_empty() {
+        return Err(format!(
+            "{} doesn't support split keyboards, run `rmkit chips list --split-only` to see the chips which do",
+            chip_or_board
+        ))
+        .or_fail(FailureKind::Config);
+    }
+
+    // Get project info from parameters
+    let target_dir = PathBuf::from(&project_name);
+    let generated_dir = if template.dry_run {
+        dry_run::staging_dir()?
+    } else {
+        fs::create_dir_all(&target_dir)?;
+        target_dir.clone()
+    };
+
+    // Convert board to chip first
+    let (chip, board) = match get_board_chip_map().get(chip_or_board.as_str()) {
+        Some(c) => (c.to_string(), Some(chip_or_board)),
+        None => (chip_or_board, None),
+    };
+
+    let project_info = ProjectInfo {
+        project_name,
+        target_dir: generated_dir.clone(),
+        remote_folder: template_folder(&chip, split),
+        split,
+        uf2_key: uf2_key(&chip),
+        chip,
+        board: board.clone(),
+        disabled_default_feature: Vec::new(),
+        enabled_feature: Vec::new(),
+    };
+    let mut project = Project {
+        name: project_info.project_name.clone(),
+        dir: target_dir,
+        chip: project_info.chip.clone(),
+        split,
+        planned_files: None,
+    };
+
+    // Download template
+    let archive = match (&options.local_template, &source) {
+        (Some(p), _) => {
+            // Copy local template to project_info.target_dir
+            let mut conflicts = Conflicts::new(template.force, reporter);
+            copy_dir_recursive(p, &project_info.target_dir, &mut conflicts)?;
+            conflicts.report();
+            None
+        }
+        (None, source) => {
+            // Use remote template
+            Some(
+                download_project_template(
+                    &project_info,
+                    source
+                        .as_ref()
+                        .expect("template source should be resolved for remote template"),
+                    template,
+                    client,
+                    reporter,
+                )
+                .await?,
+            )
+        }
+    };
+
+    // Post-process
+    let origin = match (&options.local_template, &archive) {
+        (Some(p), _) => TemplateOrigin::Local(p),
+        (None, archive) => TemplateOrigin::Remote(
+            source
+                .as_ref()
+                .expect("template source should be resolved for remote template"),
+            &archive
+                .as_ref()
+                .expect("remote template should be downloaded")
+                .path,
+        ),
+    };
+    post_process(project_info, template, &origin, reporter)?;
+
+    // Defaults from the board metadata
+    if let Some((board, info)) = board.as_deref().and_then(|b| Some((b, get_board_info(b)?))) {
+        if !info.pins.is_empty()
+            && prefill_matrix_pins(&generated_dir.join("keyboard.toml"), &info.pins)?
+        {
+            reporter.report(Event::Generate(format!(
+                "Filled the matrix pins of keyboard.toml with the pinout of {}, adjust them to your wiring",
+                board
+            )));
+        }
+        if let Some(bootloader) = info.bootloader {
+            let usb_id = match (info.usb_vid, info.usb_pid) {
+                (Some(vid), Some(pid)) => format!(" (USB {:04x}:{:04x})", vid, pid),
+                _ => String::new(),
+            };
+            reporter.report(Event::Info(format!(
+                "To flash {} without a debug probe, {}{}",
+                board,
+                bootloader.upload_hint(),
+                usb_id
+            )));
+        }
+    }
+
+    if let Some(archive) = archive {
+        archive.finish(reporter).await;
+    }
+    if template.dry_run {
+        project.planned_files = Some(dry_run::compare(&generated_dir, &project.dir)?);
+    }
+
+    Ok(project)
+}
+
+/// Extract a folder of the template archive to the project dir
+///
+/// # Parameters
+/// - `archive_path`: Zip archive of the template repository
+/// - `output_path`: Target extraction path
+/// - `folder`: Specific subdirectory to extract
+#[tracing::instrument(name = "extract", skip_all)]
+fn extract_template<P>(
+    archive_path: &Path,
+    output_path: P,
+    folder: &str,
+    conflicts: &mut Conflicts,
+) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    let output_path = output_path.as_ref();
+
+    fs::create_dir_all(output_path)?;
+
+    conflicts.reporter().report(Event::Generate(format!(
+        "Extract project template for {}...",
+        folder
+    )));
+
+    // Open the downloaded ZIP file and extract
+    let zip_file = File::open(archive_path)?;
+    let mut zip = ZipArchive::new(zip_file)?;
+
+    let mut folder_found = false;
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        let file_name = file.enclosed_name().ok_or("Invalid file path")?;
+
+        // Find the root directory from the ZIP file
+        let segments: Vec<_> = file_name.iter().collect();
+        if segments.len() > 1 && segments[1] == folder {
+            folder_found = true;
+            let relative_name = file_name.iter().skip(2).collect::<PathBuf>();
+            let out_path = output_path.join(relative_name);
+
+            if file.is_dir() {
+                fs::create_dir_all(&out_path)?;
+            } else {
+                let mut content = Vec::new();
+                file.read_to_end(&mut content)?;
+                conflicts.write(&out_path, &content)?;
+            }
+        }
+    }
+
+    if !folder_found {
+        // Check whether the remote_folder starts with stm32, do the second search using `stm32xx` and if there's still no matched template, use `stm32` template
+        if folder.starts_with("stm32") {
+            // Generate template for stm32
+            // Split templates are searched with the `_split` suffix
+            let suffix = if folder.ends_with("_split") {
+                "_split"
+            } else {
+                ""
+            };
+            if folder.len() > 7 {
+                // Do the second search, use the stm32's family name
+                let stm32_series = format!("{}{}", &folder[..7], suffix);
+                for i in 0..zip.len() {
+                    let mut file = zip.by_index(i)?;
+                    let file_name = file.enclosed_name().ok_or("Invalid file path")?;
+
+                    // Find the root directory from the ZIP file
+                    let segments: Vec<_> = file_name.iter().collect();
+                    if segments.len() > 1 && segments[1] == stm32_series.as_str() {
+                        folder_found = true;
+                        let relative_name = file_name.iter().skip(2).collect::<PathBuf>();
+                        let out_path = output_path.join(relative_name);
+
+                        if file.is_dir() {
+                            fs::create_dir_all(&out_path)?;
+                        } else {
+                            let mut content = Vec::new();
+                            file.read_to_end(&mut content)?;
+                            conflicts.write(&out_path, &content)?;
+                        }
+                    }
+                }
+            }
+            if !folder_found {
+                let default_folder = format!("stm32{}", suffix);
+                conflicts.reporter().report(Event::Warning(format!("There's no template available for [{folder}], using the default {default_folder} template. You may need to make further edit.")));
+                // Still not found, use the default stm32 template
+                for i in 0..zip.len() {
+                    let mut file = zip.by_index(i)?;
+                    let file_name = file.enclosed_name().ok_or("Invalid file path")?;
+
+                    // Find the root directory from the ZIP file
+                    let segments: Vec<_> = file_name.iter().collect();
+                    if segments.len() > 1 && segments[1] == default_folder.as_str() {
+                        folder_found = true;
+                        let relative_name = file_name.iter().skip(2).collect::<PathBuf>();
+                        let out_path = output_path.join(relative_name);
+
+                        if file.is_dir() {
+                            fs::create_dir_all(&out_path)?;
+                        } else {
+                            let mut content = Vec::new();
+                            file.read_to_end(&mut content)?;
+                            conflicts.write(&out_path, &content)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Check again
+        if !folder_found {
+            return Err(format!(
+                "The specified chip/board '{}' does not exist in the template repo",
+                folder
+            )
+            .into());
+        }
+    }
+
+    conflicts.reporter().report(Event::Success(format!(
+        "Project created, path: {}",
+        output_path.display()
+    )));
+    Ok(())
+}
+
+fn copy_dir_recursive(
+    src: &Path,
+    dest: &Path,
+    conflicts: &mut Conflicts,
+) -> Result<(), Box<dyn Error>> {
+    if !src.is_dir() {
+        return Err(format!("{} is not a directory", src.display()).into());
+    }
+
+    // Create the target folder
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let src_path = entry.path();
+        let dest_path = dest.join(entry.file_name());
+
+        if file_type.is_dir() {
+            // Recursively process
+            copy_dir_recursive(&src_path, &dest_path, conflicts)?;
+        } else {
+            // Copy file
+            conflicts.write(&dest_path, &fs::read(&src_path)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Update the rmk dependency configuration in the Cargo.toml file at the specified path
+/// Replace rmk = { version = "...", features = ["..."] } with
+/// rmk = { version = "...", default-features = false, features = ["..."] }
+///
+/// # Arguments
+/// * `target_dir` - Target directory path containing Cargo.toml
+///
+/// # Returns
+/// * `Result<(), String>` - Returns Ok on success, Err on failure
+fn disable_rmk_default_features(
+    target_dir: &PathBuf,
+    metadata: &Metadata,
+    features: Vec<String>,
+    reporter: &mut dyn Reporter,
+) -> Result<(), String> {
+    reporter.report(Event::Info(format!(
+        "Disabling default features: {:?}",
+        features
+    )));
+    // Define the path to Cargo.toml
+    let cargo_toml_path = Path::new(target_dir).join("Cargo.toml");
+
+    // Parse as Manifest using cargo_toml
+    let mut manifest =
+        cargo_toml::Manifest::from_path(&cargo_toml_path).map_err(|e| e.to_string())?;
+
+    // Get dependencies and modify rmk configuration
+    if let Some(cargo_toml::Dependency::Detailed(rmk_dep)) = manifest.dependencies.get_mut("rmk") {
+        // Set default-features = false, and keep the original version and features
+        let mut default_features = get_dependency_default_features("rmk", metadata)?;
+        default_features.retain(|s| !features.contains(s));
+
+        rmk_dep.features.append(&mut default_features);
+        rmk_dep.features.sort_unstable();
+        rmk_dep.features.dedup();
+
+        rmk_dep.default_features = false;
+    } else {
+        return Err("No valid rmk dependency found".to_string());
+    }
+
+    // Convert the modified Manifest to a string
+    let updated_toml = toml::to_string(&manifest)
+        .map_err(|e| format!("Failed to serialize updated Cargo.toml: {}", e))?;
+
+    // Write the updated content back to the file
+    fs::write(&cargo_toml_path, updated_toml)
+        .map_err(|e| format!("Failed to write updated Cargo.toml: {}", e))?;
+
+    Ok(())
+}
+
+fn get_dependency_default_features(
+    dependency: &str,
+    metadata: &Metadata,
+) -> Result<Vec<String>, String> {
+    let dep = metadata
+        .packages
+        .iter()
+        .find(|p| p.name.to_string() == dependency)
+        .ok_or(format!("Failed to find {} in dependencies", dependency))?;
+    dep.features
+        .get("default")
+        .cloned()
+        .ok_or(format!("Failed to get default {} features", dependency))
+}
+
+/// Enable non-default features for rmk dependency in Cargo.toml
+///
+/// This function adds features to the rmk dependency's feature list
+///
+/// # Arguments
+/// * `target_dir` - Target directory path containing Cargo.toml
+/// * `features` - List of features to enable
+///
+/// # Returns
+/// * `Result<(), String>` - Returns Ok on success, Err on failure
+fn enable_rmk_features(
+    target_dir: &PathBuf,
+    features: Vec<String>,
+    reporter: &mut dyn Reporter,
+) -> Result<(), String> {
+    reporter.report(Event::Info(format!("Enabling features: {:?}", features)));
+    // Define the path to Cargo.toml
+    let cargo_toml_path = Path::new(target_dir).join("Cargo.toml");
+
+    // Parse as Manifest using cargo_toml
+    let mut manifest =
+        cargo_toml::Manifest::from_path(&cargo_toml_path).map_err(|e| e.to_string())?;
+
+    // Get dependencies and modify rmk configuration
+    if let Some(cargo_toml::Dependency::Detailed(rmk_dep)) = manifest.dependencies.get_mut("rmk") {
+        // Add features to the existing feature list
+        for feature in features {
+            if !rmk_dep.features.contains(&feature) {
+                rmk_dep.features.push(feature);
+            }
+        }
+        rmk_dep.features.sort_unstable();
+        rmk_dep.features.dedup();
+    } else {
+        return Err("No valid rmk dependency found".to_string());
+    }
+
+    // Convert the modified Manifest to a string
+    let updated_toml = toml::to_string(&manifest)
+        .map_err(|e| format!("Failed to serialize updated Cargo.toml: {}", e))?;
+
+    // Write the updated content back to the file
+    fs::write(&cargo_toml_path, updated_toml)
+        .map_err(|e| format!("Failed to write updated Cargo.toml: {}", e))?;
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::error::Error;
+use std::path::Path;
+
+/// Progress of an operation, the messages are meant to be shown to the user as they are
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// The template, or the version of the official template, is chosen
+    Template(String),
+    /// The template archive is downloaded or taken from the cache
+    Download(String),
+    /// A file of the project is generated, or a command is run in it
+    Generate(String),
+    /// The firmware is being built
+    Build(String),
+    /// The firmware is being flashed
+    Flash(String),
+    Info(String),
+    Warning(String),
+    /// A step of the operation succeeded
+    Success(String),
+    /// Progress of a long step, e.g. writing a large UF2 file, `done` is `total` when it's finished
+    Progress {
+        step: String,
+        done: u64,
+        total: u64,
+    },
+}
+
+/// What to do with a file of the project which exists and differs from the template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Overwrite,
+    /// Keep the existing file
+    Skip,
+    /// Overwrite this and all following files without asking
+    OverwriteAll,
+    /// Keep this and all following existing files without asking
+    SkipAll,
+}
+
+/// Receives the events of operations and answers their questions, e.g. a terminal or a GUI
+///
+/// Closures taking an [`Event`] are reporters which keep existing files and don't join workspaces.
+pub trait Reporter {
+    fn report(&mut self, event: Event);
+
+    /// Decide what to do with an existing file which differs from the template, it's kept by default
+    ///
+    /// Not asked when the template options have `force` set.
+    fn resolve_conflict(
+        &mut self,
+        _path: &Path,
+        _existing: &[u8],
+        _template: &[u8],
+    ) -> Result<Resolution, Box<dyn Error>> {
+        Ok(Resolution::Skip)
+    }
+
+    /// Whether a project generated inside a Cargo workspace becomes a member of it, it's standalone by default
+    ///
+    /// Not asked when the project is already listed in the members.
+    fn join_workspace(
+        &mut self,
+        _project_dir: &Path,
+        _workspace_dir: &Path,
+    ) -> Result<bool, Box<dyn Error>> {
+        Ok(false)
+    }
+
+    /// Whether a compilation target which isn't installed is installed with rustup before building, it's not by
+    /// default
+    fn install_target(&mut self, _target: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(false)
+    }
+}
+
+impl<F: FnMut(Event)> Reporter for F {
+    fn report(&mut self, event: Event) {
+        self(event)
+    }
+}
@@ -0,0 +1,82 @@
+use semver::{Op, Version, VersionReq};
+use std::error::Error;
+use std::path::Path;
+
+/// keyboard.toml features and the first RMK version which supports them
+///
+/// A feature is used when the config has a value at its dotted path.
+const FEATURE_MIN_RMK_VERSION: [(&str, &str); 4] = [
+    ("input_device", "0.6.0"),
+    ("behavior.fork", "0.6.0"),
+    ("layer", "0.7.0"),
+    ("layout.matrix_map", "0.7.0"),
+];
+
+/// Check that the rmk version required in `Cargo.toml` supports all features used in the keyboard config
+///
+/// Returns a message with the suggested version bump for every unsupported feature.
+pub fn check_rmk_version(
+    config: &toml::Table,
+    cargo_toml_path: &Path,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let Some(requirement) = rmk_requirement(cargo_toml_path)? else {
+        return Ok(Vec::new());
+    };
+    let Some(lowest) = lowest_version(&requirement) else {
+        return Ok(Vec::new());
+    };
+
+    let mut problems = Vec::new();
+    for (feature, min_version) in FEATURE_MIN_RMK_VERSION {
+        let mut value = Some(config);
+        let mut segments = feature.split('.').peekable();
+        let mut used = false;
+        while let Some(segment) = segments.next() {
+            let item = value.and_then(|t| t.get(segment));
+            if segments.peek().is_none() {
+                used = item.is_some();
+            }
+            value = item.and_then(|i| i.as_table());
+        }
+        let min_version = Version::parse(min_version)?;
+        if used && lowest < min_version {
+            problems.push(format!(
+                "`{}` requires rmk {} or newer, but Cargo.toml allows rmk {}. Please bump rmk to \"{}.{}\" in Cargo.toml",
+                feature, min_version, requirement, min_version.major, min_version.minor
+            ));
+        }
+    }
+    Ok(problems)
+}
+
+/// Get the version requirement of the rmk dependency in Cargo.toml
+pub fn rmk_requirement(cargo_toml_path: &Path) -> Result<Option<VersionReq>, Box<dyn Error>> {
+    let manifest = cargo_toml::Manifest::from_path(cargo_toml_path)?;
+    let requirement = match manifest.dependencies.get("rmk") {
+        Some(cargo_toml::Dependency::Simple(version)) => version.clone(),
+        Some(cargo_toml::Dependency::Detailed(dep)) => match &dep.version {
+            Some(version) => version.clone(),
+            // Git or path dependency, the version is unknown
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(VersionReq::parse(&requirement).map_err(|e| {
+        format!("Invalid rmk version '{}' in Cargo.toml: {}", requirement, e)
+    })?))
+}
+
+/// Get the lowest version allowed by a version requirement, `None` if there's no lower bound
+fn lowest_version(requirement: &VersionReq) -> Option<Version> {
+    requirement
+        .comparators
+        .iter()
+        .filter(|c| {
+            matches!(
+                c.op,
+                Op::Exact | Op::Caret | Op::Tilde | Op::Greater | Op::GreaterEq | Op::Wildcard
+            )
+        })
+        .map(|c| Version::new(c.major, c.minor.unwrap_or(0), c.patch.unwrap_or(0)))
+        .max()
+}
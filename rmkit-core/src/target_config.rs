@@ -0,0 +1,148 @@
+use crate::chip::{self, ChipTarget};
+use crate::report::{Event, Reporter};
+use std::error::Error;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+/// Generate memory.x, .cargo/config.toml and Embed.toml of the chip, unless the template has them
+///
+/// Nothing is generated for chips without a target in the chip database.
+pub(crate) fn generate_target_files(
+    project_dir: &Path,
+    chip: &str,
+    defmt: bool,
+    reporter: &mut dyn Reporter,
+) -> Result<(), Box<dyn Error>> {
+    let Some(target) = chip::get_chip_target(chip) else {
+        return Ok(());
+    };
+    let mut files = vec![
+        (".cargo/config.toml", cargo_config(target, defmt)),
+        ("Embed.toml", embed_toml(target)),
+    ];
+    if !target.memory.is_empty() {
+        files.push(("memory.x", memory_x(target)));
+    }
+    for (name, content) in files {
+        let path = project_dir.join(name);
+        if path.exists() {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        reporter.report(Event::Generate(format!("Generated {} for {}", name, chip)));
+        if name == "memory.x" {
+            reporter.report(Event::Info(
+                "memory.x has the layout of the bare chip, adjust it if the board has a bootloader"
+                    .to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn cargo_config(target: &ChipTarget, defmt: bool) -> String {
+    let mut config = format!(
+        "[target.'cfg(all(target_arch = \"arm\", target_os = \"none\"))']\n\
+         runner = \"probe-rs run --chip {}\"\n\n\
+         [build]\n\
+         target = \"{}\"\n",
+        target.probe_rs_chip, target.target
+    );
+    if defmt {
+        config.push_str("\n[env]\nDEFMT_LOG = \"info\"\n");
+    }
+    config
+}
+
+fn embed_toml(target: &ChipTarget) -> String {
+    format!(
+        "[default.general]\n\
+         chip = \"{}\"\n\n\
+         [default.rtt]\n\
+         enabled = true\n",
+        target.probe_rs_chip
+    )
+}
+
+fn memory_x(target: &ChipTarget) -> String {
+    let mut memory = String::from("MEMORY\n{\n");
+    for region in &target.memory {
+        memory.push_str(&format!(
+            "    {} : ORIGIN = {:#010X}, LENGTH = {}\n",
+            region.name, region.origin, region.length
+        ));
+    }
+    memory.push_str("}\n");
+    memory
+}
+
+/// Get ORIGIN and LENGTH of `region` in memory.x, e.g. `FLASH`
+pub fn memory_region(memory_x: &str, region: &str) -> Option<(u64, u64)> {
+    let (origin, length) = memory_x.lines().find_map(|line| {
+        region_line(line, region).map(|(origin, length)| (origin, &line[length]))
+    })?;
+    Some((linker_size(origin)?, linker_size(length)?))
+}
+
+/// Get ORIGIN of a region line of memory.x, e.g. `FLASH : ORIGIN = 0x00027000, LENGTH = 868K`, and where LENGTH is
+pub fn region_line<'a>(line: &'a str, region: &str) -> Option<(&'a str, Range<usize>)> {
+    let (name, attributes) = line.split_once(':')?;
+    if name.split(['(', ' ', '\t']).find(|s| !s.is_empty()) != Some(region) {
+        return None;
+    }
+    let value = |key: &str| -> Option<Range<usize>> {
+        let start = name.len() + 1 + attributes.find(key)? + key.len();
+        let start = start + line[start..].find('=')? + 1;
+        let end = line[start..]
+            .find([',', '/'])
+            .map_or(line.len(), |end| start + end);
+        let value = &line[start..end];
+        let start = start + value.len() - value.trim_start().len();
+        Some(start..start + value.trim().len())
+    };
+    Some((&line[value("ORIGIN")?], value("LENGTH")?))
+}
+
+/// Evaluate a size of a linker script, e.g. `0x27000`, `868K` or `2048K - 0x100`
+pub fn linker_size(expression: &str) -> Option<u64> {
+    let mut total: i64 = 0;
+    let mut sign = 1;
+    for token in expression
+        .replace('+', " + ")
+        .replace('-', " - ")
+        .split_whitespace()
+    {
+        match token {
+            "+" => sign = 1,
+            "-" => sign = -1,
+            _ => {
+                let (number, multiplier) = match token.strip_suffix(['K', 'k']) {
+                    Some(number) => (number, 1024),
+                    None => match token.strip_suffix(['M', 'm']) {
+                        Some(number) => (number, 1024 * 1024),
+                        None => (token, 1),
+                    },
+                };
+                let value = match number.strip_prefix("0x").or(number.strip_prefix("0X")) {
+                    Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                total += sign * value * multiplier;
+            }
+        }
+    }
+    u64::try_from(total).ok()
+}
+
+/// Format a size for a linker script, in KiB if possible
+pub fn format_size(size: u64) -> String {
+    if size.is_multiple_of(1024) {
+        format!("{}K", size / 1024)
+    } else {
+        format!("{:#x}", size)
+    }
+}
@@ -0,0 +1,437 @@
+use crate::chip;
+use crate::error::{Classify, FailureKind};
+use crate::interrupt;
+use crate::keyboard_toml::ProjectInfo;
+use crate::report::{Event, Reporter};
+use minijinja::value::ValueKind;
+use minijinja::{context, Environment, ErrorKind, UndefinedBehavior};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Manifest file which templates use to declare what they need from rmkit
+pub(crate) const TEMPLATE_MANIFEST: &str = "template-manifest.toml";
+
+/// Variables which can be rendered by this version of rmkit
+pub(crate) const SUPPORTED_PLACEHOLDERS: [&str; 11] = [
+    "project_name",
+    "chip_name",
+    "uf2_key",
+    "split",
+    "storage",
+    "vial",
+    "defmt",
+    "features",
+    "disabled_features",
+    "chip",
+    "board",
+];
+
+/// Files of the user which are copied to the project, they're not templates
+const USER_FILES: [&str; 2] = ["keyboard.toml", "vial.json"];
+
+/// Extensions of files in which placeholders are rendered, if the template doesn't specify them
+const DEFAULT_RENDER_EXTENSIONS: [&str; 7] = ["toml", "json", "rs", "x", "yml", "yaml", "md"];
+
+/// Requirements declared by a project template
+#[derive(Debug, Deserialize)]
+pub(crate) struct TemplateManifest {
+    /// Minimum rmkit version which can render the template
+    min_rmkit_version: Option<String>,
+    /// Placeholders used in the template
+    #[serde(default)]
+    placeholders: Vec<String>,
+    /// Extensions of files in which placeholders are rendered
+    #[serde(default = "default_render_extensions")]
+    pub(crate) render_extensions: Vec<String>,
+    /// Variables of the template, besides the ones provided by rmkit
+    #[serde(default)]
+    variables: BTreeMap<String, TemplateVariable>,
+    /// Files to rename after rendering, the new paths can use variables
+    #[serde(default)]
+    rename: BTreeMap<String, String>,
+    /// Commands which are run in the project after it's generated, e.g. `cargo generate-lockfile`
+    #[serde(default)]
+    pub(crate) post_generate: Vec<String>,
+}
+
+/// Variable declared by a template
+#[derive(Debug, Deserialize)]
+struct TemplateVariable {
+    /// What the variable is for, shown when it's missing
+    description: Option<String>,
+    /// Value used when the variable isn't provided, variables without default are required
+    default: Option<String>,
+}
+
+impl Default for TemplateManifest {
+    fn default() -> Self {
+        Self {
+            min_rmkit_version: None,
+            placeholders: Vec::new(),
+            render_extensions: default_render_extensions(),
+            variables: BTreeMap::new(),
+            rename: BTreeMap::new(),
+            post_generate: Vec::new(),
+        }
+    }
+}
+
+fn default_render_extensions() -> Vec<String> {
+    DEFAULT_RENDER_EXTENSIONS
+        .iter()
+        .map(|e| e.to_string())
+        .collect()
+}
+
+/// Check that the template extracted to `project_dir` can be rendered by this rmkit, and get its manifest
+///
+/// The manifest is removed from the project after the check, it's not part of the generated project.
+pub(crate) fn check_template_compatibility(
+    project_dir: &Path,
+    reporter: &mut dyn Reporter,
+) -> Result<TemplateManifest, Box<dyn Error>> {
+    let manifest_path = project_dir.join(TEMPLATE_MANIFEST);
+    if !manifest_path.exists() {
+        reporter.report(Event::Info(format!(
+            "Legacy template without {}, compatibility with this rmkit is not checked",
+            TEMPLATE_MANIFEST
+        )));
+        return Ok(TemplateManifest::default());
+    }
+
+    let manifest: TemplateManifest = toml::from_str(&fs::read_to_string(&manifest_path)?)
+        .map_err(|e| format!("Invalid {}: {}", TEMPLATE_MANIFEST, e))?;
+    fs::remove_file(&manifest_path)?;
+
+    let current = semver::Version::parse(env!("CARGO_PKG_VERSION"))?;
+    if let Some(min_version) = &manifest.min_rmkit_version {
+        let min_version = semver::Version::parse(min_version)
+            .map_err(|e| format!("Invalid min_rmkit_version '{}': {}", min_version, e))?;
+        if current < min_version {
+            return Err(format!(
+                "This template requires rmkit {} or newer, but the current rmkit is {}. Please upgrade rmkit: `cargo install rmkit`",
+                min_version, current
+            )
+            .into());
+        }
+    }
+
+    let unsupported: Vec<&str> = manifest
+        .placeholders
+        .iter()
+        .map(|p| p.as_str())
+        .filter(|p| !SUPPORTED_PLACEHOLDERS.contains(p))
+        .collect();
+    if !unsupported.is_empty() {
+        return Err(format!(
+            "This template uses placeholders [{}] which are not supported by rmkit {}. Please upgrade rmkit: `cargo install rmkit`",
+            unsupported.join(", "),
+            current
+        )
+        .into());
+    }
+
+    Ok(manifest)
+}
+
+/// Variables available in templates
+///
+/// Besides the names, templates can use conditionals such as `{% if split %}` or `{% if storage %}`, and loops
+/// over `features` or `chip.split_transports`. Variables declared by the template get the value given by
+/// `vars` or their defaults, and it's an error if a required one isn't provided. `true` and `false` are bools.
+pub(crate) fn render_context(
+    project_info: &ProjectInfo,
+    manifest: &TemplateManifest,
+    vars: &[(String, String)],
+    reporter: &mut dyn Reporter,
+) -> Result<minijinja::Value, Box<dyn Error>> {
+    let mut variables = BTreeMap::new();
+    for (name, value) in vars {
+        if SUPPORTED_PLACEHOLDERS.contains(&name.as_str()) {
+            return Err(format!(
+                "`{}` is set by rmkit and can't be overridden with --var",
+                name
+            )
+            .into());
+        }
+        if !manifest.variables.contains_key(name) {
+            reporter.report(Event::Warning(format!(
+                "Variable `{}` isn't declared by the template",
+                name
+            )));
+        }
+        variables.insert(name.clone(), variable_value(value));
+    }
+    let mut missing = Vec::new();
+    for (name, variable) in &manifest.variables {
+        if SUPPORTED_PLACEHOLDERS.contains(&name.as_str()) || variables.contains_key(name) {
+            continue;
+        }
+        match &variable.default {
+            Some(default) => {
+                variables.insert(name.clone(), variable_value(default));
+            }
+            None => missing.push(match &variable.description {
+                Some(description) => format!("\n  {}: {}", name, description),
+                None => format!("\n  {}", name),
+            }),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!(
+            "The template requires variables which are not provided, set them with `--var KEY=VALUE`:{}",
+            missing.concat()
+        )
+        .into());
+    }
+
+    let disabled = |feature: &str| {
+        project_info
+            .disabled_default_feature
+            .iter()
+            .any(|f| f == feature)
+    };
+    Ok(context! {
+        project_name => project_info.project_name,
+        chip_name => project_info.chip,
+        uf2_key => project_info.uf2_key,
+        split => project_info.split,
+        storage => !disabled("storage"),
+        vial => !disabled("vial"),
+        defmt => !disabled("defmt"),
+        features => project_info.enabled_feature,
+        disabled_features => project_info.disabled_default_feature,
+        chip => context! {
+            name => project_info.chip,
+            uf2_family => chip::get_uf2_family(&project_info.chip).map(|f| format!("{:#010x}", f)),
+            split_transports => chip::split_transports(&project_info.chip),
+        },
+        board => project_info.board.as_deref().map(|name| {
+            let info = chip::get_board_info(name);
+            context! {
+                name,
+                bootloader => info.and_then(|i| i.bootloader).map(|b| b.to_string()),
+                flash_size => info.and_then(|i| i.flash_size.as_deref()),
+                pins => info.map(|i| i.pins.as_slice()).unwrap_or_default(),
+            }
+        }),
+        ..minijinja::Value::from(variables)
+    })
+}
+
+/// Value of a template variable, `true` and `false` are bools so that they work in conditionals
+fn variable_value(value: &str) -> minijinja::Value {
+    match value {
+        "true" => minijinja::Value::from(true),
+        "false" => minijinja::Value::from(false),
+        _ => minijinja::Value::from(value),
+    }
+}
+
+/// Template environment of rmkit, undefined variables are errors
+pub fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_keep_trailing_newline(true);
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    // Render bools as in TOML and Rust, instead of Python's `True`
+    env.set_formatter(|out, state, value| {
+        if value.kind() == ValueKind::Bool {
+            write!(out, "{}", value.is_true()).map_err(minijinja::Error::from)
+        } else {
+            minijinja::escape_formatter(out, state, value)
+        }
+    });
+    env
+}
+
+/// Render the template files with the given extensions in the project folder
+///
+/// Undefined variables are errors, so that a template which needs a newer rmkit fails loudly.
+#[tracing::instrument(name = "render", skip_all)]
+pub(crate) fn render_folder(
+    project_dir: &Path,
+    extensions: &[String],
+    context: &minijinja::Value,
+) -> Result<(), Box<dyn Error>> {
+    let env = environment();
+    let walker = walkdir::WalkDir::new(project_dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "target" && e.file_name() != ".git")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path()
+                .extension()
+                .is_some_and(|ext| extensions.iter().any(|e| ext == e.as_str()))
+        })
+        .filter(|e| {
+            e.path().parent() != Some(project_dir)
+                || !USER_FILES.iter().any(|f| e.file_name() == *f)
+        });
+    for entry in walker {
+        let path = entry.path();
+        let content = fs::read_to_string(path)?;
+        // Most files have no template syntax at all
+        if !["{{", "{%", "{#"].iter().any(|s| content.contains(s)) {
+            continue;
+        }
+        let name = path
+            .strip_prefix(project_dir)
+            .unwrap_or(path)
+            .display()
+            .to_string();
+        let rendered = env
+            .render_named_str(&name, &content, context)
+            .map_err(|e| {
+                let hint = if e.kind() == ErrorKind::UndefinedError {
+                    "\nThe template may require a newer rmkit, please upgrade rmkit: `cargo install rmkit`"
+                } else {
+                    ""
+                };
+                format!("Failed to render {}: {:#}{}", name, e, hint)
+            })?;
+        if rendered != content {
+            fs::write(path, rendered)?;
+        }
+    }
+    Ok(())
+}
+
+/// Rename the files listed in `rename` of the manifest, the new paths are rendered with `context`
+pub(crate) fn rename_files(
+    project_dir: &Path,
+    manifest: &TemplateManifest,
+    context: &minijinja::Value,
+) -> Result<(), Box<dyn Error>> {
+    let env = environment();
+    for (from, to) in &manifest.rename {
+        let to = env
+            .render_str(to, context)
+            .map_err(|e| format!("Failed to render the new name of {}: {:#}", from, e))?;
+        for path in [from, &to] {
+            if !Path::new(path)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+            {
+                return Err(format!(
+                    "Invalid rename {} -> {}, paths must be relative to the project",
+                    from, to
+                )
+                .into());
+            }
+        }
+        let source = project_dir.join(from);
+        if !source.exists() {
+            return Err(format!("{} to rename doesn't exist in the template", from).into());
+        }
+        let target = project_dir.join(&to);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(source, target)?;
+    }
+    Ok(())
+}
+
+/// Run the `post_generate` commands of the manifest in the project
+///
+/// Commands are split at whitespace and run without a shell.
+pub(crate) fn run_post_generate(
+    project_dir: &Path,
+    commands: &[String],
+    reporter: &mut dyn Reporter,
+) -> Result<(), Box<dyn Error>> {
+    for command in commands {
+        let mut parts = command.split_whitespace();
+        let Some(program) = parts.next() else {
+            continue;
+        };
+        reporter.report(Event::Generate(format!("Running `{}`", command)));
+        let status = tracing::info_span!("post_generate", command = command.as_str())
+            .in_scope(|| {
+                interrupt::status(Command::new(program).args(parts).current_dir(project_dir))
+            })
+            .map_err(|e| format!("Failed to run `{}`: {}", command, e))
+            .or_fail(FailureKind::Toolchain)?;
+        if !status.success() {
+            return Err(format!(
+                "Post-generation command `{}` failed with {}",
+                command, status
+            ))
+            .or_fail(FailureKind::Toolchain);
+        }
+    }
+    Ok(())
+}
+
+/// Check that no `{{ placeholder }}` is left in the generated project after rendering
+pub(crate) fn check_placeholder_coverage(project_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let leftovers = find_placeholders(project_dir)?;
+    if leftovers.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = String::from("Unrendered placeholders found in the generated project:");
+    for (path, placeholders) in leftovers {
+        let path = path.strip_prefix(project_dir).unwrap_or(&path);
+        message.push_str(&format!(
+            "\n  {}: {}",
+            path.display(),
+            placeholders.join(", ")
+        ));
+    }
+    message.push_str(
+        "\nThe template may require a newer rmkit, please upgrade rmkit: `cargo install rmkit`",
+    );
+    Err(message.into())
+}
+
+/// Find all `{{ placeholder }}` in text files under `dir`, skipping build output and git data
+fn find_placeholders(dir: &Path) -> Result<BTreeMap<PathBuf, Vec<String>>, Box<dyn Error>> {
+    let mut found = BTreeMap::new();
+    let walker = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "target" && e.file_name() != ".git")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file());
+    for entry in walker {
+        // Skip binary files
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let placeholders = placeholders_in(&content);
+        if !placeholders.is_empty() {
+            found.insert(entry.path().to_path_buf(), placeholders);
+        }
+    }
+    Ok(found)
+}
+
+/// Get all `{{ name }}` placeholders in the text, without duplicates
+fn placeholders_in(content: &str) -> Vec<String> {
+    let mut placeholders: Vec<String> = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("}}") else {
+            break;
+        };
+        let name = rest[..end].trim();
+        // Only identifiers are placeholders, e.g. `{{}}` in Rust format strings is not
+        if !name.is_empty()
+            && name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            let placeholder = format!("{{{{ {} }}}}", name);
+            if !placeholders.contains(&placeholder) {
+                placeholders.push(placeholder);
+            }
+            rest = &rest[end + 2..];
+        }
+    }
+    placeholders
+}
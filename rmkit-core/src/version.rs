@@ -0,0 +1,303 @@
+use crate::cache::template_cache_dir;
+use crate::error::{Classify, FailureKind};
+use crate::report::{Event, Reporter};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+/// Cached version mapping, used in offline mode
+const VERSION_MAPPING_FILE: &str = "version-mapping.json";
+
+/// Version to commit mapping structure
+#[derive(Debug, Deserialize)]
+struct VersionMapping {
+    #[serde(flatten)]
+    versions: HashMap<String, String>,
+}
+
+/// Resolve rmk-template version to a commit hash
+///
+/// # Arguments
+/// * `version` - Optional version string (e.g., "0.7", "0.8")
+/// * `offline` - Use the cached version mapping instead of fetching it
+/// * `client` - HTTP client used to fetch the version mapping
+/// * `reporter` - Receives the chosen version
+///
+/// # Returns
+/// * Result with commit hash or "main" for latest, or error if version is invalid
+#[tracing::instrument(name = "resolve", skip_all)]
+pub async fn resolve_template_version(
+    version: Option<&str>,
+    offline: bool,
+    client: &Client,
+    reporter: &mut dyn Reporter,
+) -> Result<String, Box<dyn Error>> {
+    match version {
+        Some(v) => {
+            if v == "latest" || v == "main" {
+                return Ok("main".to_string());
+            }
+
+            // User provided a version, validate it
+            let mapping = fetch_all_versions(offline, client).await?;
+
+            match mapping.versions.get(v) {
+                Some(commit) => {
+                    reporter.report(Event::Template(format!(
+                        "Using rmk-template version {} (commit: {})",
+                        v, commit
+                    )));
+                    Ok(commit.clone())
+                }
+                None => {
+                    // Version not found, show available versions
+                    let mut versions: Vec<String> = mapping.versions.keys().cloned().collect();
+                    versions.sort();
+                    versions.push("main".to_string());
+                    Err(format!(
+                        "Invalid version '{}'. Available versions: {}",
+                        v,
+                        versions.join(", ")
+                    )
+                    .into())
+                }
+            }
+        }
+        None => {
+            // No version provided, use main branch
+            reporter.report(Event::Template(
+                "Using latest template from main branch".to_string(),
+            ));
+            Ok("main".to_string())
+        }
+    }
+}
+
+/// Fetch all available versions from remote config, the mapping is cached for offline mode
+async fn fetch_all_versions(
+    offline: bool,
+    client: &Client,
+) -> Result<VersionMapping, Box<dyn Error>> {
+    let cache_path = template_cache_dir()?.join(VERSION_MAPPING_FILE);
+    if offline {
+        let content = fs::read_to_string(&cache_path)
+            .map_err(|_| "No cached template versions, run once without --offline to cache them")
+            .or_fail(FailureKind::Network)?;
+        return Ok(serde_json::from_str(&content)?);
+    }
+
+    let config_url =
+        "https://raw.githubusercontent.com/HaoboGu/rmk-template/main/version-mapping.json";
+
+    let response = client.get(config_url).send().await?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to fetch version mapping: {}",
+            response.status()
+        ))
+        .or_fail(FailureKind::Network);
+    }
+
+    let content = response.text().await?;
+    let mapping: VersionMapping = serde_json::from_str(&content)?;
+    // Caching is best effort, only offline mode needs it
+    if fs::create_dir_all(template_cache_dir()?).is_ok() {
+        let _ = fs::write(&cache_path, content);
+    }
+    Ok(mapping)
+}
+
+/// Options of generating a project from the template
+#[derive(Debug, Clone, Default)]
+pub struct TemplateOptions {
+    /// GitHub repository of the template, as `owner/name`, e.g. a fork of HaoboGu/rmk-template
+    pub template_repo: Option<String>,
+    /// Branch of the template repository
+    pub branch: Option<String>,
+    /// Tag or commit of the template repository
+    pub tag: Option<String>,
+    /// Only use cached templates and template versions, without network access
+    pub offline: bool,
+    /// Overwrite existing files in the project directory without asking the reporter
+    pub force: bool,
+    /// Only plan the files which would be generated, without writing anything
+    pub dry_run: bool,
+    /// Variables of the template
+    pub vars: Vec<(String, String)>,
+}
+
+/// Official template repository
+const OFFICIAL_TEMPLATE_REPO: &str = "HaoboGu/rmk-template";
+
+/// Environment variables with a GitHub token for private template repositories
+const TEMPLATE_TOKEN_VARS: [&str; 2] = ["RMKIT_TEMPLATE_TOKEN", "GITHUB_TOKEN"];
+
+/// Git reference of a template repository
+#[derive(Debug, Clone)]
+pub(crate) enum TemplateRef {
+    /// Default branch of the repository
+    Default,
+    Branch(String),
+    Tag(String),
+    Commit(String),
+}
+
+/// Template repository and the reference to download
+#[derive(Debug, Clone)]
+pub(crate) struct TemplateSource {
+    /// GitHub repository as `owner/name`
+    pub(crate) repo: String,
+    pub(crate) reference: TemplateRef,
+}
+
+impl TemplateSource {
+    /// Name of the reference, used in messages and as cache key
+    pub(crate) fn name(&self) -> String {
+        let reference = match &self.reference {
+            TemplateRef::Default => "HEAD",
+            TemplateRef::Branch(r) | TemplateRef::Tag(r) | TemplateRef::Commit(r) => r,
+        };
+        // Keep the cache keys of the official template short, as before custom templates were supported
+        if self.repo == OFFICIAL_TEMPLATE_REPO {
+            reference.to_string()
+        } else {
+            format!("{}@{}", self.repo, reference)
+        }
+    }
+
+    /// Archives of commits never change, others are refreshed
+    pub(crate) fn is_immutable(&self) -> bool {
+        matches!(self.reference, TemplateRef::Commit(_))
+    }
+
+    /// GitHub token for downloading private templates, the official template doesn't need it
+    pub(crate) fn token(&self) -> Option<String> {
+        if self.repo == OFFICIAL_TEMPLATE_REPO {
+            return None;
+        }
+        TEMPLATE_TOKEN_VARS
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|t| !t.is_empty()))
+    }
+
+    /// Zip archive URL of the template
+    ///
+    /// With a token, the GitHub API is used, because archive links of github.com don't accept tokens.
+    pub(crate) fn archive_url(&self, with_token: bool) -> String {
+        if with_token {
+            let reference = match &self.reference {
+                TemplateRef::Default => String::new(),
+                TemplateRef::Branch(r) | TemplateRef::Tag(r) | TemplateRef::Commit(r) => {
+                    format!("/{}", r)
+                }
+            };
+            return format!(
+                "https://api.github.com/repos/{}/zipball{}",
+                self.repo, reference
+            );
+        }
+        let path = match &self.reference {
+            TemplateRef::Default => "HEAD".to_string(),
+            TemplateRef::Branch(b) => format!("refs/heads/{}", b),
+            TemplateRef::Tag(t) => format!("refs/tags/{}", t),
+            TemplateRef::Commit(c) => c.clone(),
+        };
+        format!("https://github.com/{}/archive/{}.zip", self.repo, path)
+    }
+}
+
+/// Resolve the template repository and reference
+///
+/// `--template-repo`, `--branch` and `--tag` take precedence over the `[template]` section of keyboard.toml.
+/// Without a custom repository, the official template is used, and `version` selects its version.
+pub(crate) async fn resolve_template_source(
+    version: Option<&str>,
+    template: &TemplateOptions,
+    config: Option<&toml::Table>,
+    client: &Client,
+    reporter: &mut dyn Reporter,
+) -> Result<TemplateSource, Box<dyn Error>> {
+    let section = config.and_then(|c| c.get("template"));
+    let from_config = |key: &str| -> Result<Option<String>, Box<dyn Error>> {
+        match section.and_then(|s| s.get(key)) {
+            Some(value) => Ok(Some(
+                value
+                    .as_str()
+                    .ok_or(format!("`{}` in [template] should be a string", key))?
+                    .to_string(),
+            )),
+            None => Ok(None),
+        }
+    };
+    let repo = match &template.template_repo {
+        Some(repo) => Some(repo.clone()),
+        None => from_config("repo")?,
+    };
+    let reference = if template.branch.is_some() || template.tag.is_some() {
+        (template.branch.clone(), template.tag.clone())
+    } else {
+        (from_config("branch")?, from_config("tag")?)
+    };
+    let reference = match reference {
+        (Some(_), Some(_)) => return Err("Only one of `branch` and `tag` can be set".into()),
+        (Some(branch), None) => Some(TemplateRef::Branch(branch)),
+        (None, Some(tag)) if is_commit(&tag) => Some(TemplateRef::Commit(tag)),
+        (None, Some(tag)) => Some(TemplateRef::Tag(tag)),
+        (None, None) => None,
+    };
+
+    let repo = repo.map(|r| normalize_repo(&r)).transpose()?;
+    match (repo, reference) {
+        (None, None) => {
+            let commit_or_branch =
+                resolve_template_version(version, template.offline, client, reporter).await?;
+            let reference = if commit_or_branch == "main" {
+                TemplateRef::Branch(commit_or_branch)
+            } else {
+                TemplateRef::Commit(commit_or_branch)
+            };
+            Ok(TemplateSource {
+                repo: OFFICIAL_TEMPLATE_REPO.to_string(),
+                reference,
+            })
+        }
+        (repo, reference) => {
+            if version.is_some() {
+                return Err("--version only selects versions of the official template, use a branch or tag of the custom template instead".into());
+            }
+            let source = TemplateSource {
+                repo: repo.unwrap_or_else(|| OFFICIAL_TEMPLATE_REPO.to_string()),
+                reference: reference.unwrap_or(TemplateRef::Default),
+            };
+            reporter.report(Event::Template(format!("Using template {}", source.name())));
+            Ok(source)
+        }
+    }
+}
+
+fn is_commit(reference: &str) -> bool {
+    reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// `https://github.com/owner/name.git` -> `owner/name`
+fn normalize_repo(repo: &str) -> Result<String, Box<dyn Error>> {
+    let repo = repo
+        .trim()
+        .trim_start_matches("https://")
+        .trim_start_matches("github.com/")
+        .trim_end_matches('/')
+        .trim_end_matches(".git");
+    match repo.split_once('/') {
+        Some((owner, name)) if !owner.is_empty() && !name.is_empty() && !name.contains('/') => {
+            Ok(repo.to_string())
+        }
+        _ => Err(format!(
+            "Invalid template repository '{}', it should be a GitHub repository as `owner/name`",
+            repo
+        )
+        .into()),
+    }
+}
@@ -0,0 +1,200 @@
+use crate::keyboard_toml::NO_KEY;
+use serde_json::{json, Value};
+use std::error::Error;
+
+/// Generate a `vial.json` from the `[keyboard]`, `[layout]` and `[split]` sections of a keyboard config
+///
+/// Matrix positions which are `No` in every layer of the keymap aren't keys. The keys of every split half
+/// are placed on a grid next to each other, the layout can be refined later in the Vial GUI or KLE. RMK
+/// doesn't control lighting over Vial, so `lighting` is always `none`.
+pub fn generate_vial_json(config: &toml::Table) -> Result<Value, Box<dyn Error>> {
+    let keyboard = config.get("keyboard");
+    let layout = config.get("layout").ok_or("No [layout] section found")?;
+    let dimension = |key: &str| -> Result<i64, Box<dyn Error>> {
+        layout
+            .get(key)
+            .and_then(|v| v.as_integer())
+            .filter(|v| *v > 0)
+            .ok_or_else(|| format!("Missing or invalid `{}` in [layout]", key).into())
+    };
+    let rows = dimension("rows")?;
+    let cols = dimension("cols")?;
+    let id = |key: &str| -> Option<String> {
+        keyboard
+            .and_then(|k| k.get(key))
+            .and_then(|v| v.as_integer())
+            .map(|v| format!("{:#06X}", v).replace("0X", "0x"))
+    };
+
+    // Every key as (x, y, row, col) on the grid
+    let mut keys = Vec::new();
+    let mut x_base = 0;
+    for (row_offset, col_offset, half_rows, half_cols) in halves(config, rows, cols) {
+        for r in row_offset..(row_offset + half_rows).min(rows) {
+            for c in col_offset..(col_offset + half_cols).min(cols) {
+                if is_key(layout, r, c) {
+                    keys.push((x_base + c - col_offset, r - row_offset, r, c));
+                }
+            }
+        }
+        // One unit of space between split halves
+        x_base += half_cols + 1;
+    }
+    keys.sort();
+    keys.sort_by_key(|(_, y, _, _)| *y);
+
+    // KLE rows advance y by one, gaps are written as `x` and `y` offsets before a key
+    let mut keymap: Vec<Vec<Value>> = Vec::new();
+    let (mut last_y, mut cursor) = (-1, 0);
+    for (x, y, r, c) in keys {
+        let mut offset = serde_json::Map::new();
+        if y != last_y {
+            if y > last_y + 1 {
+                offset.insert("y".to_string(), (y - last_y - 1).into());
+            }
+            keymap.push(Vec::new());
+            (last_y, cursor) = (y, 0);
+        }
+        if x > cursor {
+            offset.insert("x".to_string(), (x - cursor).into());
+        }
+        cursor = x + 1;
+        let row = keymap.last_mut().ok_or("Empty layout")?;
+        if !offset.is_empty() {
+            row.push(Value::Object(offset));
+        }
+        row.push(format!("{},{}", r, c).into());
+    }
+
+    Ok(json!({
+        "name": keyboard.and_then(|k| k.get("name")).and_then(|n| n.as_str()).unwrap_or("RMK Keyboard"),
+        "vendorId": id("vendor_id").unwrap_or_else(|| "0x4C4B".to_string()),
+        "productId": id("product_id").unwrap_or_else(|| "0x4643".to_string()),
+        "lighting": "none",
+        "matrix": { "rows": rows, "cols": cols },
+        "layouts": { "keymap": keymap },
+    }))
+}
+
+/// Whether `[layout]` has the matrix size and a keymap, so that vial.json can be generated from it
+pub fn has_full_layout(config: &toml::Table) -> bool {
+    config.get("layout").is_some_and(|layout| {
+        ["rows", "cols"]
+            .iter()
+            .all(|key| layout.get(key).and_then(|v| v.as_integer()).is_some())
+            && layout
+                .get("keymap")
+                .and_then(|k| k.as_array())
+                .is_some_and(|k| !k.is_empty())
+    })
+}
+
+/// Get the matrix area of every split half as (row_offset, col_offset, rows, cols), or the whole matrix
+/// if the keyboard isn't split or the halves have no size
+fn halves(config: &toml::Table, rows: i64, cols: i64) -> Vec<(i64, i64, i64, i64)> {
+    let Some(split) = config.get("split") else {
+        return vec![(0, 0, rows, cols)];
+    };
+    let half = |half: &toml::Value| {
+        let field = |key: &str| half.get(key).and_then(|v| v.as_integer()).unwrap_or(0);
+        (
+            field("row_offset"),
+            field("col_offset"),
+            field("rows"),
+            field("cols"),
+        )
+    };
+    let peripherals = split
+        .get("peripheral")
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten();
+    let halves: Vec<_> = split
+        .get("central")
+        .into_iter()
+        .chain(peripherals)
+        .map(half)
+        .filter(|(_, _, rows, cols)| *rows > 0 && *cols > 0)
+        .collect();
+    if halves.is_empty() {
+        return vec![(0, 0, rows, cols)];
+    }
+    halves
+}
+
+/// Whether a matrix position is a key, i.e. it isn't `No` in every layer or there is no keymap
+fn is_key(layout: &toml::Value, row: i64, col: i64) -> bool {
+    let Some(layers) = layout.get("keymap").and_then(|k| k.as_array()) else {
+        return true;
+    };
+    layers.iter().any(|layer| {
+        let key = layer
+            .get(row as usize)
+            .and_then(|r| r.get(col as usize))
+            .and_then(|k| k.as_str());
+        // Positions missing in the keymap are filled with `_` by RMK
+        key.is_none_or(|k| !k.eq_ignore_ascii_case(NO_KEY) && !k.eq_ignore_ascii_case("KC_NO"))
+    })
+}
+
+/// Check that a `vial.json` matches the matrix of a keyboard config
+///
+/// Returns every mismatch as a readable `vial.json` vs `keyboard.toml` difference.
+pub fn validate_vial_json(
+    vial: &Value,
+    config: &toml::Table,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let layout = config.get("layout").ok_or("No [layout] section found")?;
+    let dimension = |key: &str| {
+        layout
+            .get(key)
+            .and_then(|v| v.as_integer())
+            .ok_or_else(|| format!("Missing or invalid `{}` in [layout]", key))
+    };
+    let (rows, cols) = (dimension("rows")?, dimension("cols")?);
+
+    let mut mismatches = Vec::new();
+    for (key, expected) in [("rows", rows), ("cols", cols)] {
+        match vial["matrix"][key].as_i64() {
+            Some(found) if found == expected => {}
+            found => mismatches.push(format!(
+                "matrix.{}: vial.json {}, keyboard.toml {}",
+                key,
+                found.map_or("missing".to_string(), |f| f.to_string()),
+                expected
+            )),
+        }
+    }
+
+    let Some(keymap) = vial["layouts"]["keymap"].as_array() else {
+        mismatches.push("layouts.keymap: missing in vial.json".to_string());
+        return Ok(mismatches);
+    };
+    for (i, row) in keymap.iter().enumerate() {
+        // Rows of the KLE layout are arrays of key labels and property objects
+        for label in row
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|k| k.as_str())
+        {
+            // The matrix position is the first line of the label, others are layout options
+            let position = label.lines().next().unwrap_or_default();
+            let parsed = position.split_once(',').and_then(|(r, c)| {
+                Some((r.trim().parse::<i64>().ok()?, c.trim().parse::<i64>().ok()?))
+            });
+            match parsed {
+                Some((r, c)) if r < rows && c < cols => {}
+                Some(_) => mismatches.push(format!(
+                    "layouts.keymap[{}]: key \"{}\" is out of the {}x{} matrix of keyboard.toml",
+                    i, position, rows, cols
+                )),
+                None => mismatches.push(format!(
+                    "layouts.keymap[{}]: key \"{}\" isn't a `row,col` matrix position",
+                    i, position
+                )),
+            }
+        }
+    }
+    Ok(mismatches)
+}
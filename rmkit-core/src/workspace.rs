@@ -0,0 +1,167 @@
+use crate::report::{Event, Reporter};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
+
+/// Tables which cargo only reads from the workspace root, they're ignored in members
+const ROOT_ONLY_TABLES: [&str; 3] = ["profile", "patch", "replace"];
+/// Files which are only used from the workspace root, the project's copies are removed when the root has them
+const ROOT_ONLY_FILES: [&str; 2] = ["rust-toolchain.toml", "rust-toolchain"];
+
+/// Find the Cargo.toml of the workspace containing `project_dir`, the project's own Cargo.toml isn't considered
+pub(crate) fn find_workspace_root(project_dir: &Path) -> Option<PathBuf> {
+    let project_dir = project_dir.canonicalize().ok()?;
+    project_dir
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|path| {
+            fs::read_to_string(path)
+                .ok()
+                .and_then(|content| content.parse::<DocumentMut>().ok())
+                .is_some_and(|doc| doc.contains_key("workspace"))
+        })
+}
+
+/// Integrate a generated project into the Cargo workspace containing it, if any
+///
+/// The project is added to the workspace members, its profiles and patches are moved to the workspace root, and
+/// files which conflict with the workspace are removed. If the reporter declines joining the workspace, the project is
+/// made standalone with an empty `[workspace]` instead, otherwise cargo refuses to build it.
+pub(crate) fn integrate_into_workspace(
+    project_dir: &Path,
+    reporter: &mut dyn Reporter,
+) -> Result<(), Box<dyn Error>> {
+    let Some(root_manifest_path) = find_workspace_root(project_dir) else {
+        return Ok(());
+    };
+    let root_dir = root_manifest_path
+        .parent()
+        .ok_or("Invalid workspace root")?;
+    let member = project_dir
+        .canonicalize()?
+        .strip_prefix(root_dir)?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut root: DocumentMut = fs::read_to_string(&root_manifest_path)?.parse()?;
+    let workspace = root["workspace"].as_table_mut().ok_or(format!(
+        "Invalid [workspace] in {}",
+        root_manifest_path.display()
+    ))?;
+    let listed = |key: &str| {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .is_some_and(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_str())
+                    .any(|p| matches_member(p, &member))
+            })
+    };
+    // Excluded projects are standalone for cargo already
+    if listed("exclude") {
+        return Ok(());
+    }
+    let is_member = listed("members");
+
+    let manifest_path = project_dir.join("Cargo.toml");
+    let mut manifest: DocumentMut = fs::read_to_string(&manifest_path)?.parse()?;
+    let integrate = is_member || reporter.join_workspace(project_dir, root_dir)?;
+    if !integrate {
+        if !manifest.contains_key("workspace") {
+            manifest["workspace"] = toml_edit::table();
+            fs::write(&manifest_path, manifest.to_string())?;
+        }
+        reporter.report(Event::Generate(
+            "Generated a standalone project, which isn't part of the workspace".to_string(),
+        ));
+        return Ok(());
+    }
+
+    if !is_member {
+        let members = workspace
+            .entry("members")
+            .or_insert(toml_edit::value(toml_edit::Array::new()))
+            .as_array_mut()
+            .ok_or("Invalid workspace.members")?;
+        members.push(member.as_str());
+    }
+
+    // Move the tables which only work in the workspace root, unless the root has its own
+    manifest.remove("workspace");
+    let mut moved = DocumentMut::new();
+    for key in ROOT_ONLY_TABLES {
+        let Some(mut item) = manifest.remove(key) else {
+            continue;
+        };
+        if root.contains_key(key) {
+            reporter.report(Event::Warning(format!(
+                "[{}] of the project is dropped, cargo only uses the one in {}",
+                key,
+                root_manifest_path.display()
+            )));
+        } else {
+            rebase_paths(&mut item, &member);
+            moved.insert(key, item);
+            reporter.report(Event::Generate(format!(
+                "Moved [{}] of the project to {}",
+                key,
+                root_manifest_path.display()
+            )));
+        }
+    }
+    let mut root_content = root.to_string();
+    if !moved.is_empty() {
+        root_content = format!("{}\n{}", root_content.trim_end(), moved);
+    }
+    fs::write(&root_manifest_path, root_content)?;
+    fs::write(&manifest_path, manifest.to_string())?;
+
+    // The workspace has a single lock file in the root
+    let lock_path = project_dir.join("Cargo.lock");
+    if lock_path.exists() {
+        fs::remove_file(lock_path)?;
+    }
+    for file in ROOT_ONLY_FILES {
+        let path = project_dir.join(file);
+        if path.exists() && root_dir.join(file).exists() {
+            fs::remove_file(path)?;
+            reporter.report(Event::Info(format!("Using {} of the workspace", file)));
+        }
+    }
+
+    reporter.report(Event::Success(format!(
+        "{} is a member of the workspace {}",
+        member,
+        root_manifest_path.display()
+    )));
+    Ok(())
+}
+
+/// Whether a `members`/`exclude` entry matches the member path, `*` matches a whole path segment
+fn matches_member(pattern: &str, member: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim_end_matches('/').split('/').collect();
+    let member: Vec<&str> = member.split('/').collect();
+    pattern.len() == member.len()
+        && pattern
+            .iter()
+            .zip(member)
+            .all(|(p, m)| *p == "*" || *p == m)
+}
+
+/// Make `path = "..."` values relative to the member relative to the workspace root instead
+fn rebase_paths(item: &mut toml_edit::Item, member: &str) {
+    if let Some(table) = item.as_table_like_mut() {
+        for (key, value) in table.iter_mut() {
+            match value.as_str() {
+                Some(path) if key == "path" && Path::new(path).is_relative() => {
+                    *value = toml_edit::value(format!("{}/{}", member, path));
+                }
+                _ => rebase_paths(value, member),
+            }
+        }
+    }
+}
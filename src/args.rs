@@ -5,17 +5,29 @@ use clap::{Parser, Subcommand};
 pub struct Args {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// (Optional) PEM file with additional CA certificates to trust for downloads
+    #[arg(long, global = true)]
+    pub ca_cert: Option<String>,
+
+    /// Disable TLS certificate verification for downloads, use with caution
+    #[arg(long, global = true)]
+    pub insecure: bool,
+
+    /// Print a tree of the time spent in each step at the end
+    #[arg(long, global = true)]
+    pub trace_spans: bool,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Create a new RMK project from keyboard.toml and vial.json
     Create {
-        /// Path to keyboard.toml file
+        /// Path to keyboard.toml file, keyboard.json and keyboard.yaml are also accepted
         #[arg(long)]
         keyboard_toml_path: Option<String>,
 
-        /// Path to vial.json file
+        /// Path to vial.json file, it's generated from keyboard.toml if not given and `[layout]` has a keymap
         #[arg(long)]
         vial_json_path: Option<String>,
 
@@ -26,6 +38,9 @@ pub enum Commands {
         /// (Optional) RMK version
         #[arg(long)]
         version: Option<String>,
+
+        #[command(flatten)]
+        template: TemplateArgs,
     },
 
     /// Initialize a new RMK project with basic configuration
@@ -43,23 +58,627 @@ pub enum Commands {
         split: Option<bool>,
 
         /// (Optional) Local project template path
-        #[arg(long)]
+        #[arg(long, conflicts_with_all = ["template_repo", "branch", "tag"])]
         local_path: Option<String>,
 
         /// (Optional) RMK version
         #[arg(long)]
         version: Option<String>,
+
+        /// (Optional) Create the project from an existing keyboard.toml, chip and split are read from it
+        #[arg(long, conflicts_with_all = ["chip", "split", "local_path"])]
+        from_keyboard_toml: Option<String>,
+
+        /// (Optional) Path to vial.json file, it's generated from the layout of keyboard.toml if not given
+        #[arg(long, requires = "from_keyboard_toml")]
+        vial_json_path: Option<String>,
+
+        #[command(flatten)]
+        template: TemplateArgs,
     },
     /// Get chip name from keyboard.toml
     GetChip {
-        /// Path to keyboard.toml file
+        /// Path to keyboard.toml file, keyboard.json and keyboard.yaml are also accepted
         #[arg(long)]
         keyboard_toml_path: String,
     },
     /// Get project name from keyboard.toml
     GetProjectName {
+        /// Path to keyboard.toml file, keyboard.json and keyboard.yaml are also accepted
+        #[arg(long)]
+        keyboard_toml_path: String,
+    },
+    /// List the known chips and boards, or show the details of one
+    Chips {
+        #[command(subcommand)]
+        command: ChipsCommands,
+    },
+    /// Manage the template cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Edit the keymap in keyboard.toml
+    Keymap {
+        #[command(subcommand)]
+        command: KeymapCommands,
+    },
+    /// Keyboard layout tools
+    Layout {
+        #[command(subcommand)]
+        command: LayoutCommands,
+    },
+    /// Convert the config of another firmware into keyboard.toml
+    Import {
+        #[command(subcommand)]
+        command: ImportCommands,
+    },
+    /// Convert keyboard.toml into the config of another firmware
+    Export {
+        #[command(subcommand)]
+        command: ExportCommands,
+    },
+    /// Check a generated project and repair common build failures interactively
+    Repair {
+        /// (Optional) Project directory, defaults to the current directory
+        #[arg(long)]
+        project_dir: Option<String>,
+
+        /// Run `cargo build --release` instead of `cargo check`, which catches linker errors like memory overflow
+        #[arg(long)]
+        build: bool,
+
+        /// Apply all fixes without asking
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Print a summary of a project: keyboard, chip, matrix, split, features, versions and build artifacts
+    Info {
+        /// (Optional) Project directory, defaults to the current directory
+        #[arg(long)]
+        project_dir: Option<String>,
+
+        /// Print the summary as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Build the firmware, flash it and attach to its log output
+    Run {
+        /// (Optional) Project directory, defaults to the current directory
+        #[arg(long)]
+        project_dir: Option<String>,
+
+        /// (Optional) Binary to flash and attach to, e.g. `peripheral` of a split keyboard, all halves are flashed by default
+        #[arg(long)]
+        bin: Option<String>,
+
+        /// Don't install a missing compilation target of the chip with rustup
+        #[arg(long)]
+        no_install: bool,
+
+        #[command(flatten)]
+        cargo: CargoArgs,
+    },
+    /// Show the logs of a flashed keyboard, defmt over RTT with probe-rs or the USB serial port
+    Monitor {
+        /// (Optional) Project directory, defaults to the current directory
+        #[arg(long)]
+        project_dir: Option<String>,
+
+        /// (Optional) Binary whose ELF decodes the defmt logs, e.g. `peripheral` of a split keyboard
+        #[arg(long, conflicts_with = "serial")]
+        bin: Option<String>,
+
+        /// Read the USB serial port instead of RTT, the port is detected by the vendor id if it's not given
+        #[arg(long, value_name = "PORT", num_args = 0..=1)]
+        serial: Option<Option<String>>,
+
+        /// Only show lines containing the text, ignoring case
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Prefix lines with the seconds since the monitor started
+        #[arg(long)]
+        timestamps: bool,
+    },
+    /// Show the flash usage of the release build by crate, rmk subsystem and function
+    Size {
+        /// (Optional) Project directory, defaults to the current directory
+        #[arg(long)]
+        project_dir: Option<String>,
+
+        /// (Optional) Binary to analyze, e.g. `peripheral` of a split keyboard
+        #[arg(long)]
+        bin: Option<String>,
+
+        /// Number of entries shown in each table
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+
+        /// Print the usage, the memory of the chip and the tables as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Build all binaries and bundle their firmware files, checksums and a manifest into a zip archive
+    Release {
+        /// (Optional) Project directory, defaults to the current directory
+        #[arg(long)]
+        project_dir: Option<String>,
+
+        /// (Optional) Name of the firmware files, a template with keyboard, bin, version, chip, rmk, date and
+        /// git_sha, defaults to `name` of [build.release] in keyboard.toml or {{ keyboard }}-{{ bin }}-v{{ version }}
+        #[arg(long)]
+        name: Option<String>,
+
+        /// (Optional) Path of the archive, defaults to <keyboard>-v<version>.zip in the output directory
+        #[arg(long)]
+        output: Option<String>,
+
+        /// (Optional) Output directory of the archive, defaults to `out_dir` of [build.release] in keyboard.toml or
+        /// the project
+        #[arg(long, conflicts_with = "output")]
+        out_dir: Option<String>,
+
+        /// (Optional) Firmware formats to bundle, e.g. uf2,hex, defaults to all formats the chip supports
+        #[arg(long, value_delimiter = ',')]
+        format: Vec<String>,
+
+        /// Don't install a missing compilation target of the chip with rustup
+        #[arg(long)]
+        no_install: bool,
+
+        #[command(flatten)]
+        cargo: CargoArgs,
+    },
+    /// Convert firmware to UF2 and inspect firmware files
+    Uf2 {
+        #[command(subcommand)]
+        command: Uf2Commands,
+    },
+    /// Show the blocks, family id and extension tags of a UF2 file, e.g. one bundled by `rmkit release`, same as
+    /// `rmkit uf2 inspect`
+    Inspect {
+        /// Path of the UF2, Intel HEX or raw binary file
+        file: String,
+    },
+    /// Remove the firmware files generated in a project, and optionally its build output and the template cache
+    Clean {
+        /// (Optional) Project directory, defaults to the current directory
+        #[arg(long)]
+        project_dir: Option<String>,
+
+        /// Also remove the build output with `cargo clean`
+        #[arg(long)]
+        target: bool,
+
+        /// Also remove the cached templates, like `rmkit cache clean`
+        #[arg(long)]
+        cache: bool,
+    },
+    /// Bundle a project and tool versions into an archive for reproducing build failures
+    Capture {
+        /// (Optional) Project directory, defaults to the current directory
+        #[arg(long)]
+        project_dir: Option<String>,
+
+        /// Path of the created archive
+        #[arg(long, default_value = "rmkit-capture.zip")]
+        output: String,
+    },
+    /// Rebuild a project captured by `rmkit capture` in a temporary directory
+    Replay {
+        /// Archive created by `rmkit capture`
+        archive: String,
+    },
+    /// Print the JSON Schema of keyboard.toml
+    Schema {
+        /// (Optional) Write the schema to a file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Check keyboard.toml
+    Check {
+        /// Path to keyboard.toml file, keyboard.json and keyboard.yaml are also accepted
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// Validate the config against the JSON Schema of keyboard.toml
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Rewrite deprecated keys and sections of keyboard.toml into the current format
+    Migrate {
+        /// Path to keyboard.toml file
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// (Optional) Write the migrated config to a file instead of editing it in place
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Convert keyboard config between TOML, JSON and YAML formats
+    ConvertConfig {
+        /// Input config file, the format is detected by extension
+        #[arg(long)]
+        input: String,
+
+        /// Output config file, the format is detected by extension
+        #[arg(long)]
+        output: String,
+    },
+    /// Print the shell completion script, e.g. `rmkit completions bash > /etc/bash_completion.d/rmkit`
+    Completions {
+        /// Shell to complete in
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page of rmkit
+    Man {
+        /// (Optional) Write the man pages of rmkit and all subcommands to a directory instead of stdout
+        #[arg(long)]
+        out_dir: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ChipsCommands {
+    /// List the known chips and boards
+    List {
+        /// Only list chips which can be used for split keyboards
+        #[arg(long)]
+        split_only: bool,
+
+        /// Only list chips with a known UF2 family id
+        #[arg(long)]
+        uf2_only: bool,
+
+        /// (Optional) Only list chips of the vendor, e.g. stm32, nrf, esp32
+        #[arg(long)]
+        vendor: Option<String>,
+    },
+    /// Show the details of a chip or board
+    Info {
+        /// Chip or board name, e.g. nrf52840 or nice!nano
+        chip: String,
+    },
+    /// Check the chip database, including ~/.config/rmkit/chips.toml, for inconsistent entries
+    Audit {
+        /// (Optional) uf2families.json of microsoft/uf2, the UF2 family ids are checked against it too
+        #[arg(long)]
+        uf2_families: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Uf2Commands {
+    /// Convert an ELF, Intel HEX or raw binary firmware file to UF2, e.g. one built outside of rmkit
+    Convert {
+        /// Path of the firmware file, its format is taken from the extension: .elf, .hex or .bin
+        input: String,
+
+        /// (Optional) Chip or board the firmware is for, which gives the UF2 family id
+        #[arg(long, required_unless_present = "family_id")]
+        chip: Option<String>,
+
+        /// (Optional) UF2 family id, e.g. 0xada52840, instead of the one of the chip
+        #[arg(long)]
+        family_id: Option<String>,
+
+        /// (Optional) Address of a raw binary, defaults to the flash origin of the chip
+        #[arg(long)]
+        base: Option<String>,
+
+        /// (Optional) Path of the UF2 file, defaults to the input with the .uf2 extension
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Show the address ranges and gaps of a UF2, Intel HEX or raw binary file, and the blocks, family id and
+    /// extension tags of UF2
+    Inspect {
+        /// Path of the firmware file, its format is taken from the extension: .uf2, .hex or .bin
+        file: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommands {
+    /// Remove all cached templates
+    Clean,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeymapCommands {
+    /// Set a key in a layer
+    Set {
+        /// Path to keyboard.toml file
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// Layer index
+        #[arg(long)]
+        layer: usize,
+
+        /// Position of the key in the matrix, as `row,col`
+        #[arg(long)]
+        pos: String,
+
+        /// Key, e.g. A, MO(1), KC_VOLU
+        key: String,
+    },
+    /// Copy all keys of a layer to another layer
+    CopyLayer {
         /// Path to keyboard.toml file
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// Source layer index
+        from: usize,
+
+        /// Target layer index
+        to: usize,
+    },
+    /// Set all keys of a layer to transparent (`_`)
+    Clear {
+        /// Path to keyboard.toml file
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// Layer index
         #[arg(long)]
+        layer: usize,
+    },
+    /// Seed the keymap from a Vial `.vil` or VIA backup
+    Import {
+        /// Path to keyboard.toml file
+        #[arg(long, default_value = "./keyboard.toml")]
         keyboard_toml_path: String,
+
+        /// Path to the `.vil` or VIA `.json` backup
+        #[arg(long)]
+        backup: String,
     },
+    /// Export the keymap as a Vial `.vil` or VIA backup
+    Export {
+        /// Path to keyboard.toml file
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// Path of the backup, a `.vil` file for Vial and a VIA backup otherwise
+        #[arg(long)]
+        output: String,
+    },
+    /// Render the layers of the keymap as ASCII art, or as an SVG or HTML page
+    Render {
+        /// Path to keyboard.toml file
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// (Optional) Path to vial.json for the key positions, defaults to vial.json next to keyboard.toml
+        #[arg(long)]
+        vial_json_path: Option<String>,
+
+        /// (Optional) Render only this layer
+        #[arg(long)]
+        layer: Option<usize>,
+
+        /// (Optional) Output file, `.svg` and `.html` files are rendered with the layout, others as ASCII.
+        /// ASCII is printed if it's not set
+        #[arg(long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LayoutCommands {
+    /// Validate the matrix wiring in keyboard.toml
+    Validate {
+        /// Path to keyboard.toml file, keyboard.json and keyboard.yaml are also accepted
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// Path to KiCad netlist export (.net) to check the matrix wiring against
+        #[arg(long)]
+        against_pcb: String,
+
+        /// (Optional) Index of the split peripheral which the PCB belongs to, the central is checked by default
+        #[arg(long)]
+        peripheral: Option<usize>,
+
+        /// Reference prefix of key switches in the netlist
+        #[arg(long, default_value = "SW")]
+        switch_prefix: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ImportCommands {
+    /// Import a QMK info.json or keyboard.json: keyboard info, matrix, split, encoders and indicators
+    Qmk {
+        /// Path to the QMK info.json or keyboard.json
+        #[arg(long)]
+        info: String,
+
+        /// (Optional) Path to the rules.mk of the keyboard, the one next to info.json is used by default
+        #[arg(long)]
+        rules: Option<String>,
+
+        /// Output config file, the format is detected by extension
+        #[arg(long, default_value = "./keyboard.toml")]
+        output: String,
+
+        /// Overwrite the output file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Import an Ergogen config or points output into keyboard.toml and vial.json
+    Ergogen {
+        /// Path to the Ergogen config, or the points.yaml it outputs
+        #[arg(long)]
+        input: String,
+
+        /// Chip or board of the keyboard (e.g., nrf52840, nice!nano)
+        #[arg(long)]
+        chip: String,
+
+        /// Output config file, the format is detected by extension
+        #[arg(long, default_value = "./keyboard.toml")]
+        output: String,
+
+        /// Output vial.json file
+        #[arg(long, default_value = "./vial.json")]
+        vial_json_path: String,
+
+        /// Overwrite the output files if they exist
+        #[arg(long)]
+        force: bool,
+    },
+    /// Import the layers of a QMK keymap.c into the keymap of keyboard.toml
+    QmkKeymap {
+        /// Path to the QMK keymap.c
+        #[arg(long)]
+        keymap: String,
+
+        /// (Optional) Path to the info.json or keyboard.json of the keyboard, for the matrix positions of the layout
+        #[arg(long)]
+        info: Option<String>,
+
+        /// Path to keyboard.toml file
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+    },
+    /// Convert a VIA definition into a vial.json for RMK
+    Via {
+        /// Path to the VIA definition
+        #[arg(long)]
+        definition: String,
+
+        /// Path to keyboard.toml file, it's checked against the definition and gets `unlock_keys` if it exists
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// Output vial.json file
+        #[arg(long, default_value = "./vial.json")]
+        output: String,
+
+        /// Overwrite the output file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Import a Keyboard Layout Editor layout into the matrix of keyboard.toml and the layout of vial.json
+    Kle {
+        /// Path to the KLE layout, the downloaded JSON or the raw data
+        #[arg(long)]
+        kle: String,
+
+        /// Path to keyboard.toml file
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// Path to vial.json file, it's created if it doesn't exist
+        #[arg(long, default_value = "./vial.json")]
+        vial_json_path: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportCommands {
+    /// Export a QMK info.json: keyboard info, matrix, split, encoders, indicators and the layout of vial.json
+    Qmk {
+        /// Path to keyboard.toml file
+        #[arg(long, default_value = "./keyboard.toml")]
+        keyboard_toml_path: String,
+
+        /// Path to vial.json file, the layout is placed on a grid of the matrix if it doesn't exist
+        #[arg(long, default_value = "./vial.json")]
+        vial_json_path: String,
+
+        /// Output info.json
+        #[arg(long, default_value = "./info.json")]
+        output: String,
+
+        /// Overwrite the output file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Options of generating the project from the template
+#[derive(clap::Args, Debug, Default)]
+pub struct TemplateArgs {
+    /// (Optional) GitHub repository of the template, as `owner/name`, e.g. a fork of HaoboGu/rmk-template
+    #[arg(long, conflicts_with = "version")]
+    pub template_repo: Option<String>,
+
+    /// (Optional) Branch of the template repository
+    #[arg(long, conflicts_with_all = ["version", "tag"])]
+    pub branch: Option<String>,
+
+    /// (Optional) Tag or commit of the template repository
+    #[arg(long, conflicts_with = "version")]
+    pub tag: Option<String>,
+
+    /// Only use cached templates and template versions, without network access
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Overwrite existing files in the project directory without asking
+    #[arg(long)]
+    pub force: bool,
+
+    /// Print the files which would be generated, with diffs against an existing project, without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Variable of the template, can be repeated, e.g. `--var usb_pid=0x4643`
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_var)]
+    pub vars: Vec<(String, String)>,
+}
+
+impl From<TemplateArgs> for rmkit_core::TemplateOptions {
+    fn from(args: TemplateArgs) -> Self {
+        Self {
+            template_repo: args.template_repo,
+            branch: args.branch,
+            tag: args.tag,
+            offline: args.offline,
+            force: args.force,
+            dry_run: args.dry_run,
+            vars: args.vars,
+        }
+    }
+}
+
+/// Extra arguments of cargo when building the firmware, added to `[build.cargo]` of keyboard.toml
+#[derive(clap::Args, Debug, Default)]
+pub struct CargoArgs {
+    /// (Optional) Features to enable, e.g. `rmk/storage`, can be repeated or comma separated
+    #[arg(long, value_delimiter = ',')]
+    pub features: Vec<String>,
+
+    /// Disable the default features of the project
+    #[arg(long)]
+    pub no_default_features: bool,
+
+    /// (Optional) Other arguments of cargo, after `--`
+    #[arg(last = true)]
+    pub cargo_args: Vec<String>,
+}
+
+impl From<CargoArgs> for rmkit_core::firmware::CargoArgs {
+    fn from(args: CargoArgs) -> Self {
+        Self {
+            features: args.features,
+            no_default_features: args.no_default_features,
+            args: args.cargo_args,
+        }
+    }
+}
+
+/// Parse a `KEY=VALUE` template variable
+fn parse_var(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("`{}` isn't in the KEY=VALUE format", var)),
+    }
 }
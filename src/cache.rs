@@ -0,0 +1,25 @@
+use rmkit_core::template_cache_dir;
+use std::error::Error;
+use std::fs;
+
+/// Remove all cached templates
+pub(crate) fn clean_cache() -> Result<(), Box<dyn Error>> {
+    let cache_dir = template_cache_dir()?;
+    if !cache_dir.exists() {
+        println!("✅ The template cache is already empty");
+        return Ok(());
+    }
+    let archives: Vec<u64> = fs::read_dir(&cache_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|e| e == "zip"))
+        .filter_map(|entry| entry.metadata().ok().map(|m| m.len()))
+        .collect();
+    fs::remove_dir_all(&cache_dir)?;
+    println!(
+        "🧹 Removed {} cached template(s), {:.1} MB, from {}",
+        archives.len(),
+        archives.iter().sum::<u64>() as f64 / 1024.0 / 1024.0,
+        cache_dir.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use rmkit_core::interrupt::{self, TempPath};
+use rmkit_core::{Classify, FailureKind};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// File in the archive which records the environment of the captured project
+const CAPTURE_INFO: &str = "rmkit-capture.toml";
+
+/// Bundle the project in `project_dir` and the versions of the tools into a reproducer archive
+///
+/// Build output and git data are skipped, everything else in the project is captured, including keyboard.toml, vial.json and Cargo.lock
+pub(crate) fn capture_project(
+    project_dir: Option<String>,
+    output: &str,
+) -> Result<(), Box<dyn Error>> {
+    let project_dir = PathBuf::from(project_dir.unwrap_or_else(|| ".".to_string()));
+    if !project_dir.join("Cargo.toml").exists() {
+        return Err(format!("No Cargo.toml found in {}", project_dir.display()).into());
+    }
+    let output_path = PathBuf::from(output);
+    // Don't capture the archive itself when it's written into the project
+    let output_dir = output_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let output_abs = fs::canonicalize(output_dir)
+        .ok()
+        .and_then(|dir| output_path.file_name().map(|name| dir.join(name)));
+
+    // An unfinished archive is removed on failure or interrupt
+    let archive = TempPath::new(&output_path);
+    let mut zip = ZipWriter::new(File::create(archive.path())?);
+    let options = SimpleFileOptions::default();
+
+    let walker = walkdir::WalkDir::new(&project_dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "target" && e.file_name() != ".git")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file());
+    let mut count = 0;
+    for entry in walker {
+        let path = entry.path();
+        if output_abs.is_some() && fs::canonicalize(path).ok() == output_abs {
+            continue;
+        }
+        let relative = path.strip_prefix(&project_dir)?;
+        let name = relative
+            .iter()
+            .map(|s| s.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        zip.start_file(name, options)?;
+        io::copy(&mut File::open(path)?, &mut zip)?;
+        count += 1;
+    }
+
+    zip.start_file(CAPTURE_INFO, options)?;
+    zip.write_all(toml::to_string(&environment_info())?.as_bytes())?;
+    zip.finish()?;
+    archive.keep();
+
+    println!(
+        "✅ Captured {} files and tool versions to {}",
+        count,
+        output_path.display()
+    );
+    Ok(())
+}
+
+/// Extract an archive created by `rmkit capture` to a temporary dir and build it
+pub(crate) fn replay_capture(archive: &str) -> Result<(), Box<dyn Error>> {
+    let mut zip = ZipArchive::new(
+        File::open(archive).map_err(|e| format!("Failed to open {}: {}", archive, e))?,
+    )
+    .map_err(|e| format!("Invalid capture archive {}: {}", archive, e))?;
+    let replay_dir = std::env::temp_dir().join(format!("rmkit-replay-{}", process::id()));
+    if replay_dir.exists() {
+        fs::remove_dir_all(&replay_dir)?;
+    }
+    zip.extract(&replay_dir)?;
+
+    let info_path = replay_dir.join(CAPTURE_INFO);
+    if !info_path.exists() {
+        return Err(format!("{} is not created by `rmkit capture`", archive).into());
+    }
+    let captured: toml::Table = toml::from_str(&fs::read_to_string(&info_path)?)?;
+    fs::remove_file(&info_path)?;
+
+    // Show where the environment differs from the captured one
+    let current = environment_info();
+    for (tool, version) in &current {
+        let captured_version = captured
+            .get(tool)
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown");
+        let version = version.as_str().unwrap_or("unknown");
+        if captured_version == version {
+            println!("  {}: {}", tool, version);
+        } else {
+            println!("⚠️  {}: {} (captured: {})", tool, version, captured_version);
+        }
+    }
+
+    println!("🔨 Building captured project in {}", replay_dir.display());
+    let status = tracing::info_span!("cargo", command = "build")
+        .in_scope(|| {
+            interrupt::status(
+                Command::new("cargo")
+                    .args(["build", "--release"])
+                    .current_dir(&replay_dir),
+            )
+        })
+        .or_fail(FailureKind::Toolchain)?;
+    if !status.success() {
+        return Err(format!(
+            "Captured project failed to build, reproduce it in {}",
+            replay_dir.display()
+        ))
+        .or_fail(FailureKind::Build);
+    }
+    println!(
+        "✅ Captured project builds successfully in {}",
+        replay_dir.display()
+    );
+    Ok(())
+}
+
+/// Versions of rmkit and the Rust toolchain, and the host platform
+fn environment_info() -> toml::Table {
+    let mut info = toml::Table::new();
+    info.insert("rmkit".to_string(), env!("CARGO_PKG_VERSION").into());
+    for tool in ["rustc", "cargo"] {
+        let version = Command::new(tool)
+            .arg("--version")
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .unwrap_or_else(|| "not installed".to_string());
+        info.insert(tool.to_string(), version.into());
+    }
+    info.insert(
+        "host".to_string(),
+        format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS).into(),
+    );
+    info
+}
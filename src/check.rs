@@ -0,0 +1,165 @@
+use crate::lint::{display_path, lint_keyboard_config, Lint};
+use rmkit_core::check_vial_json;
+use rmkit_core::keyboard_toml::{read_keyboard_config, ConfigFormat};
+use rmkit_core::rmk_version::check_rmk_version;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// JSON Schema of the keyboard.toml format
+const KEYBOARD_SCHEMA: &str = include_str!("keyboard.schema.json");
+
+/// Print the JSON Schema of keyboard.toml, or write it to `output`
+pub(crate) fn export_schema(output: Option<&str>) -> Result<(), Box<dyn Error>> {
+    match output {
+        Some(path) => {
+            fs::write(path, KEYBOARD_SCHEMA)?;
+            println!("✅ JSON Schema written to {}", path);
+        }
+        None => print!("{}", KEYBOARD_SCHEMA),
+    }
+    Ok(())
+}
+
+/// Check a keyboard config, `schema` validates it against the JSON Schema of keyboard.toml
+///
+/// The config is also checked for semantic problems, such as pins which don't match the matrix size.
+/// A `vial.json` next to the config is checked against its matrix.
+/// If there's an RMK project next to the config, the rmk version in its `Cargo.toml` is checked against the features
+/// used by the config.
+pub(crate) fn check_keyboard_config(path: &str, schema: bool) -> Result<(), Box<dyn Error>> {
+    let config = read_keyboard_config(path)?;
+
+    let mut problems = Vec::new();
+    if schema {
+        problems.extend(schema_errors(&serde_json::to_value(&config)?)?);
+    }
+    problems.extend(lint_keyboard_config(&config));
+    if !problems.is_empty() {
+        let locator = Locator::new(path);
+        for problem in &problems {
+            println!("❌ {}", locator.locate(problem));
+        }
+        return Err(format!("{} has {} problem(s)", path, problems.len()).into());
+    }
+
+    let vial_json_path = Path::new(path).with_file_name("vial.json");
+    if vial_json_path.exists() {
+        check_vial_json(&vial_json_path, &config)?;
+    }
+
+    // The firmware project is next to keyboard.toml, check that its rmk version supports the config
+    let cargo_toml_path = Path::new(path).with_file_name("Cargo.toml");
+    if is_rmk_project(&cargo_toml_path) {
+        let problems = match check_rmk_version(&config, &cargo_toml_path) {
+            Ok(problems) => problems,
+            Err(e) => {
+                println!(
+                    "⚠️  Skipped checking the rmk version of {}: {}",
+                    cargo_toml_path.display(),
+                    e
+                );
+                Vec::new()
+            }
+        };
+        if !problems.is_empty() {
+            for problem in &problems {
+                println!("❌ {}", problem);
+            }
+            return Err(format!(
+                "{} uses features which aren't supported by the rmk version in {}",
+                path,
+                cargo_toml_path.display()
+            )
+            .into());
+        }
+    }
+
+    println!("✅ {} is valid", path);
+    Ok(())
+}
+
+/// Whether a Cargo.toml depends on rmk, other projects next to keyboard.toml aren't checked
+fn is_rmk_project(cargo_toml_path: &Path) -> bool {
+    fs::read_to_string(cargo_toml_path)
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .is_some_and(|manifest| {
+            manifest
+                .get("dependencies")
+                .and_then(|d| d.get("rmk"))
+                .is_some()
+        })
+}
+
+/// Validate a config against the JSON Schema
+fn schema_errors(config: &Value) -> Result<Vec<Lint>, Box<dyn Error>> {
+    let schema: Value = serde_json::from_str(KEYBOARD_SCHEMA)?;
+    let validator = jsonschema::validator_for(&schema).map_err(|e| e.to_string())?;
+    Ok(validator
+        .iter_errors(config)
+        .map(|e| Lint {
+            // `/matrix/row_pins/0` -> `["matrix", "row_pins", "0"]`
+            path: e
+                .instance_path
+                .to_string()
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect(),
+            message: e.to_string(),
+        })
+        .collect())
+}
+
+/// Find where a value is in the config file, only TOML files keep the positions of values
+struct Locator {
+    path: String,
+    doc: Option<toml_edit::Document<String>>,
+}
+
+impl Locator {
+    fn new(path: &str) -> Self {
+        let doc = (ConfigFormat::from_path(path) == ConfigFormat::Toml)
+            .then(|| fs::read_to_string(path).ok())
+            .flatten()
+            .and_then(|content| toml_edit::Document::parse(content).ok());
+        Self {
+            path: path.to_string(),
+            doc,
+        }
+    }
+
+    /// Get `:line:col` of the value at `path`
+    fn position(&self, path: &[String]) -> Option<String> {
+        let doc = self.doc.as_ref().filter(|_| !path.is_empty())?;
+        let mut item = doc.as_item();
+        for segment in path {
+            item = match segment.parse::<usize>() {
+                Ok(index) => item.get(index)?,
+                Err(_) => item.get(segment.as_str())?,
+            };
+        }
+        let before = &doc.raw()[..item.span()?.start];
+        let line = before.matches('\n').count() + 1;
+        let col = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        Some(format!(":{}:{}", line, col))
+    }
+
+    /// Format a problem as `file:line:col: key.path: message`
+    fn locate(&self, problem: &Lint) -> String {
+        let position = self.position(&problem.path).unwrap_or_default();
+        if problem.path.is_empty() {
+            format!("{}{}: {}", self.path, position, problem.message)
+        } else {
+            format!(
+                "{}{}: {}: {}",
+                self.path,
+                position,
+                display_path(&problem.path),
+                problem.message
+            )
+        }
+    }
+}
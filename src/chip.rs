@@ -1,1059 +1,149 @@
-use std::collections::HashMap;
+use rmkit_core::chip::{
+    audit_database, audit_uf2_families, database_summary, get_board_chip_map, get_board_info,
+    get_chip_options, get_chip_packages, get_chip_target, get_uf2_family, split_transports,
+    user_database_error,
+};
+use rmkit_core::keyboard_toml::{template_folder, uf2_key};
+use std::error::Error;
+use std::fs;
 
-pub fn get_board_chip_map() -> HashMap<&'static str, &'static str> {
-    let mut map = HashMap::new();
+/// Print the known chips and boards
+///
+/// # Arguments
+/// * `split_only` - Only list chips which can be used for split keyboards
+/// * `uf2_only` - Only list chips with a known UF2 family id
+/// * `vendor` - Only list chips whose name starts with the vendor prefix, e.g. `stm32` or `nrf`
+pub(crate) fn list_chips(split_only: bool, uf2_only: bool, vendor: Option<&str>) {
+    warn_user_database();
+    let board_chip_map = get_board_chip_map();
+    let mut seen = Vec::new();
+    for option in get_chip_options(false) {
+        if seen.contains(&option) {
+            continue;
+        }
+        seen.push(option);
 
-    // Nordic boards
-    map.insert("nrfmicro", "nrf52840");
-    map.insert("bluemicro840", "nrf52840");
-    map.insert("puchi_ble", "nrf52840");
-    map.insert("nice!nano", "nrf52840");
-    map.insert("nice!nano_v2", "nrf52840");
-    map.insert("XIAO BLE", "nrf52840");
-    map.insert("Pi Pico W", "pico_w");
-    map.insert("Pico W", "pico_w");
-    map.insert("pi_pico_w", "pico_w");
-    map.insert("pico_w", "pico_w");
+        let chip = board_chip_map.get(option).copied().unwrap_or(option);
+        let transports = split_transports(option);
+        let split = !transports.is_empty();
+        let uf2_family = get_uf2_family(option);
+        if (split_only && !split)
+            || (uf2_only && uf2_family.is_none())
+            || vendor.is_some_and(|v| !chip.to_lowercase().starts_with(&v.to_lowercase()))
+        {
+            continue;
+        }
 
-    map
+        let mut tags = Vec::new();
+        if chip != option {
+            tags.push(format!("board with {}", chip));
+        }
+        if split {
+            tags.push(format!("split over {}", transports.join("/")));
+        }
+        if uf2_family.is_some() {
+            tags.push("uf2".to_string());
+        }
+        if tags.is_empty() {
+            println!("{}", option);
+        } else {
+            println!("{} ({})", option, tags.join(", "));
+        }
+    }
 }
 
-/// All supported chips
-pub(crate) fn get_chip_options(split: bool) -> Vec<&'static str> {
+/// Print the details of a chip or board
+pub(crate) fn print_chip_info(chip_or_board: &str) -> Result<(), Box<dyn Error>> {
+    warn_user_database();
+    let chip_or_board = resolve_chip(chip_or_board)?;
+    let chip_or_board = chip_or_board.as_str();
+    let chip = get_board_chip_map()
+        .get(chip_or_board)
+        .copied()
+        .unwrap_or(chip_or_board);
+    let transports = split_transports(chip_or_board);
+    let split = !transports.is_empty();
+    if chip != chip_or_board {
+        println!("Board:          {}", chip_or_board);
+    }
+    println!("Chip:           {}", chip);
     if split {
-        vec![
-            "rp2040",
-            "nrf52840",
-            "Pi Pico W",
-            "esp32c3",
-            "esp32c6",
-            "esp32s3",
-        ]
+        println!("Split:          over {}", transports.join(", "));
     } else {
-        vec![
-            "nrf52840",
-            "rp2040",
-            "Pi Pico W",
-            "nrf52833",
-            "nrf52832",
-            "nrf52811",
-            "nrf52810",
-            "esp32c3",
-            "esp32s3",
-            "esp32c6",
-            "nice!nano_v2",
-            "XIAO BLE",
-            "nice!nano",
-            "nrfmicro",
-            "bluemicro840",
-            "puchi_ble",
-            "stm32h730vb",
-            "stm32g473qc",
-            "stm32g0c1ve",
-            "stm32f207ig",
-            "stm32h562vi",
-            "stm32u545ne",
-            "stm32l432kb",
-            "stm32f302ve",
-            "stm32l072v8",
-            "stm32g474ce",
-            "stm32g0c1me",
-            "stm32h747ag",
-            "stm32l4p5cg",
-            "stm32f102c8",
-            "stm32l083rz",
-            "stm32f750n8",
-            "stm32h7b0ib",
-            "stm32f102r6",
-            "stm32f302rd",
-            "stm32f439ig",
-            "stm32f413vg",
-            "stm32u595qj",
-            "stm32wb35ce",
-            "stm32f413mg",
-            "stm32g473pe",
-            "stm32f413ch",
-            "stm32f427ig",
-            "stm32f215rg",
-            "stm32f207vf",
-            "stm32h7b0rb",
-            "stm32l452ce",
-            "stm32l072rb",
-            "stm32l552ce",
-            "stm32l152c6-a",
-            "stm32l152vd",
-            "stm32g0b1re",
-            "stm32f479ag",
-            "stm32l162rc",
-            "stm32f479zg",
-            "stm32h742vg",
-            "stm32f217vg",
-            "stm32f205rf",
-            "stm32l422tb",
-            "stm32f207ze",
-            "stm32g441kb",
-            "stm32f469ve",
-            "stm32l152vd-x",
-            "stm32h723ze",
-            "stm32f437vg",
-            "stm32f767ng",
-            "stm32h733vg",
-            "stm32l152re",
-            "stm32u575vi",
-            "stm32f205vg",
-            "stm32f215ze",
-            "stm32f746bg",
-            "stm32f429vg",
-            "stm32u5a9nj",
-            "stm32l475vc",
-            "stm32h7b3ai",
-            "stm32f479ii",
-            "stm32f103re",
-            "stm32u575oi",
-            "stm32l476ze",
-            "stm32f102c4",
-            "stm32f107rb",
-            "stm32h7b3zi",
-            "stm32u083hc",
-            "stm32f401cd",
-            "stm32f103vd",
-            "stm32f723ie",
-            "stm32g441rb",
-            "stm32g471me",
-            "stm32l4p5re",
-            "stm32l496qe",
-            "stm32l062k8",
-            "stm32f745vg",
-            "stm32g471ve",
-            "stm32l151cb",
-            "stm32l152qc",
-            "stm32f042c6",
-            "stm32f107vc",
-            "stm32f303cb",
-            "stm32g0b1ke",
-            "stm32l4s5qi",
-            "stm32f413zh",
-            "stm32f103zg",
-            "stm32l476rg",
-            "stm32l053r6",
-            "stm32l552ze",
-            "stm32f765bi",
-            "stm32l072kb",
-            "stm32f070f6",
-            "stm32wb55vc",
-            "stm32f469vi",
-            "stm32h747ii",
-            "stm32f105r8",
-            "stm32u073cb",
-            "stm32l162vc-a",
-            "stm32f105rc",
-            "stm32g473re",
-            "stm32f722zc",
-            "stm32f439ai",
-            "stm32l443vc",
-            "stm32l152rc-a",
-            "stm32l4p5zg",
-            "stm32h747xg",
-            "stm32f777bi",
-            "stm32f427zi",
-            "stm32l083vb",
-            "stm32f411ce",
-            "stm32f078vb",
-            "stm32l4p5ag",
-            "stm32l072vz",
-            "stm32f730z8",
-            "stm32f427ai",
-            "stm32l053c8",
-            "stm32f042k4",
-            "stm32h755ii",
-            "stm32f733ve",
-            "stm32f439zi",
-            "stm32f769bi",
-            "stm32f105vb",
-            "stm32f105vc",
-            "stm32g473ve",
-            "stm32h562ag",
-            "stm32wb55rc",
-            "stm32f105v8",
-            "stm32l476mg",
-            "stm32l476vg",
-            "stm32f469ii",
-            "stm32h562zg",
-            "stm32g473me",
-            "stm32l083v8",
-            "stm32u073c8",
-            "stm32f105rb",
-            "stm32f745ze",
-            "stm32g0c1ke",
-            "stm32f078rb",
-            "stm32l072rz",
-            "stm32h743bg",
-            "stm32u073cc",
-            "stm32f733ie",
-            "stm32l083rb",
-            "stm32f103zf",
-            "stm32l443rc",
-            "stm32f303cc",
-            "stm32h757ii",
-            "stm32f479vi",
-            "stm32l151c8",
-            "stm32h742zi",
-            "stm32l151cc",
-            "stm32f107vb",
-            "stm32l4r5qi",
-            "stm32g484qe",
-            "stm32h745xg",
-            "stm32h742ai",
-            "stm32l475rc",
-            "stm32f103ve",
-            "stm32h745ii",
-            "stm32f723ve",
-            "stm32g491cc",
-            "stm32f437ai",
-            "stm32l4q5ag",
-            "stm32f401ce",
-            "stm32u575zg",
-            "stm32g471re",
-            "stm32l152uc",
-            "stm32f042g6",
-            "stm32l422rb",
-            "stm32g441mb",
-            "stm32f779bi",
-            "stm32f107rc",
-            "stm32f429zi",
-            "stm32u5a5qj",
-            "stm32f429ai",
-            "stm32g441vb",
-            "stm32h7a3ng",
-            "stm32l4p5ve",
-            "stm32f745ig",
-            "stm32l162qd",
-            "stm32u575ag",
-            "stm32f437zi",
-            "stm32f767bi",
-            "stm32l4q5zg",
-            "stm32l151r6",
-            "stm32f103rd",
-            "stm32l162vc",
-            "stm32l4q5cg",
-            "stm32g0b1me",
-            "stm32f205vf",
-            "stm32h742ig",
-            "stm32l152rd",
-            "stm32f217ig",
-            "stm32u575cg",
-            "stm32g0b1ve",
-            "stm32f469ie",
-            "stm32l422kb",
-            "stm32h7a3lg",
-            "stm32h742xi",
-            "stm32u575ri",
-            "stm32f205rg",
-            "stm32f437ig",
-            "stm32l152ve",
-            "stm32h745zg",
-            "stm32g471qc",
-            "stm32u535ce",
-            "stm32l151rb-a",
-            "stm32l151zc",
-            "stm32f429ig",
-            "stm32u599nj",
-            "stm32h562ii",
-            "stm32f207vg",
-            "stm32l152c8-a",
-            "stm32h730ib",
-            "stm32f469zg",
-            "stm32f217ze",
-            "stm32f446ze",
-            "stm32f302re",
-            "stm32l072kz",
-            "stm32f469ag",
-            "stm32g0c1re",
-            "stm32h562ri",
-            "stm32u545je",
-            "stm32f765ng",
-            "stm32f215vg",
-            "stm32f746ne",
-            "stm32h7b0vb",
-            "stm32f429ze",
-            "stm32l072vb",
-            "stm32f102cb",
-            "stm32f439vg",
-            "stm32f769ng",
-            "stm32l152v8-a",
-            "stm32f756bg",
-            "stm32f205ze",
-            "stm32l432kc",
-            "stm32f302vd",
-            "stm32f413rg",
-            "stm32g473qb",
-            "stm32l083vz",
-            "stm32f207if",
-            "stm32h723vg",
-            "stm32f427vg",
-            "stm32l475ve",
-            "stm32f415og",
-            "stm32f103rc",
-            "stm32f103r8",
-            "stm32l162qc",
-            "stm32f479bg",
-            "stm32l052k8",
-            "stm32f072vb",
-            "stm32f401cb",
-            "stm32f469ne",
-            "stm32l412r8",
-            "stm32h7a3zi",
-            "stm32f723ic",
-            "stm32f103vb",
-            "stm32f767vg",
-            "stm32h753ii",
-            "stm32l073cb",
-            "stm32h563vg",
-            "stm32g471mc",
-            "stm32f103c6",
-            "stm32g471vc",
-            "stm32u083rc",
-            "stm32f072r8",
-            "stm32l152qe",
-            "stm32l082cz",
-            "stm32f746zg",
-            "stm32l151r8-a",
-            "stm32h7a3ai",
-            "stm32g0b1kc",
-            "stm32f429ng",
-            "stm32g0c1ne",
-            "stm32l152cb-a",
-            "stm32h747bg",
-            "stm32u585zi",
-            "stm32u545ve",
-            "stm32l063c8",
-            "stm32l552zc",
-            "stm32wb55ve",
-            "stm32f765ig",
-            "stm32g0b0ce",
-            "stm32g473rc",
-            "stm32u585ai",
-            "stm32f722ze",
-            "stm32f048c6",
-            "stm32g473mb",
-            "stm32f746ie",
-            "stm32f750v8",
-            "stm32u595rj",
-            "stm32l152vb-a",
-            "stm32f769ig",
-            "stm32f411cc",
-            "stm32l562ze",
-            "stm32f070rb",
-            "stm32h750xb",
-            "stm32h743vi",
-            "stm32g473vb",
-            "stm32f423zh",
-            "stm32g0c1vc",
-            "stm32f417ig",
-            "stm32g431cb",
-            "stm32f103r4",
-            "stm32g473qe",
-            "stm32l462ce",
-            "stm32l562ce",
-            "stm32g431m6",
-            "stm32f412ve",
-            "stm32l476jg",
-            "stm32f765zi",
-            "stm32f302vc",
-            "stm32f423ch",
-            "stm32f373rb",
-            "stm32h750zb",
-            "stm32l476qg",
-            "stm32g431v6",
-            "stm32g474cc",
-            "stm32f469ni",
-            "stm32g0c1mc",
-            "stm32g4a1ce",
-            "stm32f769ai",
-            "stm32h725ae",
-            "stm32f302rb",
-            "stm32l496ag",
-            "stm32f777zi",
-            "stm32h573zi",
-            "stm32f373vc",
-            "stm32g473pc",
-            "stm32l496zg",
-            "stm32u585ci",
-            "stm32h573ai",
-            "stm32wb35cc",
-            "stm32h735vg",
-            "stm32h725ze",
-            "stm32f439bi",
-            "stm32f405rg",
-            "stm32l552cc",
-            "stm32f373v8",
-            "stm32l452cc",
-            "stm32u083kc",
-            "stm32u5a9vj",
-            "stm32l151zd",
-            "stm32f303zd",
-            "stm32l152vb",
-            "stm32f103tb",
-            "stm32u535cb",
-            "stm32g484me",
-            "stm32g0b1rc",
-            "stm32f407vg",
-            "stm32l4r5vi",
-            "stm32g484ve",
-            "stm32l162re",
-            "stm32l412k8",
-            "stm32f207zc",
-            "stm32l052c6",
-            "stm32l152c6",
-            "stm32f417ze",
-            "stm32l412tb",
-            "stm32l052r8",
-            "stm32l152r8",
-            "stm32l4r9vi",
-            "stm32h725ig",
-            "stm32h7a3rg",
-            "stm32l152rc",
-            "stm32g0b1vb",
-            "stm32l433cb",
-            "stm32l496re",
-            "stm32l4p5qe",
-            "stm32g0b1mb",
-            "stm32l4s7vi",
-            "stm32l162vd",
-            "stm32f415vg",
-            "stm32l100c6-a",
-            "stm32u5a5vj",
-            "stm32h7a3ig",
-            "stm32h725rg",
-            "stm32f479ni",
-            "stm32h742bi",
-            "stm32g484re",
-            "stm32g0b1mc",
-            "stm32l162ve",
-            "stm32l152rb",
-            "stm32f412zg",
-            "stm32l412t8",
-            "stm32f407ig",
-            "stm32l433cc",
-            "stm32g0b1vc",
-            "stm32h725vg",
-            "stm32l412kb",
-            "stm32h563ai",
-            "stm32l486zg",
-            "stm32l496ve",
-            "stm32l162rd",
-            "stm32f415rg",
-            "stm32f429bi",
-            "stm32u5a5rj",
-            "stm32f303ze",
-            "stm32h7a3vg",
-            "stm32l152vc",
-            "stm32f779ai",
-            "stm32u535cc",
-            "stm32g0b1rb",
-            "stm32g471qe",
-            "stm32h563zi",
-            "stm32f767zi",
-            "stm32l151ze",
-            "stm32wb55cg",
-            "stm32l073cz",
-            "stm32f103t8",
-            "stm32l151vc-a",
-            "stm32l152v8",
-            "stm32l4s5vi",
-            "stm32f417vg",
-            "stm32g473pb",
-            "stm32f373vb",
-            "stm32f407ze",
-            "stm32f446zc",
-            "stm32g431r6",
-            "stm32f412re",
-            "stm32f302rc",
-            "stm32g0c1rc",
-            "stm32f302r8",
-            "stm32f302c6",
-            "stm32f405vg",
-            "stm32l4r7vi",
-            "stm32f373r8",
-            "stm32f373rc",
-            "stm32h743zg",
-            "stm32f205zc",
-            "stm32g474cb",
-            "stm32h735rg",
-            "stm32l4s9vi",
-            "stm32h735ig",
-            "stm32f302vb",
-            "stm32h503kb",
-            "stm32h743ag",
-            "stm32g431c8",
-            "stm32u599vj",
-            "stm32g473vc",
-            "stm32f469bg",
-            "stm32h743xg",
-            "stm32h503rb",
-            "stm32f048g6",
-            "stm32wb55re",
-            "stm32f405og",
-            "stm32g473mc",
-            "stm32f765vg",
-            "stm32u545re",
-            "stm32f746ve",
-            "stm32g473rb",
-            "stm32f429be",
-            "stm32f439ng",
-            "stm32f302k8",
-            "stm32g0c1kc",
-            "stm32f756zg",
-            "stm32u595vj",
-            "stm32f103t4",
-            "stm32f070c6",
-            "stm32l151r6-a",
-            "stm32g431k6",
-            "stm32h743ii",
-            "stm32l4r5zg",
-            "stm32f072rb",
-            "stm32g483ce",
-            "stm32g0b1kb",
-            "stm32f732ze",
-            "stm32u595ai",
-            "stm32l152qd",
-            "stm32l052t8",
-            "stm32f103v8",
-            "stm32u595zi",
-            "stm32g0b1ne",
-            "stm32l412rb",
-            "stm32l475re",
-            "stm32f042f6",
-            "stm32l4r5ag",
-            "stm32f103vc",
-            "stm32g491ce",
-            "stm32f412cg",
-            "stm32u599zi",
-            "stm32f723vc",
-            "stm32f401cc",
-            "stm32g484pe",
-            "stm32g471rc",
-            "stm32l4r9ag",
-            "stm32h753vi",
-            "stm32f767ig",
-            "stm32f072v8",
-            "stm32h563ig",
-            "stm32l4a6ag",
-            "stm32u575qi",
-            "stm32l4a6zg",
-            "stm32h563rg",
-            "stm32l4r9zg",
-            "stm32u083mc",
-            "stm32h745bg",
-            "stm32f103rb",
-            "stm32u535rb",
-            "stm32l151uc",
-            "stm32g0b1cc",
-            "stm32l433vc",
-            "stm32h7a3ii",
-            "stm32u599vi",
-            "stm32f723zc",
-            "stm32l082kz",
-            "stm32h753zi",
-            "stm32l052r6",
-            "stm32l4r9vg",
-            "stm32l152r6",
-            "stm32h753ai",
-            "stm32l4a6vg",
-            "stm32f746ig",
-            "stm32h7a3ri",
-            "stm32u535vc",
-            "stm32l433rb",
-            "stm32l4r5vg",
-            "stm32l152cc",
-            "stm32l073vz",
-            "stm32wb55vg",
-            "stm32l052c8",
-            "stm32l152c8",
-            "stm32f732ve",
-            "stm32l152rb-a",
-            "stm32u595vi",
-            "stm32l476rc",
-            "stm32f103zc",
-            "stm32f429ne",
-            "stm32f423rh",
-            "stm32f746ze",
-            "stm32l462re",
-            "stm32f756vg",
-            "stm32l151c8-a",
-            "stm32l562re",
-            "stm32u595aj",
-            "stm32g431rb",
-            "stm32f103c4",
-            "stm32f439bg",
-            "stm32f769ag",
-            "stm32u595zj",
-            "stm32g474rc",
-            "stm32f373cb",
-            "stm32f302cb",
-            "stm32f469ng",
-            "stm32g431v8",
-            "stm32f722re",
-            "stm32g474mb",
-            "stm32u599zj",
-            "stm32l151v8-a",
-            "stm32g4a1re",
-            "stm32l552rc",
-            "stm32l452rc",
-            "stm32f765zg",
-            "stm32g474vb",
-            "stm32h503eb",
-            "stm32f722ie",
-            "stm32u585ri",
-            "stm32g431m8",
-            "stm32l475vg",
-            "stm32f405zg",
-            "stm32l4r7zi",
-            "stm32g474qe",
-            "stm32l063r8",
-            "stm32h725re",
-            "stm32l4s9ai",
-            "stm32f205vc",
-            "stm32h743vg",
-            "stm32f777ii",
-            "stm32l496rg",
-            "stm32l4p5qg",
-            "stm32h573ii",
-            "stm32g473cc",
-            "stm32h573ri",
-            "stm32h755bi",
-            "stm32l4s9zi",
-            "stm32l100rc",
-            "stm32g0b0re",
-            "stm32l100r8",
-            "stm32h725ie",
-            "stm32f769ii",
-            "stm32l4r7ai",
-            "stm32g4a1ke",
-            "stm32l100c6",
-            "stm32l4s5zi",
-            "stm32f417zg",
-            "stm32f205rb",
-            "stm32f446vc",
-            "stm32f765ii",
-            "stm32f407ve",
-            "stm32l552qe",
-            "stm32h750ib",
-            "stm32u073hb",
-            "stm32f446mc",
-            "stm32g474pc",
-            "stm32h747bi",
-            "stm32f070cb",
-            "stm32l4s5ai",
-            "stm32g431kb",
-            "stm32f411rc",
-            "stm32h563mi",
-            "stm32l486vg",
-            "stm32f103c8",
-            "stm32h725zg",
-            "stm32h7a3ag",
-            "stm32g483me",
-            "stm32l073v8",
-            "stm32f429ni",
-            "stm32l496ze",
-            "stm32l496ae",
-            "stm32f401rb",
-            "stm32h7a3zg",
-            "stm32f303ve",
-            "stm32l152zc",
-            "stm32g483ve",
-            "stm32h725ag",
-            "stm32l151ve",
-            "stm32h563vi",
-            "stm32f767vi",
-            "stm32u535ne",
-            "stm32l052k6",
-            "stm32h753xi",
-            "stm32f479bi",
-            "stm32l073rb",
-            "stm32g491me",
-            "stm32l476qe",
-            "stm32l162ze",
-            "stm32l412c8",
-            "stm32f401vc",
-            "stm32g491ve",
-            "stm32f412vg",
-            "stm32l476je",
-            "stm32f303rd",
-            "stm32f207ic",
-            "stm32f072c8",
-            "stm32h7b3qi",
-            "stm32f103r6",
-            "stm32l151rd",
-            "stm32u083cc",
-            "stm32f417ie",
-            "stm32l4r9zi",
-            "stm32l100r8-a",
-            "stm32h745bi",
-            "stm32u575qg",
-            "stm32l151re",
-            "stm32h563ri",
-            "stm32f401vb",
-            "stm32l4s7ai",
-            "stm32f779ii",
-            "stm32u5a5aj",
-            "stm32f303re",
-            "stm32f072cb",
-            "stm32g483re",
-            "stm32u5a5zj",
-            "stm32l4s7zi",
-            "stm32l162zd",
-            "stm32f415zg",
-            "stm32l442kc",
-            "stm32h563ii",
-            "stm32l4q5qg",
-            "stm32l486rg",
-            "stm32f767ii",
-            "stm32l412cb",
-            "stm32l082kb",
-            "stm32l4r9ai",
-            "stm32h757bi",
-            "stm32g471cc",
-            "stm32l052t6",
-            "stm32l151vd",
-            "stm32u5a9zj",
-            "stm32f401rc",
-            "stm32l4r5ai",
-            "stm32g491re",
-            "stm32f412rg",
-            "stm32f303vd",
-            "stm32f103cb",
-            "stm32f407zg",
-            "stm32l4r5zi",
-            "stm32f417ve",
-            "stm32u535je",
-            "stm32f207vc",
-            "stm32l073vb",
-            "stm32h7b3ni",
-            "stm32wb55ce",
-            "stm32u073h8",
-            "stm32h503cb",
-            "stm32f777vi",
-            "stm32h743ig",
-            "stm32g431k8",
-            "stm32h573vi",
-            "stm32u073hc",
-            "stm32g474pb",
-            "stm32h735ag",
-            "stm32g0b0ve",
-            "stm32h735zg",
-            "stm32u545ce",
-            "stm32f205rc",
-            "stm32l496vg",
-            "stm32h573mi",
-            "stm32u585oi",
-            "stm32f439ni",
-            "stm32f302k6",
-            "stm32h725ve",
-            "stm32l151rc-a",
-            "stm32f411vc",
-            "stm32g473cb",
-            "stm32f407ie",
-            "stm32f765vi",
-            "stm32f412ze",
-            "stm32l100rb",
-            "stm32h743xi",
-            "stm32f469bi",
-            "stm32f205vb",
-            "stm32f446rc",
-            "stm32h750vb",
-            "stm32f750z8",
-            "stm32g474vc",
-            "stm32g431c6",
-            "stm32h743ai",
-            "stm32f423mh",
-            "stm32g431mb",
-            "stm32l562me",
-            "stm32f756ig",
-            "stm32l462ve",
-            "stm32l562ve",
-            "stm32f302c8",
-            "stm32g0c1cc",
-            "stm32g431vb",
-            "stm32f302cc",
-            "stm32f423vh",
-            "stm32g474mc",
-            "stm32h743zi",
-            "stm32f412ce",
-            "stm32f042f4",
-            "stm32g474rb",
-            "stm32f373cc",
-            "stm32u585vi",
-            "stm32l475rg",
-            "stm32f048t6",
-            "stm32l552vc",
-            "stm32f373c8",
-            "stm32l452vc",
-            "stm32g4a1me",
-            "stm32f302r6",
-            "stm32g4a1ve",
-            "stm32g431r8",
-            "stm32f722ve",
-            "stm32h7a3vi",
-            "stm32g484ce",
-            "stm32f103t6",
-            "stm32l151c6-a",
-            "stm32f767zg",
-            "stm32h563zg",
-            "stm32l4a6rg",
-            "stm32h563ag",
-            "stm32h7b3li",
-            "stm32l151vd-x",
-            "stm32f429bg",
-            "stm32l151qc",
-            "stm32l433rc",
-            "stm32l152cb",
-            "stm32g491ke",
-            "stm32f746vg",
-            "stm32g483pe",
-            "stm32f405oe",
-            "stm32u595ri",
-            "stm32f732ie",
-            "stm32l476vc",
-            "stm32wb55rg",
-            "stm32l073rz",
-            "stm32h742bg",
-            "stm32f732re",
-            "stm32f479ng",
-            "stm32u535rc",
-            "stm32f469be",
-            "stm32g0b1cb",
-            "stm32f429ve",
-            "stm32h7b0zb",
-            "stm32f746be",
-            "stm32l053c6",
-            "stm32f427ag",
-            "stm32g474qc",
-            "stm32f215zg",
-            "stm32f205ve",
-            "stm32f756ng",
-            "stm32f769bg",
-            "stm32f439zg",
-            "stm32f302zd",
-            "stm32g473ce",
-            "stm32l562qe",
-            "stm32f427zg",
-            "stm32h747xi",
-            "stm32u073rb",
-            "stm32h723zg",
-            "stm32h7b0ab",
-            "stm32u585qi",
-            "stm32h747ig",
-            "stm32f469vg",
-            "stm32f207zg",
-            "stm32u073mc",
-            "stm32u599bj",
-            "stm32f446ve",
-            "stm32u073m8",
-            "stm32f217ve",
-            "stm32l552qc",
-            "stm32l162vd-x",
-            "stm32l152r6-a",
-            "stm32g474pe",
-            "stm32f446me",
-            "stm32h755xi",
-            "stm32f765bg",
-            "stm32f411re",
-            "stm32l053r8",
-            "stm32h7a3qi",
-            "stm32f215re",
-            "stm32u599ni",
-            "stm32h753bi",
-            "stm32l151v8",
-            "stm32f401rd",
-            "stm32f303vc",
-            "stm32l152ze",
-            "stm32f102r4",
-            "stm32l151vc",
-            "stm32l4p5ce",
-            "stm32u535nc",
-            "stm32g441cb",
-            "stm32f205zf",
-            "stm32g491mc",
-            "stm32l162zc",
-            "stm32f479ig",
-            "stm32f401ve",
-            "stm32u575og",
-            "stm32f303rb",
-            "stm32g491vc",
-            "stm32f207ie",
-            "stm32l151rb",
-            "stm32f768ai",
-            "stm32g0b1ce",
-            "stm32u575vg",
-            "stm32l100rb-a",
-            "stm32l162rc-a",
-            "stm32f723ze",
-            "stm32f429vi",
-            "stm32f779ni",
-            "stm32f042k6",
-            "stm32l4p5ae",
-            "stm32l062c8",
-            "stm32l4p5ze",
-            "stm32f767ni",
-            "stm32f437vi",
-            "stm32l4q5vg",
-            "stm32l151qd",
-            "stm32h742vi",
-            "stm32u535ve",
-            "stm32h7b3ri",
-            "stm32f479zi",
-            "stm32l152vc-a",
-            "stm32l476re",
-            "stm32h7b3ii",
-            "stm32f479ai",
-            "stm32f103ze",
-            "stm32f042c4",
-            "stm32f730r8",
-            "stm32f427ii",
-            "stm32f745ve",
-            "stm32l4p5rg",
-            "stm32f730i8",
-            "stm32l496qg",
-            "stm32f102r8",
-            "stm32h755zi",
-            "stm32f439ii",
-            "stm32g474re",
-            "stm32f103vf",
-            "stm32f722rc",
-            "stm32u073kb",
-            "stm32f102c6",
-            "stm32h747ai",
-            "stm32l083cz",
-            "stm32l452re",
-            "stm32l072cb",
-            "stm32h562vg",
-            "stm32l552re",
-            "stm32h747zi",
-            "stm32u5a5qi",
-            "stm32l476zg",
-            "stm32f413rh",
-            "stm32f103rg",
-            "stm32f722ic",
-            "stm32g474ve",
-            "stm32f103rf",
-            "stm32f745ie",
-            "stm32l4p5vg",
-            "stm32f427vi",
-            "stm32f777ni",
-            "stm32f733ze",
-            "stm32u073kc",
-            "stm32f042g4",
-            "stm32f730v8",
-            "stm32g0c1ce",
-            "stm32g474me",
-            "stm32f769ni",
-            "stm32f439vi",
-            "stm32u073k8",
-            "stm32f413vh",
-            "stm32f103vg",
-            "stm32f102rb",
-            "stm32f765ni",
-            "stm32l452ve",
-            "stm32h562rg",
-            "stm32l552ve",
-            "stm32f469ai",
-            "stm32f469zi",
-            "stm32l552me",
-            "stm32h562ig",
-            "stm32f722vc",
-            "stm32f413cg",
-            "stm32f413mh",
-            "stm32h745zi",
-            "stm32f103zd",
-            "stm32l486qg",
-            "stm32l4q5rg",
-            "stm32f042t6",
-            "stm32f429ii",
-            "stm32g483qe",
-            "stm32f745zg",
-            "stm32l151qe",
-            "stm32u575rg",
-            "stm32l486jg",
-            "stm32f437ii",
-            "stm32g491kc",
-            "stm32h742xg",
-            "stm32u575ci",
-            "stm32h757zi",
-            "stm32u5a9bj",
-            "stm32h742ii",
-            "stm32h7a3li",
-            "stm32l476ve",
-            "stm32l476me",
-            "stm32u535re",
-            "stm32h757ai",
-            "stm32h7b3vi",
-            "stm32h723ve",
-            "stm32f429ag",
-            "stm32h7a3ni",
-            "stm32l151rc",
-            "stm32l151r8",
-            "stm32f401vd",
-            "stm32u575ai",
-            "stm32f303rc",
-            "stm32h733zg",
-            "stm32h757xi",
-            "stm32f767bg",
-            "stm32f437zg",
-            "stm32f205zg",
-            "stm32l4a6qg",
-            "stm32u575zi",
-            "stm32h745ig",
-            "stm32f429zg",
-            "stm32f746ng",
-            "stm32f215ve",
-            "stm32l4r5qg",
-            "stm32l422cb",
-            "stm32g471ce",
-            "stm32l151vb",
-            "stm32f469ae",
-            "stm32f401re",
-            "stm32f303vb",
-            "stm32h742ag",
-            "stm32h745xi",
-            "stm32l152zd",
-            "stm32g491rc",
-            "stm32l151c6",
-            "stm32l152r8-a",
-            "stm32h742zg",
-            "stm32f217zg",
-            "stm32f479vg",
-            "stm32u595qi",
-            "stm32f469ze",
-            "stm32f207ve",
-            "stm32wb55vy",
-            "stm32wb55cc",
-            "stm32f429ie",
-            "stm32f413zg",
-            "stm32l151cb-a",
-            "stm32f205re",
-            "stm32f207zf",
-            "stm32u073mb",
-            "stm32h743bi",
-            "stm32u073rc",
-            "stm32h562zi",
-            "stm32l151vb-a",
-            "stm32f078cb",
-            "stm32f411ve",
-            "stm32h730zb",
-            "stm32l072cz",
-            "stm32l496wg",
-            "stm32f469ig",
-            "stm32f778ai",
-            "stm32f302ze",
-            "stm32u073r8",
-            "stm32f217ie",
-            "stm32f446re",
-            "stm32l443cc",
-            "stm32h730ab",
-            "stm32l083cb",
-            "stm32h562ai",
-            "stm32g474qb",
-        ]
+        println!("Split:          not supported");
+    }
+    println!("Template:       {}", template_folder(chip, false));
+    if split {
+        println!("Split template: {}", template_folder(chip, true));
+    }
+    println!("UF2 key:        {}", uf2_key(chip));
+    match get_uf2_family(chip_or_board) {
+        Some(family) => println!("UF2 family id:  {:#010x}", family),
+        None => println!("UF2 family id:  unknown"),
+    }
+    if let Some(target) = get_chip_target(chip_or_board) {
+        println!("Target:         {}", target.target);
+        println!("Formats:        {}", target.formats.join(", "));
+        println!("probe-rs chip:  {}", target.probe_rs_chip);
+    }
+    if let Some(packages) = get_chip_packages(chip_or_board) {
+        let mut packages: Vec<String> = packages
+            .iter()
+            .map(|(name, package)| format!("{} ({} pins)", name, package.pins().len()))
+            .collect();
+        packages.sort();
+        println!("Packages:       {}", packages.join(", "));
+    }
+    if let Some(info) = get_board_info(chip_or_board) {
+        if let Some(bootloader) = info.bootloader {
+            println!("Bootloader:     {}", bootloader);
+        }
+        if let Some(flash_size) = &info.flash_size {
+            println!("Flash size:     {}", flash_size);
+        }
+        if let (Some(vid), Some(pid)) = (info.usb_vid, info.usb_pid) {
+            println!("Bootloader USB: {:04x}:{:04x}", vid, pid);
+        }
+        if !info.pins.is_empty() {
+            println!("Matrix pins:    {}", info.pins.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Check the chip database, including the user's chips.toml, for inconsistent entries
+///
+/// With `uf2_families`, the path of uf2families.json of microsoft/uf2, UF2 family ids missing in it are errors too.
+pub(crate) fn audit_chips(uf2_families: Option<String>) -> Result<(), Box<dyn Error>> {
+    warn_user_database();
+    let mut errors = audit_database();
+    if let Some(path) = uf2_families {
+        let official =
+            fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        errors.extend(audit_uf2_families(&official)?);
+    }
+    for error in &errors {
+        println!("❌ {}", error);
+    }
+    if !errors.is_empty() {
+        return Err(format!("Chip database has {} error(s)", errors.len()).into());
+    }
+    println!("✅ Chip database is consistent: {}", database_summary());
+    Ok(())
+}
+
+/// Resolve a chip or board name given by the user, printing the name it's resolved to if it differs
+pub(crate) fn resolve_chip(name: &str) -> Result<String, Box<dyn Error>> {
+    let resolved = rmkit_core::chip::resolve_chip(name)?;
+    if resolved != name {
+        println!("ℹ️  Using {} for {}", resolved, name);
+    }
+    Ok(resolved)
+}
+
+/// Print why the user's chips.toml is ignored, if it's invalid
+pub(crate) fn warn_user_database() {
+    if let Some(error) = user_database_error() {
+        eprintln!("⚠️  {}", error);
     }
 }
@@ -0,0 +1,57 @@
+use crate::cache;
+use rmkit_core::interrupt;
+use rmkit_core::{Classify, FailureKind};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Extensions of firmware files generated in the project root
+pub(crate) const FIRMWARE_EXTENSIONS: [&str; 3] = ["hex", "bin", "uf2"];
+
+/// Remove the generated firmware files of a project
+///
+/// `target` also runs `cargo clean` to remove the build output, `cache` also removes the cached templates.
+pub(crate) fn clean_project(
+    project_dir: Option<String>,
+    target: bool,
+    cache: bool,
+) -> Result<(), Box<dyn Error>> {
+    let project_dir = PathBuf::from(project_dir.unwrap_or_else(|| ".".to_string()));
+    if !project_dir.join("Cargo.toml").exists() {
+        return Err(format!("No Cargo.toml found in {}", project_dir.display()).into());
+    }
+
+    let mut removed = 0;
+    for entry in fs::read_dir(&project_dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| FIRMWARE_EXTENSIONS.iter().any(|e| ext == *e))
+        {
+            fs::remove_file(&path)?;
+            println!("🧹 Removed {}", path.display());
+            removed += 1;
+        }
+    }
+    if removed == 0 {
+        println!("✅ No firmware files in {}", project_dir.display());
+    }
+
+    if target {
+        let status = tracing::info_span!("cargo", command = "clean")
+            .in_scope(|| {
+                interrupt::status(Command::new("cargo").arg("clean").current_dir(&project_dir))
+            })
+            .or_fail(FailureKind::Toolchain)?;
+        if !status.success() {
+            return Err("cargo clean failed".into());
+        }
+    }
+
+    if cache {
+        cache::clean_cache()?;
+    }
+    Ok(())
+}
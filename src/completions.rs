@@ -0,0 +1,51 @@
+use crate::args::Args;
+use clap::builder::PossibleValuesParser;
+use clap::{Command, CommandFactory};
+use clap_complete::Shell;
+use rmkit_core::chip::get_chip_and_board_names;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Print the completion script of `shell` to stdout
+///
+/// The known chips and boards, including the ones of `~/.config/rmkit/chips.toml`, are completed for
+/// `init --chip` and `chips info`, regenerate the script after changing the chip database.
+pub(crate) fn print_completions(shell: Shell) -> Result<(), Box<dyn Error>> {
+    let mut cmd = command_with_chips();
+    let name = cmd.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut cmd, name, &mut script);
+    io::stdout().write_all(&script)?;
+    Ok(())
+}
+
+/// Print the man page of rmkit to stdout, or write the pages of rmkit and all subcommands to `out_dir`
+pub(crate) fn print_man(out_dir: Option<String>) -> Result<(), Box<dyn Error>> {
+    let cmd = Args::command();
+    match out_dir {
+        Some(out_dir) => {
+            let out_dir = PathBuf::from(out_dir);
+            fs::create_dir_all(&out_dir)?;
+            clap_mangen::generate_to(cmd, &out_dir)?;
+            println!("✅ Man pages written to {}", out_dir.display());
+        }
+        None => clap_mangen::Man::new(cmd).render(&mut io::stdout())?,
+    }
+    Ok(())
+}
+
+/// The rmkit command, with the chip arguments limited to the known chips so that shells can complete them
+fn command_with_chips() -> Command {
+    let chips = || PossibleValuesParser::new(get_chip_and_board_names());
+    Args::command()
+        .mut_subcommand("init", |init| {
+            init.mut_arg("chip", |chip| chip.value_parser(chips()))
+        })
+        .mut_subcommand("chips", |chips_cmd| {
+            chips_cmd.mut_subcommand("info", |info| {
+                info.mut_arg("chip", |chip| chip.value_parser(chips()))
+            })
+        })
+}
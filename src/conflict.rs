@@ -0,0 +1,56 @@
+use crate::prompt;
+use inquire::Select;
+use rmkit_core::Resolution;
+use std::error::Error;
+use std::path::Path;
+
+const OVERWRITE: &str = "Overwrite";
+const SKIP: &str = "Skip, keep the existing file";
+const DIFF: &str = "Show diff";
+const OVERWRITE_ALL: &str = "Overwrite all";
+const SKIP_ALL: &str = "Skip all";
+
+/// Ask what to do with an existing file of the project which differs from the template
+pub(crate) fn ask_resolution(
+    path: &Path,
+    existing: &[u8],
+    template: &[u8],
+) -> Result<Resolution, Box<dyn Error>> {
+    prompt::ensure_interactive("--force to overwrite existing files")?;
+    let message = format!(
+        "{} already exists and differs from the template",
+        path.display()
+    );
+    loop {
+        match Select::new(
+            &message,
+            vec![OVERWRITE, SKIP, DIFF, OVERWRITE_ALL, SKIP_ALL],
+        )
+        .prompt()?
+        {
+            OVERWRITE => return Ok(Resolution::Overwrite),
+            DIFF => print_diff(path, existing, template),
+            OVERWRITE_ALL => return Ok(Resolution::OverwriteAll),
+            SKIP_ALL => return Ok(Resolution::SkipAll),
+            _ => return Ok(Resolution::Skip),
+        }
+    }
+}
+
+/// Print a unified diff from the existing file to the template file
+pub(crate) fn print_diff(path: &Path, existing: &[u8], template: &[u8]) {
+    let (existing, template) = (
+        String::from_utf8_lossy(existing),
+        String::from_utf8_lossy(template),
+    );
+    let name = path.display().to_string();
+    print!(
+        "{}",
+        similar::TextDiff::from_lines(&existing, &template)
+            .unified_diff()
+            .header(
+                &format!("{} (existing)", name),
+                &format!("{} (template)", name)
+            )
+    );
+}
@@ -0,0 +1,33 @@
+use crate::conflict::print_diff;
+use rmkit_core::{FileChange, PlannedFile};
+use std::path::Path;
+
+/// Print the files of a dry run with their diffs against `target_dir`
+pub(crate) fn report(files: &[PlannedFile], target_dir: &Path) {
+    let (mut new, mut changed, mut unchanged) = (0, 0, 0);
+    println!("🔍 Files of {}:", target_dir.display());
+    for file in files {
+        match &file.change {
+            FileChange::Unchanged => {
+                unchanged += 1;
+                println!("    {}", file.path.display());
+            }
+            FileChange::Changed {
+                existing,
+                generated,
+            } => {
+                changed += 1;
+                println!("  ~ {}", file.path.display());
+                print_diff(&target_dir.join(&file.path), existing, generated);
+            }
+            FileChange::New => {
+                new += 1;
+                println!("  + {}", file.path.display());
+            }
+        }
+    }
+    println!(
+        "ℹ️  Dry run: {} new, {} changed and {} unchanged file(s), nothing was written",
+        new, changed, unchanged
+    );
+}
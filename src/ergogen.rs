@@ -0,0 +1,605 @@
+use crate::chip::resolve_chip;
+use crate::keymap::{DEFAULT_LAYERS, NO_KEY};
+use rmkit_core::chip::{get_board_chip_map, split_transports};
+use rmkit_core::keyboard_toml::write_keyboard_config;
+use rmkit_core::vial::generate_vial_json;
+use serde_json::json;
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Ergogen's `u`, the default spacing of keys in mm
+const U: f64 = 19.0;
+/// Key of matrix positions which are used by the layout, left for the user to fill in
+const LAYOUT_KEY: &str = "_";
+
+/// A key of the Ergogen layout, positioned by its center in mm with y pointing up
+struct Point {
+    x: f64,
+    y: f64,
+    /// Rotation in degrees
+    r: f64,
+    zone: String,
+    col: String,
+    row: String,
+    /// Whether the key is on the mirrored half
+    mirrored: bool,
+}
+
+/// Import an Ergogen config, or the points it outputs, into keyboard.toml and vial.json
+///
+/// The rows of every zone become matrix rows and their columns matrix cols, with the mirrored half as the
+/// peripheral of a split keyboard. The matrix pins are placeholders, since Ergogen doesn't route them.
+/// The layout of vial.json follows the key positions, rotations aren't converted.
+pub(crate) fn import_ergogen(
+    input: &str,
+    chip: &str,
+    output: &str,
+    vial_json_path: &str,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    for path in [output, vial_json_path] {
+        if Path::new(path).exists() && !force {
+            return Err(format!("{} already exists, use --force to overwrite it", path).into());
+        }
+    }
+    let chip = resolve_chip(chip)?;
+    let content =
+        fs::read_to_string(input).map_err(|e| format!("Failed to read {}: {}", input, e))?;
+    let ergogen: Value = serde_yaml::from_str(&content)
+        .map_err(|e| format!("Invalid Ergogen YAML {}: {}", input, e))?;
+
+    let mut warnings = Vec::new();
+    let points = if ergogen["points"]["zones"].is_mapping() {
+        config_points(&ergogen, &mut warnings)?
+    } else {
+        output_points(&ergogen)?
+    };
+    if points.is_empty() {
+        return Err(format!("No keys found in {}", input).into());
+    }
+
+    let name = ergogen["meta"]["name"]
+        .as_str()
+        .or_else(|| Path::new(input).file_stem().and_then(|s| s.to_str()))
+        .unwrap_or("ergogen");
+    let (config, positions) = keyboard_config(name, &chip, &points, &mut warnings);
+    write_keyboard_config(output, &config)?;
+
+    let mut vial = generate_vial_json(&config)?;
+    vial["layouts"]["keymap"] = kle_layout(&points, &positions).into();
+    fs::write(vial_json_path, serde_json::to_string_pretty(&vial)?)?;
+    if points.iter().any(|p| p.r != 0.0) {
+        warnings.push(
+            "Rotated keys are placed without rotation in vial.json, refine the layout in KLE"
+                .to_string(),
+        );
+    }
+
+    for warning in &warnings {
+        println!("⚠️  {}", warning);
+    }
+    println!(
+        "✅ Imported {} keys of {} to {} and {}",
+        points.len(),
+        input,
+        output,
+        vial_json_path
+    );
+    println!(
+        "ℹ️  Set the matrix pins in {}, then check the config with `rmkit check`",
+        output
+    );
+    Ok(())
+}
+
+/// Get the points of the `points.yaml` output of Ergogen, a map of point names to positions and metadata
+fn output_points(points: &Value) -> Result<Vec<Point>, Box<dyn Error>> {
+    let points = points
+        .as_mapping()
+        .ok_or("It's neither an Ergogen config with `points.zones` nor Ergogen's points output")?;
+    points
+        .iter()
+        .map(|(name, point)| {
+            let name = name.as_str().unwrap_or_default();
+            let (Some(x), Some(y)) = (point["x"].as_f64(), point["y"].as_f64()) else {
+                return Err(format!("Point {} has no x and y", name).into());
+            };
+            let meta = &point["meta"];
+            // Points are named `zone_col_row`, and `mirror_zone_col_row` on the mirrored half
+            let mirrored = meta["mirrored"]
+                .as_bool()
+                .unwrap_or(name.starts_with("mirror_"));
+            let mut parts = name.trim_start_matches("mirror_").splitn(3, '_');
+            let mut field = |value: &Value| {
+                let part = parts.next().unwrap_or_default().to_string();
+                value.as_str().map(|s| s.to_string()).unwrap_or(part)
+            };
+            Ok(Point {
+                x,
+                y,
+                r: point["r"].as_f64().unwrap_or(0.0),
+                zone: field(&meta["zone"]["name"]),
+                col: field(&meta["col"]["name"]),
+                row: field(&meta["row"]),
+                mirrored,
+            })
+        })
+        .collect()
+}
+
+/// Compute the points of the zones of an Ergogen config
+///
+/// Zone anchors are followed by their `shift`, columns by their `spread` and `stagger`, and rows by their
+/// `padding`. Rotations, `splay` and anchors relative to other points are not, and are reported.
+fn config_points(
+    ergogen: &Value,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Point>, Box<dyn Error>> {
+    let zones = ergogen["points"]["zones"]
+        .as_mapping()
+        .ok_or("Invalid `points.zones`")?;
+    let global_key = &ergogen["points"]["key"];
+    let mut points = Vec::new();
+    for (zone_name, zone) in zones {
+        let zone_name = zone_name.as_str().unwrap_or_default();
+        let anchor = &zone["anchor"];
+        if !anchor["ref"].is_null() || !anchor["rotate"].is_null() || !zone["rotate"].is_null() {
+            warnings.push(format!(
+                "Anchor references and rotations of zone {} aren't followed, check its position",
+                zone_name
+            ));
+        }
+        let zone_x = length(&anchor["shift"][0], warnings);
+        let zone_y = length(&anchor["shift"][1], warnings);
+        let zone_key = &zone["key"];
+
+        let columns: Vec<(String, &Value)> = match zone["columns"].as_mapping() {
+            Some(columns) => columns
+                .iter()
+                .map(|(name, column)| (name.as_str().unwrap_or_default().to_string(), column))
+                .collect(),
+            None => vec![("default".to_string(), &Value::Null)],
+        };
+        let rows: Vec<(String, &Value)> = match zone["rows"].as_mapping() {
+            Some(rows) => rows
+                .iter()
+                .map(|(name, row)| (name.as_str().unwrap_or_default().to_string(), row))
+                .collect(),
+            None => vec![("default".to_string(), &Value::Null)],
+        };
+
+        let (mut x, mut stagger) = (zone_x, zone_y);
+        for (c, (col_name, column)) in columns.iter().enumerate() {
+            // Ergogen v4 keeps column attributes in `key`, older versions on the column itself
+            let attr = |key: &str| {
+                [
+                    &column["key"][key],
+                    &column[format!("key.{}", key).as_str()],
+                    &column[key],
+                    &zone_key[key],
+                    &global_key[key],
+                ]
+                .into_iter()
+                .find(|v| !v.is_null())
+            };
+            if attr("splay").is_some() || attr("rotate").is_some() {
+                warnings.push(format!(
+                    "Splay of column {} in zone {} isn't followed, check its position",
+                    col_name, zone_name
+                ));
+            }
+            if c > 0 {
+                x += attr("spread").map_or(U, |v| length(v, warnings));
+            }
+            stagger += attr("stagger").map_or(0.0, |v| length(v, warnings));
+
+            let mut y = stagger;
+            for (row_name, row) in &rows {
+                let skip = [
+                    &column["rows"][row_name.as_str()]["skip"],
+                    &row["skip"],
+                    &column["key"]["skip"],
+                ]
+                .into_iter()
+                .any(|s| s.as_bool() == Some(true));
+                if !skip {
+                    points.push(Point {
+                        x,
+                        y,
+                        r: 0.0,
+                        zone: zone_name.to_string(),
+                        col: col_name.clone(),
+                        row: row_name.clone(),
+                        mirrored: false,
+                    });
+                }
+                let padding = [
+                    &row["padding"],
+                    &column["key"]["padding"],
+                    &zone_key["padding"],
+                    &global_key["padding"],
+                ]
+                .into_iter()
+                .find(|v| !v.is_null());
+                y += padding.map_or(U, |v| length(v, warnings));
+            }
+        }
+
+        // The mirrored half is reflected at an axis `distance` right of the zone
+        let mirror = [&zone["mirror"], &ergogen["points"]["mirror"]]
+            .into_iter()
+            .find(|m| !m.is_null() && m.as_bool() != Some(false));
+        if let Some(mirror) = mirror {
+            let zone_points: Vec<usize> = (0..points.len())
+                .filter(|i| points[*i].zone == zone_name)
+                .collect();
+            let max_x = zone_points
+                .iter()
+                .map(|i| points[*i].x)
+                .fold(f64::MIN, f64::max);
+            let distance = match &mirror["distance"] {
+                Value::Null => 2.0 * U,
+                distance => length(distance, warnings),
+            };
+            let axis = max_x + distance / 2.0;
+            for i in zone_points {
+                let point = &points[i];
+                points.push(Point {
+                    x: 2.0 * axis - point.x,
+                    y: point.y,
+                    r: -point.r,
+                    zone: point.zone.clone(),
+                    col: point.col.clone(),
+                    row: point.row.clone(),
+                    mirrored: true,
+                });
+            }
+        }
+    }
+    Ok(points)
+}
+
+/// Parse an Ergogen length in mm, like `18`, `u`, `0.5u`, `U` or `cx`
+fn length(value: &Value, warnings: &mut Vec<String>) -> f64 {
+    if let Some(n) = value.as_f64() {
+        return n;
+    }
+    let text = value.as_str().unwrap_or_default().replace(' ', "");
+    for (unit, mm) in [("U", 19.05), ("u", U), ("cx", 18.0), ("cy", 17.0)] {
+        if let Some(factor) = text.strip_suffix(unit) {
+            let factor = match factor {
+                "" => Some(1.0),
+                "-" => Some(-1.0),
+                factor => factor.trim_end_matches('*').parse::<f64>().ok(),
+            };
+            if let Some(factor) = factor {
+                return factor * mm;
+            }
+        }
+    }
+    if let Ok(n) = text.parse() {
+        return n;
+    }
+    if !value.is_null() {
+        warnings.push(format!("Can't evaluate the length `{}`, using 0", text));
+    }
+    0.0
+}
+
+/// Create the keyboard config, returning it with the matrix position of every point
+///
+/// Every row of a zone is a matrix row, the columns of the zone are matrix cols. The mirrored half has
+/// the same matrix, after the cols of the other half.
+fn keyboard_config(
+    name: &str,
+    chip: &str,
+    points: &[Point],
+    warnings: &mut Vec<String>,
+) -> (toml::Table, Vec<(usize, usize)>) {
+    // Rows of a zone are ordered from top to bottom, and its columns from left to right
+    let mean = |values: Vec<f64>| values.iter().sum::<f64>() / values.len() as f64;
+    let left: Vec<&Point> = points.iter().filter(|p| !p.mirrored).collect();
+    let mut zones: Vec<&str> = Vec::new();
+    let mut rows: Vec<(&str, &str, f64)> = Vec::new();
+    let mut cols: HashMap<&str, Vec<(&str, f64)>> = HashMap::new();
+    for point in &left {
+        let in_zone = |p: &&&Point| p.zone == point.zone;
+        if !zones.contains(&point.zone.as_str()) {
+            zones.push(&point.zone);
+        }
+        if !rows
+            .iter()
+            .any(|(z, r, _)| (*z, *r) == (&point.zone, &point.row))
+        {
+            let y = mean(
+                left.iter()
+                    .filter(in_zone)
+                    .filter(|p| p.row == point.row)
+                    .map(|p| p.y)
+                    .collect(),
+            );
+            rows.push((&point.zone, &point.row, y));
+        }
+        let zone_cols = cols.entry(&point.zone).or_default();
+        if !zone_cols.iter().any(|(c, _)| *c == point.col) {
+            let x = mean(
+                left.iter()
+                    .filter(in_zone)
+                    .filter(|p| p.col == point.col)
+                    .map(|p| p.x)
+                    .collect(),
+            );
+            zone_cols.push((&point.col, x));
+        }
+    }
+    let zone_index = |zone: &str| zones.iter().position(|z| *z == zone);
+    rows.sort_by(|a, b| {
+        zone_index(a.0)
+            .cmp(&zone_index(b.0))
+            .then(b.2.total_cmp(&a.2))
+    });
+    for zone_cols in cols.values_mut() {
+        zone_cols.sort_by(|a, b| a.1.total_cmp(&b.1));
+    }
+    let half_rows = rows.len();
+    let half_cols = cols.values().map(|c| c.len()).max().unwrap_or(0);
+    let split = points.iter().any(|p| p.mirrored);
+    let total_cols = if split { half_cols * 2 } else { half_cols };
+
+    let positions: Vec<(usize, usize)> = points
+        .iter()
+        .map(|p| {
+            let row = rows
+                .iter()
+                .position(|(z, r, _)| (*z, *r) == (&p.zone, &p.row))
+                .unwrap_or(0);
+            let col = cols
+                .get(p.zone.as_str())
+                .and_then(|zone_cols| zone_cols.iter().position(|(c, _)| *c == p.col))
+                .unwrap_or(0);
+            (row, if p.mirrored { half_cols + col } else { col })
+        })
+        .collect();
+
+    let mut keyboard = toml::Table::new();
+    keyboard.insert("name".to_string(), name.into());
+    keyboard.insert("product_name".to_string(), name.into());
+    let key = if get_board_chip_map().contains_key(chip) {
+        "board"
+    } else {
+        "chip"
+    };
+    keyboard.insert(key.to_string(), chip.into());
+
+    // Placeholders for the pins, with the diodes from col to row
+    let matrix = || {
+        let pins = |prefix: &str, count: usize| -> Vec<String> {
+            (0..count).map(|i| format!("{}{}", prefix, i)).collect()
+        };
+        let mut matrix = toml::Table::new();
+        matrix.insert("row_pins".to_string(), pins("ROW", half_rows).into());
+        matrix.insert("col_pins".to_string(), pins("COL", half_cols).into());
+        matrix.insert("row2col".to_string(), false.into());
+        matrix
+    };
+    let mut config = toml::Table::new();
+    config.insert("keyboard".to_string(), keyboard.into());
+    if split {
+        let half = |col_offset: usize| {
+            let mut half = toml::Table::new();
+            half.insert("rows".to_string(), (half_rows as i64).into());
+            half.insert("cols".to_string(), (half_cols as i64).into());
+            half.insert("row_offset".to_string(), 0.into());
+            half.insert("col_offset".to_string(), (col_offset as i64).into());
+            half.insert("matrix".to_string(), matrix().into());
+            toml::Value::from(half)
+        };
+        let transport = split_transports(chip).first().map(|t| t.as_str());
+        if transport.is_none() {
+            warnings.push(format!("{} doesn't support split keyboards", chip));
+        }
+        let mut split_config = toml::Table::new();
+        split_config.insert(
+            "connection".to_string(),
+            transport.unwrap_or("serial").into(),
+        );
+        split_config.insert("central".to_string(), half(0));
+        split_config.insert(
+            "peripheral".to_string(),
+            toml::Value::Array(vec![half(half_cols)]),
+        );
+        config.insert("split".to_string(), split_config.into());
+    } else {
+        config.insert("matrix".to_string(), matrix().into());
+    }
+    warnings.push(format!(
+        "The matrix pins are placeholders: ROW0..ROW{} are the rows and COL0..COL{} the cols of every half",
+        half_rows.saturating_sub(1),
+        half_cols.saturating_sub(1)
+    ));
+
+    let mut layer = vec![vec![NO_KEY.to_string(); total_cols]; half_rows];
+    for (r, c) in &positions {
+        layer[*r][*c] = LAYOUT_KEY.to_string();
+    }
+    let mut layout = toml::Table::new();
+    layout.insert("rows".to_string(), (half_rows as i64).into());
+    layout.insert("cols".to_string(), (total_cols as i64).into());
+    layout.insert("layers".to_string(), (DEFAULT_LAYERS as i64).into());
+    layout.insert("keymap".to_string(), vec![layer; DEFAULT_LAYERS].into());
+    config.insert("layout".to_string(), layout.into());
+    (config, positions)
+}
+
+/// Create the KLE layout of vial.json, with the top-left corners of the keys in units from the top left
+fn kle_layout(points: &[Point], positions: &[(usize, usize)]) -> Vec<serde_json::Value> {
+    let min_x = points.iter().map(|p| p.x).fold(f64::MAX, f64::min);
+    let max_y = points.iter().map(|p| p.y).fold(f64::MIN, f64::max);
+    let round = |n: f64| (n * 100.0).round() / 100.0;
+    let mut keys: Vec<(f64, f64, String)> = points
+        .iter()
+        .zip(positions)
+        .map(|(p, (r, c))| {
+            // Ergogen's y points up, KLE's down
+            let x = round((p.x - min_x) / U);
+            let y = round((max_y - p.y) / U);
+            (x, y, format!("{},{}", r, c))
+        })
+        .collect();
+    keys.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.total_cmp(&b.0)));
+
+    // Keys with the same y are a KLE row, every row advances y by one
+    let mut rows: Vec<Vec<serde_json::Value>> = Vec::new();
+    let (mut row_y, mut cursor_x) = (None, 0.0);
+    for (x, y, label) in keys {
+        let mut offset = serde_json::Map::new();
+        if row_y != Some(y) {
+            let next_y = row_y.map_or(0.0, |r: f64| r + 1.0);
+            if y != next_y {
+                offset.insert("y".to_string(), json!(round(y - next_y)));
+            }
+            rows.push(Vec::new());
+            (row_y, cursor_x) = (Some(y), 0.0);
+        }
+        if x != cursor_x {
+            offset.insert("x".to_string(), json!(round(x - cursor_x)));
+        }
+        cursor_x = x + 1.0;
+        let Some(row) = rows.last_mut() else {
+            continue;
+        };
+        if !offset.is_empty() {
+            row.push(offset.into());
+        }
+        row.push(label.into());
+    }
+    rows.into_iter().map(serde_json::Value::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    /// Ergogen config of a 2x2 split with the columns of the right half mirrored
+    const CONFIG: &str = r#"
+meta:
+  name: tiny
+points:
+  zones:
+    matrix:
+      columns:
+        pinky:
+        ring:
+          key.stagger: 0
+      rows:
+        bottom:
+        top:
+  mirror:
+    distance: 2u
+"#;
+
+    /// Import `input` into a temporary directory, returning keyboard.toml and vial.json
+    fn import(name: &str, input: &str) -> Result<(toml::Table, serde_json::Value), Box<dyn Error>> {
+        let dir = env::temp_dir().join(format!("rmkit-ergogen-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = |file: &str| dir.join(file).display().to_string();
+        fs::write(path("ergogen.yaml"), input)?;
+        let result = import_ergogen(
+            &path("ergogen.yaml"),
+            "nrf52840",
+            &path("keyboard.toml"),
+            &path("vial.json"),
+            true,
+        )
+        .and_then(|_| {
+            let config = toml::from_str(&fs::read_to_string(path("keyboard.toml"))?)?;
+            let vial = serde_json::from_str(&fs::read_to_string(path("vial.json"))?)?;
+            Ok((config, vial))
+        });
+        fs::remove_dir_all(&dir)?;
+        result
+    }
+
+    #[test]
+    fn config_is_imported_as_a_split_keyboard() {
+        let (config, vial) = import("config", CONFIG).unwrap();
+        let keymap =
+            ["[[\"_\", \"_\", \"_\", \"_\"], [\"_\", \"_\", \"_\", \"_\"]]"; DEFAULT_LAYERS];
+        let expected: toml::Table = toml::from_str(&format!(
+            r#"
+            [keyboard]
+            name = "tiny"
+            product_name = "tiny"
+            chip = "nrf52840"
+
+            [split]
+            connection = "ble"
+
+            [split.central]
+            rows = 2
+            cols = 2
+            row_offset = 0
+            col_offset = 0
+            matrix = {{ row_pins = ["ROW0", "ROW1"], col_pins = ["COL0", "COL1"], row2col = false }}
+
+            [[split.peripheral]]
+            rows = 2
+            cols = 2
+            row_offset = 0
+            col_offset = 2
+            matrix = {{ row_pins = ["ROW0", "ROW1"], col_pins = ["COL0", "COL1"], row2col = false }}
+
+            [layout]
+            rows = 2
+            cols = 4
+            layers = {}
+            keymap = [{}]
+            "#,
+            DEFAULT_LAYERS,
+            keymap.join(", ")
+        ))
+        .unwrap();
+        assert_eq!(config, expected);
+
+        // The top row comes first, the mirrored pinky is the rightmost key
+        assert_eq!(
+            vial["layouts"]["keymap"],
+            json!([
+                ["0,0", "0,1", { "x": 1.0 }, "0,3", "0,2"],
+                ["1,0", "1,1", { "x": 1.0 }, "1,3", "1,2"]
+            ])
+        );
+        assert_eq!(vial["matrix"], json!({ "rows": 2, "cols": 4 }));
+    }
+
+    #[test]
+    fn points_output_is_imported() {
+        let points = "matrix_a_home: { x: 0, y: 0 }\nmatrix_b_home: { x: 19, y: -4.75 }\n";
+        let (config, vial) = import("points", points).unwrap();
+        assert_eq!(
+            config["matrix"]["col_pins"],
+            toml::Value::from(vec!["COL0", "COL1"])
+        );
+        assert_eq!(
+            config["layout"]["keymap"][0],
+            toml::Value::from(vec![vec!["_", "_"]])
+        );
+        assert_eq!(
+            vial["layouts"]["keymap"],
+            json!([["0,0"], [{ "y": -0.75, "x": 1.0 }, "0,1"]])
+        );
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        let error = |name: &str, input: &str| import(name, input).unwrap_err().to_string();
+        assert!(error("no-position", "matrix_a_home: { x: 0 }\n").contains("has no x and y"));
+        assert!(error("list", "- 1\n- 2\n").contains("neither an Ergogen config"));
+        assert!(error("empty", "{}\n").contains("No keys found"));
+        assert!(error("yaml", "points: [\n").contains("Invalid Ergogen YAML"));
+    }
+}
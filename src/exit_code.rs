@@ -0,0 +1,55 @@
+use crate::interrupt::EXIT_INTERRUPTED;
+use rmkit_core::{failure_kind, FailureKind};
+use std::error::Error;
+
+/// Exit code when the failure isn't classified
+pub(crate) const EXIT_GENERIC: u8 = 1;
+
+/// Stable exit code of every failure class, so that wrappers and CI can branch on it
+///
+/// Exit code 2 is used by clap for invalid command line arguments.
+fn failure_exit_code(kind: FailureKind) -> u8 {
+    match kind {
+        FailureKind::Config => 3,
+        FailureKind::Network => 4,
+        FailureKind::Toolchain => 5,
+        FailureKind::Build => 6,
+        FailureKind::Conversion => 7,
+        FailureKind::Flash => 8,
+    }
+}
+
+/// Get the exit code of an error returned by a subcommand
+pub(crate) fn exit_code(error: &(dyn Error + 'static)) -> u8 {
+    if let Some(kind) = failure_kind(error) {
+        return failure_exit_code(kind);
+    }
+    // Ctrl-C in a prompt is handled by the prompt instead of the signal handler
+    if matches!(
+        error.downcast_ref::<inquire::InquireError>(),
+        Some(inquire::InquireError::OperationInterrupted)
+    ) {
+        return EXIT_INTERRUPTED;
+    }
+    EXIT_GENERIC
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmkit_core::keyboard_toml::parse_keyboard_toml;
+    use std::{env, fs, process};
+
+    #[test]
+    fn unknown_chip_is_a_config_failure() {
+        let path = env::temp_dir().join(format!("rmkit-nochip-{}.toml", process::id()));
+        fs::write(
+            &path,
+            "[keyboard]\nname = \"kb\"\nvendor_id = 0x4c4b\nproduct_id = 0x4643\nchip = \"nochip\"\n",
+        )
+        .unwrap();
+        let error = parse_keyboard_toml(path.to_str().unwrap(), None).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(exit_code(error.as_ref()), 3);
+    }
+}
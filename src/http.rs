@@ -0,0 +1,41 @@
+use reqwest::{Certificate, Client};
+use std::error::Error;
+use std::fs;
+
+/// Build the HTTP client used for all downloads
+///
+/// The client trusts both the bundled webpki roots and the system certificate store, so that
+/// networks with TLS interception work when the interception CA is installed on the system.
+///
+/// # Arguments
+/// * `ca_cert` - Optional path to a PEM file with additional CA certificates to trust
+/// * `insecure` - Disable TLS certificate verification entirely
+pub(crate) fn build_client(
+    ca_cert: Option<&str>,
+    insecure: bool,
+) -> Result<Client, Box<dyn Error>> {
+    // The GitHub API, which is used for private templates, rejects requests without user agent
+    let mut builder = Client::builder()
+        .user_agent(concat!("rmkit/", env!("CARGO_PKG_VERSION")))
+        .tls_built_in_native_certs(true);
+
+    if let Some(path) = ca_cert {
+        let pem =
+            fs::read(path).map_err(|e| format!("Failed to read CA certificate {}: {}", path, e))?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA certificate {}: {}", path, e))?;
+        if certs.is_empty() {
+            return Err(format!("No certificate found in {}", path).into());
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    if insecure {
+        eprintln!("⚠️  TLS certificate verification is disabled by --insecure, downloaded templates can be tampered with!");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    Ok(builder.build()?)
+}
@@ -0,0 +1,192 @@
+use crate::clean::FIRMWARE_EXTENSIONS;
+use crate::release::{default_firmware_format, firmware_formats};
+use rmkit_core::chip::get_board_chip_map;
+use rmkit_core::firmware::release_dir;
+use rmkit_core::keyboard_toml::read_keyboard_config;
+use rmkit_core::lock::LOCK_FILE;
+use rmkit_core::rmk_version;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Print a summary of the project in `project_dir`, as JSON if `json` is set
+pub(crate) fn print_project_info(
+    project_dir: Option<String>,
+    json: bool,
+) -> Result<(), Box<dyn Error>> {
+    let project_dir = PathBuf::from(project_dir.unwrap_or_else(|| ".".to_string()));
+    let info = project_info(&project_dir)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(());
+    }
+
+    let text = |v: &Value| match v {
+        Value::Null => "unknown".to_string(),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    };
+    let list = |v: &Value| {
+        let items: Vec<String> = v.as_array().into_iter().flatten().map(text).collect();
+        if items.is_empty() {
+            "none".to_string()
+        } else {
+            items.join(", ")
+        }
+    };
+    println!("Keyboard:   {}", text(&info["name"]));
+    if !info["board"].is_null() {
+        println!("Board:      {}", text(&info["board"]));
+    }
+    println!("Chip:       {}", text(&info["chip"]));
+    let matrix = &info["matrix"];
+    println!(
+        "Matrix:     {} rows x {} cols, {} layers",
+        text(&matrix["rows"]),
+        text(&matrix["cols"]),
+        text(&matrix["layers"])
+    );
+    match &info["split"] {
+        Value::Null => println!("Split:      no"),
+        split => println!(
+            "Split:      central and {} peripheral(s) over {}",
+            text(&split["peripherals"]),
+            text(&split["connection"])
+        ),
+    }
+    println!("Features:   {}", list(&info["features"]));
+    println!(
+        "Firmware:   {}{}",
+        list(&info["firmware_formats"]),
+        info["default_format"]
+            .as_str()
+            .map(|f| format!(" (default {})", f))
+            .unwrap_or_default()
+    );
+    println!("rmk:        {}", text(&info["rmk"]));
+    let template = &info["template"];
+    if !template.is_null() {
+        let origin = if template["path"].is_string() {
+            text(&template["path"])
+        } else {
+            let reference = ["tag", "branch", "commit"]
+                .iter()
+                .find_map(|key| template[*key].as_str())
+                .unwrap_or("default branch");
+            format!("{} {}", text(&template["repo"]), reference)
+        };
+        println!("Template:   {}", origin);
+    }
+    let artifacts = info["artifacts"].as_array().cloned().unwrap_or_default();
+    if artifacts.is_empty() {
+        println!("Artifacts:  none");
+    } else {
+        println!("Artifacts:");
+    }
+    for artifact in &artifacts {
+        let built = if artifact["exists"] == true {
+            ""
+        } else {
+            " (not built)"
+        };
+        println!("    {}{}", text(&artifact["path"]), built);
+    }
+    Ok(())
+}
+
+/// Collect the summary of a project from keyboard.toml, Cargo.toml, rmkit.lock and the build output
+fn project_info(project_dir: &Path) -> Result<Value, Box<dyn Error>> {
+    let cargo_toml_path = project_dir.join("Cargo.toml");
+    if !cargo_toml_path.exists() {
+        return Err(format!("No Cargo.toml found in {}", project_dir.display()).into());
+    }
+    let config = read_keyboard_config(project_dir.join("keyboard.toml"))?;
+    let lock: Option<toml::Table> = fs::read_to_string(project_dir.join(LOCK_FILE))
+        .ok()
+        .and_then(|content| toml::from_str(&content).ok());
+
+    let keyboard = config.get("keyboard");
+    let get = |table: Option<&toml::Value>, key: &str| table.and_then(|t| t.get(key)).cloned();
+    let board = get(keyboard, "board").and_then(|b| b.as_str().map(|b| b.to_string()));
+    let chip = get(keyboard, "chip")
+        .and_then(|c| c.as_str().map(|c| c.to_string()))
+        .or_else(|| {
+            board
+                .as_deref()
+                .and_then(|b| get_board_chip_map().get(b).map(|c| c.to_string()))
+        });
+    let layout = config.get("layout");
+    let split = config.get("split").map(|split| {
+        json!({
+            "connection": get(Some(split), "connection"),
+            "peripherals": split.get("peripheral").and_then(|p| p.as_array()).map_or(0, |p| p.len()),
+        })
+    });
+
+    let manifest = cargo_toml::Manifest::from_path(&cargo_toml_path)?;
+    let (features, default_features) = match manifest.dependencies.get("rmk") {
+        Some(cargo_toml::Dependency::Detailed(rmk)) => (rmk.features.clone(), rmk.default_features),
+        _ => (Vec::new(), true),
+    };
+    let firmware_formats = chip
+        .as_deref()
+        .map_or_else(Vec::new, |chip| firmware_formats(chip, board.as_deref()));
+    let rmk = lock
+        .as_ref()
+        .and_then(|l| l.get("rmk"))
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string())
+        .or_else(|| {
+            rmk_version::rmk_requirement(&cargo_toml_path)
+                .ok()
+                .flatten()
+                .map(|r| r.to_string())
+        });
+
+    // Build output of the binaries, and firmware files converted from it in the project root
+    let release_dir = release_dir(project_dir, &project_dir.join("target"));
+    let mut artifacts: Vec<PathBuf> = manifest
+        .bin
+        .iter()
+        .filter_map(|bin| bin.name.as_ref())
+        .map(|name| release_dir.join(name))
+        .collect();
+    if let Ok(entries) = fs::read_dir(project_dir) {
+        let mut firmware: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.extension()
+                    .is_some_and(|ext| FIRMWARE_EXTENSIONS.iter().any(|e| ext == *e))
+            })
+            .collect();
+        firmware.sort();
+        artifacts.extend(firmware);
+    }
+
+    Ok(json!({
+        "name": get(keyboard, "name"),
+        "board": board,
+        "chip": chip,
+        "matrix": {
+            "rows": get(layout, "rows"),
+            "cols": get(layout, "cols"),
+            "layers": get(layout, "layers"),
+        },
+        "split": split,
+        "features": features,
+        "default_features": default_features,
+        "firmware_formats": firmware_formats,
+        "default_format": chip
+            .as_deref()
+            .map(|chip| default_firmware_format(chip, board.as_deref())),
+        "rmk": rmk,
+        "template": lock.as_ref().and_then(|l| l.get("template")),
+        "rmkit": lock.as_ref().and_then(|l| l.get("rmkit")),
+        "artifacts": artifacts.iter().map(|path| json!({
+            "path": path.display().to_string(),
+            "exists": path.exists(),
+        })).collect::<Vec<_>>(),
+    }))
+}
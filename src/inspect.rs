@@ -0,0 +1,227 @@
+use rmkit_core::chip::Uf2Family;
+use rmkit_core::firmware::{
+    read_intel_hex, Uf2Tags, UF2_BLOCK, UF2_FLAG_EXTENSION_TAGS, UF2_FLAG_FAMILY_ID, UF2_MAGIC_END,
+    UF2_MAGIC_START,
+};
+use std::error::Error;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+/// Summary of a UF2 file
+#[derive(Debug, PartialEq)]
+struct Uf2Info {
+    blocks: usize,
+    /// Family id of the blocks, `None` if they have a file size instead
+    family: Option<u32>,
+    /// Contiguous address ranges of the payloads, sorted by address
+    ranges: Vec<Range<u32>>,
+    tags: Uf2Tags,
+}
+
+/// Print the address ranges and gaps of a firmware file, and the blocks, family id and extension tags of UF2
+///
+/// The format is taken from the extension, `.uf2`, `.hex` or `.bin`.
+pub(crate) fn inspect_firmware(path: &str) -> Result<(), Box<dyn Error>> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "hex" | "ihex" => {
+            let segments = read_intel_hex(&String::from_utf8_lossy(&data))
+                .map_err(|e| format!("{} in {}", e, path))?;
+            let ranges: Vec<Range<u64>> = segments
+                .iter()
+                .map(|(address, data)| *address..*address + data.len() as u64)
+                .collect();
+            print_ranges(&ranges);
+            Ok(())
+        }
+        "bin" => {
+            println!("Size:           {} bytes", data.len());
+            println!(
+                "ℹ️  Raw binaries have no address, it's given when flashing or converting them"
+            );
+            Ok(())
+        }
+        _ => inspect_uf2(path, &data),
+    }
+}
+
+/// Print the blocks, family id and extension tags of a UF2 file
+fn inspect_uf2(path: &str, data: &[u8]) -> Result<(), Box<dyn Error>> {
+    let info = read_uf2(data).map_err(|e| format!("{} in {}", e, path))?;
+    println!("Blocks:         {}", info.blocks);
+    match info.family {
+        Some(family) => match Uf2Family::from_family_id(family) {
+            Some(known) => println!("UF2 family id:  {:#010x} ({})", family, known.key),
+            None => println!("UF2 family id:  {:#010x}", family),
+        },
+        None => println!("UF2 family id:  none"),
+    }
+    let ranges: Vec<Range<u64>> = info
+        .ranges
+        .iter()
+        .map(|r| u64::from(r.start)..u64::from(r.end))
+        .collect();
+    print_ranges(&ranges);
+    let Uf2Tags {
+        version,
+        board,
+        date,
+    } = info.tags;
+    if version.is_none() && board.is_none() && date.is_none() {
+        println!("No extension tags");
+    }
+    if let Some(version) = version {
+        println!("Version:        {}", version);
+    }
+    if let Some(board) = board {
+        println!("Board:          {}", board);
+    }
+    if let Some(date) = date {
+        println!("Build date:     {}", date);
+    }
+    Ok(())
+}
+
+/// Print the address ranges of firmware, the gaps between them and the total size
+fn print_ranges(ranges: &[Range<u64>]) {
+    for (i, range) in ranges.iter().enumerate() {
+        let label = if i == 0 { "Address range:" } else { "" };
+        println!(
+            "{:<15} {:#010x}..{:#010x} ({} bytes)",
+            label,
+            range.start,
+            range.end,
+            range.end - range.start
+        );
+    }
+    for pair in ranges.windows(2) {
+        if pair[1].start > pair[0].end {
+            println!(
+                "⚠️  Gap of {} bytes at {:#010x}..{:#010x}",
+                pair[1].start - pair[0].end,
+                pair[0].end,
+                pair[1].start
+            );
+        } else if pair[1].start < pair[0].end {
+            println!(
+                "⚠️  Overlap at {:#010x}..{:#010x}",
+                pair[1].start,
+                pair[0].end.min(pair[1].end)
+            );
+        }
+    }
+    let size: u64 = ranges.iter().map(|r| r.end - r.start).sum();
+    println!("Size:           {} bytes", size);
+}
+
+/// Read the blocks of a UF2 file, the extension tags are taken from the first block which has them
+fn read_uf2(data: &[u8]) -> Result<Uf2Info, Box<dyn Error>> {
+    if data.is_empty() || !data.len().is_multiple_of(UF2_BLOCK) {
+        return Err(format!(
+            "Invalid UF2 size {}, it should be a multiple of {}",
+            data.len(),
+            UF2_BLOCK
+        )
+        .into());
+    }
+    let mut info = Uf2Info {
+        blocks: 0,
+        family: None,
+        ranges: Vec::new(),
+        tags: Uf2Tags::default(),
+    };
+    let mut tags = None;
+    let mut payloads = Vec::new();
+    for (i, block) in data.chunks(UF2_BLOCK).enumerate() {
+        let word = |n: usize| u32::from_le_bytes(block[n * 4..n * 4 + 4].try_into().unwrap());
+        if [word(0), word(1)] != UF2_MAGIC_START || word(127) != UF2_MAGIC_END {
+            return Err(format!("Invalid magic number in block {}", i).into());
+        }
+        let (flags, address, payload_size) = (word(2), word(3), word(4) as usize);
+        if payload_size > 476 {
+            return Err(format!("Invalid payload size {} in block {}", payload_size, i).into());
+        }
+        if flags & UF2_FLAG_FAMILY_ID != 0 {
+            info.family = Some(word(7));
+        }
+        if flags & UF2_FLAG_EXTENSION_TAGS != 0 && tags.is_none() {
+            tags = Some(Uf2Tags::decode(&block[32 + payload_size..508]));
+        }
+        payloads.push(address..address + payload_size as u32);
+        info.blocks += 1;
+    }
+    payloads.sort_by_key(|p| p.start);
+    for payload in payloads {
+        match info.ranges.last_mut() {
+            Some(range) if range.end == payload.start => range.end = payload.end,
+            _ => info.ranges.push(payload),
+        }
+    }
+    info.tags = tags.unwrap_or_default();
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmkit_core::firmware::uf2;
+
+    #[test]
+    fn extension_tags_are_read_back() {
+        let tags = Uf2Tags {
+            version: Some("1.2.0".to_string()),
+            board: Some("nice!nano_v2".to_string()),
+            date: Some("2026-10-16".to_string()),
+        };
+        let image = vec![0x5a; 600];
+        let data = uf2(0x1000, &image, 0xada5_2840, &tags);
+        let info = read_uf2(&data).unwrap();
+        assert_eq!(info.blocks, 3);
+        assert_eq!(info.family, Some(0xada5_2840));
+        assert_eq!(info.ranges.len(), 1);
+        assert_eq!(info.ranges[0], 0x1000..0x1300);
+        assert_eq!(info.tags, tags);
+
+        let untagged = uf2(0x1000, &image, 0xada5_2840, &Uf2Tags::default());
+        assert_eq!(read_uf2(&untagged).unwrap().tags, Uf2Tags::default());
+        assert_eq!(&untagged[32..32 + 256], &data[32..32 + 256]);
+        assert_eq!(&untagged[512..], &data[512..]);
+    }
+
+    #[test]
+    fn gaps_split_address_ranges() {
+        let mut data = uf2(0x1000, &[0; 512], 0xe48b_ff56, &Uf2Tags::default());
+        data.extend(uf2(0x2000, &[0; 256], 0xe48b_ff56, &Uf2Tags::default()));
+        assert_eq!(
+            read_uf2(&data).unwrap().ranges,
+            [0x1000..0x1200, 0x2000..0x2100]
+        );
+    }
+
+    #[test]
+    fn long_tags_are_truncated_to_fit_the_block() {
+        let tags = Uf2Tags {
+            version: Some("v".repeat(300)),
+            board: Some("é".repeat(100)),
+            date: Some("2026-10-16".to_string()),
+        };
+        let data = uf2(0, &[0; 16], 0xe48b_ff56, &tags);
+        let read = read_uf2(&data).unwrap().tags;
+        assert_eq!(read.version, Some("v".repeat(64)));
+        assert_eq!(read.board, Some("é".repeat(32)));
+        assert_eq!(read.date, tags.date);
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        assert!(read_uf2(&[]).is_err());
+        assert!(read_uf2(&[0; 512]).is_err());
+        assert!(read_uf2(&[0; 100]).is_err());
+    }
+}
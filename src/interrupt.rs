@@ -0,0 +1,41 @@
+use rmkit_core::interrupt;
+use std::io::{self, IsTerminal};
+
+/// Exit code when rmkit is interrupted, 128 + SIGINT as in shells
+pub(crate) const EXIT_INTERRUPTED: u8 = 130;
+
+/// Handle Ctrl-C and SIGTERM: terminate child processes, remove temporary files and restore the terminal
+pub(crate) fn install_handler() {
+    tokio::spawn(async {
+        wait_for_signal().await;
+        eprintln!("\n⚠️  Interrupted, cleaning up...");
+        interrupt::cleanup();
+        // Prompts put the terminal in raw mode and hide the cursor
+        if io::stderr().is_terminal() {
+            let _ = crossterm::terminal::disable_raw_mode();
+            let _ = crossterm::execute!(io::stderr(), crossterm::cursor::Show);
+        }
+        std::process::exit(EXIT_INTERRUPTED.into());
+    });
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,349 @@
+/// Keycodes of RMK, numbered ones such as `F1` are in [`NUMBERED_KEYCODES`]
+const KEYCODES: &[&str] = &[
+    "No",
+    "Transparent",
+    "Trns",
+    "Enter",
+    "Escape",
+    "Backspace",
+    "Tab",
+    "Space",
+    "Minus",
+    "Equal",
+    "LeftBracket",
+    "RightBracket",
+    "Backslash",
+    "NonusHash",
+    "Semicolon",
+    "Quote",
+    "Grave",
+    "Comma",
+    "Dot",
+    "Slash",
+    "CapsLock",
+    "PrintScreen",
+    "ScrollLock",
+    "Pause",
+    "Insert",
+    "Home",
+    "PageUp",
+    "Delete",
+    "End",
+    "PageDown",
+    "Right",
+    "Left",
+    "Down",
+    "Up",
+    "NumLock",
+    "KpSlash",
+    "KpAsterisk",
+    "KpMinus",
+    "KpPlus",
+    "KpEnter",
+    "KpDot",
+    "NonusBackslash",
+    "Application",
+    "KbPower",
+    "KpEqual",
+    "Execute",
+    "Help",
+    "Menu",
+    "Select",
+    "Stop",
+    "Again",
+    "Undo",
+    "Cut",
+    "Copy",
+    "Paste",
+    "Find",
+    "KbMute",
+    "KbVolumeUp",
+    "KbVolumeDown",
+    "LockingCapsLock",
+    "LockingNumLock",
+    "LockingScrollLock",
+    "KpComma",
+    "KpEqualAs400",
+    "AlternateErase",
+    "SystemRequest",
+    "Cancel",
+    "Clear",
+    "Prior",
+    "Return",
+    "Separator",
+    "Out",
+    "Oper",
+    "ClearAgain",
+    "Crsel",
+    "Exsel",
+    "LCtrl",
+    "LShift",
+    "LAlt",
+    "LGui",
+    "RCtrl",
+    "RShift",
+    "RAlt",
+    "RGui",
+    "SystemPower",
+    "SystemSleep",
+    "SystemWake",
+    "AudioMute",
+    "AudioVolUp",
+    "AudioVolDown",
+    "MediaNextTrack",
+    "MediaPrevTrack",
+    "MediaStop",
+    "MediaPlayPause",
+    "MediaSelect",
+    "MediaEject",
+    "Mail",
+    "Calculator",
+    "MyComputer",
+    "WwwSearch",
+    "WwwHome",
+    "WwwBack",
+    "WwwForward",
+    "WwwStop",
+    "WwwRefresh",
+    "WwwFavorites",
+    "MediaFastForward",
+    "MediaRewind",
+    "BrightnessUp",
+    "BrightnessDown",
+    "ControlPanel",
+    "Assistant",
+    "MissionControl",
+    "Launchpad",
+    "MouseUp",
+    "MouseDown",
+    "MouseLeft",
+    "MouseRight",
+    "MouseWheelUp",
+    "MouseWheelDown",
+    "MouseWheelLeft",
+    "MouseWheelRight",
+    "Bootloader",
+    "Reboot",
+    "DebugToggle",
+    "ClearEeprom",
+    "OutputAuto",
+    "OutputUsb",
+    "OutputBluetooth",
+    "ComboOn",
+    "ComboOff",
+    "ComboToggle",
+    "CapsWordToggle",
+    "GraveEscape",
+    "Repeat",
+];
+
+/// Families of numbered keycodes, e.g. `F1` to `F24`
+const NUMBERED_KEYCODES: &[(&str, u32, u32)] = &[
+    ("Kc", 0, 9),
+    ("F", 1, 24),
+    ("Kp", 0, 9),
+    ("International", 1, 9),
+    ("Language", 1, 9),
+    ("MouseBtn", 1, 8),
+    ("MouseAccel", 0, 2),
+    ("Macro", 0, 31),
+    ("User", 0, 31),
+];
+
+/// Modifiers which can be combined with `|` in `LM`, `OSM`, `WM` and `MT`
+const MODIFIERS: [&str; 8] = [
+    "LCtrl", "LShift", "LAlt", "LGui", "RCtrl", "RShift", "RAlt", "RGui",
+];
+
+/// Argument of a key action
+#[derive(Clone, Copy)]
+enum Arg {
+    Layer,
+    Key,
+    Modifiers,
+    Index,
+}
+
+/// Key actions and their arguments
+const ACTIONS: &[(&str, &[Arg])] = &[
+    ("MO", &[Arg::Layer]),
+    ("OSL", &[Arg::Layer]),
+    ("TT", &[Arg::Layer]),
+    ("TG", &[Arg::Layer]),
+    ("TO", &[Arg::Layer]),
+    ("DF", &[Arg::Layer]),
+    ("LT", &[Arg::Layer, Arg::Key]),
+    ("LM", &[Arg::Layer, Arg::Modifiers]),
+    ("OSM", &[Arg::Modifiers]),
+    ("WM", &[Arg::Key, Arg::Modifiers]),
+    ("MT", &[Arg::Key, Arg::Modifiers]),
+    ("TH", &[Arg::Key, Arg::Key]),
+    ("SHIFTED", &[Arg::Key]),
+    ("TD", &[Arg::Index]),
+];
+
+/// Compare names ignoring case, underscores and the QMK `KC_` prefix, so that `KC_ENTER` matches `Enter`
+fn normalize(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    name.strip_prefix("kc_").unwrap_or(&name).replace('_', "")
+}
+
+fn keycode_names() -> impl Iterator<Item = String> {
+    KEYCODES
+        .iter()
+        .map(|k| k.to_string())
+        .chain(('A'..='Z').map(|c| c.to_string()))
+        .chain(
+            NUMBERED_KEYCODES.iter().flat_map(|(prefix, from, to)| {
+                (*from..=*to).map(move |n| format!("{}{}", prefix, n))
+            }),
+        )
+}
+
+/// Get an error message with a suggestion for an unknown name
+fn unknown(kind: &str, name: &str, known: impl Iterator<Item = String>) -> String {
+    let normalized = normalize(name);
+    let suggestion = known
+        .map(|k| (strsim::jaro_winkler(&normalize(&k), &normalized), k))
+        .filter(|(score, _)| *score > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0));
+    match suggestion {
+        Some((_, k)) => format!("unknown {} `{}`, did you mean `{}`?", kind, name, k),
+        None => format!("unknown {} `{}`", kind, name),
+    }
+}
+
+/// Check a key of the keymap, such as `A`, `LT(1, Space)` or `@my_alias`
+///
+/// Layer numbers must be less than `layers`, and `@alias` must be one of `aliases`.
+pub(crate) fn check_key(key: &str, layers: usize, aliases: &[&str]) -> Result<(), String> {
+    let key = key.trim();
+    // `_`, `__`, ... are transparent
+    if !key.is_empty() && key.chars().all(|c| c == '_') {
+        return Ok(());
+    }
+    if let Some(alias) = key.strip_prefix('@') {
+        return if aliases.contains(&alias) {
+            Ok(())
+        } else {
+            Err(unknown(
+                "alias",
+                key,
+                aliases.iter().map(|a| format!("@{}", a)),
+            ))
+        };
+    }
+    let Some((name, args)) = key.strip_suffix(')').and_then(|k| k.split_once('(')) else {
+        let normalized = normalize(key);
+        return if keycode_names().any(|k| normalize(&k) == normalized) {
+            Ok(())
+        } else {
+            Err(unknown("key", key, keycode_names()))
+        };
+    };
+
+    let Some((action, expected)) = ACTIONS
+        .iter()
+        .find(|(action, _)| action.eq_ignore_ascii_case(name.trim()))
+    else {
+        return Err(unknown(
+            "action",
+            &format!("{}(...)", name.trim()),
+            ACTIONS.iter().map(|(a, _)| format!("{}(...)", a)),
+        ));
+    };
+    let args = split_args(args);
+    if args.len() != expected.len() {
+        return Err(format!(
+            "{} takes {} argument(s), but `{}` has {}",
+            action,
+            expected.len(),
+            key,
+            args.len()
+        ));
+    }
+    for (arg, kind) in args.iter().zip(expected.iter()) {
+        match kind {
+            Arg::Layer => match arg.parse::<usize>() {
+                Ok(layer) if layer < layers => {}
+                Ok(layer) => {
+                    return Err(format!(
+                        "layer {} in `{}` doesn't exist, there are {} layers",
+                        layer, key, layers
+                    ))
+                }
+                Err(_) => return Err(format!("`{}` in `{}` isn't a layer number", arg, key)),
+            },
+            Arg::Index => {
+                if arg.parse::<usize>().is_err() {
+                    return Err(format!("`{}` in `{}` isn't a number", arg, key));
+                }
+            }
+            Arg::Key => check_key(arg, layers, aliases)?,
+            Arg::Modifiers => {
+                for modifier in arg.split('|') {
+                    if !MODIFIERS
+                        .iter()
+                        .any(|m| normalize(m) == normalize(modifier))
+                    {
+                        return Err(unknown(
+                            "modifier",
+                            modifier.trim(),
+                            MODIFIERS.iter().map(|m| m.to_string()),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Get the RMK name of a keycode, which may be written with different case or as QMK's `KC_` name
+pub(crate) fn canonical_keycode(name: &str) -> Option<String> {
+    let normalized = normalize(name);
+    keycode_names().find(|k| normalize(k) == normalized)
+}
+
+/// Split the arguments of an action at top level commas
+pub(crate) fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
+/// Split the `keys` of a `[[layer]]` into keys, spaces inside parentheses don't split
+pub(crate) fn split_layer_keys(keys: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, None);
+    for (i, c) in keys.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(s) = start.take() {
+                    parts.push(&keys[s..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(s) = start {
+        parts.push(&keys[s..]);
+    }
+    parts
+}
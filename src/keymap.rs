@@ -0,0 +1,370 @@
+use rmkit_core::keyboard_toml::ConfigFormat;
+pub(crate) use rmkit_core::keyboard_toml::NO_KEY;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use toml_edit::{Array, DocumentMut, Value};
+
+/// Key which passes through to the lower layer, used to fill new and cleared layers
+const TRANSPARENT_KEY: &str = "_";
+/// Number of layers of a new keymap when `layers` isn't set, like QMK's dynamic keymap
+pub(crate) const DEFAULT_LAYERS: usize = 4;
+
+/// Keymap in the `[layout]` section of `keyboard.toml`, edited in place to keep comments and formatting
+struct Keymap {
+    path: String,
+    doc: DocumentMut,
+    rows: usize,
+    cols: usize,
+    layers: usize,
+}
+
+impl Keymap {
+    fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        if ConfigFormat::from_path(path) != ConfigFormat::Toml {
+            return Err(format!(
+                "Keymap editing only supports keyboard.toml, convert {} with `rmkit convert-config` first",
+                path
+            )
+            .into());
+        }
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let doc: DocumentMut = content.parse()?;
+        let layout = doc
+            .get("layout")
+            .and_then(|l| l.as_table_like())
+            .ok_or("No [layout] section found")?;
+        let dimension = |key: &str| -> Result<usize, Box<dyn Error>> {
+            layout
+                .get(key)
+                .and_then(|v| v.as_integer())
+                .filter(|v| *v > 0)
+                .map(|v| v as usize)
+                .ok_or_else(|| format!("Missing or invalid `{}` in [layout]", key).into())
+        };
+        let (rows, cols, layers) = (dimension("rows")?, dimension("cols")?, dimension("layers")?);
+        Ok(Self {
+            path: path.to_string(),
+            doc,
+            rows,
+            cols,
+            layers,
+        })
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        fs::write(&self.path, self.doc.to_string())?;
+        Ok(())
+    }
+
+    /// Get the keymap array, creating it if it's missing
+    fn keymap_mut(&mut self) -> Result<&mut Array, Box<dyn Error>> {
+        let layout = self.doc["layout"]
+            .as_table_like_mut()
+            .ok_or("Invalid [layout] section")?;
+        if layout.get("keymap").is_none() {
+            layout.insert("keymap", toml_edit::value(Array::new()));
+        }
+        layout
+            .get_mut("keymap")
+            .and_then(|k| k.as_array_mut())
+            .ok_or_else(|| "Invalid `keymap` in [layout], it should be an array of layers".into())
+    }
+
+    /// Get a layer of the keymap, missing layers up to it are added as transparent layers
+    fn layer_mut(&mut self, layer: usize) -> Result<&mut Array, Box<dyn Error>> {
+        if layer >= self.layers {
+            return Err(format!(
+                "Layer {} is out of range, the keyboard has {} layers",
+                layer, self.layers
+            )
+            .into());
+        }
+        let (rows, cols) = (self.rows, self.cols);
+        let keymap = self.keymap_mut()?;
+        while keymap.len() <= layer {
+            // Follow the formatting of the last layer if there is one
+            let new_layer = match keymap.get(keymap.len().wrapping_sub(1)) {
+                Some(Value::Array(last)) => {
+                    let mut new_layer = last.clone();
+                    new_layer.decor_mut().set_prefix("\n    ");
+                    fill_layer(&mut new_layer, TRANSPARENT_KEY);
+                    new_layer
+                }
+                _ => transparent_layer(rows, cols),
+            };
+            keymap.push_formatted(Value::Array(new_layer));
+        }
+        keymap
+            .get_mut(layer)
+            .and_then(|l| l.as_array_mut())
+            .ok_or_else(|| format!("Invalid layer {} in keymap", layer).into())
+    }
+
+    /// Get the keys of a layer as `[row][col]`
+    fn layer_keys(&mut self, layer: usize) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        self.layer_mut(layer)?
+            .iter()
+            .map(|row| {
+                row.as_array()
+                    .ok_or_else(|| format!("Invalid row in layer {}", layer))?
+                    .iter()
+                    .map(|key| {
+                        key.as_str().map(|k| k.to_string()).ok_or_else(|| {
+                            format!("Invalid key in layer {}: {}", layer, key).into()
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Create a layer with all keys transparent
+fn transparent_layer(rows: usize, cols: usize) -> Array {
+    (0..rows)
+        .map(|_| {
+            Value::Array(
+                (0..cols)
+                    .map(|_| Value::from(TRANSPARENT_KEY))
+                    .collect::<Array>(),
+            )
+        })
+        .collect()
+}
+
+/// Set all keys of a layer to `key`, keeping the formatting
+fn fill_layer(layer: &mut Array, key: &str) {
+    for row in layer.iter_mut().filter_map(|r| r.as_array_mut()) {
+        for col in 0..row.len() {
+            row.replace(col, key);
+        }
+    }
+}
+
+/// Parse a `row,col` position
+fn parse_pos(pos: &str) -> Result<(usize, usize), Box<dyn Error>> {
+    let (row, col) = pos
+        .split_once(',')
+        .ok_or_else(|| format!("Invalid position '{}', it should be `row,col`", pos))?;
+    Ok((row.trim().parse()?, col.trim().parse()?))
+}
+
+/// Set the key at `pos` (`row,col`) of a layer
+pub(crate) fn set_key(
+    path: &str,
+    layer: usize,
+    pos: &str,
+    key: &str,
+) -> Result<(), Box<dyn Error>> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("Key can't be empty".into());
+    }
+    let (row, col) = parse_pos(pos)?;
+    let mut keymap = Keymap::load(path)?;
+    if row >= keymap.rows || col >= keymap.cols {
+        return Err(format!(
+            "Position {},{} is out of the {}x{} matrix",
+            row, col, keymap.rows, keymap.cols
+        )
+        .into());
+    }
+    let row_keys = keymap
+        .layer_mut(layer)?
+        .get_mut(row)
+        .and_then(|r| r.as_array_mut())
+        .ok_or_else(|| format!("Row {} of layer {} is missing in keymap", row, layer))?;
+    if col >= row_keys.len() {
+        return Err(format!("Col {} of row {} is missing in keymap", col, row).into());
+    }
+    let old = row_keys.replace(col, key);
+    keymap.save()?;
+    println!(
+        "✅ Layer {} {},{}: {} -> {}",
+        layer,
+        row,
+        col,
+        old.as_str().unwrap_or("?"),
+        key
+    );
+    Ok(())
+}
+
+/// Copy all keys of layer `from` to layer `to`
+pub(crate) fn copy_layer(path: &str, from: usize, to: usize) -> Result<(), Box<dyn Error>> {
+    let mut keymap = Keymap::load(path)?;
+    let source = keymap.layer_keys(from)?;
+    let target = keymap.layer_mut(to)?;
+    if target.len() != source.len() {
+        return Err(format!("Layer {} and layer {} have different row counts", from, to).into());
+    }
+    for (row, keys) in target.iter_mut().zip(source) {
+        let row = row.as_array_mut().ok_or("Invalid row in keymap")?;
+        if row.len() != keys.len() {
+            return Err(
+                format!("Layer {} and layer {} have different col counts", from, to).into(),
+            );
+        }
+        for (col, key) in keys.into_iter().enumerate() {
+            row.replace(col, key);
+        }
+    }
+    keymap.save()?;
+    println!("✅ Copied layer {} to layer {}", from, to);
+    Ok(())
+}
+
+/// Set all keys of a layer to transparent
+pub(crate) fn clear_layer(path: &str, layer: usize) -> Result<(), Box<dyn Error>> {
+    let mut keymap = Keymap::load(path)?;
+    fill_layer(keymap.layer_mut(layer)?, TRANSPARENT_KEY);
+    keymap.save()?;
+    println!("✅ Cleared layer {}", layer);
+    Ok(())
+}
+
+/// Read all layers of the keymap as `[layer][row][col]`, missing layers are transparent
+pub(crate) fn read_keymap(path: &str) -> Result<Vec<Vec<Vec<String>>>, Box<dyn Error>> {
+    let mut keymap = Keymap::load(path)?;
+    (0..keymap.layers)
+        .map(|layer| keymap.layer_keys(layer))
+        .collect()
+}
+
+/// Replace the keys of the first layers of the keymap with `layers`, keeping the formatting
+///
+/// Layers beyond `layers` in `[layout]` are ignored, the layers must have the size of the matrix. `todos`
+/// are written as `# TODO` comments above the rows of their keys, as (layer, row, col, note).
+pub(crate) fn write_keymap(
+    path: &str,
+    layers: &[Vec<Vec<String>>],
+    todos: &[(usize, usize, usize, String)],
+) -> Result<(), Box<dyn Error>> {
+    let mut keymap = Keymap::load(path)?;
+    let (rows, cols) = (keymap.rows, keymap.cols);
+    for (i, keys) in layers.iter().enumerate().take(keymap.layers) {
+        if keys.len() != rows || keys.iter().any(|row| row.len() != cols) {
+            return Err(format!(
+                "Layer {} doesn't fit the {}x{} matrix of {}",
+                i, rows, cols, path
+            )
+            .into());
+        }
+        let layer = keymap.layer_mut(i)?;
+        while layer.len() < rows {
+            layer.push(Array::new());
+        }
+        for (r, (row, row_keys)) in layer.iter_mut().zip(keys).enumerate() {
+            let notes: Vec<String> = todos
+                .iter()
+                .filter(|(l, row, _, _)| (*l, *row) == (i, r))
+                .map(|(_, _, col, note)| format!("col {}: {}", col, note))
+                .collect();
+            if !notes.is_empty() {
+                // Comments need a line of their own, keep the indentation of the row
+                let prefix = row
+                    .decor()
+                    .prefix()
+                    .and_then(|p| p.as_str())
+                    .unwrap_or_default();
+                let indent = prefix
+                    .rsplit_once('\n')
+                    .map_or("", |(_, indent)| indent)
+                    .to_string();
+                row.decor_mut().set_prefix(format!(
+                    "\n{}# TODO: {}\n{}",
+                    indent,
+                    notes.join(", "),
+                    indent
+                ));
+            }
+            let row = row.as_array_mut().ok_or("Invalid row in keymap")?;
+            for (col, key) in row_keys.iter().enumerate() {
+                if col < row.len() {
+                    row.replace(col, key.as_str());
+                } else {
+                    row.push(key.as_str());
+                }
+            }
+        }
+    }
+    keymap.save()
+}
+
+/// Resize the matrix in `[layout]` to `rows` x `cols`, keeping the keys of the keymap inside the new matrix
+///
+/// New positions are `_` if they're in `keys`, the positions of the layout, and `No` otherwise. A missing
+/// keymap is created with `layers` layers, which defaults to 4.
+pub(crate) fn resize_keymap(
+    path: &str,
+    rows: usize,
+    cols: usize,
+    keys: &HashSet<(usize, usize)>,
+) -> Result<(), Box<dyn Error>> {
+    if ConfigFormat::from_path(path) != ConfigFormat::Toml {
+        return Err(format!(
+            "Keymap editing only supports keyboard.toml, convert {} with `rmkit convert-config` first",
+            path
+        )
+        .into());
+    }
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let mut doc: DocumentMut = content.parse()?;
+    if doc.get("layout").is_none() {
+        doc["layout"] = toml_edit::table();
+    }
+    let layout = doc["layout"]
+        .as_table_like_mut()
+        .ok_or("Invalid [layout] section")?;
+    layout.insert("rows", toml_edit::value(rows as i64));
+    layout.insert("cols", toml_edit::value(cols as i64));
+    let layers = match layout.get("layers").and_then(|l| l.as_integer()) {
+        Some(layers) => layers as usize,
+        None => {
+            layout.insert("layers", toml_edit::value(DEFAULT_LAYERS as i64));
+            DEFAULT_LAYERS
+        }
+    };
+    if layout.get("keymap").is_none() {
+        layout.insert("keymap", toml_edit::value(Array::new()));
+    }
+    let keymap = layout
+        .get_mut("keymap")
+        .and_then(|k| k.as_array_mut())
+        .ok_or("Invalid `keymap` in [layout], it should be an array of layers")?;
+
+    let new_key = |row: usize, col: usize| {
+        Value::from(if keys.contains(&(row, col)) {
+            TRANSPARENT_KEY
+        } else {
+            NO_KEY
+        })
+    };
+    while keymap.len() < layers {
+        keymap.push(Array::new());
+    }
+    for layer in keymap.iter_mut() {
+        let layer = layer.as_array_mut().ok_or("Invalid layer in keymap")?;
+        while layer.len() > rows {
+            layer.remove(layer.len() - 1);
+        }
+        while layer.len() < rows {
+            layer.push(Array::new());
+        }
+        for (row, keys) in layer.iter_mut().enumerate() {
+            let keys = keys.as_array_mut().ok_or("Invalid row in keymap")?;
+            while keys.len() > cols {
+                keys.remove(keys.len() - 1);
+            }
+            for col in keys.len()..cols {
+                keys.push_formatted(new_key(row, col));
+            }
+            keys.fmt();
+        }
+    }
+    fs::write(path, doc.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Nets and components of a KiCad netlist export
+#[derive(Debug)]
+pub(crate) struct Netlist {
+    pub(crate) nets: Vec<Net>,
+    /// Components by reference
+    pub(crate) components: HashMap<String, Component>,
+}
+
+/// A component of a KiCad netlist export
+#[derive(Debug)]
+pub(crate) struct Component {
+    /// Symbol as `library:part`, e.g. `Device:D`
+    pub(crate) lib_id: Option<String>,
+    /// Footprint as `library:name`, e.g. `Diode_SMD:D_SOD-123`
+    pub(crate) footprint: Option<String>,
+}
+
+impl Component {
+    /// Whether the symbol or the footprint of the component is a diode
+    pub(crate) fn is_diode(&self) -> bool {
+        [&self.lib_id, &self.footprint]
+            .into_iter()
+            .flatten()
+            .filter_map(|id| id.split_once(':'))
+            .any(|(library, name)| {
+                library.to_lowercase().contains("diode")
+                    || name.to_lowercase().contains("diode")
+                    || name == "D"
+                    || name.starts_with("D_")
+            })
+    }
+}
+
+/// A net in a KiCad netlist export
+#[derive(Debug)]
+pub(crate) struct Net {
+    /// Net name, without the hierarchical sheet prefix
+    pub(crate) name: String,
+    /// Component pins connected to this net
+    pub(crate) nodes: Vec<Node>,
+}
+
+/// A component pin connected to a net
+#[derive(Debug)]
+pub(crate) struct Node {
+    /// Component reference, e.g. `SW1`, `D3` or `U1`
+    pub(crate) reference: String,
+    /// Pin number of the component
+    pub(crate) pin: String,
+    /// Pin function from the symbol, e.g. `GPIO9` or `P0.12`
+    pub(crate) pin_function: Option<String>,
+}
+
+/// S-expression node of a KiCad netlist
+enum SExpr {
+    Atom(String),
+    List(Vec<SExpr>),
+}
+
+impl SExpr {
+    /// Get the keyword of a list, e.g. `net` for `(net (code 1) ...)`
+    fn keyword(&self) -> Option<&str> {
+        match self {
+            SExpr::List(items) => match items.first() {
+                Some(SExpr::Atom(a)) => Some(a.as_str()),
+                _ => None,
+            },
+            SExpr::Atom(_) => None,
+        }
+    }
+
+    /// Get all child lists with the given keyword
+    fn children<'a>(&'a self, keyword: &'a str) -> impl Iterator<Item = &'a SExpr> + 'a {
+        let items: &[SExpr] = match self {
+            SExpr::List(items) => items,
+            SExpr::Atom(_) => &[],
+        };
+        items.iter().filter(move |i| i.keyword() == Some(keyword))
+    }
+
+    /// Get the first atom value of child `(keyword value)`
+    fn value_of(&self, keyword: &str) -> Option<String> {
+        self.children(keyword).next().and_then(|c| match c {
+            SExpr::List(items) => match items.get(1) {
+                Some(SExpr::Atom(a)) => Some(a.clone()),
+                _ => None,
+            },
+            SExpr::Atom(_) => None,
+        })
+    }
+}
+
+/// Read and parse a KiCad netlist (`.net`) file
+pub(crate) fn read_netlist<P: AsRef<Path>>(path: P) -> Result<Netlist, Box<dyn Error>> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read netlist {}: {}", path.display(), e))?;
+    parse_netlist(&content)
+}
+
+/// Parse the content of a KiCad netlist export, supports both KiCad 5 and KiCad 6+ formats
+pub(crate) fn parse_netlist(content: &str) -> Result<Netlist, Box<dyn Error>> {
+    let root = parse_sexpr(content)?;
+    if root.keyword() != Some("export") {
+        return Err("Not a KiCad netlist: missing `(export ...)` root".into());
+    }
+
+    let nets_section = root
+        .children("nets")
+        .next()
+        .ok_or("Invalid KiCad netlist: missing `(nets ...)` section")?;
+
+    let mut nets = Vec::new();
+    for net in nets_section.children("net") {
+        let full_name = net.value_of("name").unwrap_or_default();
+        // Strip hierarchical sheet path, e.g. `/Matrix/ROW0` -> `ROW0`
+        let name = full_name
+            .rsplit('/')
+            .next()
+            .unwrap_or(full_name.as_str())
+            .to_string();
+        let nodes = net
+            .children("node")
+            .filter_map(|node| {
+                Some(Node {
+                    reference: node.value_of("ref")?,
+                    pin: node.value_of("pin").unwrap_or_default(),
+                    pin_function: node.value_of("pinfunction"),
+                })
+            })
+            .collect();
+        nets.push(Net { name, nodes });
+    }
+
+    let components = root
+        .children("components")
+        .flat_map(|c| c.children("comp"))
+        .filter_map(|comp| {
+            let lib_id = comp.children("libsource").next().and_then(|source| {
+                Some(format!(
+                    "{}:{}",
+                    source.value_of("lib")?,
+                    source.value_of("part")?
+                ))
+            });
+            let component = Component {
+                lib_id,
+                footprint: comp.value_of("footprint"),
+            };
+            Some((comp.value_of("ref")?, component))
+        })
+        .collect();
+
+    Ok(Netlist { nets, components })
+}
+
+fn parse_sexpr(content: &str) -> Result<SExpr, Box<dyn Error>> {
+    let mut stack: Vec<Vec<SExpr>> = Vec::new();
+    let mut root = None;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => stack.push(Vec::new()),
+            ')' => {
+                let list = SExpr::List(stack.pop().ok_or("Unbalanced ')' in netlist")?);
+                match stack.last_mut() {
+                    Some(parent) => parent.push(list),
+                    None => {
+                        root = Some(list);
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                let mut atom = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => {
+                            if let Some(escaped) = chars.next() {
+                                atom.push(escaped);
+                            }
+                        }
+                        Some('"') => break,
+                        Some(ch) => atom.push(ch),
+                        None => return Err("Unterminated string in netlist".into()),
+                    }
+                }
+                stack
+                    .last_mut()
+                    .ok_or("Unexpected string outside of a list in netlist")?
+                    .push(SExpr::Atom(atom));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut atom = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '(' || next == ')' {
+                        break;
+                    }
+                    atom.push(next);
+                    chars.next();
+                }
+                stack
+                    .last_mut()
+                    .ok_or("Unexpected atom outside of a list in netlist")?
+                    .push(SExpr::Atom(atom));
+            }
+        }
+    }
+
+    root.ok_or_else(|| "Netlist is empty or unbalanced".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETLIST: &str = r#"(export (version "E")
+  (design (source "/home/user/keyboard/keyboard.kicad_sch") (tool "Eeschema 8.0.4"))
+  (components
+    (comp (ref "D1")
+      (value "1N4148W")
+      (footprint "Diode_SMD:D_SOD-123")
+      (libsource (lib "Device") (part "D") (description "Diode")))
+    (comp (ref "SW1")
+      (value "SW_Push")
+      (footprint "Button_Switch_Keyboard:SW_Cherry_MX_1.00u_PCB")
+      (libsource (lib "Switch") (part "SW_Push") (description "Push button switch")))
+    (comp (ref "U1")
+      (value "nice!nano")
+      (libsource (lib "keyboard") (part "nice_nano"))))
+  (nets
+    (net (code "1") (name "/Matrix/ROW0")
+      (node (ref "D1") (pin "2") (pinfunction "A") (pintype "passive"))
+      (node (ref "U1") (pin "5") (pinfunction "P0.08") (pintype "bidirectional")))
+    (net (code "2") (name "COL0")
+      (node (ref "SW1") (pin "1") (pintype "passive"))
+      (node (ref "U1") (pin "6") (pinfunction "P0.17/\"AIN\"") (pintype "bidirectional")))
+    (net (code "3") (name "Net-(D1-K)")
+      (node (ref "D1") (pin "1") (pinfunction "K") (pintype "passive"))
+      (node (ref "SW1") (pin "2") (pintype "passive")))))
+"#;
+
+    #[test]
+    fn netlist_is_parsed() {
+        let netlist = parse_netlist(NETLIST).unwrap();
+        let names: Vec<&str> = netlist.nets.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["ROW0", "COL0", "Net-(D1-K)"]);
+
+        let mcu = &netlist.nets[1].nodes[1];
+        assert_eq!((mcu.reference.as_str(), mcu.pin.as_str()), ("U1", "6"));
+        assert_eq!(mcu.pin_function.as_deref(), Some("P0.17/\"AIN\""));
+        assert_eq!(netlist.nets[1].nodes[0].pin_function, None);
+
+        let diode = &netlist.components["D1"];
+        assert_eq!(diode.lib_id.as_deref(), Some("Device:D"));
+        assert_eq!(diode.footprint.as_deref(), Some("Diode_SMD:D_SOD-123"));
+        assert!(diode.is_diode());
+        assert!(!netlist.components["SW1"].is_diode());
+        assert!(!netlist.components["U1"].is_diode());
+    }
+
+    #[test]
+    fn diodes_are_recognized_by_symbol_or_footprint() {
+        let component = |lib_id: Option<&str>, footprint: Option<&str>| Component {
+            lib_id: lib_id.map(String::from),
+            footprint: footprint.map(String::from),
+        };
+        assert!(component(Some("Device:D_Small"), None).is_diode());
+        assert!(component(Some("Diode:1N4148W"), None).is_diode());
+        assert!(component(None, Some("keyboard:Diode_SMD_TH")).is_diode());
+        // Displays and connectors are also often referenced as D*
+        assert!(!component(Some("Display:OLED-128x32"), Some("keyboard:OLED")).is_diode());
+        assert!(!component(None, None).is_diode());
+    }
+
+    #[test]
+    fn invalid_netlists_are_rejected() {
+        assert!(parse_netlist("(kicad_sch (version 20231120))").is_err());
+        assert!(parse_netlist("(export (version \"E\")").is_err());
+        assert!(parse_netlist("(export (version \"E\"))").is_err());
+        assert!(parse_netlist("(export (nets (net (name \"unterminated))))").is_err());
+    }
+}
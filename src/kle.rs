@@ -0,0 +1,365 @@
+use crate::keymap::resize_keymap;
+use crate::prompt;
+use inquire::{Select, Text};
+use rmkit_core::keyboard_toml::read_keyboard_config;
+use rmkit_core::vial::generate_vial_json;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// A key of a KLE layout
+struct KleKey {
+    /// Index of the KLE row and of the key in the row
+    row: usize,
+    index: usize,
+    /// Legends of the key, the first is the matrix position in VIA and Vial layouts
+    legends: Vec<String>,
+    /// Matrix position as (row, col)
+    position: Option<(usize, usize)>,
+}
+
+/// A key of a KLE layout at its matrix position, placed in key units from the top left
+pub(crate) struct PlacedKey {
+    pub(crate) row: usize,
+    pub(crate) col: usize,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) w: f64,
+    pub(crate) h: f64,
+}
+
+/// Import a Keyboard Layout Editor layout into keyboard.toml and vial.json
+///
+/// Both the downloaded JSON and the raw data of KLE are accepted. The matrix position of every key is read
+/// from its top-left legend as `row,col`, like in VIA and Vial layouts, and asked for the keys without one.
+/// The matrix size and keymap of keyboard.toml are resized to the layout, and the layout of vial.json is
+/// replaced, vial.json is created if it doesn't exist.
+pub(crate) fn import_kle(
+    kle_path: &str,
+    keyboard_toml_path: &str,
+    vial_json_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let content =
+        fs::read_to_string(kle_path).map_err(|e| format!("Failed to read {}: {}", kle_path, e))?;
+    let mut rows =
+        parse_kle(&content).map_err(|e| format!("Invalid KLE layout {}: {}", kle_path, e))?;
+    let mut keys = layout_keys(&rows);
+    if keys.is_empty() {
+        return Err(format!("No keys found in {}", kle_path).into());
+    }
+    assign_positions(&mut keys)?;
+
+    let positions: HashSet<(usize, usize)> = keys.iter().filter_map(|k| k.position).collect();
+    let matrix_rows = positions.iter().map(|(r, _)| r + 1).max().unwrap_or(0);
+    let matrix_cols = positions.iter().map(|(_, c)| c + 1).max().unwrap_or(0);
+    resize_keymap(keyboard_toml_path, matrix_rows, matrix_cols, &positions)?;
+    println!(
+        "✅ Updated {}: {}x{} matrix with {} keys",
+        keyboard_toml_path,
+        matrix_rows,
+        matrix_cols,
+        keys.len()
+    );
+
+    // The top-left legend of every key is replaced by its matrix position for Vial
+    for key in &keys {
+        let (r, c) = key.position.ok_or("Key without matrix position")?;
+        let mut legends = key.legends.clone();
+        legends[0] = format!("{},{}", r, c);
+        let label = rows[key.row]
+            .as_array_mut()
+            .and_then(|row| row.iter_mut().filter(|k| k.is_string()).nth(key.index))
+            .ok_or("Key not found in the layout")?;
+        *label = Value::from(legends.join("\n"));
+    }
+    let mut vial = match fs::read_to_string(vial_json_path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", vial_json_path, e))?,
+        Err(_) if !Path::new(vial_json_path).exists() => {
+            generate_vial_json(&read_keyboard_config(keyboard_toml_path)?)?
+        }
+        Err(e) => return Err(format!("Failed to read {}: {}", vial_json_path, e).into()),
+    };
+    vial["matrix"] = serde_json::json!({ "rows": matrix_rows, "cols": matrix_cols });
+    vial["layouts"]["keymap"] = Value::Array(rows);
+    fs::write(vial_json_path, serde_json::to_string_pretty(&vial)?)?;
+    println!("✅ Wrote the layout to {}", vial_json_path);
+    Ok(())
+}
+
+/// Parse a KLE layout into its rows, the metadata object at the start is skipped
+///
+/// The raw data of KLE has unquoted keys and no brackets around the rows, so it's parsed as JSON5.
+fn parse_kle(content: &str) -> Result<Vec<Value>, Box<dyn Error>> {
+    let layout_rows = |value: Value| match value {
+        Value::Array(items)
+            if items
+                .iter()
+                .enumerate()
+                .all(|(i, item)| item.is_array() || (i == 0 && item.is_object())) =>
+        {
+            Some(items.into_iter().filter(|row| row.is_array()).collect())
+        }
+        _ => None,
+    };
+    if let Some(rows) = json5::from_str(content).ok().and_then(layout_rows) {
+        return Ok(rows);
+    }
+    layout_rows(json5::from_str(&format!("[{}]", content))?)
+        .ok_or_else(|| "it's not a list of key rows".into())
+}
+
+/// Get the keys of the layout, with the matrix positions of their top-left legends
+fn layout_keys(rows: &[Value]) -> Vec<KleKey> {
+    let mut keys = Vec::new();
+    for (r, row) in rows.iter().enumerate() {
+        let labels = row
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|k| k.as_str());
+        for (index, label) in labels.enumerate() {
+            let legends: Vec<String> = label.split('\n').map(|l| l.to_string()).collect();
+            let position = parse_position(&legends[0]);
+            keys.push(KleKey {
+                row: r,
+                index,
+                legends,
+                position,
+            });
+        }
+    }
+    keys
+}
+
+/// Place the keys of a KLE layout with their matrix position in the top-left legend, like in vial.json
+///
+/// Only the default choice of layout options is placed and decals are skipped. Rotations aren't
+/// followed, the number of rotated key groups is returned with the keys.
+pub(crate) fn place_keys(rows: &[Value]) -> (Vec<PlacedKey>, usize) {
+    let mut keys = Vec::new();
+    let mut y = 0.0;
+    let mut rotated = 0;
+    for row in rows.iter().filter_map(|r| r.as_array()) {
+        let (mut x, mut w, mut h) = (0.0, 1.0, 1.0);
+        let mut skip = false;
+        for item in row {
+            if let Some(props) = item.as_object() {
+                let prop = |key: &str| props.get(key).and_then(|v| v.as_f64());
+                x += prop("x").unwrap_or(0.0);
+                y += prop("y").unwrap_or(0.0);
+                w = prop("w").unwrap_or(w);
+                h = prop("h").unwrap_or(h);
+                skip = props.get("d").and_then(|d| d.as_bool()) == Some(true);
+                if props.contains_key("r") || props.contains_key("rx") || props.contains_key("ry") {
+                    rotated += 1;
+                }
+                continue;
+            }
+            let legends: Vec<&str> = item.as_str().unwrap_or_default().split('\n').collect();
+            // Layout options are `option,choice` in the bottom-right legend
+            let option = legends.get(8).and_then(|o| o.split_once(','));
+            let default_choice = option.is_none_or(|(_, choice)| choice.trim() == "0");
+            if let (Some((row, col)), false) = (parse_position(legends[0]), skip || !default_choice)
+            {
+                keys.push(PlacedKey {
+                    row,
+                    col,
+                    x,
+                    y,
+                    w,
+                    h,
+                });
+            }
+            x += w;
+            (w, h, skip) = (1.0, 1.0, false);
+        }
+        y += 1.0;
+    }
+    (keys, rotated)
+}
+
+fn parse_position(legend: &str) -> Option<(usize, usize)> {
+    let (row, col) = legend.split_once(',')?;
+    Some((row.trim().parse().ok()?, col.trim().parse().ok()?))
+}
+
+/// Ask for the matrix positions of keys without one, and check that no position is used twice
+fn assign_positions(keys: &mut [KleKey]) -> Result<(), Box<dyn Error>> {
+    let missing = keys.iter().filter(|k| k.position.is_none()).count();
+    if missing > 0 {
+        prompt::ensure_interactive(
+            "matrix positions as `row,col` in the top-left legend of every key",
+        )?;
+        println!(
+            "ℹ️  {} of {} keys have no matrix position",
+            missing,
+            keys.len()
+        );
+        const BY_ROWS: &str = "Use the row and column of the key in the layout";
+        const ONE_BY_ONE: &str = "Enter the position of every key";
+        let choice = Select::new(
+            "How to assign the matrix positions?",
+            vec![BY_ROWS, ONE_BY_ONE],
+        )
+        .prompt()?;
+        let mut used: HashSet<(usize, usize)> = keys.iter().filter_map(|k| k.position).collect();
+        for key in keys.iter_mut().filter(|k| k.position.is_none()) {
+            let suggestion = (key.row, key.index);
+            if choice == BY_ROWS {
+                key.position = Some(suggestion);
+                used.insert(suggestion);
+                continue;
+            }
+            let name = key
+                .legends
+                .iter()
+                .find(|l| !l.is_empty())
+                .map(|l| format!("\"{}\"", l))
+                .unwrap_or_else(|| "without legend".to_string());
+            loop {
+                let answer = Text::new(&format!(
+                    "Matrix position of key {} {} in row {}:",
+                    key.index, name, key.row
+                ))
+                .with_default(&format!("{},{}", suggestion.0, suggestion.1))
+                .prompt()?;
+                match parse_position(&answer) {
+                    Some(position) if used.insert(position) => {
+                        key.position = Some(position);
+                        break;
+                    }
+                    Some(_) => println!("❌ {} is already used by another key", answer),
+                    None => println!("❌ Invalid position '{}', it should be `row,col`", answer),
+                }
+            }
+        }
+    }
+
+    let mut seen: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+    for key in keys.iter() {
+        let Some(position) = key.position else {
+            continue;
+        };
+        if let Some((row, index)) = seen.insert(position, (key.row, key.index)) {
+            return Err(format!(
+                "Matrix position {},{} is used by key {} of row {} and key {} of row {}",
+                position.0, position.1, index, row, key.index, key.row
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::{env, process};
+
+    /// Raw data of KLE, with the metadata, a legend after the matrix position and a wide key
+    const KLE: &str = r#"{name: "tiny"},
+["0,0", "0,1\nQ"],
+[{x: 0.5, w: 1.5}, "1,0"]"#;
+
+    const KEYBOARD_TOML: &str = r#"[keyboard]
+name = "tiny"
+
+[layout]
+rows = 1
+cols = 1
+layers = 2
+keymap = [[["A"]], [["B"]]]
+"#;
+
+    /// Import `kle` into a temporary project, returning keyboard.toml and vial.json
+    fn import(name: &str, kle: &str) -> Result<(toml::Table, Value), Box<dyn Error>> {
+        let dir = env::temp_dir().join(format!("rmkit-kle-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = |file: &str| dir.join(file).display().to_string();
+        fs::write(path("layout.json"), kle)?;
+        fs::write(path("keyboard.toml"), KEYBOARD_TOML)?;
+        let result = import_kle(
+            &path("layout.json"),
+            &path("keyboard.toml"),
+            &path("vial.json"),
+        )
+        .and_then(|_| {
+            let config = toml::from_str(&fs::read_to_string(path("keyboard.toml"))?)?;
+            let vial = serde_json::from_str(&fs::read_to_string(path("vial.json"))?)?;
+            Ok((config, vial))
+        });
+        fs::remove_dir_all(&dir)?;
+        result
+    }
+
+    #[test]
+    fn raw_kle_data_is_imported() {
+        let (config, vial) = import("raw", KLE).unwrap();
+        let expected: toml::Table = toml::from_str(
+            r#"
+            rows = 2
+            cols = 2
+            layers = 2
+            keymap = [[["A", "_"], ["_", "No"]], [["B", "_"], ["_", "No"]]]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config["layout"], toml::Value::from(expected));
+        assert_eq!(vial["matrix"], json!({ "rows": 2, "cols": 2 }));
+        assert_eq!(
+            vial["layouts"]["keymap"],
+            json!([["0,0", "0,1\nQ"], [{ "x": 0.5, "w": 1.5 }, "1,0"]])
+        );
+    }
+
+    #[test]
+    fn malformed_layouts_are_rejected() {
+        let error = |name: &str, kle: &str| import(name, kle).unwrap_err().to_string();
+        assert!(error("string", r#""0,0""#).contains("not a list of key rows"));
+        assert!(error("json", "[[\"0,0\"").contains("Invalid KLE layout"));
+        assert!(error("empty", "[]").contains("No keys found"));
+        assert!(error("duplicate", r#"[["0,0", "0,1"], ["0,1"]]"#)
+            .contains("Matrix position 0,1 is used by key 1 of row 0 and key 0 of row 1"));
+    }
+
+    #[test]
+    fn keys_without_matrix_label_have_no_position() {
+        let rows = parse_kle(r#"[["0,0", "Esc", "\n\n\n\n1,2"]]"#).unwrap();
+        let keys = layout_keys(&rows);
+        let positions: Vec<Option<(usize, usize)>> = keys.iter().map(|k| k.position).collect();
+        assert_eq!(positions, [Some((0, 0)), None, None]);
+        assert_eq!(keys[1].legends, ["Esc"]);
+        assert_eq!(parse_position(" 2 , 13 "), Some((2, 13)));
+        assert_eq!(parse_position("2;13"), None);
+    }
+
+    #[test]
+    fn keys_are_placed_in_units() {
+        let rows = parse_kle(
+            r#"[
+                ["0,0", {"w": 2}, "0,1", {"d": true}, "decal", "0,2"],
+                [{"y": 0.5, "x": 0.25}, "1,0", "1,1\n\n\n\n\n\n\n\n0,1", {"r": 15, "rx": 1}, "1,2"]
+            ]"#,
+        )
+        .unwrap();
+        let (keys, rotated) = place_keys(&rows);
+        let placed: Vec<(usize, usize, f64, f64, f64)> =
+            keys.iter().map(|k| (k.row, k.col, k.x, k.y, k.w)).collect();
+        // The decal and the second choice of layout option 0 aren't placed
+        assert_eq!(
+            placed,
+            [
+                (0, 0, 0.0, 0.0, 1.0),
+                (0, 1, 1.0, 0.0, 2.0),
+                (0, 2, 4.0, 0.0, 1.0),
+                (1, 0, 0.25, 1.5, 1.0),
+                (1, 2, 2.25, 1.5, 1.0)
+            ]
+        );
+        assert_eq!(rotated, 1);
+    }
+}
@@ -0,0 +1,305 @@
+use crate::kicad::read_netlist;
+use rmkit_core::keyboard_toml::read_matrix_pins;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+
+/// Matrix line which a net is connected to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Line {
+    Row(usize),
+    Col(usize),
+}
+
+/// Cross-check the matrix wiring in `keyboard.toml` against a KiCad netlist export
+///
+/// Matrix nets are recognized by name (`ROW0`, `COL1`, `R2`, `C3`...). The MCU pin on every
+/// matrix net must be the pin declared for that row/col in `keyboard.toml`, and every switch
+/// must be connected to exactly one row and one col, either directly or through its diode. Diodes are
+/// recognized by their symbol or footprint.
+pub(crate) fn validate_against_pcb(
+    keyboard_toml_path: &str,
+    netlist_path: &str,
+    peripheral: Option<usize>,
+    switch_prefix: &str,
+) -> Result<(), Box<dyn Error>> {
+    let matrix = read_matrix_pins(keyboard_toml_path, peripheral)?;
+    let netlist = read_netlist(netlist_path)?;
+    let nets = &netlist.nets;
+
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    // Matrix line of every matrix net, by net index
+    let net_lines: HashMap<usize, Line> = nets
+        .iter()
+        .enumerate()
+        .filter_map(|(i, net)| parse_matrix_line(&net.name).map(|line| (i, line)))
+        .collect();
+    if net_lines.is_empty() {
+        return Err(
+            "No matrix nets found in the netlist, name the matrix nets like ROW0/COL0 or R0/C0"
+                .into(),
+        );
+    }
+
+    // Check the MCU pin which each matrix net is wired to
+    for (i, net) in nets.iter().enumerate() {
+        let Some(&line) = net_lines.get(&i) else {
+            continue;
+        };
+        let (kind, index, declared) = match line {
+            Line::Row(r) => ("row", r, matrix.row_pins.get(r)),
+            Line::Col(c) => ("col", c, matrix.col_pins.get(c)),
+        };
+        let Some(declared) = declared else {
+            errors.push(format!(
+                "Net {} is {} {}, but keyboard.toml only declares {} rows and {} cols",
+                net.name,
+                kind,
+                index,
+                matrix.row_pins.len(),
+                matrix.col_pins.len()
+            ));
+            continue;
+        };
+
+        let mcu_pins: Vec<(&str, String)> = net
+            .nodes
+            .iter()
+            .filter_map(|n| {
+                let function = n.pin_function.as_deref()?;
+                canonical_pin(function)?;
+                Some((
+                    function,
+                    format!("{} ({} pin {})", function, n.reference, n.pin),
+                ))
+            })
+            .collect();
+        if mcu_pins.is_empty() {
+            warnings.push(format!(
+                "Net {} is not connected to any recognizable MCU pin",
+                net.name
+            ));
+        } else if !mcu_pins
+            .iter()
+            .any(|(function, _)| canonical_pin(function) == canonical_pin(declared))
+        {
+            let wired: Vec<&str> = mcu_pins.iter().map(|(_, desc)| desc.as_str()).collect();
+            errors.push(format!(
+                "{} {}: keyboard.toml declares {}, but net {} is wired to {}",
+                kind,
+                index,
+                declared,
+                net.name,
+                wired.join(", ")
+            ));
+        }
+    }
+
+    // Declared rows/cols without a net on the PCB
+    let declared_lines = matrix
+        .row_pins
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (Line::Row(i), p))
+        .chain(
+            matrix
+                .col_pins
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (Line::Col(i), p)),
+        );
+    for (line, pin) in declared_lines {
+        if !net_lines.values().any(|l| *l == line) {
+            warnings.push(format!(
+                "{:?} ({}) declared in keyboard.toml has no matching net in the netlist",
+                line, pin
+            ));
+        }
+    }
+
+    // Net indices of every component
+    let mut component_nets: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, net) in nets.iter().enumerate() {
+        for node in &net.nodes {
+            component_nets
+                .entry(node.reference.as_str())
+                .or_default()
+                .push(i);
+        }
+    }
+
+    // Resolve the matrix position of every switch
+    let mut positions: BTreeMap<(usize, usize), Vec<&str>> = BTreeMap::new();
+    let switches: BTreeSet<&str> = component_nets
+        .keys()
+        .copied()
+        .filter(|r| is_reference_of(r, switch_prefix))
+        .collect();
+    if switches.is_empty() {
+        return Err(format!(
+            "No switches with reference prefix `{}` found in the netlist",
+            switch_prefix
+        )
+        .into());
+    }
+    for switch in &switches {
+        let mut lines = BTreeSet::new();
+        for &net in &component_nets[switch] {
+            if let Some(line) = net_lines.get(&net) {
+                lines.insert(*line);
+                continue;
+            }
+            // Follow the diode on this net to the other side
+            for node in &nets[net].nodes {
+                let is_diode = netlist
+                    .components
+                    .get(&node.reference)
+                    .is_some_and(|c| c.is_diode());
+                if node.reference != *switch && is_diode {
+                    for other in &component_nets[node.reference.as_str()] {
+                        if let Some(line) = net_lines.get(other) {
+                            lines.insert(*line);
+                        }
+                    }
+                }
+            }
+        }
+
+        let rows: Vec<usize> = lines
+            .iter()
+            .filter_map(|l| match l {
+                Line::Row(r) => Some(*r),
+                Line::Col(_) => None,
+            })
+            .collect();
+        let cols: Vec<usize> = lines
+            .iter()
+            .filter_map(|l| match l {
+                Line::Col(c) => Some(*c),
+                Line::Row(_) => None,
+            })
+            .collect();
+        match (rows.as_slice(), cols.as_slice()) {
+            ([row], [col]) => positions.entry((*row, *col)).or_default().push(switch),
+            _ => errors.push(format!(
+                "Switch {} should be connected to exactly one row and one col, found rows {:?} and cols {:?}",
+                switch, rows, cols
+            )),
+        }
+    }
+    for ((row, col), switches) in &positions {
+        if switches.len() > 1 {
+            warnings.push(format!(
+                "Switches {} share matrix position ({}, {})",
+                switches.join(", "),
+                row,
+                col
+            ));
+        }
+    }
+
+    for warning in &warnings {
+        println!("⚠️  {}", warning);
+    }
+    for error in &errors {
+        println!("❌ {}", error);
+    }
+    if !errors.is_empty() {
+        return Err(format!(
+            "Found {} wiring error(s) between keyboard.toml and the PCB",
+            errors.len()
+        )
+        .into());
+    }
+
+    println!(
+        "✅ {} switches at {} matrix positions match keyboard.toml",
+        switches.len(),
+        positions.len()
+    );
+    Ok(())
+}
+
+/// Check whether a component reference has the given prefix followed by a number, e.g. `SW12` for `SW`
+fn is_reference_of(reference: &str, prefix: &str) -> bool {
+    reference
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// Parse matrix line from net name, e.g. `ROW0`, `row_1`, `COL2`, `C3`
+fn parse_matrix_line(net_name: &str) -> Option<Line> {
+    let name = net_name.to_lowercase();
+    for (prefix, is_row) in [
+        ("row", true),
+        ("column", false),
+        ("col", false),
+        ("r", true),
+        ("c", false),
+    ] {
+        if let Some(rest) = name.strip_prefix(prefix) {
+            let rest = rest.trim_start_matches(['_', '-']);
+            if !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()) {
+                let index = rest.parse().ok()?;
+                return Some(if is_row {
+                    Line::Row(index)
+                } else {
+                    Line::Col(index)
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Normalize MCU pin names so that the pin names in KiCad symbols and in `keyboard.toml` can be compared
+///
+/// `P0.12` and `P0_12` (nRF), `GPIO9`, `GP9` and `PIN_9` (RP2040/ESP32), `PA1` (STM32)
+fn canonical_pin(name: &str) -> Option<String> {
+    // Pin functions in KiCad symbols may list alternate functions, e.g. `GPIO9/SPI1_CSn`
+    name.split(['/', ',', ' ']).find_map(|name| {
+        let name = name.trim().to_uppercase();
+        for prefix in ["PIN_", "GPIO", "GP", "IO"] {
+            if let Some(n) = name
+                .strip_prefix(prefix)
+                .and_then(|n| n.parse::<u32>().ok())
+            {
+                return Some(format!("GPIO{}", n));
+            }
+        }
+        let rest = name.strip_prefix('P')?;
+        let port = rest.chars().next().filter(|c| c.is_ascii_alphanumeric())?;
+        let n: u32 = rest[1..].trim_start_matches(['.', '_']).parse().ok()?;
+        Some(format!("P{}.{}", port, n))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_are_canonical_across_vendors() {
+        assert_eq!(canonical_pin("P0.12"), canonical_pin("P0_12"));
+        assert_eq!(canonical_pin("p1.04").as_deref(), Some("P1.4"));
+        assert_eq!(canonical_pin("GPIO9"), canonical_pin("PIN_9"));
+        assert_eq!(canonical_pin("GP9"), canonical_pin("IO9"));
+        assert_eq!(canonical_pin("PA1").as_deref(), Some("PA.1"));
+        assert_eq!(canonical_pin("GPIO9/SPI1_CSn").as_deref(), Some("GPIO9"));
+        assert_ne!(canonical_pin("P0.12"), canonical_pin("P1.12"));
+        assert_eq!(canonical_pin("VCC"), None);
+        assert_eq!(canonical_pin("GND"), None);
+    }
+
+    #[test]
+    fn matrix_lines_are_parsed_from_net_names() {
+        assert_eq!(parse_matrix_line("ROW0"), Some(Line::Row(0)));
+        assert_eq!(parse_matrix_line("row_12"), Some(Line::Row(12)));
+        assert_eq!(parse_matrix_line("Column-3"), Some(Line::Col(3)));
+        assert_eq!(parse_matrix_line("C4"), Some(Line::Col(4)));
+        assert_eq!(parse_matrix_line("R2"), Some(Line::Row(2)));
+        assert_eq!(parse_matrix_line("Net-(D1-K)"), None);
+        assert_eq!(parse_matrix_line("RGB"), None);
+    }
+}
@@ -0,0 +1,983 @@
+use crate::keycode::{check_key, split_layer_keys};
+use rmkit_core::chip::{check_chip_name, get_board_chip_map, get_chip_packages, split_transports};
+use rmkit_core::keyboard_toml::{diode_direction_row2col, diode_row2col};
+use std::collections::HashMap;
+use toml::{Table, Value};
+
+/// A semantic problem in a keyboard config
+pub(crate) struct Lint {
+    /// Path of the offending value, e.g. `["matrix", "row_pins", "2"]`
+    pub(crate) path: Vec<String>,
+    pub(crate) message: String,
+}
+
+/// Pins bonded out in the package of the chip, which is set by `chip_package`
+struct Package {
+    name: String,
+    pins: Vec<String>,
+}
+
+/// Matrix size of the whole keyboard or of a split half
+#[derive(Clone, Copy)]
+struct Size {
+    rows: usize,
+    cols: usize,
+}
+
+/// Find semantic problems which a keyboard config can have even if it parses
+pub(crate) fn lint_keyboard_config(config: &Table) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let keyboard = config.get("keyboard").and_then(|k| k.as_table());
+    let board = keyboard
+        .and_then(|k| k.get("board"))
+        .and_then(|b| b.as_str());
+    let chip = keyboard
+        .and_then(|k| k.get("chip"))
+        .and_then(|c| c.as_str());
+
+    // `board` already decides the chip
+    if let (Some(board), Some(_)) = (board, chip) {
+        let board_chip = get_board_chip_map().get(board).copied();
+        lints.push(Lint {
+            path: path(&["keyboard", "chip"]),
+            message: match board_chip {
+                Some(board_chip) => format!(
+                    "`board` and `chip` can't be set together, board {} already uses {}",
+                    board, board_chip
+                ),
+                None => "`board` and `chip` can't be set together".to_string(),
+            },
+        });
+    }
+    if board.is_none() && chip.is_none() {
+        lints.push(Lint {
+            path: path(&["keyboard"]),
+            message: "either `board` or `chip` should be set".to_string(),
+        });
+    }
+    for (key, name) in [("board", board), ("chip", chip)] {
+        if let Some(Err(e)) = name.map(|name| check_chip_name(name, key == "board")) {
+            lints.push(Lint {
+                path: path(&["keyboard", key]),
+                message: e.to_string(),
+            });
+        }
+    }
+    let chip = chip.or_else(|| board.and_then(|b| get_board_chip_map().get(b).copied()));
+    let package = lint_package(keyboard, chip, &mut lints);
+
+    // rmk-config can't read a config without them
+    if keyboard.is_none() {
+        lints.push(Lint {
+            path: Vec::new(),
+            message: "no [keyboard] section".to_string(),
+        });
+    }
+    if config.get("layout").is_none() {
+        lints.push(Lint {
+            path: Vec::new(),
+            message: "no [layout] section".to_string(),
+        });
+    }
+    if config.get("matrix").is_none() && config.get("split").is_none() {
+        lints.push(Lint {
+            path: Vec::new(),
+            message: "no [matrix] section, or [split] for a split keyboard".to_string(),
+        });
+    }
+
+    let layout = config.get("layout").and_then(|l| l.as_table());
+    let dimension = |key: &str| {
+        layout
+            .and_then(|l| l.get(key))
+            .and_then(|v| v.as_integer())
+            .map(|v| v as usize)
+    };
+    let size = match (dimension("rows"), dimension("cols")) {
+        (Some(rows), Some(cols)) => Some(Size { rows, cols }),
+        _ => None,
+    };
+    if let (Some(size), Some(layers)) = (size, dimension("layers")) {
+        lint_keymap(config, size, layers, &mut lints);
+    }
+
+    lint_keys(config, dimension("layers"), &mut lints);
+
+    if let Err(e) = diode_row2col(config) {
+        lints.push(Lint {
+            path: Vec::new(),
+            message: e.to_string(),
+        });
+    }
+
+    match config.get("split").and_then(|s| s.as_table()) {
+        Some(split) => lint_split(config, split, size, chip, package.as_ref(), &mut lints),
+        None => {
+            if let Some(matrix) = config.get("matrix").and_then(|m| m.as_table()) {
+                if let Some(size) = size {
+                    lint_matrix(&["matrix"], matrix, size, &mut lints);
+                }
+                lint_diode_direction(&["matrix"], matrix, &mut lints);
+            }
+            let mut pins = matrix_pins(&["matrix"], config.get("matrix"));
+            pins.extend(accessory_pins(config));
+            lint_pins(&pins, chip, package.as_ref(), &mut lints);
+        }
+    }
+
+    lint_features(config, board.or(chip), &mut lints);
+    lints
+}
+
+/// Check the key names and actions in the keymap, `[[layer]]`s and `[aliases]`
+fn lint_keys(config: &Table, layers: Option<usize>, lints: &mut Vec<Lint>) {
+    let layer_tables = config.get("layer").and_then(|l| l.as_array());
+    let layers = layers.or(layer_tables.map(|l| l.len())).unwrap_or(1);
+    let alias_table = config.get("aliases").and_then(|a| a.as_table());
+    let aliases: Vec<&str> = alias_table
+        .into_iter()
+        .flat_map(|a| a.keys().map(|k| k.as_str()))
+        .collect();
+    let mut lint = |path: Vec<String>, location: String, key: &str| {
+        if let Err(message) = check_key(key, layers, &aliases) {
+            lints.push(Lint {
+                path,
+                message: format!("{}: {}", location, message),
+            });
+        }
+    };
+
+    let keymap = config
+        .get("layout")
+        .and_then(|l| l.get("keymap"))
+        .and_then(|k| k.as_array());
+    for (l, layer) in keymap.into_iter().flatten().enumerate() {
+        for (r, row) in layer.as_array().into_iter().flatten().enumerate() {
+            for (c, key) in row.as_array().into_iter().flatten().enumerate() {
+                if let Some(key) = key.as_str() {
+                    let (l, r, c) = (l.to_string(), r.to_string(), c.to_string());
+                    lint(
+                        path(&["layout", "keymap", &l, &r, &c]),
+                        format!("layer {}, row {}, col {}", l, r, c),
+                        key,
+                    );
+                }
+            }
+        }
+    }
+
+    for (l, layer) in layer_tables.into_iter().flatten().enumerate() {
+        if let Some(keys) = layer.get("keys").and_then(|k| k.as_str()) {
+            for (i, key) in split_layer_keys(keys).into_iter().enumerate() {
+                lint(
+                    path(&["layer", &l.to_string(), "keys"]),
+                    format!("layer {}, key {}", l, i),
+                    key,
+                );
+            }
+        }
+    }
+
+    for (name, value) in alias_table.into_iter().flatten() {
+        if let Some(key) = value.as_str() {
+            lint(path(&["aliases", name]), format!("alias {}", name), key);
+        }
+    }
+}
+
+/// Check that the keymap matches the layout size
+fn lint_keymap(config: &Table, size: Size, layers: usize, lints: &mut Vec<Lint>) {
+    let Some(keymap) = config
+        .get("layout")
+        .and_then(|l| l.get("keymap"))
+        .and_then(|k| k.as_array())
+    else {
+        return;
+    };
+    if keymap.len() > layers {
+        lints.push(Lint {
+            path: path(&["layout", "keymap"]),
+            message: format!(
+                "keymap has {} layers, but `layers` is {}",
+                keymap.len(),
+                layers
+            ),
+        });
+    }
+    for (l, layer) in keymap.iter().enumerate() {
+        let Some(layer) = layer.as_array() else {
+            continue;
+        };
+        if layer.len() != size.rows {
+            lints.push(Lint {
+                path: path(&["layout", "keymap", &l.to_string()]),
+                message: format!(
+                    "layer {} has {} rows, but `rows` is {}",
+                    l,
+                    layer.len(),
+                    size.rows
+                ),
+            });
+        }
+        for (r, row) in layer.iter().enumerate() {
+            let Some(row) = row.as_array() else {
+                continue;
+            };
+            if row.len() != size.cols {
+                lints.push(Lint {
+                    path: path(&["layout", "keymap", &l.to_string(), &r.to_string()]),
+                    message: format!(
+                        "row {} of layer {} has {} keys, but `cols` is {}",
+                        r,
+                        l,
+                        row.len(),
+                        size.cols
+                    ),
+                });
+            }
+        }
+    }
+}
+
+/// Check that the pins of a matrix match its size
+fn lint_matrix(prefix: &[&str], matrix: &Table, size: Size, lints: &mut Vec<Lint>) {
+    let count = |key: &str| matrix.get(key).and_then(|p| p.as_array()).map(|p| p.len());
+
+    if matrix.get("matrix_type").and_then(|t| t.as_str()) == Some("direct_pin") {
+        let Some(direct_pins) = matrix.get("direct_pins").and_then(|p| p.as_array()) else {
+            return;
+        };
+        if direct_pins.len() != size.rows {
+            lints.push(Lint {
+                path: join(prefix, &["direct_pins"]),
+                message: format!(
+                    "direct_pins has {} rows, but the matrix has {} rows",
+                    direct_pins.len(),
+                    size.rows
+                ),
+            });
+        }
+        for (r, row) in direct_pins.iter().enumerate() {
+            if let Some(row) = row.as_array().filter(|row| row.len() != size.cols) {
+                lints.push(Lint {
+                    path: join(prefix, &["direct_pins", &r.to_string()]),
+                    message: format!(
+                        "row {} of direct_pins has {} pins, but the matrix has {} cols",
+                        r,
+                        row.len(),
+                        size.cols
+                    ),
+                });
+            }
+        }
+        return;
+    }
+
+    // rmk 0.6 reads the pins by row and col, the diode direction doesn't swap them
+    for key in ["input_pins", "output_pins"] {
+        if matrix.contains_key(key) {
+            lints.push(Lint {
+                path: join(prefix, &[key]),
+                message: format!(
+                    "`{}` isn't supported, use `row_pins` and `col_pins`, `rmkit migrate` converts them",
+                    key
+                ),
+            });
+        }
+    }
+    for (key, name, expected) in [
+        ("row_pins", "rows", size.rows),
+        ("col_pins", "cols", size.cols),
+    ] {
+        if let Some(count) = count(key).filter(|c| *c != expected) {
+            lints.push(Lint {
+                path: join(prefix, &[key]),
+                message: format!(
+                    "{} has {} pins, but the matrix has {} {}",
+                    key, count, expected, name
+                ),
+            });
+        }
+    }
+}
+
+/// Check that `diode_direction` agrees with the `row2col` which rmk reads
+fn lint_diode_direction(prefix: &[&str], matrix: &Table, lints: &mut Vec<Lint>) {
+    let Some(direction) = matrix.get("diode_direction") else {
+        return;
+    };
+    let row2col = matrix.get("row2col").and_then(|r| r.as_bool());
+    let message = match diode_direction_row2col(direction) {
+        Ok(direction) if row2col.is_none() => format!(
+            "`diode_direction` is only read by rmkit, rmk reads `row2col`, set `row2col = {}`",
+            direction
+        ),
+        Ok(direction) if row2col != Some(direction) => format!(
+            "`diode_direction` doesn't match `row2col = {}`, rmk only reads `row2col`",
+            row2col.unwrap_or_default()
+        ),
+        Ok(_) => return,
+        Err(e) => e.to_string(),
+    };
+    lints.push(Lint {
+        path: join(prefix, &["diode_direction"]),
+        message,
+    });
+}
+
+/// Check the central and peripherals of a split keyboard
+fn lint_split(
+    config: &Table,
+    split: &Table,
+    size: Option<Size>,
+    chip: Option<&str>,
+    package: Option<&Package>,
+    lints: &mut Vec<Lint>,
+) {
+    let peripherals: Vec<&Table> = split
+        .get("peripheral")
+        .and_then(|p| p.as_array())
+        .map(|p| p.iter().filter_map(|p| p.as_table()).collect())
+        .unwrap_or_default();
+    if peripherals.is_empty() {
+        lints.push(Lint {
+            path: path(&["split"]),
+            message: "split keyboard has no [[split.peripheral]]".to_string(),
+        });
+    }
+
+    let connection = split.get("connection").and_then(|c| c.as_str());
+    if let (Some(connection), Some(chip)) = (connection, chip) {
+        let transports = split_transports(chip);
+        if !transports.is_empty() && !transports.iter().any(|t| t == connection) {
+            lints.push(Lint {
+                path: path(&["split", "connection"]),
+                message: format!(
+                    "{} doesn't support {} split, supported: {}",
+                    chip,
+                    connection,
+                    transports.join(", ")
+                ),
+            });
+        }
+    }
+
+    let central = split.get("central").and_then(|c| c.as_table());
+    let boards = central
+        .map(|c| (vec!["split".to_string(), "central".to_string()], c))
+        .into_iter()
+        .chain(peripherals.iter().enumerate().map(|(i, p)| {
+            (
+                vec!["split".to_string(), "peripheral".to_string(), i.to_string()],
+                *p,
+            )
+        }));
+    for (prefix, board) in boards {
+        let prefix: Vec<&str> = prefix.iter().map(|s| s.as_str()).collect();
+        let dimension = |key: &str| {
+            board
+                .get(key)
+                .and_then(|v| v.as_integer())
+                .map(|v| v as usize)
+        };
+        if let (Some(rows), Some(cols)) = (dimension("rows"), dimension("cols")) {
+            let board_size = Size { rows, cols };
+            if let Some(size) = size {
+                for (key, name, offset, count, total) in [
+                    (
+                        "row_offset",
+                        "rows",
+                        dimension("row_offset"),
+                        rows,
+                        size.rows,
+                    ),
+                    (
+                        "col_offset",
+                        "cols",
+                        dimension("col_offset"),
+                        cols,
+                        size.cols,
+                    ),
+                ] {
+                    if offset.is_some_and(|o| o + count > total) {
+                        lints.push(Lint {
+                            path: join(&prefix, &[key]),
+                            message: format!(
+                                "{} + {} exceeds the {} {} of the layout",
+                                key, name, total, name
+                            ),
+                        });
+                    }
+                }
+            }
+            if let Some(matrix) = board.get("matrix").and_then(|m| m.as_table()) {
+                lint_matrix(&join_str(&prefix, "matrix"), matrix, board_size, lints);
+            }
+        }
+        if let Some(matrix) = board.get("matrix").and_then(|m| m.as_table()) {
+            lint_diode_direction(&join_str(&prefix, "matrix"), matrix, lints);
+        }
+
+        let serial_count = board
+            .get("serial")
+            .and_then(|s| s.as_array())
+            .map(|s| s.len());
+        let is_central = prefix[1] == "central";
+        match connection {
+            Some("ble") if board.get("ble_addr").is_none() => lints.push(Lint {
+                path: prefix.iter().map(|s| s.to_string()).collect(),
+                message: "BLE split board needs `ble_addr`".to_string(),
+            }),
+            Some("serial") => {
+                let expected = if is_central { peripherals.len() } else { 1 };
+                if serial_count != Some(expected) {
+                    lints.push(Lint {
+                        path: prefix.iter().map(|s| s.to_string()).collect(),
+                        message: format!(
+                            "serial split board needs {} `serial` port(s), one for each {}, found {}",
+                            expected,
+                            if is_central { "peripheral" } else { "central" },
+                            serial_count.unwrap_or(0)
+                        ),
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        // Every half is a separate MCU, pins are only shared within a half
+        let mut pins = matrix_pins(&join_str(&prefix, "matrix"), board.get("matrix"));
+        pins.extend(serial_pins(&prefix, board));
+        if is_central {
+            pins.extend(accessory_pins(config));
+        }
+        lint_pins(&pins, chip, package, lints);
+    }
+}
+
+/// Check combinations of features which can't work
+fn lint_features(config: &Table, chip_or_board: Option<&str>, lints: &mut Vec<Lint>) {
+    let enabled = |section: &str, key: &str| {
+        config
+            .get(section)
+            .and_then(|s| s.get(key))
+            .and_then(|v| v.as_bool())
+    };
+    let ble_enabled = enabled("ble", "enabled") == Some(true);
+    if let Some(chip_or_board) = chip_or_board.filter(|_| ble_enabled) {
+        // Chips in the split database are known, the rest could be anything
+        let transports = split_transports(chip_or_board);
+        if !transports.is_empty() && !transports.iter().any(|t| t == "ble") {
+            lints.push(Lint {
+                path: path(&["ble", "enabled"]),
+                message: format!("{} doesn't support BLE", chip_or_board),
+            });
+        }
+    }
+    if enabled("keyboard", "usb_enable") == Some(false) && !ble_enabled {
+        lints.push(Lint {
+            path: path(&["keyboard", "usb_enable"]),
+            message: "both USB and BLE are disabled, the keyboard can't connect to the host"
+                .to_string(),
+        });
+    }
+}
+
+/// Get all pins of a matrix with their paths
+fn matrix_pins(prefix: &[&str], matrix: Option<&Value>) -> Vec<(Vec<String>, String)> {
+    let mut pins = Vec::new();
+    let Some(matrix) = matrix.and_then(|m| m.as_table()) else {
+        return pins;
+    };
+    for key in ["row_pins", "col_pins"] {
+        for (i, pin) in matrix
+            .get(key)
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            if let Some(pin) = pin.as_str() {
+                pins.push((join(prefix, &[key, &i.to_string()]), pin.to_string()));
+            }
+        }
+    }
+    for (r, row) in matrix
+        .get("direct_pins")
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        for (c, pin) in row.as_array().into_iter().flatten().enumerate() {
+            // `_` marks a position without a key
+            if let Some(pin) = pin.as_str().filter(|p| *p != "_") {
+                let (r, c) = (r.to_string(), c.to_string());
+                pins.push((join(prefix, &["direct_pins", &r, &c]), pin.to_string()));
+            }
+        }
+    }
+    pins
+}
+
+/// Get the serial pins of a split board
+fn serial_pins(prefix: &[&str], board: &Table) -> Vec<(Vec<String>, String)> {
+    let mut pins = Vec::new();
+    for (i, serial) in board
+        .get("serial")
+        .and_then(|s| s.as_array())
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        for key in ["tx_pin", "rx_pin"] {
+            if let Some(pin) = serial.get(key).and_then(|p| p.as_str()) {
+                pins.push((
+                    join(prefix, &["serial", &i.to_string(), key]),
+                    pin.to_string(),
+                ));
+            }
+        }
+    }
+    pins
+}
+
+/// Get pins of the indicator LEDs and the battery, which are on the central of split keyboards
+fn accessory_pins(config: &Table) -> Vec<(Vec<String>, String)> {
+    let mut pins = Vec::new();
+    for (section, key) in [
+        ("light", "capslock"),
+        ("light", "scrolllock"),
+        ("light", "numslock"),
+        ("ble", "charge_state"),
+        ("ble", "charge_led"),
+    ] {
+        if let Some(pin) = config
+            .get(section)
+            .and_then(|s| s.get(key))
+            .and_then(|p| p.get("pin"))
+            .and_then(|p| p.as_str())
+        {
+            pins.push((path(&[section, key, "pin"]), pin.to_string()));
+        }
+    }
+    if let Some(pin) = config
+        .get("ble")
+        .and_then(|b| b.get("battery_adc_pin"))
+        .and_then(|p| p.as_str())
+    {
+        pins.push((path(&["ble", "battery_adc_pin"]), pin.to_string()));
+    }
+    pins
+}
+
+/// Check that pins of one MCU are valid for the chip and not used twice
+fn lint_pins(
+    pins: &[(Vec<String>, String)],
+    chip: Option<&str>,
+    package: Option<&Package>,
+    lints: &mut Vec<Lint>,
+) {
+    let mut used: HashMap<&str, &[String]> = HashMap::new();
+    for (pin_path, pin) in pins {
+        if let Some(chip) = chip.filter(|chip| is_valid_pin(chip, pin) == Some(false)) {
+            lints.push(Lint {
+                path: pin_path.clone(),
+                message: format!("{} is not a valid pin of {}", pin, chip),
+            });
+        } else if let Some(package) = package.filter(|p| !p.pins.contains(pin)) {
+            lints.push(Lint {
+                path: pin_path.clone(),
+                message: format!(
+                    "{} isn't bonded out in the {} package of {}",
+                    pin,
+                    package.name,
+                    chip.unwrap_or_default()
+                ),
+            });
+        }
+        match used.get(pin.as_str()) {
+            Some(first) => lints.push(Lint {
+                path: pin_path.clone(),
+                message: format!("{} is already used by {}", pin, display_path(first)),
+            }),
+            None => {
+                used.insert(pin, pin_path);
+            }
+        }
+    }
+}
+
+/// Check `chip_package` against the packages of the chip, and get the pins of the package
+fn lint_package(
+    keyboard: Option<&Table>,
+    chip: Option<&str>,
+    lints: &mut Vec<Lint>,
+) -> Option<Package> {
+    let package = keyboard?.get("chip_package")?.as_str()?;
+    let chip = chip?;
+    let lint_path = path(&["keyboard", "chip_package"]);
+    let Some(packages) = get_chip_packages(chip) else {
+        lints.push(Lint {
+            path: lint_path,
+            message: format!(
+                "the packages of {} are unknown, pins aren't checked against {}",
+                chip, package
+            ),
+        });
+        return None;
+    };
+    match packages
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(package))
+    {
+        Some((name, info)) => Some(Package {
+            name: name.clone(),
+            pins: info.pins(),
+        }),
+        None => {
+            let mut names: Vec<&str> = packages.keys().map(|n| n.as_str()).collect();
+            names.sort();
+            lints.push(Lint {
+                path: lint_path,
+                message: format!(
+                    "unknown package {} of {}, it should be one of {}",
+                    package,
+                    chip,
+                    names.join(", ")
+                ),
+            });
+            None
+        }
+    }
+}
+
+/// Check a pin name against the naming of a chip family, `None` if the chip family is unknown
+fn is_valid_pin(chip: &str, pin: &str) -> Option<bool> {
+    let chip = chip.to_lowercase();
+    let number = |prefix: &str, max: u32| {
+        pin.strip_prefix(prefix)
+            .and_then(|n| n.parse::<u32>().ok())
+            .is_some_and(|n| n <= max)
+    };
+    Some(if chip.starts_with("nrf") {
+        number("P0_", 31) || number("P1_", 15)
+    } else if chip.starts_with("rp2040") {
+        number("PIN_", 29)
+    } else if chip.starts_with("rp2350") || chip.starts_with("rp235x") {
+        number("PIN_", 47)
+    } else if chip.starts_with("stm32") {
+        let mut chars = pin.chars();
+        chars.next() == Some('P')
+            && chars.next().is_some_and(|port| ('A'..='K').contains(&port))
+            && chars.as_str().parse::<u32>().is_ok_and(|n| n <= 15)
+    } else if chip.starts_with("esp32") {
+        number("gpio", 48) || number("GPIO", 48)
+    } else {
+        return None;
+    })
+}
+
+/// `["matrix", "row_pins", "0"]` -> `matrix.row_pins[0]`
+pub(crate) fn display_path(path: &[String]) -> String {
+    path.iter().fold(String::new(), |path, segment| {
+        if segment.chars().all(|c| c.is_ascii_digit()) {
+            format!("{}[{}]", path, segment)
+        } else if path.is_empty() {
+            segment.to_string()
+        } else {
+            format!("{}.{}", path, segment)
+        }
+    })
+}
+
+fn path(segments: &[&str]) -> Vec<String> {
+    segments.iter().map(|s| s.to_string()).collect()
+}
+
+fn join(prefix: &[&str], segments: &[&str]) -> Vec<String> {
+    prefix
+        .iter()
+        .chain(segments)
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn join_str<'a>(prefix: &[&'a str], segment: &'a str) -> Vec<&'a str> {
+    prefix.iter().copied().chain([segment]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYBOARD: &str = r#"[keyboard]
+name = "kb"
+chip = "nrf52840"
+
+[matrix]
+row_pins = ["P0_01", "P0_02"]
+col_pins = ["P0_03", "P0_04"]
+row2col = false
+
+[layout]
+rows = 2
+cols = 2
+layers = 2
+keymap = [[["A", "B"], ["MO(1)", "No"]], [["_", "_"], ["_", "LCtrl"]]]
+
+[light.capslock]
+pin = "P0_05"
+"#;
+
+    const SPLIT: &str = r#"[keyboard]
+name = "kb"
+chip = "nrf52840"
+
+[layout]
+rows = 1
+cols = 2
+layers = 1
+keymap = [[["A", "B"]]]
+
+[split]
+connection = "ble"
+
+[split.central]
+rows = 1
+cols = 1
+row_offset = 0
+col_offset = 0
+ble_addr = [0x18, 0xe2, 0x21, 0x80, 0xc0, 0xc7]
+[split.central.matrix]
+row_pins = ["P0_01"]
+col_pins = ["P0_02"]
+
+[[split.peripheral]]
+rows = 1
+cols = 1
+row_offset = 0
+col_offset = 1
+ble_addr = [0x7e, 0xfe, 0x73, 0x9e, 0x66, 0xe3]
+[split.peripheral.matrix]
+row_pins = ["P0_01"]
+col_pins = ["P0_02"]
+"#;
+
+    /// Lint `config` with the replacements of (from, to), as `path: message`
+    fn lints(config: &str, replacements: &[(&str, &str)]) -> Vec<String> {
+        let mut config = config.to_string();
+        for (from, to) in replacements {
+            assert!(config.contains(from), "{}", from);
+            config = config.replacen(from, to, 1);
+        }
+        let config: Table = toml::from_str(&config).unwrap();
+        lint_keyboard_config(&config)
+            .iter()
+            .map(|lint| format!("{}: {}", display_path(&lint.path), lint.message))
+            .collect()
+    }
+
+    #[test]
+    fn valid_configs_have_no_lints() {
+        assert!(lints(KEYBOARD, &[]).is_empty());
+        assert!(lints(SPLIT, &[]).is_empty());
+    }
+
+    #[test]
+    fn board_and_chip_conflict() {
+        assert_eq!(
+            lints(KEYBOARD, &[("chip = \"nrf52840\"", "chip = \"nrf52840\"\nboard = \"nice!nano\"")]),
+            ["keyboard.chip: `board` and `chip` can't be set together, board nice!nano already uses nrf52840"]
+        );
+        assert!(lints(
+            KEYBOARD,
+            &[("chip = \"nrf52840\"", "board = \"nice!nano\"")]
+        )
+        .is_empty());
+        assert_eq!(
+            lints(KEYBOARD, &[("chip = \"nrf52840\"", "")]),
+            ["keyboard: either `board` or `chip` should be set"]
+        );
+    }
+
+    #[test]
+    fn keymap_must_fit_the_layout() {
+        assert_eq!(
+            lints(KEYBOARD, &[("[\"MO(1)\", \"No\"]]", "[\"MO(1)\"]]")]),
+            ["layout.keymap[0][1]: row 1 of layer 0 has 1 keys, but `cols` is 2"]
+        );
+        assert_eq!(
+            lints(
+                KEYBOARD,
+                &[
+                    ("rows = 2", "rows = 3"),
+                    ("\"P0_02\"]", "\"P0_02\", \"P0_06\"]")
+                ]
+            ),
+            [
+                "layout.keymap[0]: layer 0 has 2 rows, but `rows` is 3",
+                "layout.keymap[1]: layer 1 has 2 rows, but `rows` is 3"
+            ]
+        );
+        assert_eq!(
+            lints(KEYBOARD, &[("layers = 2", "layers = 1"), ("MO(1)", "A")]),
+            ["layout.keymap: keymap has 2 layers, but `layers` is 1"]
+        );
+        // Layers without keymap are transparent
+        assert!(lints(KEYBOARD, &[("layers = 2", "layers = 4")]).is_empty());
+    }
+
+    #[test]
+    fn unknown_keys_are_reported() {
+        assert_eq!(
+            lints(KEYBOARD, &[("\"B\"", "\"Foo\""), ("MO(1)", "MO(4)")]),
+            [
+                "layout.keymap[0][0][1]: layer 0, row 0, col 1: unknown key `Foo`",
+                "layout.keymap[0][1][0]: layer 0, row 1, col 0: layer 4 in `MO(4)` doesn't exist, there are 2 layers"
+            ]
+        );
+        assert!(lints(
+            KEYBOARD,
+            &[("\"B\"", "\"WM(B, LShift)\""), ("MO(1)", "LT(1, Space)")]
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn diode_direction_must_match_row2col() {
+        assert_eq!(
+            lints(KEYBOARD, &[("row2col = false", "row2col = false\ndiode_direction = \"ROW2COL\"")]),
+            ["matrix.diode_direction: `diode_direction` doesn't match `row2col = false`, rmk only reads `row2col`"]
+        );
+        assert_eq!(
+            lints(KEYBOARD, &[("row2col = false", "diode_direction = \"COL2ROW\"")]),
+            ["matrix.diode_direction: `diode_direction` is only read by rmkit, rmk reads `row2col`, set `row2col = false`"]
+        );
+        assert!(lints(
+            KEYBOARD,
+            &[(
+                "row2col = false",
+                "row2col = false\ndiode_direction = \"COL2ROW\""
+            )]
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn split_boards_are_checked() {
+        assert_eq!(
+            lints(SPLIT, &[("col_offset = 1", "col_offset = 2")]),
+            ["split.peripheral[0].col_offset: col_offset + cols exceeds the 2 cols of the layout"]
+        );
+        assert_eq!(
+            lints(SPLIT, &[("ble_addr = [0x7e", "addr = [0x7e")]),
+            ["split.peripheral[0]: BLE split board needs `ble_addr`"]
+        );
+        assert_eq!(
+            lints(SPLIT, &[("[[split.peripheral]]", "[split.peripherals]")]),
+            ["split: split keyboard has no [[split.peripheral]]"]
+        );
+        assert_eq!(
+            lints(SPLIT, &[("connection = \"ble\"", "connection = \"serial\"")]),
+            [
+                "split.central: serial split board needs 1 `serial` port(s), one for each peripheral, found 0",
+                "split.peripheral[0]: serial split board needs 1 `serial` port(s), one for each central, found 0"
+            ]
+        );
+        let serial =
+            "\nserial = [{ instance = \"UART0\", tx_pin = \"P0_06\", rx_pin = \"P0_07\" }]";
+        assert!(lints(
+            SPLIT,
+            &[
+                ("connection = \"ble\"", "connection = \"serial\""),
+                ("0xc0, 0xc7]", &format!("0xc0, 0xc7]{}", serial)),
+                ("0x66, 0xe3]", &format!("0x66, 0xe3]{}", serial)),
+            ]
+        )
+        .is_empty());
+        assert!(
+            lints(SPLIT, &[("chip = \"nrf52840\"", "chip = \"rp2040\"")]).contains(
+                &"split.connection: rp2040 doesn't support ble split, supported: serial"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn pins_are_checked() {
+        assert_eq!(
+            lints(
+                KEYBOARD,
+                &[("\"P0_04\"", "\"P2_04\""), ("\"P0_05\"", "\"P0_01\"")]
+            ),
+            [
+                "matrix.col_pins[1]: P2_04 is not a valid pin of nrf52840",
+                "light.capslock.pin: P0_01 is already used by matrix.row_pins[0]"
+            ]
+        );
+        assert_eq!(
+            lints(KEYBOARD, &[("\"P0_04\"]", "\"P0_04\", \"P0_06\"]")]),
+            ["matrix.col_pins: col_pins has 3 pins, but the matrix has 2 cols"]
+        );
+        // The halves of split keyboards are separate MCUs
+        assert_eq!(
+            lints(SPLIT, &[("col_pins = [\"P0_02\"]\n\n", "col_pins = [\"P0_01\"]\n\n")]),
+            ["split.central.matrix.col_pins[0]: P0_01 is already used by split.central.matrix.row_pins[0]"]
+        );
+
+        let stm32 = [
+            (
+                "chip = \"nrf52840\"",
+                "chip = \"stm32f411ce\"\nchip_package = \"UFQFPN48\"",
+            ),
+            ("[\"P0_01\", \"P0_02\"]", "[\"PA0\", \"PA1\"]"),
+            ("[\"P0_03\", \"P0_04\"]", "[\"PB0\", \"PC13\"]"),
+            ("\"P0_05\"", "\"PA2\""),
+        ];
+        assert!(lints(KEYBOARD, &stm32).is_empty());
+        assert_eq!(
+            lints(
+                KEYBOARD,
+                &[
+                    stm32[0],
+                    stm32[1],
+                    ("[\"P0_03\", \"P0_04\"]", "[\"PB0\", \"PC0\"]"),
+                    stm32[3]
+                ]
+            ),
+            ["matrix.col_pins[1]: PC0 isn't bonded out in the UFQFPN48 package of stm32f411ce"]
+        );
+        assert!(lints(KEYBOARD, &[stm32[0], ("UFQFPN48", "QFN60")])
+            .contains(&"keyboard.chip_package: unknown package QFN60 of stm32f411ce, it should be one of LQFP100, LQFP64, UFBGA100, UFQFPN48, WLCSP49".to_string()));
+    }
+
+    #[test]
+    fn features_are_checked() {
+        let ble = "\n[ble]\nenabled = true\n";
+        assert_eq!(
+            lints(KEYBOARD, &[("name = \"kb\"", "name = \"kb\"\nusb_enable = false")]),
+            ["keyboard.usb_enable: both USB and BLE are disabled, the keyboard can't connect to the host"]
+        );
+        assert!(lints(
+            KEYBOARD,
+            &[
+                ("name = \"kb\"", "name = \"kb\"\nusb_enable = false"),
+                ("pin = \"P0_05\"\n", ble)
+            ]
+        )
+        .is_empty());
+        assert_eq!(
+            lints(
+                KEYBOARD,
+                &[
+                    ("chip = \"nrf52840\"", "board = \"KB2040\""),
+                    ("[light.capslock]\npin = \"P0_05\"\n", ble),
+                    ("[\"P0_01\", \"P0_02\"]", "[\"PIN_1\", \"PIN_2\"]"),
+                    ("[\"P0_03\", \"P0_04\"]", "[\"PIN_3\", \"PIN_4\"]"),
+                ]
+            ),
+            ["ble.enabled: KB2040 doesn't support BLE"]
+        );
+    }
+}
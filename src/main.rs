@@ -1,41 +1,134 @@
-use cargo_metadata::{Metadata, MetadataCommand};
-use chip::{get_board_chip_map, get_chip_options};
-use clap::Parser;
-use futures::stream::StreamExt;
+use args::TemplateArgs;
+use clap::{CommandFactory, FromArgMatches};
 use inquire::ui::{Attributes, Color, RenderConfig, StyleSheet, Styled};
 use inquire::{Select, Text};
-use keyboard_toml::{parse_keyboard_toml, ProjectInfo};
+use reporter::Terminal;
 use reqwest::Client;
+use rmkit_core::chip::get_chip_options;
+use rmkit_core::keyboard_toml::{parse_keyboard_toml, read_keyboard_config, write_keyboard_config};
+use rmkit_core::{Classify, CreateOptions, FailureKind, InitOptions, Project, VialJson};
 use std::error::Error;
-use std::fs;
-use std::fs::File;
-use std::io::{self, Write};
-use std::path::{Path, PathBuf};
-use zip::ZipArchive;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tracing::Instrument;
 
 mod args;
+mod cache;
+mod capture;
+mod check;
 mod chip;
-mod keyboard_toml;
-mod version;
+mod clean;
+mod completions;
+mod conflict;
+mod dry_run;
+mod ergogen;
+mod exit_code;
+mod http;
+mod info;
+mod inspect;
+mod interrupt;
+mod keycode;
+mod keymap;
+mod kicad;
+mod kle;
+mod layout;
+mod lint;
+mod migrate;
+mod monitor;
+mod prompt;
+mod qmk;
+mod qmk_keymap;
+mod release;
+mod render;
+mod repair;
+mod reporter;
+mod run;
+mod size;
+mod trace;
+mod uf2;
+mod via;
+mod vial;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+async fn main() -> ExitCode {
     inquire::set_global_render_config(get_render_config());
-    let args = args::Args::parse();
+    interrupt::install_handler();
+    let matches = args::Args::command().get_matches();
+    let args = args::Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let timings = args.trace_spans.then(trace::init);
+    let span = tracing::info_span!("rmkit", command = matches.subcommand_name());
+    let result = run(args).instrument(span).await;
+    if let Some(timings) = timings {
+        timings.print();
+    }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(exit_code::exit_code(e.as_ref()))
+        }
+    }
+}
+
+async fn run(args: args::Args) -> Result<(), Box<dyn Error>> {
     match args.command {
         args::Commands::Create {
             keyboard_toml_path,
             vial_json_path,
             target_dir,
             version,
-        } => create_project(keyboard_toml_path, vial_json_path, target_dir, version).await,
+            template,
+        } => {
+            let client = http::build_client(args.ca_cert.as_deref(), args.insecure)
+                .or_fail(FailureKind::Config)?;
+            create_project(
+                keyboard_toml_path,
+                vial_json_path,
+                target_dir,
+                version,
+                template,
+                false,
+                &client,
+            )
+            .await
+        }
         args::Commands::Init {
             project_name,
             chip,
             split,
             local_path,
             version,
-        } => init_project(project_name, chip, split, local_path, version).await,
+            from_keyboard_toml,
+            vial_json_path,
+            template,
+        } => {
+            let client = http::build_client(args.ca_cert.as_deref(), args.insecure)
+                .or_fail(FailureKind::Config)?;
+            if let Some(keyboard_toml_path) = from_keyboard_toml {
+                // Same as `create`, but the project name is used as target dir and vial.json is optional
+                create_project(
+                    Some(keyboard_toml_path),
+                    vial_json_path,
+                    project_name,
+                    version,
+                    template,
+                    true,
+                    &client,
+                )
+                .await
+            } else {
+                init_project(
+                    project_name,
+                    chip,
+                    split,
+                    local_path,
+                    version,
+                    template,
+                    &client,
+                )
+                .await
+            }
+        }
         args::Commands::GetChip { keyboard_toml_path } => {
             let project_info = parse_keyboard_toml(&keyboard_toml_path, None)?;
             println!("{}", project_info.chip);
@@ -46,131 +139,259 @@ async fn main() -> Result<(), Box<dyn Error>> {
             println!("{}", project_info.project_name);
             Ok(())
         }
+        args::Commands::Chips { command } => match command {
+            args::ChipsCommands::List {
+                split_only,
+                uf2_only,
+                vendor,
+            } => {
+                chip::list_chips(split_only, uf2_only, vendor.as_deref());
+                Ok(())
+            }
+            args::ChipsCommands::Info { chip } => {
+                chip::print_chip_info(&chip).or_fail(FailureKind::Config)
+            }
+            args::ChipsCommands::Audit { uf2_families } => {
+                chip::audit_chips(uf2_families).or_fail(FailureKind::Config)
+            }
+        },
+        args::Commands::Cache { command } => match command {
+            args::CacheCommands::Clean => cache::clean_cache(),
+        },
+        args::Commands::Keymap { command } => match command {
+            args::KeymapCommands::Set {
+                keyboard_toml_path,
+                layer,
+                pos,
+                key,
+            } => keymap::set_key(&keyboard_toml_path, layer, &pos, &key),
+            args::KeymapCommands::CopyLayer {
+                keyboard_toml_path,
+                from,
+                to,
+            } => keymap::copy_layer(&keyboard_toml_path, from, to),
+            args::KeymapCommands::Clear {
+                keyboard_toml_path,
+                layer,
+            } => keymap::clear_layer(&keyboard_toml_path, layer),
+            args::KeymapCommands::Import {
+                keyboard_toml_path,
+                backup,
+            } => via::import_backup(&keyboard_toml_path, &backup),
+            args::KeymapCommands::Export {
+                keyboard_toml_path,
+                output,
+            } => via::export_backup(&keyboard_toml_path, &output),
+            args::KeymapCommands::Render {
+                keyboard_toml_path,
+                vial_json_path,
+                layer,
+                output,
+            } => render::render_keymap(
+                &keyboard_toml_path,
+                vial_json_path.as_deref(),
+                layer,
+                output.as_deref(),
+            ),
+        }
+        .or_fail(FailureKind::Config),
+        args::Commands::Layout { command } => match command {
+            args::LayoutCommands::Validate {
+                keyboard_toml_path,
+                against_pcb,
+                peripheral,
+                switch_prefix,
+            } => layout::validate_against_pcb(
+                &keyboard_toml_path,
+                &against_pcb,
+                peripheral,
+                &switch_prefix,
+            )
+            .or_fail(FailureKind::Config),
+        },
+        args::Commands::Import { command } => match command {
+            args::ImportCommands::Qmk {
+                info,
+                rules,
+                output,
+                force,
+            } => qmk::import_qmk(&info, rules.as_deref(), &output, force)
+                .or_fail(FailureKind::Conversion),
+            args::ImportCommands::Ergogen {
+                input,
+                chip,
+                output,
+                vial_json_path,
+                force,
+            } => ergogen::import_ergogen(&input, &chip, &output, &vial_json_path, force)
+                .or_fail(FailureKind::Conversion),
+            args::ImportCommands::QmkKeymap {
+                keymap,
+                info,
+                keyboard_toml_path,
+            } => qmk_keymap::import_qmk_keymap(&keymap, info.as_deref(), &keyboard_toml_path)
+                .or_fail(FailureKind::Conversion),
+            args::ImportCommands::Via {
+                definition,
+                keyboard_toml_path,
+                output,
+                force,
+            } => vial::import_via_definition(&definition, &keyboard_toml_path, &output, force)
+                .or_fail(FailureKind::Conversion),
+            args::ImportCommands::Kle {
+                kle,
+                keyboard_toml_path,
+                vial_json_path,
+            } => kle::import_kle(&kle, &keyboard_toml_path, &vial_json_path)
+                .or_fail(FailureKind::Conversion),
+        },
+        args::Commands::Export { command } => match command {
+            args::ExportCommands::Qmk {
+                keyboard_toml_path,
+                vial_json_path,
+                output,
+                force,
+            } => qmk::export_qmk(&keyboard_toml_path, &vial_json_path, &output, force)
+                .or_fail(FailureKind::Conversion),
+        },
+        args::Commands::Repair {
+            project_dir,
+            build,
+            yes,
+        } => repair::repair_project(project_dir, build, yes),
+        args::Commands::Info { project_dir, json } => {
+            info::print_project_info(project_dir, json).or_fail(FailureKind::Config)
+        }
+        args::Commands::Run {
+            project_dir,
+            bin,
+            no_install,
+            cargo,
+        } => run::run_firmware(project_dir, bin, no_install, cargo.into()),
+        args::Commands::Monitor {
+            project_dir,
+            bin,
+            serial,
+            filter,
+            timestamps,
+        } => monitor::monitor(project_dir, bin, serial, filter, timestamps),
+        args::Commands::Size {
+            project_dir,
+            bin,
+            top,
+            json,
+        } => size::print_size(project_dir, bin, top, json),
+        args::Commands::Release {
+            project_dir,
+            name,
+            output,
+            out_dir,
+            format,
+            no_install,
+            cargo,
+        } => release::release_firmware(
+            project_dir,
+            name,
+            output,
+            out_dir,
+            format,
+            no_install,
+            cargo.into(),
+        ),
+        args::Commands::Uf2 { command } => match command {
+            args::Uf2Commands::Convert {
+                input,
+                chip,
+                family_id,
+                base,
+                output,
+            } => uf2::convert_uf2(
+                &input,
+                chip.as_deref(),
+                family_id.as_deref(),
+                base.as_deref(),
+                output,
+            )
+            .or_fail(FailureKind::Conversion),
+            args::Uf2Commands::Inspect { file } => {
+                inspect::inspect_firmware(&file).or_fail(FailureKind::Config)
+            }
+        },
+        args::Commands::Inspect { file } => {
+            inspect::inspect_firmware(&file).or_fail(FailureKind::Config)
+        }
+        args::Commands::Clean {
+            project_dir,
+            target,
+            cache,
+        } => clean::clean_project(project_dir, target, cache),
+        args::Commands::Capture {
+            project_dir,
+            output,
+        } => capture::capture_project(project_dir, &output),
+        args::Commands::Replay { archive } => capture::replay_capture(&archive),
+        args::Commands::Schema { output } => check::export_schema(output.as_deref()),
+        args::Commands::Check {
+            keyboard_toml_path,
+            schema,
+        } => check::check_keyboard_config(&keyboard_toml_path, schema).or_fail(FailureKind::Config),
+        args::Commands::Migrate {
+            keyboard_toml_path,
+            output,
+        } => migrate::migrate_keyboard_toml(&keyboard_toml_path, output.as_deref())
+            .or_fail(FailureKind::Config),
+        args::Commands::ConvertConfig { input, output } => {
+            write_keyboard_config(&output, &read_keyboard_config(&input)?)
+                .or_fail(FailureKind::Conversion)?;
+            println!("✅ Converted {} to {}", input, output);
+            Ok(())
+        }
+        args::Commands::Completions { shell } => completions::print_completions(shell),
+        args::Commands::Man { out_dir } => completions::print_man(out_dir),
     }
 }
 
+/// Create project from keyboard.toml and vial.json
+///
+/// If no vial.json is given, it's generated from keyboard.toml when `generate_vial_json` is set or `[layout]` has a keymap,
+/// and asked for otherwise
 async fn create_project(
     keyboard_toml_path: Option<String>,
     vial_json_path: Option<String>,
     target_dir: Option<String>,
     version: Option<String>,
+    template: TemplateArgs,
+    generate_vial_json: bool,
+    client: &Client,
 ) -> Result<(), Box<dyn Error>> {
-    // Resolve version first for fast fail
-    let commit_or_branch = version::resolve_template_version(version.as_deref()).await?;
-
     // Inquire paths interactively is no argument is specified
     let keyboard_toml_path = if let Some(path) = keyboard_toml_path {
         path
     } else {
-        Text::new("Path to keyboard.toml:")
-            .with_default("./keyboard.toml")
-            .prompt()?
+        prompt::text_or_default("Path to keyboard.toml:", "./keyboard.toml")?
     };
-    let vial_json_path = if let Some(path) = vial_json_path {
-        path
+    let vial_json = if let Some(path) = vial_json_path {
+        VialJson::Path(path.into())
+    } else if generate_vial_json
+        || rmkit_core::vial::has_full_layout(&read_keyboard_config(&keyboard_toml_path)?)
+    {
+        VialJson::Generate
     } else {
-        Text::new("Path to vial.json")
-            .with_default("./vial.json")
-            .prompt()?
+        VialJson::Path(prompt::text_or_default("Path to vial.json", "./vial.json")?.into())
     };
-    // Parse keyboard.toml to get project info
-    let project_info = parse_keyboard_toml(&keyboard_toml_path, target_dir)?;
-
-    // Download corresponding project template
-    download_project_template(&project_info, &commit_or_branch).await?;
 
-    // Copy keyboard.toml and vial.json to project_dir
-    fs::copy(
-        &keyboard_toml_path,
-        project_info.target_dir.join("keyboard.toml"),
-    )?;
-    fs::copy(&vial_json_path, project_info.target_dir.join("vial.json"))?;
-
-    // Post-process
-    post_process(project_info)?;
-
-    Ok(())
-}
-
-/// Postprocessing after generating project
-fn post_process(project_info: ProjectInfo) -> Result<(), Box<dyn Error>> {
-    // Replace {{ project_name }} in toml/json files
-    replace_in_folder(
-        &project_info,
-        "toml",
-        "{{ project_name }}",
-        &project_info.project_name,
-    )?;
-    replace_in_folder(
-        &project_info,
-        "json",
-        "{{ project_name }}",
-        &project_info.project_name,
-    )?;
-
-    // Replace {{ chip_name }} in toml files
-    replace_in_folder(&project_info, "toml", "{{ chip_name }}", &project_info.chip)?;
-
-    // Replace {{ uf2_key }} in toml files
-    replace_in_folder(
-        &project_info,
-        "toml",
-        "{{ uf2_key }}",
-        &project_info.uf2_key,
-    )?;
-
-    // Disable some default features
-    if !project_info.disabled_default_feature.is_empty() {
-        let metadata = MetadataCommand::new()
-            .current_dir(&project_info.target_dir)
-            .exec()?;
-        disable_rmk_default_features(
-            &project_info.target_dir,
-            &metadata,
-            project_info.disabled_default_feature,
-        )?;
-    }
-
-    // Enable non-default features
-    if !project_info.enabled_feature.is_empty() {
-        enable_rmk_features(&project_info.target_dir, project_info.enabled_feature)?;
-    }
-
-    Ok(())
-}
-
-fn replace_in_folder(
-    project_info: &ProjectInfo,
-    ext: &str,
-    from: &str,
-    to: &str,
-) -> Result<(), Box<dyn Error>> {
-    let walker = walkdir::WalkDir::new(&project_info.target_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().is_some_and(|e| e == ext));
-    for entry in walker {
-        let path = entry.path();
-        let content = fs::read_to_string(path)?;
-        let new_content = content.replace(from, to);
-        fs::write(path, new_content)?;
-    }
+    let options = CreateOptions {
+        keyboard_toml_path,
+        vial_json,
+        target_dir,
+        version,
+        template: template.into(),
+    };
+    let project = rmkit_core::create_project(&options, client, &mut Terminal).await?;
+    report_dry_run(&project);
     Ok(())
 }
 
-async fn download_project_template(
-    project_info: &ProjectInfo,
-    commit_or_branch: &str,
-) -> Result<(), Box<dyn Error>> {
-    let user = "HaoboGu";
-    let repo = "rmk-template";
-
-    // Build download URL
-    let url = version::build_github_archive_url(user, repo, commit_or_branch);
-
-    download_with_progress(&url, &project_info.target_dir, &project_info.remote_folder).await
-}
-
 /// Initialize project from remote url
 async fn init_project(
     project_name: Option<String>,
@@ -178,27 +399,25 @@ async fn init_project(
     split: Option<bool>,
     local_path: Option<String>,
     version: Option<String>,
+    template: TemplateArgs,
+    client: &Client,
 ) -> Result<(), Box<dyn Error>> {
-    // Resolve version first for fast fail (only when using remote template)
-    let commit_or_branch = if local_path.is_none() {
-        Some(version::resolve_template_version(version.as_deref()).await?)
-    } else {
-        None
-    };
-
     let project_name = if let Some(name) = project_name {
-        name.replace(" ", "_")
+        name
     } else {
-        Text::new("Project Name:").prompt()?.replace(" ", "_")
+        prompt::ensure_interactive("--project-name")?;
+        Text::new("Project Name:").prompt()?
     };
     let split = if let Some(s) = split {
         s
     } else {
+        prompt::ensure_interactive("--split")?;
         Select::new("Choose your keyboard type?", vec!["normal", "split"]).prompt()? == "split"
     };
-    let mut chip_or_board = if let Some(c) = chip {
-        c
+    let chip = if let Some(c) = chip {
+        chip::resolve_chip(&c).or_fail(FailureKind::Config)?
     } else {
+        prompt::ensure_interactive("--chip")?;
         Select::new(
             "Choose your microcontroller or board",
             get_chip_options(split),
@@ -207,225 +426,24 @@ async fn init_project(
         .to_string()
     };
 
-    // Get project info from parameters
-    let target_dir = PathBuf::from(&project_name);
-    fs::create_dir_all(&target_dir)?;
-
-    // Convert board to chip first
-    let board_chip_map = get_board_chip_map();
-    if let Some(c) = board_chip_map.get(chip_or_board.as_str()) {
-        chip_or_board = c.to_string();
-    };
-    let remote_folder = if split {
-        format!("{}_{}", chip_or_board, "split")
-    } else {
-        chip_or_board.clone()
-    };
-
-    let uf2_key = if chip_or_board.starts_with("stm32") {
-        chip_or_board[..7].to_string()
-    } else if chip_or_board == "pico_w" {
-        "rp2040".to_string()
-    } else {
-        chip_or_board.clone()
-    };
-
-    let project_info = ProjectInfo {
+    let options = InitOptions {
         project_name,
-        target_dir,
-        remote_folder,
-        chip: chip_or_board,
-        uf2_key,
-        disabled_default_feature: Vec::new(),
-        enabled_feature: Vec::new(),
+        chip,
+        split,
+        local_template: local_path.map(PathBuf::from),
+        version,
+        template: template.into(),
     };
-
-    // Download template
-    match local_path {
-        Some(p) => {
-            // Copy local template to project_info.target_dir
-            copy_dir_recursive(Path::new(&p), &project_info.target_dir)?;
-        }
-        None => {
-            // Use remote template
-            download_project_template(
-                &project_info,
-                commit_or_branch
-                    .as_ref()
-                    .expect("commit_or_branch should be resolved for remote template"),
-            )
-            .await?;
-        }
-    }
-
-    // Post-process
-    post_process(project_info)?;
-
+    let project = rmkit_core::init_project(&options, client, &mut Terminal).await?;
+    report_dry_run(&project);
     Ok(())
 }
 
-/// Download code from a GitHub repository link and extract it to the `repo` folder, using asynchronous download and a progress bar
-///
-/// # Parameters
-/// - `download_url`: GitHub repository link
-/// - `output_path`: Target extraction path
-/// - `folder`: Specific subdirectory to extract
-async fn download_with_progress<P>(
-    download_url: &str,
-    output_path: P,
-    folder: &str,
-) -> Result<(), Box<dyn Error>>
-where
-    P: AsRef<Path>,
-{
-    println!("download url: {}", download_url);
-    let output_path = output_path.as_ref();
-
-    // Ensure the output path is clean
-    if output_path.exists() {
-        fs::remove_dir_all(output_path)?;
+/// Print the files of a dry run, nothing is printed if the project was generated
+fn report_dry_run(project: &Project) {
+    if let Some(files) = &project.planned_files {
+        dry_run::report(files, &project.dir);
     }
-    fs::create_dir_all(output_path)?;
-
-    println!("⇣ Download project template for {}...", folder);
-
-    // Send request and get response
-    let client = Client::new();
-    let response = client.get(download_url).send().await?;
-    if !response.status().is_success() {
-        return Err(format!("Download failed: {}", response.status()).into());
-    }
-
-    // Temporary file to store the downloaded content
-    let temp_file_path = output_path.join("temp.zip");
-    let mut temp_file = File::create(&temp_file_path)?;
-
-    // Ensure the temporary file is cleaned up on error
-    struct TempFileCleanup<'a> {
-        path: &'a Path,
-    }
-    impl<'a> Drop for TempFileCleanup<'a> {
-        fn drop(&mut self) {
-            if self.path.exists() {
-                if let Err(e) = fs::remove_file(self.path) {
-                    eprintln!(
-                        "Failed to remove temp file '{}': {}",
-                        self.path.display(),
-                        e
-                    );
-                }
-            }
-        }
-    }
-    let _cleanup_guard = TempFileCleanup {
-        path: &temp_file_path,
-    };
-
-    // Stream response bytes and write to temp file
-    let mut stream = response.bytes_stream();
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        temp_file.write_all(&chunk)?;
-    }
-
-    // Open the downloaded ZIP file and extract
-    let zip_file = File::open(&temp_file_path)?;
-    let mut zip = ZipArchive::new(zip_file)?;
-
-    let mut folder_found = false;
-    for i in 0..zip.len() {
-        let mut file = zip.by_index(i)?;
-        let file_name = file.enclosed_name().ok_or("Invalid file path")?;
-
-        // Find the root directory from the ZIP file
-        let segments: Vec<_> = file_name.iter().collect();
-        if segments.len() > 1 && segments[1] == folder {
-            folder_found = true;
-            let relative_name = file_name.iter().skip(2).collect::<PathBuf>();
-            let out_path = output_path.join(relative_name);
-
-            if file.is_dir() {
-                fs::create_dir_all(&out_path)?;
-            } else {
-                if let Some(parent) = out_path.parent() {
-                    fs::create_dir_all(parent)?;
-                }
-                let mut outfile = File::create(&out_path)?;
-                io::copy(&mut file, &mut outfile)?;
-            }
-        }
-    }
-
-    if !folder_found {
-        // Check whether the remote_folder starts with stm32, do the second search using `stm32xx` and if there's still no matched template, use `stm32` template
-        if folder.starts_with("stm32") {
-            // Generate template for stm32
-            if folder.len() > 7 {
-                // Do the second search, use the stm32's family name
-                let stm32_series = &folder[..7];
-                for i in 0..zip.len() {
-                    let mut file = zip.by_index(i)?;
-                    let file_name = file.enclosed_name().ok_or("Invalid file path")?;
-
-                    // Find the root directory from the ZIP file
-                    let segments: Vec<_> = file_name.iter().collect();
-                    if segments.len() > 1 && segments[1] == stm32_series {
-                        folder_found = true;
-                        let relative_name = file_name.iter().skip(2).collect::<PathBuf>();
-                        let out_path = output_path.join(relative_name);
-
-                        if file.is_dir() {
-                            fs::create_dir_all(&out_path)?;
-                        } else {
-                            if let Some(parent) = out_path.parent() {
-                                fs::create_dir_all(parent)?;
-                            }
-                            let mut outfile = File::create(&out_path)?;
-                            io::copy(&mut file, &mut outfile)?;
-                        }
-                    }
-                }
-            }
-            if !folder_found {
-                println!("️️🚨 There's no template available for [{folder}], using the default stm32 template. You may need to make further edit.");
-                // Still not found, use the default stm32 template
-                for i in 0..zip.len() {
-                    let mut file = zip.by_index(i)?;
-                    let file_name = file.enclosed_name().ok_or("Invalid file path")?;
-
-                    // Find the root directory from the ZIP file
-                    let segments: Vec<_> = file_name.iter().collect();
-                    if segments.len() > 1 && segments[1] == "stm32" {
-                        folder_found = true;
-                        let relative_name = file_name.iter().skip(2).collect::<PathBuf>();
-                        let out_path = output_path.join(relative_name);
-
-                        if file.is_dir() {
-                            fs::create_dir_all(&out_path)?;
-                        } else {
-                            if let Some(parent) = out_path.parent() {
-                                fs::create_dir_all(parent)?;
-                            }
-                            let mut outfile = File::create(&out_path)?;
-                            io::copy(&mut file, &mut outfile)?;
-                        }
-                    }
-                }
-            }
-        }
-
-        // Check again
-        if !folder_found {
-            return Err(format!(
-                "The specified chip/board '{}' does not exist in the template repo",
-                folder
-            )
-            .into());
-        }
-    }
-
-    println!("✅ Project created, path: {}", output_path.display());
-    Ok(())
 }
 
 fn get_render_config() -> RenderConfig<'static> {
@@ -442,137 +460,3 @@ fn get_render_config() -> RenderConfig<'static> {
 
     render_config
 }
-
-fn copy_dir_recursive(src: &Path, dest: &Path) -> io::Result<()> {
-    if !src.is_dir() {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "Source is not a directory",
-        ));
-    }
-
-    // Create the target folder
-    fs::create_dir_all(dest)?;
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let file_type = entry.file_type()?;
-        let src_path = entry.path();
-        let dest_path = dest.join(entry.file_name());
-
-        if file_type.is_dir() {
-            // Recursively process
-            copy_dir_recursive(&src_path, &dest_path)?;
-        } else {
-            // Copy file
-            fs::copy(&src_path, &dest_path)?;
-        }
-    }
-    Ok(())
-}
-
-/// Update the rmk dependency configuration in the Cargo.toml file at the specified path
-/// Replace rmk = { version = "...", features = ["..."] } with
-/// rmk = { version = "...", default-features = false, features = ["..."] }
-///
-/// # Arguments
-/// * `target_dir` - Target directory path containing Cargo.toml
-///
-/// # Returns
-/// * `Result<(), String>` - Returns Ok on success, Err on failure
-fn disable_rmk_default_features(
-    target_dir: &PathBuf,
-    metadata: &Metadata,
-    features: Vec<String>,
-) -> Result<(), String> {
-    println!("Disabling default features: {:?}", features);
-    // Define the path to Cargo.toml
-    let cargo_toml_path = Path::new(target_dir).join("Cargo.toml");
-
-    // Parse as Manifest using cargo_toml
-    let mut manifest =
-        cargo_toml::Manifest::from_path(&cargo_toml_path).map_err(|e| e.to_string())?;
-
-    // Get dependencies and modify rmk configuration
-    if let Some(cargo_toml::Dependency::Detailed(rmk_dep)) = manifest.dependencies.get_mut("rmk") {
-        // Set default-features = false, and keep the original version and features
-        let mut default_features = get_dependency_default_features("rmk", metadata)?;
-        default_features.retain(|s| !features.contains(s));
-
-        rmk_dep.features.append(&mut default_features);
-        rmk_dep.features.sort_unstable();
-        rmk_dep.features.dedup();
-
-        rmk_dep.default_features = false;
-    } else {
-        return Err("No valid rmk dependency found".to_string());
-    }
-
-    // Convert the modified Manifest to a string
-    let updated_toml = toml::to_string(&manifest)
-        .map_err(|e| format!("Failed to serialize updated Cargo.toml: {}", e))?;
-
-    // Write the updated content back to the file
-    fs::write(&cargo_toml_path, updated_toml)
-        .map_err(|e| format!("Failed to write updated Cargo.toml: {}", e))?;
-
-    Ok(())
-}
-
-fn get_dependency_default_features(
-    dependency: &str,
-    metadata: &Metadata,
-) -> Result<Vec<String>, String> {
-    let dep = metadata
-        .packages
-        .iter()
-        .find(|p| p.name.to_string() == dependency)
-        .ok_or(format!("Failed to find {} in dependencies", dependency))?;
-    dep.features
-        .get("default")
-        .cloned()
-        .ok_or(format!("Failed to get default {} features", dependency))
-}
-
-/// Enable non-default features for rmk dependency in Cargo.toml
-///
-/// This function adds features to the rmk dependency's feature list
-///
-/// # Arguments
-/// * `target_dir` - Target directory path containing Cargo.toml
-/// * `features` - List of features to enable
-///
-/// # Returns
-/// * `Result<(), String>` - Returns Ok on success, Err on failure
-fn enable_rmk_features(target_dir: &PathBuf, features: Vec<String>) -> Result<(), String> {
-    println!("Enabling features: {:?}", features);
-    // Define the path to Cargo.toml
-    let cargo_toml_path = Path::new(target_dir).join("Cargo.toml");
-
-    // Parse as Manifest using cargo_toml
-    let mut manifest =
-        cargo_toml::Manifest::from_path(&cargo_toml_path).map_err(|e| e.to_string())?;
-
-    // Get dependencies and modify rmk configuration
-    if let Some(cargo_toml::Dependency::Detailed(rmk_dep)) = manifest.dependencies.get_mut("rmk") {
-        // Add features to the existing feature list
-        for feature in features {
-            if !rmk_dep.features.contains(&feature) {
-                rmk_dep.features.push(feature);
-            }
-        }
-        rmk_dep.features.sort_unstable();
-        rmk_dep.features.dedup();
-    } else {
-        return Err("No valid rmk dependency found".to_string());
-    }
-
-    // Convert the modified Manifest to a string
-    let updated_toml = toml::to_string(&manifest)
-        .map_err(|e| format!("Failed to serialize updated Cargo.toml: {}", e))?;
-
-    // Write the updated content back to the file
-    fs::write(&cargo_toml_path, updated_toml)
-        .map_err(|e| format!("Failed to write updated Cargo.toml: {}", e))?;
-
-    Ok(())
-}
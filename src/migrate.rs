@@ -0,0 +1,382 @@
+use rmkit_core::keyboard_toml::ConfigFormat;
+use std::error::Error;
+use std::fs;
+use toml_edit::{DocumentMut, Item, Key, Table};
+
+/// Keys of split boards which moved to their `matrix` table
+const SPLIT_MATRIX_KEYS: [&str; 6] = [
+    "matrix_type",
+    "input_pins",
+    "output_pins",
+    "direct_pins",
+    "direct_pin_low_active",
+    "row2col",
+];
+
+/// Rewrite deprecated keys and sections of keyboard.toml into the current format
+///
+/// The config is edited in place to keep comments and formatting, or written to `output` if given.
+pub(crate) fn migrate_keyboard_toml(
+    path: &str,
+    output: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    if ConfigFormat::from_path(path) != ConfigFormat::Toml {
+        return Err(format!(
+            "Migration only supports keyboard.toml, convert {} with `rmkit convert-config` first",
+            path
+        )
+        .into());
+    }
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let mut doc: DocumentMut = content.parse()?;
+
+    let mut changes = Vec::new();
+    migrate_split_matrices(&mut doc, &mut changes);
+    migrate_row2col(&mut doc, &mut changes);
+    migrate_matrix_pins(&mut doc, &mut changes);
+
+    for change in &changes {
+        println!("🔧 {}", change);
+    }
+    let output = output.unwrap_or(path);
+    if changes.is_empty() {
+        println!("✅ {} is up to date", path);
+        if output == path {
+            return Ok(());
+        }
+    }
+    fs::write(output, doc.to_string())?;
+    if !changes.is_empty() {
+        println!("✅ Migrated {} with {} change(s)", output, changes.len());
+    }
+    Ok(())
+}
+
+/// Tables of the split boards, with their display path
+fn split_boards(doc: &mut DocumentMut) -> Vec<(String, &mut Table)> {
+    let Some(split) = doc.get_mut("split").and_then(|s| s.as_table_mut()) else {
+        return Vec::new();
+    };
+    let mut boards = Vec::new();
+    for (key, item) in split.iter_mut() {
+        match (key.get(), item) {
+            ("central", Item::Table(central)) => {
+                boards.push(("split.central".to_string(), central))
+            }
+            ("peripheral", Item::ArrayOfTables(peripherals)) => boards.extend(
+                peripherals
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(i, p)| (format!("split.peripheral[{}]", i), p)),
+            ),
+            _ => {}
+        }
+    }
+    boards
+}
+
+/// Pins of split boards were in the board table, they're in its `matrix` table now
+fn migrate_split_matrices(doc: &mut DocumentMut, changes: &mut Vec<String>) {
+    for (name, board) in split_boards(doc) {
+        let moved: Vec<(&str, (Key, Item))> = SPLIT_MATRIX_KEYS
+            .iter()
+            .filter_map(|key| board.remove_entry(key).map(|entry| (*key, entry)))
+            .collect();
+        if moved.is_empty() {
+            continue;
+        }
+        let Some(matrix) = board
+            .entry("matrix")
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+        else {
+            continue;
+        };
+        for (key, (old_key, value)) in moved {
+            if matrix.contains_key(key) {
+                changes.push(format!(
+                    "Removed {}.{}, {}.matrix.{} is used instead",
+                    name, key, name, key
+                ));
+            } else {
+                insert_renamed(matrix, &old_key, key, value);
+                changes.push(format!("Moved {}.{} to {}.matrix.{}", name, key, name, key));
+            }
+        }
+    }
+}
+
+/// Matrix tables of the keyboard, with their display path
+fn matrices(doc: &mut DocumentMut) -> Vec<(String, &mut Table)> {
+    if doc.contains_key("split") {
+        return split_boards(doc)
+            .into_iter()
+            .filter_map(|(name, board)| {
+                let matrix = board.get_mut("matrix")?.as_table_mut()?;
+                Some((format!("{}.matrix", name), matrix))
+            })
+            .collect();
+    }
+    doc.get_mut("matrix")
+        .and_then(|m| m.as_table_mut())
+        .map(|m| ("matrix".to_string(), m))
+        .into_iter()
+        .collect()
+}
+
+/// `row2col` was in `[layout]` before `[matrix]` existed, and rmk reads `row2col` instead of `diode_direction`
+fn migrate_row2col(doc: &mut DocumentMut, changes: &mut Vec<String>) {
+    let layout_row2col = doc
+        .get_mut("layout")
+        .and_then(|l| l.as_table_mut())
+        .and_then(|l| l.remove("row2col"))
+        .and_then(|v| v.as_bool());
+    if layout_row2col.is_some() && !doc.contains_key("split") && !doc.contains_key("matrix") {
+        doc["matrix"] = toml_edit::table();
+    }
+
+    let matrices = matrices(doc);
+    if layout_row2col.is_some() && matrices.is_empty() {
+        changes.push("Removed layout.row2col, there's no matrix to set it on".to_string());
+    }
+    for (name, matrix) in matrices {
+        let direction = matrix
+            .get("diode_direction")
+            .and_then(|d| d.as_str())
+            .and_then(|d| match d.to_uppercase().as_str() {
+                "COL2ROW" => Some(false),
+                "ROW2COL" => Some(true),
+                _ => None,
+            });
+        let replaced = direction.and_then(|direction| {
+            let (old_key, _) = matrix.remove_entry("diode_direction")?;
+            Some((old_key, direction))
+        });
+        if let Some((old_key, direction)) = replaced {
+            if matrix.contains_key("row2col") {
+                changes.push(format!(
+                    "Removed {}.diode_direction, {}.row2col is used instead",
+                    name, name
+                ));
+            } else {
+                insert_renamed(matrix, &old_key, "row2col", toml_edit::value(direction));
+                changes.push(format!("Replaced {}.diode_direction with row2col", name));
+            }
+        }
+        if let Some(row2col) = layout_row2col.filter(|_| !matrix.contains_key("row2col")) {
+            matrix.insert("row2col", toml_edit::value(row2col));
+            changes.push(format!("Moved layout.row2col to {}.row2col", name));
+        }
+    }
+}
+
+/// `input_pins` and `output_pins` depended on the diode direction, they're `row_pins` and `col_pins` now
+fn migrate_matrix_pins(doc: &mut DocumentMut, changes: &mut Vec<String>) {
+    for (name, matrix) in matrices(doc) {
+        let row2col = matrix
+            .get("row2col")
+            .and_then(|r| r.as_bool())
+            .unwrap_or(false);
+        let (row_key, col_key) = if row2col {
+            ("output_pins", "input_pins")
+        } else {
+            ("input_pins", "output_pins")
+        };
+        for (old, new) in [(row_key, "row_pins"), (col_key, "col_pins")] {
+            let Some((old_key, pins)) = matrix.remove_entry(old) else {
+                continue;
+            };
+            if matrix.contains_key(new) {
+                changes.push(format!(
+                    "Removed {}.{}, {}.{} is used instead",
+                    name, old, name, new
+                ));
+            } else {
+                insert_renamed(matrix, &old_key, new, pins);
+                changes.push(format!("Replaced {}.{} with {}", name, old, new));
+            }
+        }
+    }
+}
+
+/// Insert `item` as `key`, keeping the comments and whitespace of the key it replaces
+fn insert_renamed(table: &mut Table, old_key: &Key, key: &str, item: Item) {
+    let key = Key::new(key).with_leaf_decor(old_key.leaf_decor().clone());
+    table.insert_formatted(&key, item);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    /// Run all migrations on `content`, returning the migrated config and the changes
+    fn migrate(content: &str) -> (String, Vec<String>) {
+        let mut doc: DocumentMut = content.parse().unwrap();
+        let mut changes = Vec::new();
+        migrate_split_matrices(&mut doc, &mut changes);
+        migrate_row2col(&mut doc, &mut changes);
+        migrate_matrix_pins(&mut doc, &mut changes);
+        (doc.to_string(), changes)
+    }
+
+    const COL2ROW: &str = r#"# A col2row keyboard
+[keyboard]
+name = "kb" # the name
+
+[matrix]
+# Inputs are the rows
+input_pins = ["P0_01", "P0_02"] # two rows
+output_pins = ["P0_03", "P0_04", "P0_05"]
+diode_direction = "COL2ROW"
+"#;
+
+    const ROW2COL: &str = r#"[keyboard]
+name = "kb"
+
+[layout]
+rows = 2
+row2col = true
+
+[matrix]
+input_pins = ["P0_01"]
+output_pins = ["P0_02", "P0_03"]
+"#;
+
+    const SPLIT: &str = r#"[split]
+connection = "ble"
+
+[split.central]
+rows = 2
+input_pins = ["P0_01", "P0_02"]
+output_pins = ["P0_03"]
+row2col = false
+
+[[split.peripheral]]
+rows = 2
+[split.peripheral.matrix]
+input_pins = ["P1_01"]
+output_pins = ["P1_02", "P1_03"]
+diode_direction = "row2col"
+"#;
+
+    #[test]
+    fn col2row_matrix_is_migrated() {
+        let (migrated, changes) = migrate(COL2ROW);
+        // Comments of the renamed keys and the rest of the file are kept
+        let expected = r#"# A col2row keyboard
+[keyboard]
+name = "kb" # the name
+
+[matrix]
+row2col = false
+# Inputs are the rows
+row_pins = ["P0_01", "P0_02"] # two rows
+col_pins = ["P0_03", "P0_04", "P0_05"]
+"#;
+        assert_eq!(migrated, expected);
+        assert_eq!(
+            changes,
+            [
+                "Replaced matrix.diode_direction with row2col",
+                "Replaced matrix.input_pins with row_pins",
+                "Replaced matrix.output_pins with col_pins"
+            ]
+        );
+    }
+
+    #[test]
+    fn row2col_matrix_is_migrated() {
+        let (migrated, changes) = migrate(ROW2COL);
+        // Outputs are the rows of row2col matrices
+        let expected = r#"[keyboard]
+name = "kb"
+
+[layout]
+rows = 2
+
+[matrix]
+row2col = true
+row_pins = ["P0_02", "P0_03"]
+col_pins = ["P0_01"]
+"#;
+        assert_eq!(migrated, expected);
+        assert_eq!(
+            changes,
+            [
+                "Moved layout.row2col to matrix.row2col",
+                "Replaced matrix.output_pins with row_pins",
+                "Replaced matrix.input_pins with col_pins"
+            ]
+        );
+    }
+
+    #[test]
+    fn split_matrices_are_migrated() {
+        let (migrated, changes) = migrate(SPLIT);
+        let expected = r#"[split]
+connection = "ble"
+
+[split.central]
+rows = 2
+
+[split.central.matrix]
+row2col = false
+row_pins = ["P0_01", "P0_02"]
+col_pins = ["P0_03"]
+
+[[split.peripheral]]
+rows = 2
+[split.peripheral.matrix]
+row2col = true
+row_pins = ["P1_02", "P1_03"]
+col_pins = ["P1_01"]
+"#;
+        assert_eq!(migrated, expected);
+        assert_eq!(changes.len(), 8);
+        assert_eq!(
+            changes[0],
+            "Moved split.central.input_pins to split.central.matrix.input_pins"
+        );
+    }
+
+    #[test]
+    fn current_keys_win_over_deprecated_ones() {
+        let (migrated, changes) = migrate(
+            "[matrix]\nrow2col = true\ndiode_direction = \"COL2ROW\"\nrow_pins = [\"A\"]\noutput_pins = [\"B\"]\n",
+        );
+        assert_eq!(migrated, "[matrix]\nrow2col = true\nrow_pins = [\"A\"]\n");
+        assert_eq!(
+            changes,
+            [
+                "Removed matrix.diode_direction, matrix.row2col is used instead",
+                "Removed matrix.output_pins, matrix.row_pins is used instead"
+            ]
+        );
+        // Migrated configs are left as they are
+        let (migrated, changes) = migrate(&migrated);
+        assert_eq!(migrated, "[matrix]\nrow2col = true\nrow_pins = [\"A\"]\n");
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn keyboard_toml_is_migrated_to_the_output() {
+        let dir = env::temp_dir().join(format!("rmkit-migrate-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = |file: &str| dir.join(file).display().to_string();
+        fs::write(path("keyboard.toml"), COL2ROW).unwrap();
+        migrate_keyboard_toml(&path("keyboard.toml"), Some(&path("migrated.toml"))).unwrap();
+        let migrated = fs::read_to_string(path("migrated.toml")).unwrap();
+        assert_eq!(migrated, migrate(COL2ROW).0);
+        assert_eq!(fs::read_to_string(path("keyboard.toml")).unwrap(), COL2ROW);
+        migrate_keyboard_toml(&path("keyboard.toml"), None).unwrap();
+        assert_eq!(fs::read_to_string(path("keyboard.toml")).unwrap(), migrated);
+
+        let error = migrate_keyboard_toml(&path("keyboard.json"), None).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Migration only supports keyboard.toml"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
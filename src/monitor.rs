@@ -0,0 +1,151 @@
+use crate::prompt;
+use crate::run::release_elf;
+use inquire::Select;
+use rmkit_core::firmware::probe_rs_chip;
+use rmkit_core::interrupt;
+use rmkit_core::keyboard_toml::read_keyboard_config;
+use rmkit_core::{Classify, FailureKind};
+use serialport::SerialPortType;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// USB vendor id of RMK keyboards which don't set `vendor_id` in keyboard.toml
+const DEFAULT_VENDOR_ID: u16 = 0x4c4b;
+/// Baud rate of the serial log, USB CDC ignores it
+const BAUD_RATE: u32 = 115_200;
+
+/// Prints log lines which match the filter, with the time since the monitor started
+struct LogPrinter {
+    start: Instant,
+    filter: Option<String>,
+    timestamps: bool,
+}
+
+impl LogPrinter {
+    fn print(&self, line: &str) {
+        if self
+            .filter
+            .as_ref()
+            .is_some_and(|f| !line.to_lowercase().contains(&f.to_lowercase()))
+        {
+            return;
+        }
+        if self.timestamps {
+            println!("[{:>10.3}] {}", self.start.elapsed().as_secs_f64(), line);
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Show the logs of a flashed keyboard
+///
+/// By default the defmt logs are read over RTT by probe-rs, which decodes them with the ELF of `bin` from the
+/// release build. `serial` reads the USB serial port instead, which is detected by the vendor id of keyboard.toml
+/// if it's not given. Only lines containing `filter`, ignoring case, are shown.
+pub(crate) fn monitor(
+    project_dir: Option<String>,
+    bin: Option<String>,
+    serial: Option<Option<String>>,
+    filter: Option<String>,
+    timestamps: bool,
+) -> Result<(), Box<dyn Error>> {
+    let project_dir = PathBuf::from(project_dir.unwrap_or_else(|| ".".to_string()));
+    let printer = LogPrinter {
+        start: Instant::now(),
+        filter,
+        timestamps,
+    };
+    match serial {
+        Some(port) => monitor_serial(&project_dir, port, &printer),
+        None => monitor_rtt(&project_dir, bin, &printer),
+    }
+}
+
+fn monitor_rtt(
+    project_dir: &Path,
+    bin: Option<String>,
+    printer: &LogPrinter,
+) -> Result<(), Box<dyn Error>> {
+    let (bin, elf) = release_elf(project_dir, bin, "Which binary to monitor?")?;
+    let chip = probe_rs_chip(project_dir)?;
+
+    println!("📟 Attaching to {} on {}, press Ctrl-C to stop", bin, chip);
+    let status = interrupt::lines(
+        Command::new("probe-rs")
+            .args(["attach", "--chip", &chip])
+            .arg(&elf),
+        |line| printer.print(line),
+    )
+    .map_err(|e| format!("Failed to run probe-rs, is it installed? {}", e))
+    .or_fail(FailureKind::Toolchain)?;
+    if !status.success() {
+        return Err(format!("probe-rs failed to attach to {}", chip).into());
+    }
+    Ok(())
+}
+
+fn monitor_serial(
+    project_dir: &Path,
+    port: Option<String>,
+    printer: &LogPrinter,
+) -> Result<(), Box<dyn Error>> {
+    let port_name = match port {
+        Some(port) => port,
+        None => detect_serial_port(project_dir)?,
+    };
+    let port = serialport::new(&port_name, BAUD_RATE)
+        .timeout(Duration::from_secs(1))
+        .open()
+        .map_err(|e| format!("Failed to open {}: {}", port_name, e))?;
+
+    println!("📟 Monitoring {}, press Ctrl-C to stop", port_name);
+    let mut reader = BufReader::new(port);
+    let mut line = String::new();
+    loop {
+        match reader.read_line(&mut line) {
+            Ok(0) => return Err(format!("{} was disconnected", port_name).into()),
+            Ok(_) => {
+                printer.print(line.trim_end());
+                line.clear();
+            }
+            // A partial line is kept in `line` until the rest arrives
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+            Err(e) => return Err(format!("Lost connection to {}: {}", port_name, e).into()),
+        }
+    }
+}
+
+/// Find the USB serial port of the keyboard by the vendor id in keyboard.toml
+fn detect_serial_port(project_dir: &Path) -> Result<String, Box<dyn Error>> {
+    let vendor_id = read_keyboard_config(project_dir.join("keyboard.toml"))
+        .ok()
+        .and_then(|config| {
+            config
+                .get("keyboard")
+                .and_then(|k| k.get("vendor_id"))
+                .and_then(|v| v.as_integer())
+        })
+        .and_then(|v| u16::try_from(v).ok())
+        .unwrap_or(DEFAULT_VENDOR_ID);
+    let ports: Vec<String> = serialport::available_ports()?
+        .into_iter()
+        .filter(|p| matches!(&p.port_type, SerialPortType::UsbPort(usb) if usb.vid == vendor_id))
+        .map(|p| p.port_name)
+        .collect();
+    match ports.len() {
+        0 => Err(format!(
+            "No USB serial port with vendor id {:#06x} found, connect the keyboard or give the port with --serial <PORT>",
+            vendor_id
+        )
+        .into()),
+        1 => Ok(ports[0].clone()),
+        _ => {
+            prompt::ensure_interactive("--serial <PORT>")?;
+            Ok(Select::new("Which serial port to monitor?", ports).prompt()?)
+        }
+    }
+}
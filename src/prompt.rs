@@ -0,0 +1,34 @@
+use inquire::Text;
+use std::error::Error;
+use std::io::{stdin, stdout, IsTerminal};
+
+/// Whether prompts can be answered, i.e. rmkit isn't running in a pipe or CI
+pub(crate) fn is_interactive() -> bool {
+    stdin().is_terminal() && stdout().is_terminal()
+}
+
+/// Fail with a message naming the missing `flag` instead of prompting in a non-interactive environment
+pub(crate) fn ensure_interactive(flag: &str) -> Result<(), Box<dyn Error>> {
+    if is_interactive() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Not running in an interactive terminal, please specify {}",
+            flag
+        )
+        .into())
+    }
+}
+
+/// Ask for a text, the default is taken without asking in a non-interactive environment
+pub(crate) fn text_or_default(message: &str, default: &str) -> Result<String, Box<dyn Error>> {
+    if is_interactive() {
+        Ok(Text::new(message).with_default(default).prompt()?)
+    } else {
+        eprintln!(
+            "ℹ️  Not running in an interactive terminal, using {}",
+            default
+        );
+        Ok(default.to_string())
+    }
+}
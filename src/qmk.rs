@@ -0,0 +1,981 @@
+use crate::keymap::{DEFAULT_LAYERS, NO_KEY};
+use crate::kle::place_keys;
+use rmkit_core::chip::{get_board_chip_map, get_chip_options};
+use rmkit_core::keyboard_toml::{
+    config_feature_switches, derive_rmk_features, diode_direction_row2col, read_keyboard_config,
+    write_keyboard_config,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Key of matrix positions which are used by the layout, left for the user to fill in
+const LAYOUT_KEY: &str = "_";
+/// Sections of info.json for hardware which RMK doesn't drive
+const UNSUPPORTED_SECTIONS: &[(&str, &str)] = &[
+    ("rgblight", "RGB underglow"),
+    ("rgb_matrix", "RGB matrix"),
+    ("led_matrix", "LED matrix"),
+    ("backlight", "backlight"),
+    ("audio", "audio"),
+    ("haptic", "haptic feedback"),
+    ("oled", "OLED display"),
+    ("pointing_device", "pointing device"),
+];
+
+/// Convert a QMK `info.json` or `keyboard.json` into a keyboard config at `output`
+///
+/// The keyboard info, chip, matrix, split halves, encoders and indicator LEDs are converted, everything
+/// else is reported as a warning. The keymap is filled with `_` for keys of the layout, since QMK keeps
+/// keymaps separately. The features of `rules.mk`, next to info.json if `rules_path` isn't given, enable
+/// the sections of the config which decide the rmk features of the project.
+pub(crate) fn import_qmk(
+    info_path: &str,
+    rules_path: Option<&str>,
+    output: &str,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    if Path::new(output).exists() && !force {
+        return Err(format!("{} already exists, use --force to overwrite it", output).into());
+    }
+    let content = fs::read_to_string(info_path)
+        .map_err(|e| format!("Failed to read {}: {}", info_path, e))?;
+    let info: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid QMK info.json {}: {}", info_path, e))?;
+
+    let rules_path = rules_path.map(PathBuf::from).or_else(|| {
+        let rules = Path::new(info_path).with_file_name("rules.mk");
+        rules.exists().then_some(rules)
+    });
+    let rules = match &rules_path {
+        Some(path) => {
+            println!("ℹ️  Reading the features of {}", path.display());
+            read_rules(path)?
+        }
+        None => HashMap::new(),
+    };
+    let features = qmk_features(&info, &rules);
+
+    let mut warnings = Vec::new();
+    let config = keyboard_config(&info, &features, &mut warnings)?;
+    write_keyboard_config(output, &config)?;
+
+    for warning in &warnings {
+        println!("⚠️  {}", warning);
+    }
+    let (disabled, enabled) = derive_rmk_features(config_feature_switches(&config)?);
+    if !disabled.is_empty() {
+        println!("ℹ️  Disabled default rmk features: {}", disabled.join(", "));
+    }
+    if !enabled.is_empty() {
+        println!("ℹ️  Enabled rmk features: {}", enabled.join(", "));
+    }
+    println!("✅ Imported {} to {}", info_path, output);
+    println!(
+        "ℹ️  Fill in the keymap with `rmkit keymap set`, then check the config with `rmkit check`"
+    );
+    Ok(())
+}
+
+fn keyboard_config(
+    info: &Value,
+    features: &HashMap<String, bool>,
+    warnings: &mut Vec<String>,
+) -> Result<toml::Table, Box<dyn Error>> {
+    let feature = |name: &str| features.get(name).copied();
+    let mut config = toml::Table::new();
+
+    let (chip, board) = chip_or_board(info, warnings);
+    let mut keyboard = toml::Table::new();
+    if let Some(name) = info["keyboard_name"].as_str() {
+        keyboard.insert("name".to_string(), name.into());
+        keyboard.insert("product_name".to_string(), name.into());
+    }
+    for (key, qmk_key) in [("vendor_id", "vid"), ("product_id", "pid")] {
+        if let Some(id) = info["usb"][qmk_key].as_str().and_then(parse_hex) {
+            keyboard.insert(key.to_string(), id.into());
+        }
+    }
+    if let Some(manufacturer) = info["manufacturer"].as_str() {
+        keyboard.insert("manufacturer".to_string(), manufacturer.into());
+    }
+    if let Some(board) = &board {
+        keyboard.insert("board".to_string(), board.clone().into());
+    } else if let Some(chip) = &chip {
+        keyboard.insert("chip".to_string(), chip.clone().into());
+    }
+    config.insert("keyboard".to_string(), keyboard.into());
+
+    let chip = chip.as_deref();
+    let mut pins = PinConverter {
+        chip,
+        unconverted: Vec::new(),
+    };
+    let matrix_pins = &info["matrix_pins"];
+    if matrix_pins.is_null() {
+        return Err("No matrix_pins in info.json, the matrix wiring can't be imported".into());
+    }
+    let diode_direction = info["diode_direction"]
+        .as_str()
+        .unwrap_or("COL2ROW")
+        .to_lowercase();
+    let (matrix, rows, cols) = convert_matrix(matrix_pins, &diode_direction, &mut pins)?;
+
+    // Halves of a QMK split keyboard have the same matrix size, the rows of the right half follow the left
+    let split = info["split"]["enabled"].as_bool() == Some(true) || feature("split") == Some(true);
+    let total_rows = if split { rows * 2 } else { rows };
+    if split {
+        let right_pins = match &info["split"]["matrix_pins"]["right"] {
+            Value::Null => matrix_pins,
+            right => right,
+        };
+        let (right_matrix, _, _) = convert_matrix(right_pins, &diode_direction, &mut pins)?;
+        let half = |matrix: toml::Table, row_offset: usize| {
+            let mut half = toml::Table::new();
+            half.insert("rows".to_string(), (rows as i64).into());
+            half.insert("cols".to_string(), (cols as i64).into());
+            half.insert("row_offset".to_string(), (row_offset as i64).into());
+            half.insert("col_offset".to_string(), 0.into());
+            half.insert("matrix".to_string(), matrix.into());
+            toml::Value::from(half)
+        };
+        let mut split_config = toml::Table::new();
+        split_config.insert("connection".to_string(), "serial".into());
+        split_config.insert("central".to_string(), half(matrix, 0));
+        split_config.insert(
+            "peripheral".to_string(),
+            toml::Value::Array(vec![half(right_matrix, rows)]),
+        );
+        config.insert("split".to_string(), split_config.into());
+        warnings.push(
+            "QMK's split serial is a single half-duplex pin, add the UART `serial` pins of both halves to [split]"
+                .to_string(),
+        );
+    } else {
+        config.insert("matrix".to_string(), matrix.into());
+    }
+
+    let layers = info["dynamic_keymap"]["layer_count"]
+        .as_i64()
+        .unwrap_or(DEFAULT_LAYERS as i64);
+    let mut layout = toml::Table::new();
+    layout.insert("rows".to_string(), (total_rows as i64).into());
+    layout.insert("cols".to_string(), (cols as i64).into());
+    layout.insert("layers".to_string(), layers.into());
+    layout.insert(
+        "keymap".to_string(),
+        keymap(info, total_rows, cols, layers as usize, warnings).into(),
+    );
+    config.insert("layout".to_string(), layout.into());
+
+    if let Some(light) = indicators(&info["indicators"], &mut pins) {
+        config.insert("light".to_string(), light.into());
+    }
+    if let Some(encoders) = encoders(&info["encoder"], &mut pins) {
+        let mut input_device = toml::Table::new();
+        input_device.insert("encoder".to_string(), encoders.into());
+        config.insert("input_device".to_string(), input_device.into());
+    } else if feature("encoder") == Some(true) {
+        warnings.push(
+            "Encoders are enabled, but info.json has no encoder pins, add them to [[input_device.encoder]]"
+                .to_string(),
+        );
+    }
+    if !info["split"]["encoder"]["right"].is_null() {
+        warnings.push(
+            "Encoders of the right half aren't imported, add them to the peripheral".to_string(),
+        );
+    }
+
+    // VIA and Vial keep the keymap in storage, Vial is enabled if the keyboard uses either
+    let (via, vial) = (feature("via"), feature("vial"));
+    if via.is_some() || vial.is_some() {
+        let enabled = via == Some(true) || vial == Some(true);
+        let mut host = toml::Table::new();
+        host.insert("vial_enabled".to_string(), enabled.into());
+        config.insert("host".to_string(), host.into());
+    }
+    if let Some(console) = feature("console") {
+        let mut dependency = toml::Table::new();
+        dependency.insert("defmt_log".to_string(), console.into());
+        config.insert("dependency".to_string(), dependency.into());
+    }
+    if feature("bluetooth") == Some(true) {
+        let mut ble = toml::Table::new();
+        ble.insert("enabled".to_string(), true.into());
+        config.insert("ble".to_string(), ble.into());
+    }
+    if feature("nkro") == Some(true) {
+        warnings.push("NKRO is not supported by RMK, keys are reported as 6KRO".to_string());
+    }
+
+    for (section, name) in UNSUPPORTED_SECTIONS {
+        let enabled = feature(section).unwrap_or(!info[section].is_null());
+        if !enabled {
+            continue;
+        }
+        let details = match (
+            info[section]["driver"].as_str(),
+            info[section]["led_count"].as_u64(),
+        ) {
+            (Some(driver), Some(count)) => format!(" ({}, {} LEDs)", driver, count),
+            (Some(driver), None) => format!(" ({})", driver),
+            _ => String::new(),
+        };
+        warnings.push(format!(
+            "{}{} is not supported by RMK, skipped",
+            name, details
+        ));
+    }
+    if !pins.unconverted.is_empty() {
+        warnings.push(format!(
+            "Pins {} are kept in QMK format, rename them to the pins of {}",
+            pins.unconverted.join(", "),
+            chip.unwrap_or("the chip")
+        ));
+    }
+    Ok(config)
+}
+
+/// Read the variables of a QMK `rules.mk`, e.g. `ENCODER_ENABLE = yes`
+fn read_rules(path: &Path) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .filter_map(|line| {
+            let (name, value) = line.split_once('=')?;
+            let name = name.trim().trim_end_matches(['?', ':', '+']).trim();
+            Some((name.to_string(), value.trim().to_string()))
+        })
+        .collect())
+}
+
+/// Get the enabled and disabled features from `features` of info.json and the flags of rules.mk
+///
+/// Features are named like in info.json, `RGB_MATRIX_ENABLE` of rules.mk is `rgb_matrix`, and
+/// `SPLIT_KEYBOARD` is `split`. rules.mk overrides info.json.
+fn qmk_features(info: &Value, rules: &HashMap<String, String>) -> HashMap<String, bool> {
+    let mut features: HashMap<String, bool> = info["features"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, enabled)| Some((name.clone(), enabled.as_bool()?)))
+        .collect();
+    for (name, value) in rules {
+        let feature = match (name.strip_suffix("_ENABLE"), name.as_str()) {
+            (Some(feature), _) => feature.to_lowercase(),
+            (None, "SPLIT_KEYBOARD") => "split".to_string(),
+            _ => continue,
+        };
+        let enabled = matches!(value.to_lowercase().as_str(), "yes" | "true" | "1");
+        features.insert(feature, enabled);
+    }
+    features
+}
+
+/// Get the chip or board of the keyboard from `development_board` or `processor`
+fn chip_or_board(info: &Value, warnings: &mut Vec<String>) -> (Option<String>, Option<String>) {
+    let boards = get_board_chip_map();
+    if let Some(board) = info["development_board"].as_str() {
+        if let Some(chip) = boards.get(board) {
+            return (Some(chip.to_string()), Some(board.to_string()));
+        }
+    }
+    let Some(processor) = info["processor"].as_str() else {
+        warnings.push("No processor in info.json, set `chip` in [keyboard]".to_string());
+        return (None, None);
+    };
+    let processor = processor.to_lowercase();
+    let chips = get_chip_options(false);
+    if chips.contains(&processor.as_str()) {
+        return (Some(processor), None);
+    }
+    // QMK names STM32 chips without the package, e.g. `STM32F411`
+    match chips.iter().find(|c| c.starts_with(&processor)) {
+        Some(chip) => {
+            warnings.push(format!(
+                "Using {} for {}, check the package of the chip on the PCB",
+                chip, processor
+            ));
+            (Some(chip.to_string()), None)
+        }
+        None => {
+            warnings.push(format!(
+                "{} is not supported by RMK, set `chip` in [keyboard] to the MCU of a replacement controller",
+                processor
+            ));
+            (None, None)
+        }
+    }
+}
+
+/// Convert QMK pin names to RMK, `GP2` is `PIN_2` on RP2040 and `A15` is `PA15` on STM32
+struct PinConverter<'a> {
+    chip: Option<&'a str>,
+    unconverted: Vec<String>,
+}
+
+impl PinConverter<'_> {
+    fn convert(&mut self, pin: &Value) -> String {
+        let pin = pin.as_str().unwrap_or_default();
+        if pin == "NO_PIN" {
+            return "_".to_string();
+        }
+        let chip = self.chip.unwrap_or_default();
+        let converted = if chip.starts_with("rp") {
+            pin.strip_prefix("GP").map(|n| format!("PIN_{}", n))
+        } else if chip.starts_with("stm32") {
+            let mut chars = pin.chars();
+            chars
+                .next()
+                .filter(|port| port.is_ascii_uppercase() && chars.as_str().parse::<u32>().is_ok())
+                .map(|_| format!("P{}", pin))
+        } else {
+            None
+        };
+        converted.unwrap_or_else(|| {
+            self.unconverted.push(pin.to_string());
+            pin.to_string()
+        })
+    }
+
+    /// Convert an RMK pin back to QMK, `_` is `NO_PIN`
+    fn convert_back(&mut self, pin: Option<&str>) -> String {
+        let pin = pin.unwrap_or_default();
+        if pin == "_" {
+            return "NO_PIN".to_string();
+        }
+        let chip = self.chip.unwrap_or_default();
+        let converted = if chip.starts_with("rp") {
+            pin.strip_prefix("PIN_").map(|n| format!("GP{}", n))
+        } else if chip.starts_with("stm32") {
+            pin.strip_prefix('P')
+                .filter(|p| {
+                    let mut chars = p.chars();
+                    chars.next().is_some_and(|port| port.is_ascii_uppercase())
+                        && chars.as_str().parse::<u32>().is_ok()
+                })
+                .map(|p| p.to_string())
+        } else {
+            None
+        };
+        converted.unwrap_or_else(|| {
+            self.unconverted.push(pin.to_string());
+            pin.to_string()
+        })
+    }
+
+    fn convert_all(&mut self, pins: &Value) -> Vec<String> {
+        pins.as_array()
+            .into_iter()
+            .flatten()
+            .map(|p| self.convert(p))
+            .collect()
+    }
+}
+
+/// Convert `matrix_pins` to an RMK matrix, returning it with the number of rows and cols
+fn convert_matrix(
+    matrix_pins: &Value,
+    diode_direction: &str,
+    pins: &mut PinConverter,
+) -> Result<(toml::Table, usize, usize), Box<dyn Error>> {
+    let mut matrix = toml::Table::new();
+    if let Some(direct) = matrix_pins["direct"].as_array() {
+        let direct_pins: Vec<Vec<String>> =
+            direct.iter().map(|row| pins.convert_all(row)).collect();
+        let cols = direct_pins.iter().map(|row| row.len()).max().unwrap_or(0);
+        let rows = direct_pins.len();
+        matrix.insert("matrix_type".to_string(), "direct_pin".into());
+        matrix.insert("direct_pins".to_string(), direct_pins.into());
+        return Ok((matrix, rows, cols));
+    }
+
+    let rows = pins.convert_all(&matrix_pins["rows"]);
+    let cols = pins.convert_all(&matrix_pins["cols"]);
+    if rows.is_empty() || cols.is_empty() {
+        return Err("matrix_pins in info.json has no rows or cols".into());
+    }
+    let (row_count, col_count) = (rows.len(), cols.len());
+    matrix.insert("row_pins".to_string(), rows.into());
+    matrix.insert("col_pins".to_string(), cols.into());
+    matrix.insert("row2col".to_string(), (diode_direction == "row2col").into());
+    Ok((matrix, row_count, col_count))
+}
+
+/// Create an empty keymap, with `_` for the keys of the first layout and `No` for the other positions
+fn keymap(
+    info: &Value,
+    rows: usize,
+    cols: usize,
+    layers: usize,
+    warnings: &mut Vec<String>,
+) -> Vec<Vec<Vec<String>>> {
+    let mut layer = vec![vec![NO_KEY.to_string(); cols]; rows];
+    let layout = info["layouts"].as_object().and_then(|layouts| {
+        layouts
+            .get("LAYOUT")
+            .or_else(|| layouts.values().next())
+            .and_then(|l| l["layout"].as_array())
+    });
+    match layout {
+        Some(keys) => {
+            for key in keys {
+                let position = (key["matrix"][0].as_u64(), key["matrix"][1].as_u64());
+                match position {
+                    (Some(r), Some(c)) if (r as usize) < rows && (c as usize) < cols => {
+                        layer[r as usize][c as usize] = LAYOUT_KEY.to_string();
+                    }
+                    _ => warnings.push(format!(
+                        "Key {} of the layout is outside of the {}x{} matrix",
+                        key["matrix"], rows, cols
+                    )),
+                }
+            }
+        }
+        None => {
+            warnings.push("No layouts in info.json, all matrix positions are used".to_string());
+            layer = vec![vec![LAYOUT_KEY.to_string(); cols]; rows];
+        }
+    }
+    vec![layer; layers]
+}
+
+/// Convert `indicators` to the indicator LEDs of [light]
+fn indicators(indicators: &Value, pins: &mut PinConverter) -> Option<toml::Table> {
+    let low_active = indicators["on_state"].as_u64() == Some(0);
+    let mut light = toml::Table::new();
+    for (key, qmk_key) in [
+        ("capslock", "caps_lock"),
+        ("numslock", "num_lock"),
+        ("scrolllock", "scroll_lock"),
+    ] {
+        if indicators[qmk_key].is_string() {
+            let mut led = toml::Table::new();
+            led.insert("pin".to_string(), pins.convert(&indicators[qmk_key]).into());
+            led.insert("low_active".to_string(), low_active.into());
+            light.insert(key.to_string(), led.into());
+        }
+    }
+    (!light.is_empty()).then_some(light)
+}
+
+/// Convert the rotary encoders of `encoder` to [[input_device.encoder]]
+fn encoders(encoder: &Value, pins: &mut PinConverter) -> Option<Vec<toml::Value>> {
+    let encoders: Vec<toml::Value> = encoder["rotary"]
+        .as_array()?
+        .iter()
+        .map(|rotary| {
+            let mut encoder = toml::Table::new();
+            encoder.insert("pin_a".to_string(), pins.convert(&rotary["pin_a"]).into());
+            encoder.insert("pin_b".to_string(), pins.convert(&rotary["pin_b"]).into());
+            encoder.insert(
+                "resolution".to_string(),
+                rotary["resolution"].as_i64().unwrap_or(4).into(),
+            );
+            encoder.into()
+        })
+        .collect();
+    (!encoders.is_empty()).then_some(encoders)
+}
+
+/// Parse a USB id like `0xFEED`
+fn parse_hex(id: &str) -> Option<i64> {
+    i64::from_str_radix(id.trim_start_matches("0x").trim_start_matches("0X"), 16).ok()
+}
+
+/// Export keyboard.toml to a QMK `info.json` at `output`, with the layout of vial.json if it exists
+///
+/// The keyboard info, USB ids, chip, matrix, split halves, encoders and indicator LEDs are converted. Keys
+/// of the layout are placed like in the KLE layout of vial.json, or on a grid of the matrix without one.
+pub(crate) fn export_qmk(
+    keyboard_toml_path: &str,
+    vial_json_path: &str,
+    output: &str,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    if Path::new(output).exists() && !force {
+        return Err(format!("{} already exists, use --force to overwrite it", output).into());
+    }
+    let config = read_keyboard_config(keyboard_toml_path)?;
+    let vial = match fs::read_to_string(vial_json_path) {
+        Ok(content) => Some(
+            serde_json::from_str::<Value>(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", vial_json_path, e))?,
+        ),
+        Err(_) => None,
+    };
+
+    let mut warnings = Vec::new();
+    let info = info_json(&config, vial.as_ref(), keyboard_toml_path, &mut warnings)?;
+    fs::write(output, serde_json::to_string_pretty(&info)?)?;
+
+    for warning in &warnings {
+        println!("⚠️  {}", warning);
+    }
+    println!("✅ Exported {} to {}", keyboard_toml_path, output);
+    Ok(())
+}
+
+fn info_json(
+    config: &toml::Table,
+    vial: Option<&Value>,
+    keyboard_toml_path: &str,
+    warnings: &mut Vec<String>,
+) -> Result<Value, Box<dyn Error>> {
+    let keyboard = config.get("keyboard");
+    let field = |key: &str| keyboard.and_then(|k| k.get(key));
+    let text = |key: &str| field(key).and_then(|v| v.as_str());
+    let mut info = json!({});
+
+    if let Some(name) = text("name") {
+        info["keyboard_name"] = name.into();
+    }
+    if let Some(manufacturer) = text("manufacturer") {
+        info["manufacturer"] = manufacturer.into();
+    }
+    for (key, qmk_key) in [("vendor_id", "vid"), ("product_id", "pid")] {
+        if let Some(id) = field(key).and_then(|v| v.as_integer()) {
+            info["usb"][qmk_key] = format!("0x{:04X}", id).into();
+        }
+    }
+    if !info["usb"].is_null() {
+        info["usb"]["device_version"] = "1.0.0".into();
+    }
+
+    let chip =
+        text("chip").or_else(|| text("board").and_then(|b| get_board_chip_map().get(b).copied()));
+    match chip {
+        Some(chip) if chip.starts_with("rp2040") => {
+            info["processor"] = "RP2040".into();
+            info["bootloader"] = "rp2040".into();
+        }
+        // QMK names STM32 chips without the package, e.g. `STM32F411`
+        Some(chip) if chip.starts_with("stm32") => {
+            info["processor"] = chip
+                .to_uppercase()
+                .chars()
+                .take(9)
+                .collect::<String>()
+                .into();
+            info["bootloader"] = "stm32-dfu".into();
+        }
+        Some(chip) => {
+            info["processor"] = chip.to_uppercase().into();
+            warnings.push(format!(
+                "{} is not supported by QMK, set `processor` to the MCU of a QMK controller",
+                chip
+            ));
+        }
+        None => warnings.push(format!("No chip or board found in {}", keyboard_toml_path)),
+    }
+
+    let mut pins = PinConverter {
+        chip,
+        unconverted: Vec::new(),
+    };
+    let split = config.get("split");
+    let central = split.and_then(|s| s.get("central"));
+    let matrix = central
+        .and_then(|c| c.get("matrix"))
+        .or_else(|| config.get("matrix"))
+        .ok_or_else(|| format!("No [matrix] in {}", keyboard_toml_path))?;
+    let (matrix_pins, diode_direction) = export_matrix(matrix, &mut pins)?;
+    info["matrix_pins"] = matrix_pins;
+    info["diode_direction"] = diode_direction.into();
+    if let Some(split) = split {
+        let peripherals = split
+            .get("peripheral")
+            .and_then(|p| p.as_array())
+            .map(|p| p.as_slice())
+            .unwrap_or_default();
+        info["split"]["enabled"] = true.into();
+        match peripherals {
+            [peripheral] => {
+                if let Some(matrix) = peripheral.get("matrix") {
+                    info["split"]["matrix_pins"]["right"] = export_matrix(matrix, &mut pins)?.0;
+                }
+                let size = |half: Option<&toml::Value>| {
+                    ["rows", "cols"]
+                        .map(|k| half.and_then(|h| h.get(k)).and_then(|v| v.as_integer()))
+                };
+                if size(central) != size(Some(peripheral)) {
+                    warnings.push(
+                        "The halves of a QMK split keyboard have the same matrix size, pad the smaller half"
+                            .to_string(),
+                    );
+                }
+            }
+            _ => warnings.push(format!(
+                "QMK supports split keyboards with two halves, the keyboard has {} peripherals",
+                peripherals.len()
+            )),
+        }
+        warnings.push(
+            "QMK's split serial is a single half-duplex pin, set `split.serial.pin` in info.json"
+                .to_string(),
+        );
+    }
+
+    let layout = config
+        .get("layout")
+        .ok_or_else(|| format!("No [layout] in {}", keyboard_toml_path))?;
+    let size = |key: &str| layout.get(key).and_then(|v| v.as_integer()).unwrap_or(0);
+    let (rows, cols) = (size("rows") as usize, size("cols") as usize);
+    info["matrix_size"] = json!({ "rows": rows, "cols": cols });
+    info["dynamic_keymap"]["layer_count"] = size("layers").into();
+
+    let keys = match vial.and_then(|v| v["layouts"]["keymap"].as_array()) {
+        Some(kle) => kle_layout(kle, warnings),
+        None => {
+            warnings.push(
+                "No layout in vial.json, keys are placed on a grid of the matrix".to_string(),
+            );
+            grid_layout(layout, rows, cols)
+        }
+    };
+    info["layouts"]["LAYOUT"]["layout"] = keys.into();
+
+    if let Some(indicators) = config
+        .get("light")
+        .and_then(|l| export_indicators(l, &mut pins))
+    {
+        info["indicators"] = indicators;
+    }
+    let encoders: Vec<Value> = config
+        .get("input_device")
+        .and_then(|d| d.get("encoder"))
+        .and_then(|e| e.as_array())
+        .into_iter()
+        .flatten()
+        .map(|encoder| {
+            let mut pin = |key: &str| pins.convert_back(encoder.get(key).and_then(|p| p.as_str()));
+            json!({
+                "pin_a": pin("pin_a"),
+                "pin_b": pin("pin_b"),
+                "resolution": encoder.get("resolution").and_then(|r| r.as_integer()).unwrap_or(4),
+            })
+        })
+        .collect();
+    if !encoders.is_empty() {
+        info["encoder"]["rotary"] = encoders.into();
+        info["features"]["encoder"] = true.into();
+    }
+    if !pins.unconverted.is_empty() {
+        warnings.push(format!(
+            "Pins {} are kept in RMK format, rename them to the pins of {} in QMK",
+            pins.unconverted.join(", "),
+            chip.unwrap_or("the chip")
+        ));
+    }
+    Ok(info)
+}
+
+/// Convert an RMK matrix to `matrix_pins`, returning it with the diode direction
+fn export_matrix(
+    matrix: &toml::Value,
+    pins: &mut PinConverter,
+) -> Result<(Value, &'static str), Box<dyn Error>> {
+    let pin_list = |pins: &mut PinConverter, key: &str| -> Vec<String> {
+        matrix
+            .get(key)
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+            .map(|p| pins.convert_back(p.as_str()))
+            .collect()
+    };
+    if matrix.get("matrix_type").and_then(|t| t.as_str()) == Some("direct_pin") {
+        let direct: Vec<Vec<String>> = matrix
+            .get("direct_pins")
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+            .map(|row| {
+                row.as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|p| pins.convert_back(p.as_str()))
+                    .collect()
+            })
+            .collect();
+        return Ok((json!({ "direct": direct }), "COL2ROW"));
+    }
+    let row_pins = pin_list(pins, "row_pins");
+    let col_pins = pin_list(pins, "col_pins");
+    if row_pins.is_empty() || col_pins.is_empty() {
+        return Err("The matrix has no row_pins or col_pins".into());
+    }
+    let row2col = match matrix.get("row2col").and_then(|r| r.as_bool()) {
+        Some(row2col) => row2col,
+        None => matrix
+            .get("diode_direction")
+            .map(diode_direction_row2col)
+            .transpose()?
+            .unwrap_or(false),
+    };
+    Ok((
+        json!({ "rows": row_pins, "cols": col_pins }),
+        if row2col { "ROW2COL" } else { "COL2ROW" },
+    ))
+}
+
+/// Get the keys of a KLE layout with their matrix position in the top-left legend, like in vial.json
+fn kle_layout(rows: &[Value], warnings: &mut Vec<String>) -> Vec<Value> {
+    let (keys, rotated) = place_keys(rows);
+    if rotated > 0 {
+        warnings.push(format!(
+            "Rotation of {} key groups in the layout isn't exported, adjust x and y of their keys",
+            rotated
+        ));
+    }
+    keys.iter()
+        .map(|k| {
+            let mut key = json!({ "matrix": [k.row, k.col], "x": number(k.x), "y": number(k.y) });
+            if k.w != 1.0 {
+                key["w"] = number(k.w);
+            }
+            if k.h != 1.0 {
+                key["h"] = number(k.h);
+            }
+            key
+        })
+        .collect()
+}
+
+/// Place the keys of the matrix positions used by the keymap on a grid
+fn grid_layout(layout: &toml::Value, rows: usize, cols: usize) -> Vec<Value> {
+    let first_layer = layout
+        .get("keymap")
+        .and_then(|k| k.as_array())
+        .and_then(|k| k.first())
+        .and_then(|l| l.as_array());
+    let mut keys = Vec::new();
+    for r in 0..rows {
+        for c in 0..cols {
+            let key = first_layer
+                .and_then(|l| l.get(r))
+                .and_then(|row| row.as_array())
+                .and_then(|row| row.get(c))
+                .and_then(|k| k.as_str());
+            if key.is_some_and(|k| k.eq_ignore_ascii_case(NO_KEY)) {
+                continue;
+            }
+            keys.push(json!({ "matrix": [r, c], "x": c, "y": r }));
+        }
+    }
+    keys
+}
+
+/// Convert the indicator LEDs of [light] to `indicators`
+fn export_indicators(light: &toml::Value, pins: &mut PinConverter) -> Option<Value> {
+    let mut indicators = json!({});
+    for (key, qmk_key) in [
+        ("capslock", "caps_lock"),
+        ("numslock", "num_lock"),
+        ("scrolllock", "scroll_lock"),
+    ] {
+        if let Some(led) = light.get(key) {
+            indicators[qmk_key] = pins
+                .convert_back(led.get("pin").and_then(|p| p.as_str()))
+                .into();
+            let low_active = led.get("low_active").and_then(|l| l.as_bool()) == Some(true);
+            indicators["on_state"] = if low_active { 0 } else { 1 }.into();
+        }
+    }
+    (indicators != json!({})).then_some(indicators)
+}
+
+/// A KLE coordinate as an integer if it's whole, like QMK writes them
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 {
+        (n as i64).into()
+    } else {
+        n.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INFO_JSON: &str = r#"{
+        "keyboard_name": "macropad",
+        "manufacturer": "rmk",
+        "processor": "RP2040",
+        "usb": { "vid": "0xFEED", "pid": "0x0001", "device_version": "1.0.0" },
+        "matrix_pins": { "rows": ["GP0", "GP1"], "cols": ["GP2", "GP3", "GP4"] },
+        "diode_direction": "ROW2COL",
+        "indicators": { "caps_lock": "GP25", "on_state": 0 },
+        "encoder": { "rotary": [{ "pin_a": "GP5", "pin_b": "GP6" }] },
+        "layouts": {
+            "LAYOUT": {
+                "layout": [
+                    { "matrix": [0, 0], "x": 0, "y": 0 },
+                    { "matrix": [0, 2], "x": 1, "y": 0 },
+                    { "matrix": [1, 1], "x": 0, "y": 1, "w": 2 }
+                ]
+            }
+        }
+    }"#;
+
+    fn converter(chip: Option<&str>) -> PinConverter<'_> {
+        PinConverter {
+            chip,
+            unconverted: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn pins_are_converted_to_rmk_and_back() {
+        let mut rp2040 = converter(Some("rp2040"));
+        assert_eq!(rp2040.convert(&json!("GP2")), "PIN_2");
+        assert_eq!(rp2040.convert_back(Some("PIN_2")), "GP2");
+        assert_eq!(rp2040.convert(&json!("NO_PIN")), "_");
+        assert_eq!(rp2040.convert_back(Some("_")), "NO_PIN");
+
+        let mut stm32 = converter(Some("stm32f411ce"));
+        assert_eq!(stm32.convert(&json!("A15")), "PA15");
+        assert_eq!(stm32.convert_back(Some("PA15")), "A15");
+        assert!(stm32.unconverted.is_empty());
+
+        // Pins which don't follow the naming of the chip are kept and reported
+        assert_eq!(stm32.convert(&json!("GP2")), "GP2");
+        let mut nrf = converter(Some("nrf52840"));
+        assert_eq!(nrf.convert(&json!("P0_08")), "P0_08");
+        assert_eq!(stm32.unconverted, ["GP2"]);
+        assert_eq!(nrf.unconverted, ["P0_08"]);
+    }
+
+    #[test]
+    fn matrix_follows_the_diode_direction() {
+        let matrix_pins = json!({ "rows": ["GP0", "GP1"], "cols": ["GP2", "GP3", "GP4"] });
+        let mut pins = converter(Some("rp2040"));
+        let (matrix, rows, cols) = convert_matrix(&matrix_pins, "col2row", &mut pins).unwrap();
+        assert_eq!((rows, cols), (2, 3));
+        assert_eq!(
+            matrix["row_pins"],
+            toml::Value::from(vec!["PIN_0", "PIN_1"])
+        );
+        assert_eq!(
+            matrix["col_pins"],
+            toml::Value::from(vec!["PIN_2", "PIN_3", "PIN_4"])
+        );
+        assert_eq!(matrix["row2col"], toml::Value::from(false));
+
+        let (matrix, _, _) = convert_matrix(&matrix_pins, "row2col", &mut pins).unwrap();
+        assert_eq!(matrix["row2col"], toml::Value::from(true));
+
+        let direct = json!({ "direct": [["GP0", "GP1"], ["GP2", "NO_PIN"]] });
+        let (matrix, rows, cols) = convert_matrix(&direct, "col2row", &mut pins).unwrap();
+        assert_eq!((rows, cols), (2, 2));
+        assert_eq!(matrix["matrix_type"], toml::Value::from("direct_pin"));
+        assert_eq!(
+            matrix["direct_pins"],
+            toml::Value::from(vec![vec!["PIN_0", "PIN_1"], vec!["PIN_2", "_"]])
+        );
+
+        let no_cols = json!({ "rows": ["GP0"], "cols": [] });
+        assert!(convert_matrix(&no_cols, "col2row", &mut pins).is_err());
+    }
+
+    #[test]
+    fn usb_ids_are_hex() {
+        assert_eq!(parse_hex("0xFEED"), Some(0xfeed));
+        assert_eq!(parse_hex("0X4d44"), Some(0x4d44));
+        assert_eq!(parse_hex("1234"), Some(0x1234));
+        assert_eq!(parse_hex("0xZZ"), None);
+    }
+
+    #[test]
+    fn keys_outside_of_the_layout_are_holes() {
+        let info: Value = serde_json::from_str(INFO_JSON).unwrap();
+        let mut warnings = Vec::new();
+        let layers = keymap(&info, 2, 3, 2, &mut warnings);
+        let layer = [["_", NO_KEY, "_"], [NO_KEY, "_", NO_KEY]];
+        assert_eq!(layers, [layer, layer]);
+        assert!(warnings.is_empty());
+
+        // Keys outside of the matrix are reported, without layouts every position is a key
+        let keymap_2x2 = keymap(&info, 2, 2, 1, &mut warnings);
+        assert_eq!(keymap_2x2, [[["_", NO_KEY], [NO_KEY, "_"]]]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("outside of the 2x2 matrix"));
+        let no_layouts = keymap(&json!({}), 1, 2, 1, &mut warnings);
+        assert_eq!(no_layouts, [[["_", "_"]]]);
+    }
+
+    #[test]
+    fn import_and_export_round_trip() {
+        let info: Value = serde_json::from_str(INFO_JSON).unwrap();
+        let mut warnings = Vec::new();
+        let config = keyboard_config(&info, &HashMap::new(), &mut warnings).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let expected: toml::Table = toml::from_str(
+            r#"
+            [keyboard]
+            name = "macropad"
+            product_name = "macropad"
+            vendor_id = 0xfeed
+            product_id = 0x0001
+            manufacturer = "rmk"
+            chip = "rp2040"
+
+            [matrix]
+            row_pins = ["PIN_0", "PIN_1"]
+            col_pins = ["PIN_2", "PIN_3", "PIN_4"]
+            row2col = true
+
+            [layout]
+            rows = 2
+            cols = 3
+            layers = 4
+            keymap = [
+                [["_", "No", "_"], ["No", "_", "No"]],
+                [["_", "No", "_"], ["No", "_", "No"]],
+                [["_", "No", "_"], ["No", "_", "No"]],
+                [["_", "No", "_"], ["No", "_", "No"]],
+            ]
+
+            [light.capslock]
+            pin = "PIN_25"
+            low_active = true
+
+            [[input_device.encoder]]
+            pin_a = "PIN_5"
+            pin_b = "PIN_6"
+            resolution = 4
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config, expected);
+
+        let exported = info_json(&config, None, "keyboard.toml", &mut warnings).unwrap();
+        for key in [
+            "keyboard_name",
+            "manufacturer",
+            "processor",
+            "usb",
+            "matrix_pins",
+            "diode_direction",
+            "indicators",
+        ] {
+            assert_eq!(exported[key], info[key], "{}", key);
+        }
+        assert_eq!(exported["encoder"]["rotary"][0]["pin_a"], "GP5");
+        assert_eq!(exported["matrix_size"], json!({ "rows": 2, "cols": 3 }));
+        // Without vial.json, the keys of the layout are placed on a grid
+        let positions: Vec<&Value> = exported["layouts"]["LAYOUT"]["layout"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|key| &key["matrix"])
+            .collect();
+        assert_eq!(positions, [&json!([0, 0]), &json!([0, 2]), &json!([1, 1])]);
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,534 @@
+use crate::keycode::split_args;
+use crate::keymap::{read_keymap, write_keymap, NO_KEY};
+use crate::via::from_qmk;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+/// Largest depth of `#define`s expanded for a key, deeper ones are most likely recursive
+const MAX_DEFINE_DEPTH: usize = 8;
+
+/// A `LAYOUT(...)` entry of the `keymaps` array in keymap.c
+struct LayoutEntry {
+    /// Layer index, from the `[layer] =` designator or the position in the array
+    layer: usize,
+    /// Name of the layout macro, e.g. `LAYOUT_split_3x6_3`
+    macro_name: String,
+    keys: Vec<String>,
+}
+
+/// Import the layers of a QMK keymap.c into the keymap of keyboard.toml
+///
+/// This is a best-effort parser: simple `#define`s and layer `enum`s are resolved, but the preprocessor
+/// isn't run. The keys of `LAYOUT(...)` are in the order of the layout in `info.json`, without it they
+/// have to be the matrix positions with keys in keyboard.toml, row by row. Keys which can't be converted,
+/// like custom keycodes, are set to `No` with a `# TODO` comment.
+pub(crate) fn import_qmk_keymap(
+    keymap_path: &str,
+    info_path: Option<&str>,
+    keyboard_toml_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let content = fs::read_to_string(keymap_path)
+        .map_err(|e| format!("Failed to read {}: {}", keymap_path, e))?;
+    let source = strip_comments(&content);
+    let defines = defines(&source);
+    let enums = enum_values(&source);
+    let entries = layout_entries(&source, &enums)
+        .map_err(|e| format!("Failed to parse {}: {}", keymap_path, e))?;
+    let info: Option<Value> = match info_path {
+        Some(path) => {
+            let content =
+                fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            Some(
+                serde_json::from_str(&content)
+                    .map_err(|e| format!("Invalid QMK info.json {}: {}", path, e))?,
+            )
+        }
+        None => None,
+    };
+
+    let mut layers = read_keymap(keyboard_toml_path)?;
+    let matrix_keys = matrix_positions(&layers);
+    let mut todos = Vec::new();
+    let mut skipped = Vec::new();
+    for entry in &entries {
+        let positions = match &info {
+            Some(info) => info_positions(info, &entry.macro_name)?,
+            None => matrix_keys.clone(),
+        };
+        if positions.len() != entry.keys.len() {
+            return Err(format!(
+                "{} of layer {} has {} keys, but the layout has {}{}",
+                entry.macro_name,
+                entry.layer,
+                entry.keys.len(),
+                positions.len(),
+                if info.is_none() {
+                    ", pass the info.json of the keyboard with --info"
+                } else {
+                    ""
+                }
+            )
+            .into());
+        }
+        let Some(layer) = layers.get_mut(entry.layer) else {
+            skipped.push(entry.layer);
+            continue;
+        };
+        for (key, (r, c)) in entry.keys.iter().zip(positions) {
+            let Some(slot) = layer.get_mut(r).and_then(|row| row.get_mut(c)) else {
+                return Err(format!(
+                    "Matrix position {},{} of {} is outside of the matrix of {}",
+                    r, c, entry.macro_name, keyboard_toml_path
+                )
+                .into());
+            };
+            *slot = match from_qmk(&expand(key, &defines, &enums)) {
+                Some(rmk) => rmk,
+                None => {
+                    todos.push((entry.layer, r, c, format!("`{}`", key)));
+                    NO_KEY.to_string()
+                }
+            };
+        }
+    }
+    write_keymap(keyboard_toml_path, &layers, &todos)?;
+
+    for (layer, r, c, key) in &todos {
+        println!(
+            "⚠️  No RMK keycode for {} on layer {} at {},{}, marked with TODO",
+            key, layer, r, c
+        );
+    }
+    if !skipped.is_empty() {
+        println!(
+            "⚠️  Layers {:?} are skipped, increase `layers` in [layout] to import them",
+            skipped
+        );
+    }
+    println!(
+        "✅ Imported {} layers of {} into {}",
+        entries.len() - skipped.len(),
+        keymap_path,
+        keyboard_toml_path
+    );
+    Ok(())
+}
+
+/// Remove `//` and `/* */` comments, string literals are kept as they are
+fn strip_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                // Keep the newline, it ends `#define`s
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                out.push(' ');
+            }
+            ('"', _) => {
+                out.push(c);
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    out.push(c);
+                    match c {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => break,
+                        _ => escaped = false,
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Get the object-like `#define`s, e.g. `#define CTL_ESC LCTL_T(KC_ESC)`
+fn defines(source: &str) -> HashMap<String, String> {
+    source
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("#define"))
+        .filter_map(|define| {
+            let define = define.trim();
+            let end = define
+                .find(|c: char| !is_ident_char(c))
+                .unwrap_or(define.len());
+            let (name, body) = define.split_at(end);
+            // Function-like macros are skipped
+            (!name.is_empty() && !body.starts_with('(') && !body.trim().is_empty())
+                .then(|| (name.to_string(), body.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Get the values of all `enum` constants, e.g. the layer names `_BASE` and `_LOWER`
+fn enum_values(source: &str) -> HashMap<String, i64> {
+    let mut values = HashMap::new();
+    let mut rest = source;
+    while let Some(start) = find_word(rest, "enum") {
+        rest = &rest[start + 4..];
+        let (Some(open), Some(close)) = (rest.find('{'), rest.find('}')) else {
+            break;
+        };
+        if open > close || rest[..open].contains(';') {
+            continue;
+        }
+        let mut next = Some(0);
+        for constant in rest[open + 1..close].split(',') {
+            let (name, value) = match constant.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim().parse::<i64>().ok()),
+                None => (constant.trim(), next),
+            };
+            // Constants after a non-numeric value, like `= SAFE_RANGE`, aren't layers
+            next = value.map(|v| v + 1);
+            if let (false, Some(value)) = (name.is_empty(), value) {
+                values.insert(name.to_string(), value);
+            }
+        }
+        rest = &rest[close..];
+    }
+    values
+}
+
+/// Get the `LAYOUT(...)` entries of the `keymaps` array
+fn layout_entries(
+    source: &str,
+    enums: &HashMap<String, i64>,
+) -> Result<Vec<LayoutEntry>, Box<dyn Error>> {
+    let start = find_word(source, "keymaps").ok_or("no `keymaps` array found")?;
+    let open = start
+        + source[start..]
+            .find('{')
+            .ok_or("no `keymaps` array found")?;
+    let body = &source[open + 1..open + matching_close(&source[open..], '{', '}')?];
+
+    let mut entries = Vec::new();
+    let mut rest = body;
+    while let Some(name_start) = rest.find("LAYOUT") {
+        let name_end = name_start
+            + rest[name_start..]
+                .find(|c: char| !is_ident_char(c))
+                .ok_or("unterminated layout macro")?;
+        let macro_name = &rest[name_start..name_end];
+        let open = name_end
+            + rest[name_end..]
+                .find('(')
+                .ok_or("layout macro without keys")?;
+        let close = open + matching_close(&rest[open..], '(', ')')?;
+
+        // The designator before the macro, e.g. `[_BASE] =`
+        let prefix = &rest[..name_start];
+        let layer = match prefix.rfind('[').zip(prefix.rfind(']')) {
+            Some((l, r)) if l < r => {
+                let designator = prefix[l + 1..r].trim();
+                designator
+                    .parse::<usize>()
+                    .ok()
+                    .or_else(|| enums.get(designator).map(|v| *v as usize))
+                    .ok_or_else(|| format!("unknown layer `{}`", designator))?
+            }
+            _ => entries.len(),
+        };
+        let keys: Vec<String> = split_args(&rest[open + 1..close])
+            .into_iter()
+            .filter(|k| !k.is_empty())
+            .map(|k| k.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        entries.push(LayoutEntry {
+            layer,
+            macro_name: macro_name.to_string(),
+            keys,
+        });
+        rest = &rest[close + 1..];
+    }
+    if entries.is_empty() {
+        return Err("no `LAYOUT(...)` found in the `keymaps` array".into());
+    }
+    Ok(entries)
+}
+
+/// Expand `#define`s of a key and replace layer names in its arguments with their values
+fn expand(key: &str, defines: &HashMap<String, String>, enums: &HashMap<String, i64>) -> String {
+    let mut key = key.to_string();
+    for _ in 0..MAX_DEFINE_DEPTH {
+        match defines.get(&key) {
+            Some(body) => key = body.clone(),
+            None => break,
+        }
+    }
+    let Some((name, args)) = key.strip_suffix(')').and_then(|k| k.split_once('(')) else {
+        return key;
+    };
+    let args: Vec<String> = split_args(args)
+        .into_iter()
+        .map(|arg| match enums.get(arg) {
+            Some(value) => value.to_string(),
+            None => expand(arg, defines, enums),
+        })
+        .collect();
+    format!("{}({})", name.trim(), args.join(","))
+}
+
+/// Get the matrix positions of a layout in info.json, falling back to `LAYOUT` for the plain macro name
+fn info_positions(info: &Value, macro_name: &str) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+    let layouts = info["layouts"]
+        .as_object()
+        .ok_or("No layouts in info.json")?;
+    let layout = layouts
+        .get(macro_name)
+        // keymap.c may use another name for the only layout, e.g. `LAYOUT_all`
+        .or_else(|| layouts.values().next().filter(|_| layouts.len() == 1))
+        .ok_or_else(|| {
+            format!(
+                "No layout {} in info.json, it has {}",
+                macro_name,
+                layouts.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+    layout["layout"]
+        .as_array()
+        .ok_or_else(|| format!("No keys in layout {} of info.json", macro_name))?
+        .iter()
+        .map(
+            |key| match (key["matrix"][0].as_u64(), key["matrix"][1].as_u64()) {
+                (Some(r), Some(c)) => Ok((r as usize, c as usize)),
+                _ => Err(format!("Key without matrix position in layout {}", macro_name).into()),
+            },
+        )
+        .collect()
+}
+
+/// Get the matrix positions which are keys in the first layer, row by row
+fn matrix_positions(layers: &[Vec<Vec<String>>]) -> Vec<(usize, usize)> {
+    let Some(layer) = layers.first() else {
+        return Vec::new();
+    };
+    layer
+        .iter()
+        .enumerate()
+        .flat_map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, key)| !key.eq_ignore_ascii_case(NO_KEY))
+                .map(move |(c, _)| (r, c))
+        })
+        .collect()
+}
+
+/// Get the offset of the closing bracket of the bracket at the start of `source`
+fn matching_close(source: &str, open: char, close: char) -> Result<usize, Box<dyn Error>> {
+    let mut depth = 0;
+    for (i, c) in source.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Ok(i);
+            }
+        }
+    }
+    Err(format!("unmatched `{}`", open).into())
+}
+
+/// Find `word` in `source` as a whole identifier
+fn find_word(source: &str, word: &str) -> Option<usize> {
+    source.match_indices(word).map(|(i, _)| i).find(|&i| {
+        let before = source[..i].chars().next_back();
+        let after = source[i + word.len()..].chars().next();
+        !before.is_some_and(is_ident_char) && !after.is_some_and(is_ident_char)
+    })
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    const KEYBOARD_TOML: &str = r#"[keyboard]
+name = "tiny"
+
+[layout]
+rows = 2
+cols = 3
+layers = 2
+keymap = [
+  [
+    ["A", "A", "A"],
+    ["A", "A", "No"],
+  ],
+  [
+    ["_", "_", "_"],
+    ["_", "_", "_"],
+  ],
+]
+"#;
+
+    const KEYMAP_C: &str = r#"#include QMK_KEYBOARD_H
+
+enum layers { _BASE, _FN };
+enum custom_keycodes { MY_KEY = SAFE_RANGE };
+
+#define CTL_ESC LCTL_T(KC_ESC)
+#define FN_SPC LT(_FN, KC_SPC)
+
+/* Two layers, LAYOUT(...) isn't used outside of keymaps */
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    [_FN] = LAYOUT(
+        _______, KC_1, KC_F2,
+        MO(_BASE), XXXXXXX
+    ),
+    [_BASE] = LAYOUT(
+        CTL_ESC, KC_A, KC_B, // comment, with a LAYOUT(
+        FN_SPC, MY_KEY
+    )
+};
+"#;
+
+    /// Import `keymap_c` into a temporary keyboard.toml, returning its content
+    fn import(name: &str, keymap_c: &str, info: Option<&str>) -> Result<String, Box<dyn Error>> {
+        let dir = env::temp_dir().join(format!("rmkit-qmk-keymap-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = |file: &str| dir.join(file).display().to_string();
+        fs::write(path("keymap.c"), keymap_c)?;
+        fs::write(path("keyboard.toml"), KEYBOARD_TOML)?;
+        if let Some(info) = info {
+            fs::write(path("info.json"), info)?;
+        }
+        let info_path = info.map(|_| path("info.json"));
+        let result = import_qmk_keymap(
+            &path("keymap.c"),
+            info_path.as_deref(),
+            &path("keyboard.toml"),
+        )
+        .and_then(|_| Ok(fs::read_to_string(path("keyboard.toml"))?));
+        fs::remove_dir_all(&dir)?;
+        result
+    }
+
+    #[test]
+    fn keymap_is_imported() {
+        let expected = r#"[keyboard]
+name = "tiny"
+
+[layout]
+rows = 2
+cols = 3
+layers = 2
+keymap = [
+  [
+    ["MT(Escape, LCtrl)", "A", "B"],
+    # TODO: col 1: `MY_KEY`
+    ["LT(1, Space)", "No", "No"],
+  ],
+  [
+    ["_", "Kc1", "F2"],
+    ["MO(0)", "No", "_"],
+  ],
+]
+"#;
+        assert_eq!(import("plain", KEYMAP_C, None).unwrap(), expected);
+    }
+
+    #[test]
+    fn keys_are_placed_by_info_json() {
+        let keymap_c = "const uint16_t keymaps[][2][3] = { LAYOUT_ortho(KC_A, KC_B, KC_C, KC_D, KC_E, KC_F) };";
+        let info = r#"{"layouts": {"LAYOUT_ortho": {"layout": [
+            {"matrix": [1, 2]}, {"matrix": [1, 1]}, {"matrix": [1, 0]},
+            {"matrix": [0, 2]}, {"matrix": [0, 1]}, {"matrix": [0, 0]}
+        ]}}}"#;
+        let content = import("info", keymap_c, Some(info)).unwrap();
+        let keymap = &toml::from_str::<toml::Table>(&content).unwrap()["layout"]["keymap"];
+        let expected: toml::Table = toml::from_str(
+            r#"keymap = [[["F", "E", "D"], ["C", "B", "A"]], [["_", "_", "_"], ["_", "_", "_"]]]"#,
+        )
+        .unwrap();
+        assert_eq!(keymap, &expected["keymap"]);
+        // The only layout of info.json is used for other macro names
+        let keymap_c = keymap_c.replace("LAYOUT_ortho", "LAYOUT_all");
+        assert!(import("info-all", &keymap_c, Some(info)).is_ok());
+    }
+
+    #[test]
+    fn malformed_keymaps_are_rejected() {
+        let error = |name: &str, keymap_c: &str, info: Option<&str>| {
+            import(name, keymap_c, info).unwrap_err().to_string()
+        };
+        assert!(
+            error("empty", "#include QMK_KEYBOARD_H", None).contains("no `keymaps` array found")
+        );
+        assert!(
+            error("no-layout", "const uint16_t keymaps[][2][3] = { };", None)
+                .contains("no `LAYOUT(...)` found")
+        );
+        assert!(error(
+            "unmatched",
+            "const uint16_t keymaps[][2][3] = { LAYOUT(KC_A };",
+            None
+        )
+        .contains("unmatched `(`"));
+        assert!(error(
+            "layer",
+            "const uint16_t keymaps[][2][3] = { [_NAV] = LAYOUT(KC_A) };",
+            None
+        )
+        .contains("unknown layer `_NAV`"));
+        assert!(error(
+            "count",
+            "const uint16_t keymaps[][2][3] = { LAYOUT(KC_A, KC_B) };",
+            None
+        )
+        .contains("LAYOUT of layer 0 has 2 keys, but the layout has 5, pass the info.json"));
+        let keymap_c = "const uint16_t keymaps[][2][3] = { LAYOUT(KC_A) };";
+        assert!(error("info", keymap_c, Some("{")).contains("Invalid QMK info.json"));
+        assert!(error(
+            "info-layouts",
+            keymap_c,
+            Some(r#"{"layouts": {"A": {}, "B": {}}}"#)
+        )
+        .contains("No layout LAYOUT in info.json, it has A, B"));
+        assert!(error(
+            "info-outside",
+            keymap_c,
+            Some(r#"{"layouts": {"LAYOUT": {"layout": [{"matrix": [5, 0]}]}}}"#)
+        )
+        .contains("Matrix position 5,0 of LAYOUT is outside of the matrix"));
+    }
+
+    #[test]
+    fn defines_and_layer_names_are_expanded() {
+        let source = strip_comments(KEYMAP_C);
+        let (defines, enums) = (defines(&source), enum_values(&source));
+        assert_eq!(enums.get("_FN"), Some(&1));
+        // Constants after `SAFE_RANGE` aren't numbered
+        assert_eq!(enums.get("MY_KEY"), None);
+        assert_eq!(expand("FN_SPC", &defines, &enums), "LT(1,KC_SPC)");
+        assert_eq!(
+            expand("LT(_FN, CTL_ESC)", &defines, &enums),
+            "LT(1,LCTL_T(KC_ESC))"
+        );
+        let recursive = HashMap::from([
+            ("A".to_string(), "B".to_string()),
+            ("B".to_string(), "A".to_string()),
+        ]);
+        assert_eq!(expand("A", &recursive, &enums), "A");
+    }
+}
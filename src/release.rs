@@ -0,0 +1,343 @@
+use crate::reporter::BuildTerminal;
+use crate::size;
+use minijinja::context;
+use rmkit_core::chip::{
+    get_board_chip_map, get_board_info, get_chip_target, get_uf2_family, Bootloader,
+};
+use rmkit_core::firmware::{
+    cargo_with, check_target, flash_image, intel_hex, release_dir, uf2, CargoArgs, Uf2Tags,
+};
+use rmkit_core::interrupt::TempPath;
+use rmkit_core::keyboard_toml::{read_keyboard_config, sanitize_project_name};
+use rmkit_core::lock::locked_rmk_version;
+use rmkit_core::template;
+use rmkit_core::{Classify, FailureKind};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Manifest of the release bundle, with the keyboard, versions and checksums
+const RELEASE_MANIFEST: &str = "release.toml";
+/// Checksums of the firmware files, in the format of `sha256sum`
+const CHECKSUMS: &str = "SHA256SUMS";
+/// Name of the firmware files if neither the command line nor keyboard.toml has one
+const DEFAULT_NAME: &str = "{{ keyboard }}-{{ bin }}-v{{ version }}";
+
+/// Build all binaries of the project and bundle their firmware files into a zip archive
+///
+/// Every binary is included in the firmware formats of the chip, `formats`, or `formats` of `[build.release]` in
+/// keyboard.toml, if they're not empty.
+/// Files are named by `name`, a template with `keyboard`, `bin`, `version`, `chip`, `rmk`, `date` and `git_sha`.
+/// The archive is `output`, or in `out_dir`, which default to `[build.release]` of keyboard.toml. It also
+/// contains `SHA256SUMS` and `release.toml`, the manifest of the release.
+pub(crate) fn release_firmware(
+    project_dir: Option<String>,
+    name: Option<String>,
+    output: Option<String>,
+    out_dir: Option<String>,
+    formats: Vec<String>,
+    no_install: bool,
+    cargo_args: CargoArgs,
+) -> Result<(), Box<dyn Error>> {
+    let project_dir = PathBuf::from(project_dir.unwrap_or_else(|| ".".to_string()));
+    let cargo_toml_path = project_dir.join("Cargo.toml");
+    if !cargo_toml_path.exists() {
+        return Err(format!("No Cargo.toml found in {}", project_dir.display()).into());
+    }
+    let manifest = cargo_toml::Manifest::from_path(&cargo_toml_path)?;
+    let package = manifest
+        .package
+        .as_ref()
+        .ok_or("No package in Cargo.toml")?;
+    let version = package.version.get().map(|v| v.to_string())?;
+    let bins: Vec<String> = manifest.bin.iter().filter_map(|b| b.name.clone()).collect();
+
+    let config = read_keyboard_config(project_dir.join("keyboard.toml"))?;
+    let keyboard_field = |key: &str| {
+        config
+            .get("keyboard")
+            .and_then(|k| k.get(key))
+            .and_then(|v| v.as_str())
+    };
+    let keyboard = sanitize_project_name(keyboard_field("name").unwrap_or(&package.name));
+    let board = keyboard_field("board");
+    let chip = keyboard_field("chip")
+        .or_else(|| board.and_then(|b| get_board_chip_map().get(b).copied()))
+        .ok_or("No chip or board found in keyboard.toml")?;
+    let uf2_family = uf2_family(chip, board);
+    let supported = firmware_formats(chip, board);
+    // The command line comes before [build.release] of keyboard.toml, whose output directory is in the project
+    let release_config = |key: &str| {
+        config
+            .get("build")
+            .and_then(|b| b.get("release"))
+            .and_then(|r| r.get(key))
+            .map(|v| {
+                v.as_str()
+                    .map(|s| s.to_string())
+                    .ok_or(format!("`{}` in [build.release] should be a string", key))
+            })
+            .transpose()
+            .or_fail(FailureKind::Config)
+    };
+    let formats = match config
+        .get("build")
+        .and_then(|b| b.get("release"))
+        .and_then(|r| r.get("formats"))
+    {
+        Some(release_formats) if formats.is_empty() => release_formats
+            .as_array()
+            .and_then(|f| {
+                f.iter()
+                    .map(|f| f.as_str().map(|f| f.to_string()))
+                    .collect()
+            })
+            .ok_or("`formats` in [build.release] should be an array of strings")
+            .or_fail(FailureKind::Config)?,
+        _ => formats,
+    };
+    // Unsupported formats fail before the build
+    if let Some(format) = formats.iter().find(|f| !supported.contains(&f.as_str())) {
+        return Err(format!(
+            "{} doesn't support firmware format {}, it supports {}",
+            board.unwrap_or(chip),
+            format,
+            supported.join(", ")
+        ))
+        .or_fail(FailureKind::Config);
+    }
+    let name = match name {
+        Some(name) => name,
+        None => release_config("name")?.unwrap_or_else(|| DEFAULT_NAME.to_string()),
+    };
+    let out_dir = match out_dir {
+        Some(out_dir) => PathBuf::from(out_dir),
+        None => match release_config("out_dir")? {
+            Some(out_dir) => project_dir.join(out_dir),
+            None => project_dir.clone(),
+        },
+    };
+    let git_sha = git_sha(&project_dir);
+    if git_sha.is_none() && name.contains("git_sha") {
+        return Err(format!(
+            "{} isn't in a git repository, its file names can't have git_sha",
+            project_dir.display()
+        ))
+        .or_fail(FailureKind::Config);
+    }
+    let selected = |format: &str| formats.is_empty() || formats.iter().any(|f| f == format);
+    let rmk = locked_rmk_version(&project_dir.join("Cargo.lock"));
+    let date = time::OffsetDateTime::now_utc().date().to_string();
+    let uf2_tags = Uf2Tags {
+        version: Some(version.clone()),
+        board: Some(board.unwrap_or(chip).to_string()),
+        date: Some(date.clone()),
+    };
+
+    check_target(&project_dir, &mut BuildTerminal { no_install })?;
+    println!("🔨 Building firmware...");
+    let cargo_args = cargo_args
+        .with_keyboard_toml(&project_dir)?
+        .with_rmk_features(&project_dir)?;
+    cargo_with(&project_dir, &["build", "--release"], &cargo_args)?;
+    let release_dir = release_dir(&project_dir, &project_dir.join("target"));
+    for bin in &bins {
+        size::report_usage(&project_dir, bin, &release_dir.join(bin))?;
+    }
+
+    let output = match output {
+        Some(output) => PathBuf::from(output),
+        None => out_dir.join(format!("{}-v{}.zip", keyboard, version)),
+    };
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    // An unfinished archive is removed on failure or interrupt
+    let archive = TempPath::new(&output);
+    let mut zip = ZipWriter::new(File::create(archive.path())?);
+    let options = SimpleFileOptions::default();
+
+    let env = template::environment();
+    let mut checksums = String::new();
+    let mut files = Vec::new();
+    for bin in &bins {
+        let elf = fs::read(release_dir.join(bin))
+            .map_err(|e| format!("Failed to read the ELF of {}: {}", bin, e))?;
+        let base_name = env
+            .render_str(
+                &name,
+                context! { keyboard, bin, version, chip, rmk, date, git_sha },
+            )
+            .map_err(|e| format!("Failed to render the file name: {:#}", e))?;
+
+        let mut contents = Vec::new();
+        // ESP32 images have their own format, which is created by espflash from the ELF
+        if chip.starts_with("esp") {
+            println!(
+                "ℹ️  Only the ELF of {} is bundled, flash it with espflash",
+                bin
+            );
+        } else if ["hex", "uf2", "bin"].iter().any(|f| selected(f)) {
+            let (base, image) = flash_image(&elf)?;
+            contents.push(("hex", intel_hex(base, &image)));
+            if let Some(family) = uf2_family.filter(|_| supported.contains(&"uf2")) {
+                contents.push(("uf2", uf2(base, &image, family, &uf2_tags)));
+            }
+            contents.push(("bin", image));
+        }
+        contents.insert(0, ("elf", elf));
+        for (format, content) in contents.into_iter().filter(|(f, _)| selected(f)) {
+            let file_name = format!("{}.{}", base_name, format);
+            let sha256 = format!("{:x}", Sha256::digest(&content));
+            zip.start_file(file_name.as_str(), options)?;
+            zip.write_all(&content)?;
+            checksums.push_str(&format!("{}  {}\n", sha256, file_name));
+            println!("📦 {}", file_name);
+
+            let mut file = toml::Table::new();
+            file.insert("name".to_string(), file_name.into());
+            file.insert("bin".to_string(), bin.clone().into());
+            file.insert("format".to_string(), format.into());
+            file.insert("sha256".to_string(), sha256.into());
+            files.push(toml::Value::from(file));
+        }
+    }
+
+    let mut release = toml::Table::new();
+    release.insert("keyboard".to_string(), keyboard.clone().into());
+    release.insert("version".to_string(), version.clone().into());
+    release.insert("chip".to_string(), chip.into());
+    release.insert(
+        "default_format".to_string(),
+        default_firmware_format(chip, board).into(),
+    );
+    if let Some(rmk) = rmk {
+        release.insert("rmk".to_string(), rmk.into());
+    }
+    release.insert("rmkit".to_string(), env!("CARGO_PKG_VERSION").into());
+    release.insert("date".to_string(), date.into());
+    if let Some(git_sha) = git_sha {
+        release.insert("git_sha".to_string(), git_sha.into());
+    }
+    release.insert("files".to_string(), files.into());
+    zip.start_file(RELEASE_MANIFEST, options)?;
+    zip.write_all(toml::to_string(&release)?.as_bytes())?;
+    zip.start_file(CHECKSUMS, options)?;
+    zip.write_all(checksums.as_bytes())?;
+    zip.finish()?;
+    archive.keep();
+
+    println!(
+        "✅ Released {} v{} to {}",
+        keyboard,
+        version,
+        output.display()
+    );
+    Ok(())
+}
+
+/// Get the short hash of the commit of the project, if it's in a git repository
+fn git_sha(project_dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(project_dir)
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    let sha = String::from_utf8(output.stdout).ok()?;
+    (output.status.success() && !sha.trim().is_empty()).then(|| sha.trim().to_string())
+}
+
+/// Get the UF2 family id of the chip, if the board, if it's known, has a UF2 bootloader
+///
+/// Boards with another bootloader can't use UF2 files, boards without board info are assumed to support them.
+fn uf2_family(chip: &str, board: Option<&str>) -> Option<u32> {
+    get_uf2_family(board.unwrap_or(chip)).filter(|_| {
+        board
+            .and_then(get_board_info)
+            .and_then(|info| info.bootloader)
+            .is_none_or(|bootloader| bootloader == Bootloader::Uf2)
+    })
+}
+
+/// Firmware formats of a chip or board, from `formats` of its target in the chip database
+///
+/// Without a target, it's ELF, Intel HEX and raw binary, and UF2 with a UF2 family id, ESP32 only has ELF.
+/// UF2 is dropped for boards with another bootloader.
+pub(crate) fn firmware_formats(chip: &str, board: Option<&str>) -> Vec<&'static str> {
+    let uf2 = uf2_family(chip, board).is_some();
+    let formats: Vec<&str> = match get_chip_target(board.unwrap_or(chip)) {
+        Some(target) => target.formats.iter().map(|f| f.as_str()).collect(),
+        None if chip.starts_with("esp") => vec!["elf"],
+        None => vec!["elf", "hex", "bin", "uf2"],
+    };
+    formats.into_iter().filter(|f| *f != "uf2" || uf2).collect()
+}
+
+/// Firmware format a chip or board is usually flashed from, uf2 for boards with a UF2 bootloader
+///
+/// Without `default_format` in the chip database, it's the raw binary for STM32, which DFU and ST-Link take, the ELF
+/// for ESP32 and Intel HEX for the others.
+pub(crate) fn default_firmware_format(chip: &str, board: Option<&str>) -> &'static str {
+    let uf2_bootloader = board
+        .and_then(get_board_info)
+        .is_some_and(|info| info.bootloader == Some(Bootloader::Uf2));
+    if uf2_bootloader && uf2_family(chip, board).is_some() {
+        return "uf2";
+    }
+    match get_chip_target(board.unwrap_or(chip)).and_then(|t| t.default_format.as_deref()) {
+        Some(format) => format,
+        None if chip.starts_with("esp") => "elf",
+        None if chip.starts_with("stm32") => "bin",
+        None => "hex",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn firmware_formats_of_chips() {
+        assert_eq!(
+            firmware_formats("nrf52840", None),
+            ["elf", "hex", "bin", "uf2"]
+        );
+        assert_eq!(
+            firmware_formats("nrf52840", Some("nice!nano")),
+            ["elf", "hex", "bin", "uf2"]
+        );
+        // No UF2 family id
+        assert_eq!(firmware_formats("nrf52811", None), ["elf", "hex", "bin"]);
+        assert_eq!(firmware_formats("esp32c3", None), ["elf"]);
+        assert_eq!(
+            firmware_formats("stm32f411ce", None),
+            ["elf", "hex", "bin", "uf2"]
+        );
+    }
+
+    #[test]
+    fn default_firmware_format_of_chips() {
+        assert_eq!(default_firmware_format("rp2040", None), "uf2");
+        assert_eq!(default_firmware_format("nrf52840", None), "hex");
+        assert_eq!(
+            default_firmware_format("nrf52840", Some("nice!nano")),
+            "uf2"
+        );
+        assert_eq!(default_firmware_format("stm32f411ce", None), "bin");
+        assert_eq!(default_firmware_format("esp32c3", None), "elf");
+        for (chip, board) in [
+            ("rp2040", None),
+            ("nrf52840", Some("nice!nano")),
+            ("stm32h7b0vb", None),
+        ] {
+            assert!(firmware_formats(chip, board).contains(&default_firmware_format(chip, board)));
+        }
+    }
+}
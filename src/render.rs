@@ -0,0 +1,234 @@
+use crate::keymap::{read_keymap, NO_KEY};
+use crate::kle::{place_keys, PlacedKey};
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Widest key label in ASCII renderings, longer labels are cut
+const MAX_ASCII_WIDTH: usize = 12;
+/// Size of a key unit in SVG renderings, in px
+const UNIT: f64 = 56.0;
+/// Height of the title of a layer in SVG renderings, in px
+const TITLE_HEIGHT: f64 = 28.0;
+
+/// Render the layers of the keymap as ASCII art, or as SVG or HTML for `.svg` and `.html` outputs
+///
+/// ASCII follows the matrix. SVG and HTML place the keys like the layout of vial.json, which is read from
+/// next to keyboard.toml if `vial_json_path` isn't given, or on a grid of the matrix without one.
+pub(crate) fn render_keymap(
+    keyboard_toml_path: &str,
+    vial_json_path: Option<&str>,
+    layer: Option<usize>,
+    output: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let keymap = read_keymap(keyboard_toml_path)?;
+    let layers: Vec<(usize, &Vec<Vec<String>>)> = match layer {
+        Some(layer) => vec![(
+            layer,
+            keymap.get(layer).ok_or_else(|| {
+                format!(
+                    "Layer {} is out of range, the keyboard has {} layers",
+                    layer,
+                    keymap.len()
+                )
+            })?,
+        )],
+        None => keymap.iter().enumerate().collect(),
+    };
+
+    let extension = output
+        .and_then(|o| Path::new(o).extension())
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    let rendered = match extension.as_deref() {
+        Some("svg") | Some("html") | Some("htm") => {
+            let keys = placed_keys(keyboard_toml_path, vial_json_path, &keymap)?;
+            let svg = svg(&layers, &keys);
+            if extension.as_deref() == Some("svg") {
+                svg
+            } else {
+                html(keyboard_toml_path, &svg)
+            }
+        }
+        _ => ascii(&layers),
+    };
+
+    match output {
+        Some(output) => {
+            fs::write(output, rendered)?;
+            println!(
+                "✅ Rendered the keymap of {} to {}",
+                keyboard_toml_path, output
+            );
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+/// Render every layer as a table of the matrix, `No` keys are left empty
+fn ascii(layers: &[(usize, &Vec<Vec<String>>)]) -> String {
+    let mut out = String::new();
+    for (index, layer) in layers {
+        let label = |key: &String| -> String {
+            if key.eq_ignore_ascii_case(NO_KEY) {
+                String::new()
+            } else if key.chars().count() > MAX_ASCII_WIDTH {
+                let cut: String = key.chars().take(MAX_ASCII_WIDTH - 1).collect();
+                format!("{}~", cut)
+            } else {
+                key.clone()
+            }
+        };
+        let width = layer
+            .iter()
+            .flatten()
+            .map(|k| label(k).chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let cols = layer.iter().map(|r| r.len()).max().unwrap_or(0);
+        let border = format!("+{}\n", format!("{}+", "-".repeat(width + 2)).repeat(cols));
+
+        out.push_str(&format!("Layer {}\n", index));
+        out.push_str(&border);
+        for row in layer.iter() {
+            out.push('|');
+            for key in row {
+                out.push_str(&format!(" {:<width$} |", label(key), width = width));
+            }
+            out.push('\n');
+            out.push_str(&border);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Get the keys of the layout in vial.json, or of the matrix positions which aren't `No` in every layer
+fn placed_keys(
+    keyboard_toml_path: &str,
+    vial_json_path: Option<&str>,
+    keymap: &[Vec<Vec<String>>],
+) -> Result<Vec<PlacedKey>, Box<dyn Error>> {
+    let vial_json_path = match vial_json_path {
+        Some(path) => Some(path.into()),
+        None => {
+            Some(Path::new(keyboard_toml_path).with_file_name("vial.json")).filter(|p| p.exists())
+        }
+    };
+    if let Some(path) = vial_json_path {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let vial: Value = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        let rows = vial["layouts"]["keymap"]
+            .as_array()
+            .ok_or_else(|| format!("No layouts.keymap in {}", path.display()))?;
+        let (keys, rotated) = place_keys(rows);
+        if rotated > 0 {
+            println!(
+                "⚠️  Rotated keys of {} are drawn without rotation",
+                path.display()
+            );
+        }
+        return Ok(keys);
+    }
+
+    let Some(first) = keymap.first() else {
+        return Ok(Vec::new());
+    };
+    let mut keys = Vec::new();
+    for (row, keys_of_row) in first.iter().enumerate() {
+        for col in 0..keys_of_row.len() {
+            let is_key = keymap
+                .iter()
+                .any(|layer| !layer[row][col].eq_ignore_ascii_case(NO_KEY));
+            if is_key {
+                keys.push(PlacedKey {
+                    row,
+                    col,
+                    x: col as f64,
+                    y: row as f64,
+                    w: 1.0,
+                    h: 1.0,
+                });
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// Render every layer as a group of key caps in one SVG image, layers are stacked vertically
+fn svg(layers: &[(usize, &Vec<Vec<String>>)], keys: &[PlacedKey]) -> String {
+    let width = keys.iter().map(|k| k.x + k.w).fold(0.0, f64::max) * UNIT;
+    let layer_height = keys.iter().map(|k| k.y + k.h).fold(0.0, f64::max) * UNIT + TITLE_HEIGHT;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\">\n",
+        w = width + 8.0,
+        h = layer_height * layers.len() as f64 + 8.0
+    );
+    for (i, (index, layer)) in layers.iter().enumerate() {
+        let top = i as f64 * layer_height + 4.0;
+        svg.push_str(&format!(
+            "  <g transform=\"translate(4 {})\">\n    <text x=\"0\" y=\"18\" font-size=\"16\" font-weight=\"bold\">Layer {}</text>\n",
+            top, index
+        ));
+        for key in keys {
+            let label = layer
+                .get(key.row)
+                .and_then(|r| r.get(key.col))
+                .map(|k| k.as_str())
+                .unwrap_or_default();
+            let (x, y) = (key.x * UNIT, key.y * UNIT + TITLE_HEIGHT);
+            let (w, h) = (key.w * UNIT - 4.0, key.h * UNIT - 4.0);
+            let fill = match label {
+                "_" => "#f4f4f4",
+                key if key.eq_ignore_ascii_case(NO_KEY) => "#e0e0e0",
+                _ => "#ffffff",
+            };
+            svg.push_str(&format!(
+                "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"5\" fill=\"{}\" stroke=\"#888\"/>\n",
+                x, y, w, h, fill
+            ));
+            let text = match label {
+                "_" => "▽".to_string(),
+                key if key.eq_ignore_ascii_case(NO_KEY) => String::new(),
+                key => escape(key),
+            };
+            if text.is_empty() {
+                continue;
+            }
+            // Shrink long labels to fit the key cap
+            let chars = label.chars().count() as f64;
+            let font_size = (w / chars * 1.6).clamp(6.0, 12.0);
+            svg.push_str(&format!(
+                "    <text x=\"{}\" y=\"{}\" font-size=\"{:.1}\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}</text>\n",
+                x + w / 2.0,
+                y + h / 2.0,
+                font_size,
+                text
+            ));
+        }
+        svg.push_str("  </g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Wrap the SVG rendering in a static HTML page
+fn html(keyboard_toml_path: &str, svg: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Keymap of {path}</title>\n</head>\n<body>\n<h1>Keymap of {path}</h1>\n{svg}</body>\n</html>\n",
+        path = escape(keyboard_toml_path),
+        svg = svg
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
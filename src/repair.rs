@@ -0,0 +1,512 @@
+use crate::prompt;
+use inquire::Confirm;
+use rmkit_core::chip::{get_board_chip_map, get_chip_target, ChipTarget};
+use rmkit_core::interrupt;
+use rmkit_core::keyboard_toml::read_keyboard_config;
+use rmkit_core::target_config::{format_size, linker_size, region_line};
+use rmkit_core::{Classify, FailureKind};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Max number of check/fix rounds, avoid looping forever on a fix which doesn't work
+const MAX_REPAIR_ROUNDS: usize = 8;
+
+/// Classified failure of a generated project
+#[derive(Debug, PartialEq)]
+enum Failure {
+    /// Compilation target of the chip is not installed
+    MissingTarget(String),
+    /// Features enabled in Cargo.toml which don't exist in the dependency
+    UnknownFeatures {
+        dependency: String,
+        features: Vec<String>,
+    },
+    /// Version requirement which doesn't match any published version
+    VersionMismatch {
+        dependency: String,
+        candidate: String,
+    },
+    /// Firmware doesn't fit into a memory region
+    MemoryOverflow { region: String },
+    /// Error which can't be repaired automatically
+    Unknown(String),
+}
+
+/// Check the project in `project_dir`, classify the first error and offer fixes interactively
+///
+/// `build` runs `cargo build --release` instead of `cargo check`, which is needed to catch linker errors such as memory overflow.
+/// `yes` applies the fixes without asking.
+pub(crate) fn repair_project(
+    project_dir: Option<String>,
+    build: bool,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    let project_dir = PathBuf::from(project_dir.unwrap_or_else(|| ".".to_string()));
+    if !project_dir.join("Cargo.toml").exists() {
+        return Err(format!("No Cargo.toml found in {}", project_dir.display()).into());
+    }
+
+    for _ in 0..MAX_REPAIR_ROUNDS {
+        let subcommand = if build { "build" } else { "check" };
+        println!("🔍 Running cargo {}...", subcommand);
+        let mut cmd = Command::new("cargo");
+        cmd.arg(subcommand).current_dir(&project_dir);
+        if build {
+            cmd.arg("--release");
+        }
+        let output = tracing::info_span!("cargo", command = subcommand)
+            .in_scope(|| interrupt::output(&mut cmd))
+            .or_fail(FailureKind::Toolchain)?;
+        if output.status.success() {
+            println!("✅ Project builds successfully");
+            return Ok(());
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let failure = classify_failure(&stderr);
+        let fix = match &failure {
+            Failure::MissingTarget(target) => {
+                format!("Install compilation target `{}` with rustup", target)
+            }
+            Failure::UnknownFeatures {
+                dependency,
+                features,
+            } => format!(
+                "Remove features {} which don't exist in `{}` from Cargo.toml",
+                features.join(", "),
+                dependency
+            ),
+            Failure::VersionMismatch {
+                dependency,
+                candidate,
+            } => format!(
+                "Pin `{}` to the available version {} in Cargo.toml",
+                dependency, candidate
+            ),
+            Failure::MemoryOverflow { region } => {
+                let (chip, grown) = grow_project_memory(&project_dir, region)?;
+                // Growing memory.x can't help, the firmware is too large for the chip
+                if grown.is_none() {
+                    eprintln!("{}", stderr);
+                    return Err(format!(
+                        "Firmware overflows {}, which already reaches the end of {} of {}, make it smaller, e.g. by disabling rmk features",
+                        region, region, chip
+                    ))
+                    .or_fail(FailureKind::Build);
+                }
+                format!(
+                    "Firmware overflows {}, grow it in memory.x up to the end of {} of {}, keeping its origin",
+                    region, region, chip
+                )
+            }
+            Failure::Unknown(error) => {
+                eprintln!("{}", stderr);
+                return Err(format!(
+                    "Unable to repair the project automatically: {}",
+                    error
+                ))
+                .or_fail(FailureKind::Build);
+            }
+        };
+
+        println!("🚨 cargo {} failed", subcommand);
+        if yes {
+            println!("🔧 {}", fix);
+        } else {
+            prompt::ensure_interactive("--yes")?;
+            if !Confirm::new(&format!("{}?", fix))
+                .with_default(true)
+                .prompt()?
+            {
+                return Err("Repair cancelled".into());
+            }
+        }
+        apply_fix(&project_dir, failure)?;
+    }
+
+    Err(format!(
+        "Project still fails after {} repair rounds",
+        MAX_REPAIR_ROUNDS
+    ))
+    .or_fail(FailureKind::Build)
+}
+
+/// Classify the first error in cargo's stderr output
+fn classify_failure(stderr: &str) -> Failure {
+    // error[E0463]: can't find crate for `core`
+    // = note: the `thumbv7em-none-eabihf` target may not be installed
+    if let Some(target) = stderr
+        .lines()
+        .find_map(|l| between(l, "the `", "` target may not be installed"))
+    {
+        return Failure::MissingTarget(target.to_string());
+    }
+
+    // the package `kb` depends on `rmk`, with features: `foo` but `rmk` does not have these features.
+    if let Some(line) = stderr
+        .lines()
+        .find(|l| l.contains("depends on `") && l.contains("does not have"))
+    {
+        if let Some(dependency) = between(line, "depends on `", "`") {
+            let features_part = line
+                .split_once("feature")
+                .map(|(_, rest)| rest.split(" but ").next().unwrap_or(rest))
+                .unwrap_or("");
+            let features: Vec<String> = features_part
+                .split('`')
+                .skip(1)
+                .step_by(2)
+                .map(|f| f.to_string())
+                .collect();
+            if !features.is_empty() {
+                return Failure::UnknownFeatures {
+                    dependency: dependency.to_string(),
+                    features,
+                };
+            }
+        }
+    }
+
+    // error: failed to select a version for the requirement `rmk = "^0.99"`
+    // candidate versions found which didn't match: 0.8.2, 0.8.1, 0.8.0, ...
+    if let Some(requirement) = between(
+        stderr,
+        "failed to select a version for the requirement `",
+        "`",
+    ) {
+        let dependency = requirement.split('=').next().unwrap_or("").trim();
+        let candidate = stderr
+            .lines()
+            .find_map(|l| l.split_once("didn't match: "))
+            .and_then(|(_, versions)| versions.split(", ").next())
+            .map(|v| v.trim().to_string());
+        if let Some(candidate) = candidate {
+            return Failure::VersionMismatch {
+                dependency: dependency.to_string(),
+                candidate,
+            };
+        }
+    }
+
+    // rust-lld: error: section '.text' will not fit in region 'FLASH': overflowed by 1234 bytes
+    // arm-none-eabi-ld: region `FLASH' overflowed by 1234 bytes
+    if let Some(region) = stderr.lines().find_map(|l| {
+        between(l, "will not fit in region '", "'")
+            .or_else(|| between(l, "region `", "' overflowed"))
+    }) {
+        return Failure::MemoryOverflow {
+            region: region.to_string(),
+        };
+    }
+
+    let first_error = stderr
+        .lines()
+        .find(|l| l.starts_with("error"))
+        .unwrap_or("unknown error");
+    Failure::Unknown(first_error.to_string())
+}
+
+/// Get the text between `start` and the following `end`
+fn between<'a>(text: &'a str, start: &str, end: &str) -> Option<&'a str> {
+    let (_, rest) = text.split_once(start)?;
+    rest.split_once(end).map(|(inner, _)| inner)
+}
+
+fn apply_fix(project_dir: &Path, failure: Failure) -> Result<(), Box<dyn Error>> {
+    let cargo_toml_path = project_dir.join("Cargo.toml");
+    match failure {
+        Failure::MissingTarget(target) => {
+            let status = interrupt::status(Command::new("rustup").args(["target", "add", &target]))
+                .or_fail(FailureKind::Toolchain)?;
+            if !status.success() {
+                return Err(format!("Failed to install target {}", target))
+                    .or_fail(FailureKind::Toolchain);
+            }
+        }
+        Failure::UnknownFeatures {
+            dependency,
+            features,
+        } => {
+            let mut manifest = cargo_toml::Manifest::from_path(&cargo_toml_path)?;
+            if let Some(cargo_toml::Dependency::Detailed(dep)) =
+                manifest.dependencies.get_mut(&dependency)
+            {
+                dep.features.retain(|f| !features.contains(f));
+            } else {
+                return Err(
+                    format!("No detailed {} dependency found in Cargo.toml", dependency).into(),
+                );
+            }
+            fs::write(&cargo_toml_path, toml::to_string(&manifest)?)?;
+        }
+        Failure::VersionMismatch {
+            dependency,
+            candidate,
+        } => {
+            let mut manifest = cargo_toml::Manifest::from_path(&cargo_toml_path)?;
+            match manifest.dependencies.get_mut(&dependency) {
+                Some(cargo_toml::Dependency::Detailed(dep)) => dep.version = Some(candidate),
+                Some(dep @ cargo_toml::Dependency::Simple(_)) => {
+                    *dep = cargo_toml::Dependency::Simple(candidate)
+                }
+                _ => {
+                    return Err(format!(
+                        "No {} dependency with version found in Cargo.toml",
+                        dependency
+                    )
+                    .into())
+                }
+            }
+            fs::write(&cargo_toml_path, toml::to_string(&manifest)?)?;
+        }
+        Failure::MemoryOverflow { region } => {
+            if let (chip, Some(memory_x)) = grow_project_memory(project_dir, &region)? {
+                fs::write(project_dir.join("memory.x"), memory_x)?;
+                println!(
+                    "🔧 Grew {} in memory.x to the end of {} of {}",
+                    region, region, chip
+                );
+            }
+        }
+        Failure::Unknown(_) => {}
+    }
+    Ok(())
+}
+
+/// Get the chip of the project from keyboard.toml
+fn project_chip(project_dir: &Path) -> Result<String, Box<dyn Error>> {
+    let config = read_keyboard_config(project_dir.join("keyboard.toml"))?;
+    let keyboard_field = |key: &str| {
+        config
+            .get("keyboard")
+            .and_then(|k| k.get(key))
+            .and_then(|v| v.as_str())
+    };
+    let chip = keyboard_field("chip")
+        .or_else(|| keyboard_field("board").and_then(|b| get_board_chip_map().get(b).copied()))
+        .ok_or("No chip or board found in keyboard.toml")?;
+    Ok(chip.to_string())
+}
+
+/// Grow `region` in memory.x of the project up to its end on the chip, returning the chip and the grown memory.x
+///
+/// The grown memory.x is `None` if the region already reaches the end.
+fn grow_project_memory(
+    project_dir: &Path,
+    region: &str,
+) -> Result<(String, Option<String>), Box<dyn Error>> {
+    let chip = project_chip(project_dir)?;
+    let target = get_chip_target(&chip)
+        .filter(|t| !t.memory.is_empty())
+        .ok_or(format!(
+            "{} has no memory layout in the chip database to grow memory.x with",
+            chip
+        ))?;
+    let memory_x_path = project_dir.join("memory.x");
+    let memory_x = fs::read_to_string(&memory_x_path)
+        .map_err(|e| format!("Failed to read {}: {}", memory_x_path.display(), e))?;
+    let grown = grow_memory_region(&memory_x, target, region)?;
+    Ok((chip, grown))
+}
+
+/// Grow LENGTH of `region` in memory.x up to the end of the region on the chip, `None` if it already reaches it
+///
+/// ORIGIN is kept, boards with a bootloader have the firmware at an offset, e.g. after the SoftDevice of nRF52.
+fn grow_memory_region(
+    memory_x: &str,
+    target: &ChipTarget,
+    region: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let chip_region = target
+        .memory
+        .iter()
+        .find(|r| r.name == region)
+        .ok_or(format!("{} isn't in the memory layout of the chip", region))?;
+    let chip_end = linker_size(&chip_region.length)
+        .map(|length| u64::from(chip_region.origin) + length)
+        .ok_or(format!(
+            "Invalid length {} of {}",
+            chip_region.length, region
+        ))?;
+
+    let mut found = false;
+    let mut grown = false;
+    let mut lines = Vec::new();
+    for line in memory_x.lines() {
+        let Some((origin, length)) = region_line(line, region) else {
+            lines.push(line.to_string());
+            continue;
+        };
+        found = true;
+        let (Some(start), Some(size)) = (linker_size(origin), linker_size(&line[length.clone()]))
+        else {
+            return Err(
+                format!("Can't read the ORIGIN and LENGTH of {} in memory.x", region).into(),
+            );
+        };
+        let max_size = chip_end.saturating_sub(start);
+        if max_size > size {
+            grown = true;
+            lines.push(format!(
+                "{}{}{}",
+                &line[..length.start],
+                format_size(max_size),
+                &line[length.end..]
+            ));
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    if !found {
+        return Err(format!("memory.x has no {} region", region).into());
+    }
+    let mut memory_x_grown = lines.join("\n");
+    if memory_x.ends_with('\n') {
+        memory_x_grown.push('\n');
+    }
+    Ok(grown.then_some(memory_x_grown))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_target_is_classified() {
+        let stderr = "   Compiling defmt v0.3.10
+warning: the `defmt-rtt` crate is deprecated, use `defmt-rtt` 0.4 instead
+error[E0463]: can't find crate for `core`
+  |
+  = note: the `thumbv7em-none-eabihf` target may not be installed
+  = help: consider downloading the target with `rustup target add thumbv7em-none-eabihf`
+
+For more information about this error, try `rustc --explain E0463`.
+error: could not compile `defmt` (lib) due to 1 previous error
+";
+        assert_eq!(
+            classify_failure(stderr),
+            Failure::MissingTarget("thumbv7em-none-eabihf".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_features_are_classified() {
+        let stderr = "    Updating crates.io index
+error: failed to select a version for `rmk`.
+    ... required by package `my-keyboard v0.1.0 (/home/user/my-keyboard)`
+versions that meet the requirements `^0.7` are: 0.7.4, 0.7.3, 0.7.2, 0.7.1, 0.7.0
+
+the package `my-keyboard` depends on `rmk`, with features: `async_matrix_v2` but `rmk` does not have these features.
+
+
+failed to select a version for `rmk` which could resolve this conflict
+";
+        assert_eq!(
+            classify_failure(stderr),
+            Failure::UnknownFeatures {
+                dependency: "rmk".to_string(),
+                features: vec!["async_matrix_v2".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn version_mismatch_is_classified() {
+        let stderr = "    Updating crates.io index
+error: failed to select a version for the requirement `rmk = \"^0.99\"`
+candidate versions found which didn't match: 0.7.4, 0.7.3, 0.7.2, 0.7.1, 0.7.0, 0.6.1, ...
+location searched: crates.io index
+required by package `my-keyboard v0.1.0 (/home/user/my-keyboard)`
+";
+        assert_eq!(
+            classify_failure(stderr),
+            Failure::VersionMismatch {
+                dependency: "rmk".to_string(),
+                candidate: "0.7.4".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn memory_overflow_is_classified() {
+        let lld = "error: linking with `rust-lld` failed: exit status: 1
+  |
+  = note: LC_ALL=\"C\" \"rust-lld\" \"-flavor\" \"gnu\" \"-Tlink.x\" \"-Tdefmt.x\"
+  = note: rust-lld: error: section '.text' will not fit in region 'FLASH': overflowed by 12840 bytes
+          rust-lld: error: section '.rodata' will not fit in region 'FLASH': overflowed by 15236 bytes
+
+error: could not compile `my-keyboard` (bin \"central\") due to 1 previous error
+";
+        let gnu_ld = "error: linking with `flip-link` failed: exit status: 1
+  |
+  = note: arm-none-eabi-ld: my-keyboard section `.bss' will not fit in region `RAM'
+          arm-none-eabi-ld: region `RAM' overflowed by 4096 bytes
+";
+        for (stderr, region) in [(lld, "FLASH"), (gnu_ld, "RAM")] {
+            assert_eq!(
+                classify_failure(stderr),
+                Failure::MemoryOverflow {
+                    region: region.to_string()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn memory_is_grown_from_its_origin() {
+        let target = ChipTarget {
+            target: "thumbv7em-none-eabihf".to_string(),
+            formats: Vec::new(),
+            default_format: None,
+            probe_rs_chip: "nRF52840_xxAA".to_string(),
+            memory: ["FLASH", "RAM"]
+                .into_iter()
+                .zip([(0x0000_0000, "1024K"), (0x2000_0000, "256K")])
+                .map(|(name, (origin, length))| rmkit_core::chip::MemoryRegion {
+                    name: name.to_string(),
+                    origin,
+                    length: length.to_string(),
+                })
+                .collect(),
+        };
+        // nice!nano: the SoftDevice and the bootloader are before the firmware
+        let memory_x = "MEMORY
+{
+    FLASH (rx) : ORIGIN = 0x00027000, LENGTH = 600K /* after the SoftDevice */
+    RAM : ORIGIN = 0x20020000, LENGTH = 0x10000
+}
+";
+        assert_eq!(
+            grow_memory_region(memory_x, &target, "FLASH")
+                .unwrap()
+                .unwrap(),
+            memory_x.replace("600K", "868K")
+        );
+        assert_eq!(
+            grow_memory_region(memory_x, &target, "RAM")
+                .unwrap()
+                .unwrap(),
+            memory_x.replace("0x10000", "128K")
+        );
+
+        let full = memory_x.replace("600K", "1024K - 0x27000");
+        assert_eq!(grow_memory_region(&full, &target, "FLASH").unwrap(), None);
+        assert!(grow_memory_region(memory_x, &target, "BOOT2").is_err());
+    }
+
+    #[test]
+    fn other_errors_are_unknown() {
+        let stderr = "error[E0433]: failed to resolve: use of undeclared crate or module `rmk`
+ --> src/main.rs:5:5
+";
+        assert_eq!(
+            classify_failure(stderr),
+            Failure::Unknown(
+                "error[E0433]: failed to resolve: use of undeclared crate or module `rmk`"
+                    .to_string()
+            )
+        );
+    }
+}
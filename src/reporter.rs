@@ -0,0 +1,92 @@
+use crate::conflict::ask_resolution;
+use crate::prompt;
+use inquire::Confirm;
+use rmkit_core::{Event, Reporter, Resolution};
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Reporter of the terminal, events are printed and questions are prompted
+pub(crate) struct Terminal;
+
+impl Reporter for Terminal {
+    fn report(&mut self, event: Event) {
+        match event {
+            Event::Template(message) => println!("📌 {}", message),
+            Event::Download(message) => println!("📦 {}", message),
+            Event::Generate(message) => println!("🔧 {}", message),
+            Event::Build(message) => println!("🔨 {}", message),
+            Event::Flash(message) => println!("⚡ {}", message),
+            Event::Info(message) => println!("ℹ️  {}", message),
+            Event::Warning(message) => eprintln!("⚠️  {}", message),
+            Event::Success(message) => println!("✅ {}", message),
+            Event::Progress { step, done, total } => {
+                print!("\r⏳ {} {}/{}", step, done, total);
+                if done == total {
+                    println!();
+                }
+                let _ = io::stdout().flush();
+            }
+            event => println!("{:?}", event),
+        }
+    }
+
+    fn resolve_conflict(
+        &mut self,
+        path: &Path,
+        existing: &[u8],
+        template: &[u8],
+    ) -> Result<Resolution, Box<dyn Error>> {
+        ask_resolution(path, existing, template)
+    }
+
+    fn join_workspace(
+        &mut self,
+        project_dir: &Path,
+        workspace_dir: &Path,
+    ) -> Result<bool, Box<dyn Error>> {
+        if !prompt::is_interactive() {
+            println!(
+                "ℹ️  Not running in an interactive terminal, {} is not added to the workspace {}",
+                project_dir.display(),
+                workspace_dir.display()
+            );
+            return Ok(false);
+        }
+        Ok(Confirm::new(&format!(
+            "{} is inside the Cargo workspace {}, add it as a workspace member?",
+            project_dir.display(),
+            workspace_dir.display()
+        ))
+        .with_default(true)
+        .prompt()?)
+    }
+}
+
+/// Reporter of the terminal for builds, which also installs missing compilation targets
+///
+/// Targets are installed after asking, or without asking in a non-interactive environment, unless `no_install` is set.
+pub(crate) struct BuildTerminal {
+    pub(crate) no_install: bool,
+}
+
+impl Reporter for BuildTerminal {
+    fn report(&mut self, event: Event) {
+        Terminal.report(event)
+    }
+
+    fn install_target(&mut self, target: &str) -> Result<bool, Box<dyn Error>> {
+        if self.no_install {
+            return Ok(false);
+        }
+        if !prompt::is_interactive() {
+            return Ok(true);
+        }
+        Ok(Confirm::new(&format!(
+            "Compilation target {} isn't installed, install it with rustup?",
+            target
+        ))
+        .with_default(true)
+        .prompt()?)
+    }
+}
@@ -0,0 +1,164 @@
+use crate::prompt;
+use crate::reporter::{BuildTerminal, Terminal};
+use crate::size;
+use inquire::{Confirm, Select};
+use rmkit_core::chip::{get_board_info, get_uf2_family, Bootloader};
+use rmkit_core::firmware::{self, flash_image, release_dir, write_uf2, Binary, CargoArgs, Uf2Tags};
+use rmkit_core::keyboard_toml::read_keyboard_config;
+use rmkit_core::{Classify, FailureKind};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Build the firmware, flash it and attach to its log output with the runner of `.cargo/config.toml`
+///
+/// Split keyboards have a binary per half, `bin` flashes and attaches to one of them. Without it, the other
+/// halves are flashed with probe-rs first, asking to connect the probe to each, then the central is attached.
+/// Halves of boards with a UF2 bootloader are written as UF2 files instead, to be copied to the board's drive.
+pub(crate) fn run_firmware(
+    project_dir: Option<String>,
+    bin: Option<String>,
+    no_install: bool,
+    cargo_args: CargoArgs,
+) -> Result<(), Box<dyn Error>> {
+    let project_dir = PathBuf::from(project_dir.unwrap_or_else(|| ".".to_string()));
+    let cargo_args = cargo_args
+        .with_keyboard_toml(&project_dir)?
+        .with_rmk_features(&project_dir)?;
+    let binaries = firmware::build(&project_dir, &cargo_args, &mut BuildTerminal { no_install })?;
+    for binary in &binaries {
+        size::report_usage(&project_dir, &binary.name, &binary.elf)?;
+    }
+    let bins: Vec<String> = binaries.iter().map(|b| b.name.clone()).collect();
+
+    let attached = match bin {
+        Some(bin) if bins.contains(&bin) => bin,
+        Some(bin) => {
+            return Err(format!("No binary {}, the project has {}", bin, bins.join(", ")).into())
+        }
+        None if bins.len() == 1 => bins[0].clone(),
+        None => {
+            prompt::ensure_interactive("--bin")?;
+            let attached = match bins.iter().find(|b| *b == "central") {
+                Some(central) => central.clone(),
+                None => Select::new("Which binary to attach to?", bins.clone())
+                    .prompt()?
+                    .to_string(),
+            };
+            // Boards with a UF2 bootloader get the halves as UF2 files, the others are flashed with the probe
+            let uf2_board = uf2_board(&project_dir);
+            if uf2_board.is_none() {
+                // Fail before asking to connect the probe if the halves can't be flashed
+                firmware::probe_rs_chip(&project_dir)?;
+            }
+            for other in binaries.iter().filter(|b| b.name != attached) {
+                if let Some((board, family)) = &uf2_board {
+                    copy_uf2(other, board, *family).or_fail(FailureKind::Flash)?;
+                    continue;
+                }
+                if !Confirm::new(&format!("Connect the probe to {}, flash it?", other.name))
+                    .with_default(true)
+                    .prompt()?
+                {
+                    println!("ℹ️  Skipped {}", other.name);
+                    continue;
+                }
+                firmware::flash(&project_dir, other, &mut Terminal)?;
+            }
+            println!("🔌 Connect the probe to {}", attached);
+            Confirm::new("Ready?").with_default(true).prompt()?;
+            attached
+        }
+    };
+
+    println!(
+        "⚡ Flashing and attaching to {}, press Ctrl-C to stop",
+        attached
+    );
+    firmware::cargo_with(
+        &project_dir,
+        &["run", "--release", "--bin", &attached],
+        &cargo_args,
+    )
+}
+
+/// Get the board of the project and its UF2 family id, if the board has a UF2 bootloader
+fn uf2_board(project_dir: &Path) -> Option<(String, u32)> {
+    let config = read_keyboard_config(project_dir.join("keyboard.toml")).ok()?;
+    let board = config.get("keyboard")?.get("board")?.as_str()?;
+    if get_board_info(board)?.bootloader != Some(Bootloader::Uf2) {
+        return None;
+    }
+    Some((board.to_string(), get_uf2_family(board)?))
+}
+
+/// Write a binary as UF2 next to its ELF, and ask to copy it to the drive of the board in bootloader mode
+fn copy_uf2(binary: &Binary, board: &str, family: u32) -> Result<(), Box<dyn Error>> {
+    let path = binary.elf.with_extension("uf2");
+    let (base, image) = flash_image(&fs::read(&binary.elf)?)?;
+    let tags = Uf2Tags {
+        board: Some(board.to_string()),
+        ..Default::default()
+    };
+    let file =
+        File::create(&path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    write_uf2(
+        BufWriter::new(file),
+        family,
+        &tags,
+        |on_data| Ok(on_data(base, &image)?),
+        &mut Terminal,
+    )?;
+    println!("📦 Wrote {}", path.display());
+    prompt::ensure_interactive("--bin")?;
+    if !Confirm::new(&format!(
+        "Double-tap reset of the {} of {} and copy {} to its drive, done?",
+        board,
+        binary.name,
+        path.display()
+    ))
+    .with_default(true)
+    .prompt()?
+    {
+        println!("ℹ️  Skipped {}", binary.name);
+    }
+    Ok(())
+}
+
+/// Get a binary of the project and its ELF from the release build, which must be built already
+///
+/// Without `bin`, the only binary or the central of a split keyboard is used, otherwise it's asked with `question`.
+pub(crate) fn release_elf(
+    project_dir: &Path,
+    bin: Option<String>,
+    question: &str,
+) -> Result<(String, PathBuf), Box<dyn Error>> {
+    let cargo_toml_path = project_dir.join("Cargo.toml");
+    if !cargo_toml_path.exists() {
+        return Err(format!("No Cargo.toml found in {}", project_dir.display()).into());
+    }
+    let bins: Vec<String> = cargo_toml::Manifest::from_path(&cargo_toml_path)?
+        .bin
+        .into_iter()
+        .filter_map(|b| b.name)
+        .collect();
+    let bin = match bin {
+        Some(bin) => bin,
+        None if bins.len() == 1 => bins[0].clone(),
+        None if bins.iter().any(|b| b == "central") => "central".to_string(),
+        None => {
+            prompt::ensure_interactive("--bin")?;
+            Select::new(question, bins).prompt()?
+        }
+    };
+    let elf = release_dir(project_dir, &project_dir.join("target")).join(&bin);
+    if !elf.exists() {
+        return Err(format!(
+            "{} isn't built, run `rmkit run` or `cargo build --release` first",
+            elf.display()
+        )
+        .into());
+    }
+    Ok((bin, elf))
+}
@@ -0,0 +1,334 @@
+use crate::run::release_elf;
+use object::{Object, ObjectSection, ObjectSymbol, SectionKind, SymbolKind};
+use rmkit_core::chip::{get_board_chip_map, get_chip_target, ChipTarget};
+use rmkit_core::keyboard_toml::read_keyboard_config;
+use rmkit_core::target_config::{linker_size, memory_region};
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Size of the flash sectors reserved for storage, if keyboard.toml doesn't place it with `start_addr`
+const STORAGE_SECTOR_SIZE: u64 = 4096;
+
+/// Dependencies which are only used by a subsystem of rmk, their size is counted for the subsystem
+const SUBSYSTEM_CRATES: &[(&str, &str)] = &[
+    ("trouble_host", "ble"),
+    ("bt_hci", "ble"),
+    ("nrf_sdc", "ble"),
+    ("nrf_mpsl", "ble"),
+    ("esp_radio", "ble"),
+    ("esp_wifi", "ble"),
+    ("cyw43", "ble"),
+    ("sequential_storage", "storage"),
+    ("embassy_usb", "usb"),
+    ("usbd_hid", "usb"),
+    ("defmt", "defmt"),
+    ("defmt_rtt", "defmt"),
+];
+
+/// Print the flash and RAM usage of the release build, by crate, rmk subsystem and function
+///
+/// Subsystems are the modules of rmk, such as `ble`, `split` or `storage`, together with the dependencies only
+/// they use, which shows what enabling a feature costs.
+///
+/// With `json`, the usage, the memory of the chip and the tables are printed as JSON instead.
+pub(crate) fn print_size(
+    project_dir: Option<String>,
+    bin: Option<String>,
+    top: usize,
+    json: bool,
+) -> Result<(), Box<dyn Error>> {
+    let project_dir = PathBuf::from(project_dir.unwrap_or_else(|| ".".to_string()));
+    let (bin, elf) = release_elf(&project_dir, bin, "Which binary to analyze?")?;
+    let data = fs::read(&elf)?;
+    let file = object::File::parse(&*data)
+        .map_err(|e| format!("Failed to read {}: {}", elf.display(), e))?;
+    let (flash, ram) = memory_usage(&file);
+    let limits = project_memory_limits(&project_dir);
+
+    let mut crates: HashMap<String, u64> = HashMap::new();
+    let mut subsystems: HashMap<String, u64> = HashMap::new();
+    let mut functions: HashMap<String, u64> = HashMap::new();
+    for symbol in file.symbols() {
+        let in_flash = symbol
+            .section_index()
+            .and_then(|i| file.section_by_index(i).ok())
+            .is_some_and(|s| s.kind() != SectionKind::UninitializedData);
+        if symbol.size() == 0 || !in_flash {
+            continue;
+        }
+        let name = format!("{:#}", rustc_demangle::demangle(symbol.name()?));
+        let krate = symbol_crate(&name);
+        *crates.entry(krate.to_string()).or_default() += symbol.size();
+        if let Some(subsystem) = subsystem(&name, krate) {
+            *subsystems.entry(subsystem.to_string()).or_default() += symbol.size();
+        }
+        if symbol.kind() == SymbolKind::Text {
+            *functions.entry(name).or_default() += symbol.size();
+        }
+    }
+
+    if json {
+        let top_entries = |sizes: &HashMap<String, u64>| -> serde_json::Value {
+            let mut sizes: Vec<(&String, &u64)> = sizes.iter().collect();
+            sizes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            sizes
+                .into_iter()
+                .take(top)
+                .map(|(name, size)| json!({ "name": name, "size": size }))
+                .collect()
+        };
+        let report = json!({
+            "bin": bin,
+            "flash": flash,
+            "ram": ram,
+            "flash_size": limits.as_ref().map(|l| l.flash),
+            "ram_size": limits.as_ref().map(|l| l.ram),
+            "storage": limits.as_ref().map(|l| l.storage),
+            "crates": top_entries(&crates),
+            "subsystems": top_entries(&subsystems),
+            "functions": top_entries(&functions),
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    print_usage(&bin, flash, ram, limits.as_ref());
+    print_table("Crates", &crates, flash, top);
+    print_table("rmk subsystems", &subsystems, flash, top);
+    print_table("Functions", &functions, flash, top);
+    Ok(())
+}
+
+/// Print the flash and RAM usage of a binary of the project after a build, warning if it doesn't fit the chip
+pub(crate) fn report_usage(
+    project_dir: &Path,
+    bin: &str,
+    elf: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let data = fs::read(elf)?;
+    let file = object::File::parse(&*data)
+        .map_err(|e| format!("Failed to read {}: {}", elf.display(), e))?;
+    let (flash, ram) = memory_usage(&file);
+    print_usage(bin, flash, ram, project_memory_limits(project_dir).as_ref());
+    Ok(())
+}
+
+/// Get the flash and RAM used by an ELF
+///
+/// Initialized data is stored in flash and copied to RAM at startup.
+fn memory_usage(file: &object::File) -> (u64, u64) {
+    let (mut flash, mut ram) = (0, 0);
+    for section in file.sections() {
+        match section.kind() {
+            SectionKind::Text | SectionKind::ReadOnlyData | SectionKind::ReadOnlyString => {
+                flash += section.size()
+            }
+            SectionKind::Data => {
+                flash += section.size();
+                ram += section.size();
+            }
+            SectionKind::UninitializedData => ram += section.size(),
+            _ => {}
+        }
+    }
+    (flash, ram)
+}
+
+/// Flash and RAM of the chip which the firmware can use
+#[derive(Debug, PartialEq)]
+struct MemoryLimits {
+    /// LENGTH of FLASH
+    flash: u64,
+    /// LENGTH of RAM
+    ram: u64,
+    /// Flash at the end of FLASH which is reserved for the storage of rmk
+    storage: u64,
+}
+
+/// Get the memory limits of the project, from its memory.x or the memory layout of its chip
+fn project_memory_limits(project_dir: &Path) -> Option<MemoryLimits> {
+    let config = read_keyboard_config(project_dir.join("keyboard.toml")).ok()?;
+    let keyboard = config.get("keyboard")?;
+    let chip = keyboard.get("chip").and_then(|c| c.as_str()).or_else(|| {
+        let board = keyboard.get("board")?.as_str()?;
+        get_board_chip_map().get(board).copied()
+    });
+    let memory_x = fs::read_to_string(project_dir.join("memory.x")).ok();
+    memory_limits(
+        memory_x.as_deref(),
+        chip.and_then(get_chip_target),
+        config.get("storage").and_then(|s| s.as_table()),
+    )
+}
+
+/// Get the memory limits from memory.x, or the chip if there's none, and `[storage]` of keyboard.toml
+///
+/// Storage is enabled by default and takes `num_sectors`, 2 by default, at the end of the flash, or everything from
+/// `start_addr` on.
+fn memory_limits(
+    memory_x: Option<&str>,
+    target: Option<&ChipTarget>,
+    storage: Option<&toml::Table>,
+) -> Option<MemoryLimits> {
+    let region = |name: &str| -> Option<(u64, u64)> {
+        match memory_x {
+            Some(memory_x) => memory_region(memory_x, name),
+            None => {
+                let region = target?.memory.iter().find(|r| r.name == name)?;
+                Some((u64::from(region.origin), linker_size(&region.length)?))
+            }
+        }
+    };
+    let (flash_origin, flash) = region("FLASH")?;
+    let (_, ram) = region("RAM")?;
+
+    let field = |key: &str| storage.and_then(|s| s.get(key));
+    let storage = if field("enabled").and_then(|e| e.as_bool()) == Some(false) {
+        0
+    } else {
+        let start_addr = field("start_addr")
+            .and_then(|a| a.as_integer())
+            .and_then(|a| u64::try_from(a).ok())
+            .unwrap_or(0);
+        let num_sectors = field("num_sectors")
+            .and_then(|n| n.as_integer())
+            .and_then(|n| u64::try_from(n).ok())
+            .unwrap_or(2);
+        let flash_end = flash_origin + flash;
+        if start_addr > flash_origin && start_addr < flash_end {
+            flash_end - start_addr
+        } else {
+            num_sectors * STORAGE_SECTOR_SIZE
+        }
+    };
+    Some(MemoryLimits {
+        flash,
+        ram,
+        storage: storage.min(flash),
+    })
+}
+
+/// Print the usage against the limits, warning if the firmware overlaps the storage or doesn't fit the RAM
+fn print_usage(bin: &str, flash: u64, ram: u64, limits: Option<&MemoryLimits>) {
+    let Some(limits) = limits else {
+        println!("📦 {}: {} flash, {} RAM", bin, kib(flash), kib(ram));
+        return;
+    };
+    let available = limits.flash - limits.storage;
+    println!(
+        "📦 {}: {} flash ({:.1}% of {}), {} RAM ({:.1}% of {})",
+        bin,
+        kib(flash),
+        flash as f64 * 100.0 / available.max(1) as f64,
+        kib(available),
+        kib(ram),
+        ram as f64 * 100.0 / limits.ram.max(1) as f64,
+        kib(limits.ram)
+    );
+    if flash > available {
+        println!(
+            "⚠️  {} needs {} flash, but only {} is left besides the {} of the storage",
+            bin,
+            kib(flash),
+            kib(available),
+            kib(limits.storage)
+        );
+    }
+    if ram > limits.ram {
+        println!(
+            "⚠️  {} needs {} RAM, but RAM is only {}",
+            bin,
+            kib(ram),
+            kib(limits.ram)
+        );
+    }
+}
+
+/// Print the largest `top` entries, with their share of the flash
+fn print_table(title: &str, sizes: &HashMap<String, u64>, flash: u64, top: usize) {
+    if sizes.is_empty() {
+        return;
+    }
+    let mut sizes: Vec<(&String, &u64)> = sizes.iter().collect();
+    sizes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    println!("\n{}:", title);
+    for (name, size) in sizes.iter().take(top) {
+        let share = **size as f64 * 100.0 / flash.max(1) as f64;
+        println!("  {:>10} {:>5.1}%  {}", kib(**size), share, name);
+    }
+    if sizes.len() > top {
+        println!("  ... and {} more", sizes.len() - top);
+    }
+}
+
+fn kib(size: u64) -> String {
+    format!("{:.1} KiB", size as f64 / 1024.0)
+}
+
+/// Get the crate of a demangled symbol, e.g. `rmk` for `<rmk::ble::BleState as core::fmt::Debug>::fmt`
+///
+/// Symbols of C code and the linker, such as `memcpy`, have no crate.
+fn symbol_crate(name: &str) -> &str {
+    let path = name.trim_start_matches(['<', '&', '*', '(']);
+    let path = path
+        .strip_prefix("mut ")
+        .or_else(|| path.strip_prefix("dyn "))
+        .unwrap_or(path);
+    match path.split_once("::") {
+        Some((krate, _)) if krate.chars().all(|c| c.is_alphanumeric() || c == '_') => krate,
+        _ => "[no crate]",
+    }
+}
+
+/// Get the rmk subsystem of a symbol, the module of rmk or the subsystem using its crate
+fn subsystem<'a>(name: &'a str, krate: &'a str) -> Option<&'a str> {
+    if krate == "rmk" {
+        let path = &name[name.find("rmk::")? + "rmk::".len()..];
+        let module = path.split("::").next()?;
+        // Types and functions in the root of rmk
+        if !module.starts_with(|c: char| c.is_ascii_lowercase()) || !path.contains("::") {
+            return Some("rmk");
+        }
+        return Some(module);
+    }
+    SUBSYSTEM_CRATES
+        .iter()
+        .find(|(c, _)| *c == krate)
+        .map(|(_, subsystem)| *subsystem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_is_taken_from_the_flash() {
+        let memory_x = "MEMORY\n{\n    FLASH : ORIGIN = 0x00027000, LENGTH = 868K\n    RAM : ORIGIN = 0x20020000, LENGTH = 128K\n}\n";
+        let limits = |storage: &str| {
+            memory_limits(
+                Some(memory_x),
+                None,
+                Some(&toml::from_str(storage).unwrap()),
+            )
+        };
+        assert_eq!(
+            limits(""),
+            Some(MemoryLimits {
+                flash: 868 * 1024,
+                ram: 128 * 1024,
+                storage: 8 * 1024
+            })
+        );
+        assert_eq!(limits("num_sectors = 4").unwrap().storage, 16 * 1024);
+        assert_eq!(limits("enabled = false").unwrap().storage, 0);
+        // Everything from start_addr on is the storage
+        assert_eq!(
+            limits("start_addr = 0x000ec000").unwrap().storage,
+            0x100000 - 0xec000
+        );
+        assert_eq!(memory_limits(Some("MEMORY\n{\n}\n"), None, None), None);
+    }
+}
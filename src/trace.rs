@@ -0,0 +1,124 @@
+use std::fmt::{Debug, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// A finished or running span
+struct SpanRecord {
+    parent: Option<usize>,
+    name: &'static str,
+    /// Fields of the span, as ` key=value` pairs
+    fields: String,
+    /// `None` if the span hasn't been closed
+    elapsed: Option<Duration>,
+}
+
+/// Start of a span, stored in the span's extensions
+struct SpanStart {
+    index: usize,
+    start: Instant,
+}
+
+/// Timings of all spans, in the order they were created
+#[derive(Clone, Default)]
+pub(crate) struct SpanTimings {
+    spans: Arc<Mutex<Vec<SpanRecord>>>,
+}
+
+impl SpanTimings {
+    /// Print all spans as a tree with their timings
+    pub(crate) fn print(&self) {
+        let spans = self.spans.lock().unwrap();
+        eprintln!("⏱️  Span timings:");
+        for (index, _) in spans.iter().enumerate().filter(|(_, s)| s.parent.is_none()) {
+            print_span(&spans, index, 1);
+        }
+    }
+}
+
+fn print_span(spans: &[SpanRecord], index: usize, depth: usize) {
+    let span = &spans[index];
+    let elapsed = match span.elapsed {
+        Some(elapsed) => format!("{:.2?}", elapsed),
+        None => "unfinished".to_string(),
+    };
+    eprintln!(
+        "{:indent$}{}{} {}",
+        "",
+        span.name,
+        span.fields,
+        elapsed,
+        indent = depth * 2
+    );
+    for (child, _) in spans
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.parent == Some(index))
+    {
+        print_span(spans, child, depth + 1);
+    }
+}
+
+/// Collect span fields as ` key=value` pairs
+struct FieldVisitor<'a>(&'a mut String);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let _ = write!(self.0, " {}={}", field.name(), value);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let _ = write!(self.0, " {}={:?}", field.name(), value);
+    }
+}
+
+impl<S> Layer<S> for SpanTimings
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span
+            .parent()
+            .and_then(|p| p.extensions().get::<SpanStart>().map(|s| s.index));
+        let mut fields = String::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(SpanRecord {
+            parent,
+            name: attrs.metadata().name(),
+            fields,
+            elapsed: None,
+        });
+        span.extensions_mut().insert(SpanStart {
+            index: spans.len() - 1,
+            start: Instant::now(),
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        if let Some(start) = extensions.get::<SpanStart>() {
+            self.spans.lock().unwrap()[start.index].elapsed = Some(start.start.elapsed());
+        }
+    }
+}
+
+/// Record timings of all spans, print them with [`SpanTimings::print`] at the end
+pub(crate) fn init() -> SpanTimings {
+    let timings = SpanTimings::default();
+    let subscriber = tracing_subscriber::registry().with(timings.clone());
+    if tracing::subscriber::set_global_default(subscriber).is_err() {
+        eprintln!("⚠️  Failed to enable span tracing");
+    }
+    timings
+}
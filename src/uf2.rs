@@ -0,0 +1,146 @@
+use crate::reporter::Terminal;
+use rmkit_core::chip::{get_board_info, get_chip_target, get_uf2_family, Bootloader};
+use rmkit_core::firmware::{flash_image, for_each_intel_hex, write_uf2, Uf2Tags, UF2_PAYLOAD};
+use rmkit_core::interrupt::TempPath;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read};
+use std::path::Path;
+
+/// Convert an ELF, Intel HEX or raw binary firmware file to UF2
+///
+/// The family id is `family_id`, or the one of `chip`. Raw binaries are placed at `base`, or at the flash origin
+/// of the chip, boards with a bootloader need `base`.
+pub(crate) fn convert_uf2(
+    input: &str,
+    chip: Option<&str>,
+    family_id: Option<&str>,
+    base: Option<&str>,
+    output: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let family = match (family_id, chip) {
+        (Some(family_id), _) => parse_number(family_id)
+            .and_then(|id| u32::try_from(id).ok())
+            .ok_or(format!("Invalid UF2 family id {}", family_id))?,
+        (None, Some(chip)) => get_uf2_family(chip).ok_or(format!(
+            "{} has no UF2 family id, give it with --family-id",
+            chip
+        ))?,
+        (None, None) => return Err("Either --chip or --family-id is needed".into()),
+    };
+    let extension = Path::new(input)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let open = || File::open(input).map_err(|e| format!("Failed to read {}: {}", input, e));
+    let tags = Uf2Tags {
+        board: chip.map(|c| c.to_string()),
+        ..Uf2Tags::default()
+    };
+    let output =
+        output.unwrap_or_else(|| Path::new(input).with_extension("uf2").display().to_string());
+    // An unfinished UF2 file is removed on failure or interrupt
+    let uf2_path = TempPath::new(&output);
+    let writer = BufWriter::new(
+        File::create(uf2_path.path()).map_err(|e| format!("Failed to write {}: {}", output, e))?,
+    );
+
+    // The input is streamed, except ELF files, whose segments are read from the whole file
+    let (mut start, mut end) = (u64::MAX, 0);
+    let mut track = |address: u64, length: usize| {
+        start = start.min(address);
+        end = end.max(address + length as u64);
+    };
+    match extension.as_str() {
+        "hex" | "ihex" => {
+            write_uf2(
+                writer,
+                family,
+                &tags,
+                |on_data| {
+                    for_each_intel_hex(BufReader::new(open()?), |address, data| {
+                        track(address, data.len());
+                        on_data(address, data)
+                    })
+                },
+                &mut Terminal,
+            )?;
+        }
+        "bin" => {
+            let address = match base {
+                Some(base) => parse_number(base).ok_or(format!("Invalid address {}", base))?,
+                // Firmware of boards with a bootloader starts after it, e.g. after the SoftDevice of nice!nano
+                None if chip
+                    .and_then(get_board_info)
+                    .and_then(|info| info.bootloader)
+                    .is_some_and(|bootloader| bootloader != Bootloader::None) =>
+                {
+                    return Err(format!(
+                        "The address of a raw binary depends on the bootloader of {}, give it with --base",
+                        chip.unwrap_or_default()
+                    )
+                    .into())
+                }
+                None => chip
+                    .and_then(get_chip_target)
+                    .and_then(|t| t.memory.iter().find(|r| r.name == "FLASH"))
+                    .map(|flash| u64::from(flash.origin))
+                    .ok_or("The address of a raw binary is unknown, give it with --base")?,
+            };
+            write_uf2(
+                writer,
+                family,
+                &tags,
+                |on_data| {
+                    let mut file = open()?;
+                    let mut buffer = [0; UF2_PAYLOAD];
+                    let mut offset = address;
+                    loop {
+                        let length = file.read(&mut buffer)?;
+                        if length == 0 {
+                            return Ok(());
+                        }
+                        track(offset, length);
+                        on_data(offset, &buffer[..length])?;
+                        offset += length as u64;
+                    }
+                },
+                &mut Terminal,
+            )?;
+        }
+        // ELF files usually have no extension
+        _ => {
+            let data = fs::read(input).map_err(|e| format!("Failed to read {}: {}", input, e))?;
+            let (base, image) = flash_image(&data)?;
+            write_uf2(
+                writer,
+                family,
+                &tags,
+                |on_data| {
+                    track(base, image.len());
+                    Ok(on_data(base, &image)?)
+                },
+                &mut Terminal,
+            )?;
+        }
+    }
+    if start >= end {
+        return Err(format!("{} has no data", input).into());
+    }
+    uf2_path.keep();
+
+    println!(
+        "✅ Converted {} to {}, {:#010x}..{:#010x}",
+        input, output, start, end
+    );
+    Ok(())
+}
+
+/// Parse a decimal, or `0x` prefixed hexadecimal, number
+fn parse_number(number: &str) -> Option<u64> {
+    match number.strip_prefix("0x").or(number.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(&hex.replace('_', ""), 16).ok(),
+        None => number.replace('_', "").parse().ok(),
+    }
+}
@@ -0,0 +1,642 @@
+use crate::keycode::{canonical_keycode, split_args};
+use crate::keymap::{read_keymap, write_keymap, NO_KEY};
+use rmkit_core::keyboard_toml::read_keyboard_config;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+
+/// QMK keycodes whose names differ from RMK, the first QMK name of a key is used for exporting
+const QMK_KEYCODES: &[(&str, &str)] = &[
+    ("KC_NO", "No"),
+    ("XXXXXXX", "No"),
+    ("KC_ENT", "Enter"),
+    ("KC_ESC", "Escape"),
+    ("KC_BSPC", "Backspace"),
+    ("KC_BSPACE", "Backspace"),
+    ("KC_SPC", "Space"),
+    ("KC_MINS", "Minus"),
+    ("KC_EQL", "Equal"),
+    ("KC_LBRC", "LeftBracket"),
+    ("KC_LBRACKET", "LeftBracket"),
+    ("KC_RBRC", "RightBracket"),
+    ("KC_RBRACKET", "RightBracket"),
+    ("KC_BSLS", "Backslash"),
+    ("KC_BSLASH", "Backslash"),
+    ("KC_NUHS", "NonusHash"),
+    ("KC_SCLN", "Semicolon"),
+    ("KC_SCOLON", "Semicolon"),
+    ("KC_QUOT", "Quote"),
+    ("KC_GRV", "Grave"),
+    ("KC_COMM", "Comma"),
+    ("KC_SLSH", "Slash"),
+    ("KC_CAPS", "CapsLock"),
+    ("KC_PSCR", "PrintScreen"),
+    ("KC_PSCREEN", "PrintScreen"),
+    ("KC_SCRL", "ScrollLock"),
+    ("KC_PAUS", "Pause"),
+    ("KC_INS", "Insert"),
+    ("KC_PGUP", "PageUp"),
+    ("KC_DEL", "Delete"),
+    ("KC_PGDN", "PageDown"),
+    ("KC_PGDOWN", "PageDown"),
+    ("KC_RGHT", "Right"),
+    ("KC_NUM", "NumLock"),
+    ("KC_PSLS", "KpSlash"),
+    ("KC_PAST", "KpAsterisk"),
+    ("KC_PMNS", "KpMinus"),
+    ("KC_PPLS", "KpPlus"),
+    ("KC_PENT", "KpEnter"),
+    ("KC_PDOT", "KpDot"),
+    ("KC_NUBS", "NonusBackslash"),
+    ("KC_APP", "Application"),
+    ("KC_PEQL", "KpEqual"),
+    ("KC_LCTL", "LCtrl"),
+    ("KC_LSFT", "LShift"),
+    ("KC_LALT", "LAlt"),
+    ("KC_LGUI", "LGui"),
+    ("KC_RCTL", "RCtrl"),
+    ("KC_RSFT", "RShift"),
+    ("KC_RALT", "RAlt"),
+    ("KC_RGUI", "RGui"),
+    ("KC_MUTE", "AudioMute"),
+    ("KC_VOLU", "AudioVolUp"),
+    ("KC_VOLD", "AudioVolDown"),
+    ("KC_MNXT", "MediaNextTrack"),
+    ("KC_MPRV", "MediaPrevTrack"),
+    ("KC_MSTP", "MediaStop"),
+    ("KC_MPLY", "MediaPlayPause"),
+    ("KC_MSEL", "MediaSelect"),
+    ("KC_EJCT", "MediaEject"),
+    ("KC_CALC", "Calculator"),
+    ("KC_MYCM", "MyComputer"),
+    ("KC_WSCH", "WwwSearch"),
+    ("KC_WHOM", "WwwHome"),
+    ("KC_WBAK", "WwwBack"),
+    ("KC_WFWD", "WwwForward"),
+    ("KC_WSTP", "WwwStop"),
+    ("KC_WREF", "WwwRefresh"),
+    ("KC_WFAV", "WwwFavorites"),
+    ("KC_MFFD", "MediaFastForward"),
+    ("KC_MRWD", "MediaRewind"),
+    ("KC_BRIU", "BrightnessUp"),
+    ("KC_BRID", "BrightnessDown"),
+    ("KC_PWR", "SystemPower"),
+    ("KC_SLEP", "SystemSleep"),
+    ("KC_WAKE", "SystemWake"),
+    ("KC_MS_U", "MouseUp"),
+    ("KC_MS_D", "MouseDown"),
+    ("KC_MS_L", "MouseLeft"),
+    ("KC_MS_R", "MouseRight"),
+    ("KC_WH_U", "MouseWheelUp"),
+    ("KC_WH_D", "MouseWheelDown"),
+    ("KC_WH_L", "MouseWheelLeft"),
+    ("KC_WH_R", "MouseWheelRight"),
+    ("QK_BOOT", "Bootloader"),
+    ("RESET", "Bootloader"),
+    ("QK_RBT", "Reboot"),
+    ("QK_GESC", "GraveEscape"),
+    ("KC_GESC", "GraveEscape"),
+    ("QK_REP", "Repeat"),
+    ("CW_TOGG", "CapsWordToggle"),
+];
+
+/// Numbered QMK keycodes and their RMK prefix, e.g. `KC_BTN1` is `MouseBtn1`
+const QMK_NUMBERED_KEYCODES: &[(&str, &str)] = &[
+    ("KC_P", "Kp"),
+    ("KC_F", "F"),
+    ("KC_BTN", "MouseBtn"),
+    ("KC_ACL", "MouseAccel"),
+    ("KC_INT", "International"),
+    ("KC_LNG", "Language"),
+    ("USER", "User"),
+    ("M", "Macro"),
+    ("KC_", "Kc"),
+];
+
+/// QMK modifier names, in `MOD_*`, mod-tap `*_T(kc)` and modded keys `*(kc)`, and their RMK modifiers
+const QMK_MODIFIERS: &[(&str, &[&str])] = &[
+    ("LCTL", &["LCtrl"]),
+    ("LSFT", &["LShift"]),
+    ("LALT", &["LAlt"]),
+    ("LGUI", &["LGui"]),
+    ("RCTL", &["RCtrl"]),
+    ("RSFT", &["RShift"]),
+    ("RALT", &["RAlt"]),
+    ("RGUI", &["RGui"]),
+    ("C", &["LCtrl"]),
+    ("S", &["LShift"]),
+    ("A", &["LAlt"]),
+    ("G", &["LGui"]),
+    ("LOPT", &["LAlt"]),
+    ("LCMD", &["LGui"]),
+    ("LWIN", &["LGui"]),
+    ("ROPT", &["RAlt"]),
+    ("ALGR", &["RAlt"]),
+    ("RCMD", &["RGui"]),
+    ("RWIN", &["RGui"]),
+    ("C_S", &["LCtrl", "LShift"]),
+    ("LCA", &["LCtrl", "LAlt"]),
+    ("LSA", &["LShift", "LAlt"]),
+    ("SGUI", &["LShift", "LGui"]),
+    ("MEH", &["LCtrl", "LShift", "LAlt"]),
+    ("HYPR", &["LCtrl", "LShift", "LAlt", "LGui"]),
+    ("ALL", &["LCtrl", "LShift", "LAlt", "LGui"]),
+];
+
+/// Layer actions which are written the same in QMK and RMK
+const LAYER_ACTIONS: [&str; 7] = ["MO", "TG", "TO", "DF", "OSL", "TT", "TD"];
+
+/// Seed the keymap of keyboard.toml from a Vial `.vil` or VIA backup
+///
+/// QMK keycodes are converted to RMK, keys without an RMK equivalent become `No` and are listed.
+pub(crate) fn import_backup(
+    keyboard_toml_path: &str,
+    backup_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let content = fs::read_to_string(backup_path)
+        .map_err(|e| format!("Failed to read {}: {}", backup_path, e))?;
+    let backup: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", backup_path, e))?;
+    let current = read_keymap(keyboard_toml_path)?;
+    let cols = current
+        .first()
+        .and_then(|layer| layer.first())
+        .map_or(0, |row| row.len());
+
+    // Vial saves `[layer][row][col]`, VIA saves the keys of a layer row by row
+    let backup_layers: Vec<Vec<Vec<Value>>> = if let Some(layout) = backup["layout"].as_array() {
+        layout
+            .iter()
+            .map(|layer| {
+                layer
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|row| row.as_array().cloned().unwrap_or_default())
+                    .collect()
+            })
+            .collect()
+    } else if let Some(layers) = backup["layers"].as_array() {
+        layers
+            .iter()
+            .map(|layer| {
+                let keys = layer.as_array().cloned().unwrap_or_default();
+                keys.chunks(cols.max(1)).map(|row| row.to_vec()).collect()
+            })
+            .collect()
+    } else {
+        return Err(format!("{} is not a Vial .vil or VIA backup", backup_path).into());
+    };
+
+    let mut unknown = Vec::new();
+    let layers: Vec<Vec<Vec<String>>> = backup_layers
+        .iter()
+        .enumerate()
+        .map(|(l, layer)| {
+            layer
+                .iter()
+                .enumerate()
+                .map(|(r, row)| {
+                    row.iter()
+                        .enumerate()
+                        .map(|(c, key)| {
+                            let converted = match key {
+                                // Positions without a key in the Vial layout
+                                Value::Number(n) if n.as_i64() == Some(-1) => {
+                                    Some(NO_KEY.to_string())
+                                }
+                                Value::String(key) => from_qmk(key),
+                                _ => None,
+                            };
+                            converted.unwrap_or_else(|| {
+                                unknown.push(format!("layer {} {},{}: {}", l, r, c, key));
+                                NO_KEY.to_string()
+                            })
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+
+    write_keymap(keyboard_toml_path, &layers, &[])?;
+    for key in &unknown {
+        println!("⚠️  No RMK keycode for {}, set to No", key);
+    }
+    if layers.len() > current.len() {
+        println!(
+            "⚠️  Only the first {} of {} layers are imported, increase `layers` in [layout] to import all",
+            current.len(),
+            layers.len()
+        );
+    }
+    println!(
+        "✅ Imported the keymap of {} into {}",
+        backup_path, keyboard_toml_path
+    );
+    Ok(())
+}
+
+/// Export the keymap of keyboard.toml as a Vial `.vil` file, or as a VIA backup for other extensions
+pub(crate) fn export_backup(keyboard_toml_path: &str, output: &str) -> Result<(), Box<dyn Error>> {
+    let layers = read_keymap(keyboard_toml_path)?;
+    let vial = output.ends_with(".vil");
+    let mut unknown = Vec::new();
+    let qmk_layers: Vec<Vec<Vec<String>>> = layers
+        .iter()
+        .enumerate()
+        .map(|(l, layer)| {
+            layer
+                .iter()
+                .enumerate()
+                .map(|(r, row)| {
+                    row.iter()
+                        .enumerate()
+                        .map(|(c, key)| {
+                            to_qmk(key, vial).unwrap_or_else(|| {
+                                unknown.push(format!("layer {} {},{}: {}", l, r, c, key));
+                                "KC_NO".to_string()
+                            })
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+
+    let backup = if vial {
+        json!({
+            "version": 1,
+            "uid": 0,
+            "layout": qmk_layers,
+            "encoder_layout": [],
+            "layout_options": -1,
+            "macro": [],
+            "vial_protocol": 6,
+            "via_protocol": 9,
+            "tap_dance": [],
+            "combo": [],
+            "key_override": [],
+            "settings": {},
+        })
+    } else {
+        let config = read_keyboard_config(keyboard_toml_path)?;
+        let keyboard = config.get("keyboard");
+        let field = |key: &str| keyboard.and_then(|k| k.get(key));
+        let id = |key: &str| field(key).and_then(|v| v.as_integer()).unwrap_or(0);
+        let flat: Vec<Vec<String>> = qmk_layers.into_iter().map(|l| l.concat()).collect();
+        json!({
+            "name": field("name").and_then(|n| n.as_str()).unwrap_or("RMK Keyboard"),
+            "vendorProductId": (id("vendor_id") << 16) | id("product_id"),
+            "macros": [],
+            "layers": flat,
+        })
+    };
+    fs::write(output, serde_json::to_string_pretty(&backup)?)?;
+    for key in &unknown {
+        println!("⚠️  No QMK keycode for {}, exported as KC_NO", key);
+    }
+    println!(
+        "✅ Exported the keymap of {} to {}",
+        keyboard_toml_path, output
+    );
+    Ok(())
+}
+
+/// Convert a QMK keycode to RMK, e.g. `LT(1,KC_SPC)` or Vial's `LT1(KC_SPC)` to `LT(1, Space)`
+pub(crate) fn from_qmk(key: &str) -> Option<String> {
+    let key = key.trim();
+    if ["KC_TRNS", "KC_TRANSPARENT", "_______"].contains(&key) {
+        return Some("_".to_string());
+    }
+    let Some((name, args)) = key.strip_suffix(')').and_then(|k| k.split_once('(')) else {
+        return basic_from_qmk(key);
+    };
+    let name = name.trim().to_uppercase();
+    let args = split_args(args);
+    let layer = |arg: &str| arg.parse::<usize>().ok();
+    // Vial writes the layer of some actions into the name, e.g. `LT1(kc)` and `LM1(mod)`
+    let numbered = |prefix: &str| name.strip_prefix(prefix).and_then(layer);
+    match (name.as_str(), args.as_slice()) {
+        (action, [n]) if LAYER_ACTIONS.contains(&action) => {
+            Some(format!("{}({})", action, layer(n)?))
+        }
+        ("LT", [n, kc]) => Some(format!("LT({}, {})", layer(n)?, from_qmk(kc)?)),
+        (_, [kc]) if numbered("LT").is_some() => {
+            Some(format!("LT({}, {})", numbered("LT")?, from_qmk(kc)?))
+        }
+        ("LM", [n, mods]) => Some(format!("LM({}, {})", layer(n)?, mods_from_qmk(mods)?)),
+        (_, [mods]) if numbered("LM").is_some() => {
+            Some(format!("LM({}, {})", numbered("LM")?, mods_from_qmk(mods)?))
+        }
+        ("OSM", [mods]) => Some(format!("OSM({})", mods_from_qmk(mods)?)),
+        ("MT", [mods, kc]) => Some(format!("MT({}, {})", from_qmk(kc)?, mods_from_qmk(mods)?)),
+        (mod_tap, [kc]) if mod_tap.ends_with("_T") => {
+            let mods = qmk_modifiers(mod_tap.strip_suffix("_T")?)?;
+            Some(format!("MT({}, {})", from_qmk(kc)?, mods.join("|")))
+        }
+        (modifier, [kc]) => {
+            // Modded keys can be nested, e.g. `LCTL(LSFT(KC_A))`
+            let mut mods = qmk_modifiers(modifier)?.to_vec();
+            let mut kc = kc.to_string();
+            while let Some((inner, arg)) = kc.strip_suffix(')').and_then(|k| k.split_once('(')) {
+                mods.extend(qmk_modifiers(&inner.trim().to_uppercase())?);
+                kc = arg.to_string();
+            }
+            Some(format!("WM({}, {})", from_qmk(&kc)?, mods.join("|")))
+        }
+        _ => None,
+    }
+}
+
+fn basic_from_qmk(key: &str) -> Option<String> {
+    let upper = key.to_uppercase();
+    if let Some((_, rmk)) = QMK_KEYCODES.iter().find(|(qmk, _)| *qmk == upper) {
+        return Some(rmk.to_string());
+    }
+    for (prefix, rmk_prefix) in QMK_NUMBERED_KEYCODES {
+        if let Some(n) = upper
+            .strip_prefix(prefix)
+            .and_then(|n| n.parse::<u32>().ok())
+        {
+            if let Some(rmk) = canonical_keycode(&format!("{}{}", rmk_prefix, n)) {
+                return Some(rmk);
+            }
+        }
+    }
+    canonical_keycode(key)
+}
+
+/// Get the RMK modifiers of a QMK modifier name, e.g. `LCTL` or `MEH`
+fn qmk_modifiers(name: &str) -> Option<&'static [&'static str]> {
+    QMK_MODIFIERS
+        .iter()
+        .find(|(qmk, _)| *qmk == name)
+        .map(|(_, mods)| *mods)
+}
+
+/// Convert QMK modifiers like `MOD_LCTL|MOD_LSFT` to RMK's `LCtrl|LShift`
+fn mods_from_qmk(mods: &str) -> Option<String> {
+    let mut rmk: Vec<&str> = Vec::new();
+    for modifier in mods.split('|') {
+        rmk.extend(qmk_modifiers(
+            modifier.trim().to_uppercase().strip_prefix("MOD_")?,
+        )?);
+    }
+    Some(rmk.join("|"))
+}
+
+/// Convert an RMK key to QMK, layer taps are written as `LT1(kc)` for Vial and `LT(1,kc)` for VIA
+fn to_qmk(key: &str, vial: bool) -> Option<String> {
+    let key = key.trim();
+    if !key.is_empty() && key.chars().all(|c| c == '_') {
+        return Some("KC_TRNS".to_string());
+    }
+    let Some((name, args)) = key.strip_suffix(')').and_then(|k| k.split_once('(')) else {
+        return basic_to_qmk(key);
+    };
+    let name = name.trim().to_uppercase();
+    let args = split_args(args);
+    let layer = |arg: &str| arg.parse::<usize>().ok();
+    match (name.as_str(), args.as_slice()) {
+        (action, [n]) if LAYER_ACTIONS.contains(&action) => {
+            Some(format!("{}({})", action, layer(n)?))
+        }
+        ("LT", [n, kc]) if vial => Some(format!("LT{}({})", layer(n)?, to_qmk(kc, vial)?)),
+        ("LT", [n, kc]) => Some(format!("LT({},{})", layer(n)?, to_qmk(kc, vial)?)),
+        ("LM", [n, mods]) => Some(format!(
+            "LM({},{})",
+            layer(n)?,
+            mods_to_qmk(mods)?.join("|")
+        )),
+        ("OSM", [mods]) => Some(format!("OSM({})", mods_to_qmk(mods)?.join("|"))),
+        ("MT", [kc, mods]) => {
+            let kc = to_qmk(kc, vial)?;
+            match mods_to_qmk(mods)?.as_slice() {
+                [single] => Some(format!("{}_T({})", single.strip_prefix("MOD_")?, kc)),
+                mods => Some(format!("MT({},{})", mods.join("|"), kc)),
+            }
+        }
+        ("WM", [kc, mods]) => {
+            let mut qmk = to_qmk(kc, vial)?;
+            for modifier in mods_to_qmk(mods)?.iter().rev() {
+                qmk = format!("{}({})", modifier.strip_prefix("MOD_")?, qmk);
+            }
+            Some(qmk)
+        }
+        _ => None,
+    }
+}
+
+fn basic_to_qmk(key: &str) -> Option<String> {
+    // keyboard.toml may use QMK names too, e.g. `KC_VOLU`
+    let rmk = basic_from_qmk(key)?;
+    if let Some((qmk, _)) = QMK_KEYCODES.iter().find(|(_, r)| *r == rmk) {
+        return Some(qmk.to_string());
+    }
+    if rmk.len() == 1 {
+        return Some(format!("KC_{}", rmk));
+    }
+    for (prefix, rmk_prefix) in QMK_NUMBERED_KEYCODES {
+        if let Some(n) = rmk
+            .strip_prefix(rmk_prefix)
+            .and_then(|n| n.parse::<u32>().ok())
+        {
+            return Some(match *prefix {
+                "USER" => format!("USER{:02}", n),
+                _ => format!("{}{}", prefix, n),
+            });
+        }
+    }
+    // `CapsLock` -> `KC_CAPS_LOCK`
+    let mut qmk = "KC".to_string();
+    for c in rmk.chars() {
+        if c.is_ascii_uppercase() {
+            qmk.push('_');
+        }
+        qmk.push(c.to_ascii_uppercase());
+    }
+    Some(qmk)
+}
+
+/// Convert RMK modifiers like `LCtrl|LShift` to QMK's `MOD_LCTL`, `MOD_LSFT`
+fn mods_to_qmk(mods: &str) -> Option<Vec<String>> {
+    mods.split('|')
+        .map(|m| {
+            QMK_MODIFIERS
+                .iter()
+                .take(8)
+                .find(|(_, rmk)| rmk[0].eq_ignore_ascii_case(m.trim()))
+                .map(|(qmk, _)| format!("MOD_{}", qmk))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    const KEYBOARD_TOML: &str = r#"[keyboard]
+name = "tiny"
+vendor_id = 0x4c4b
+product_id = 0x4643
+
+[layout]
+rows = 2
+cols = 2
+layers = 2
+keymap = [
+  [
+    ["A", "B"],
+    ["C", "D"],
+  ],
+  [
+    ["_", "_"],
+    ["_", "_"],
+  ],
+]
+"#;
+
+    /// A Vial backup with an unknown keycode, a position without key and more layers than keyboard.toml
+    const VIL: &str = r#"{
+  "version": 1,
+  "layout": [
+    [["KC_ESC", "LT1(KC_SPC)"], ["LCTL(LSFT(KC_B))", -1]],
+    [["KC_TRNS", "LSFT_T(KC_ENT)"], ["QK_UNKNOWN", "MO(0)"]],
+    [["KC_A", "KC_B"], ["KC_C", "KC_D"]]
+  ]
+}"#;
+
+    /// A VIA backup, with the keys of a layer row by row
+    const VIA: &str = r#"{
+  "name": "tiny",
+  "vendorProductId": 1280066115,
+  "layers": [
+    ["KC_Q", "KC_W", "KC_BSPC", "LT(1,KC_SPC)"],
+    ["KC_F1", "KC_VOLU", "MT(MOD_LCTL|MOD_LALT,KC_X)", "_______"]
+  ]
+}"#;
+
+    /// Run `f` with a temporary keyboard.toml and a backup file `name`, returning both afterwards
+    fn with_project(
+        name: &str,
+        f: impl FnOnce(&str, &str) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(toml::Table, String), Box<dyn Error>> {
+        let dir = env::temp_dir().join(format!("rmkit-via-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir)?;
+        let keyboard_toml = dir.join("keyboard.toml").display().to_string();
+        let other = dir.join(name).display().to_string();
+        fs::write(&keyboard_toml, KEYBOARD_TOML)?;
+        let result = f(&keyboard_toml, &other).and_then(|_| {
+            let config = toml::from_str(&fs::read_to_string(&keyboard_toml)?)?;
+            Ok((config, fs::read_to_string(&other)?))
+        });
+        fs::remove_dir_all(&dir)?;
+        result
+    }
+
+    fn import(name: &str, backup: &str) -> Result<toml::Value, Box<dyn Error>> {
+        let (config, _) = with_project(name, |keyboard_toml, backup_path| {
+            fs::write(backup_path, backup)?;
+            import_backup(keyboard_toml, backup_path)
+        })?;
+        Ok(config["layout"]["keymap"].clone())
+    }
+
+    fn keymap(keymap: &str) -> toml::Value {
+        toml::from_str::<toml::Table>(&format!("keymap = {}", keymap)).unwrap()["keymap"].clone()
+    }
+
+    #[test]
+    fn vial_backup_is_imported() {
+        let expected = keymap(
+            r#"[
+                [["Escape", "LT(1, Space)"], ["WM(B, LCtrl|LShift)", "No"]],
+                [["_", "MT(Enter, LShift)"], ["No", "MO(0)"]]
+            ]"#,
+        );
+        assert_eq!(import("tiny.vil", VIL).unwrap(), expected);
+    }
+
+    #[test]
+    fn via_backup_is_imported() {
+        let expected = keymap(
+            r#"[
+                [["Q", "W"], ["Backspace", "LT(1, Space)"]],
+                [["F1", "AudioVolUp"], ["MT(X, LCtrl|LAlt)", "_"]]
+            ]"#,
+        );
+        assert_eq!(import("tiny.json", VIA).unwrap(), expected);
+    }
+
+    #[test]
+    fn malformed_backups_are_rejected() {
+        let error = |name: &str, backup: &str| import(name, backup).unwrap_err().to_string();
+        assert!(error("broken.vil", r#"{"layout": [["#).contains("Failed to parse"));
+        assert!(
+            error("keymap.json", r#"{"keymap": []}"#).contains("is not a Vial .vil or VIA backup")
+        );
+        // A row longer than the matrix
+        assert!(error(
+            "wide.vil",
+            r#"{"layout": [[["KC_A", "KC_B", "KC_C"], ["KC_D", "KC_E"]]]}"#
+        )
+        .contains("Layer 0 doesn't fit the 2x2 matrix"));
+    }
+
+    #[test]
+    fn keymap_is_exported() {
+        let export = |name: &str| {
+            let (_, backup) = with_project(name, export_backup).unwrap();
+            serde_json::from_str::<Value>(&backup).unwrap()
+        };
+        let vial = export("tiny.vil");
+        assert_eq!(
+            vial["layout"],
+            json!([
+                [["KC_A", "KC_B"], ["KC_C", "KC_D"]],
+                [["KC_TRNS", "KC_TRNS"], ["KC_TRNS", "KC_TRNS"]]
+            ])
+        );
+        let via = export("tiny.json");
+        assert_eq!(via["name"], "tiny");
+        assert_eq!(via["vendorProductId"], 0x4c4b4643);
+        assert_eq!(
+            via["layers"],
+            json!([
+                ["KC_A", "KC_B", "KC_C", "KC_D"],
+                ["KC_TRNS", "KC_TRNS", "KC_TRNS", "KC_TRNS"]
+            ])
+        );
+    }
+
+    #[test]
+    fn keycodes_are_converted_both_ways() {
+        for (qmk, rmk) in [
+            ("KC_SPC", "Space"),
+            ("KC_F12", "F12"),
+            ("KC_BTN1", "MouseBtn1"),
+            ("USER03", "User3"),
+            ("LT(2,KC_BSPC)", "LT(2, Backspace)"),
+            ("LM(1,MOD_LCTL|MOD_RSFT)", "LM(1, LCtrl|RShift)"),
+            ("OSM(MOD_LGUI)", "OSM(LGui)"),
+            ("MEH_T(KC_A)", "MT(A, LCtrl|LShift|LAlt)"),
+        ] {
+            assert_eq!(from_qmk(qmk).as_deref(), Some(rmk), "{}", qmk);
+            assert_eq!(
+                to_qmk(rmk, false).as_deref().map(from_qmk),
+                Some(Some(rmk.to_string()))
+            );
+        }
+        assert_eq!(to_qmk("LT(1, A)", true).as_deref(), Some("LT1(KC_A)"));
+        assert_eq!(to_qmk("LT(1, A)", false).as_deref(), Some("LT(1,KC_A)"));
+        assert_eq!(
+            to_qmk("MT(A, LCtrl)", false).as_deref(),
+            Some("LCTL_T(KC_A)")
+        );
+        assert_eq!(from_qmk("QK_UNKNOWN"), None);
+        assert_eq!(from_qmk("LT(x,KC_A)"), None);
+        assert_eq!(from_qmk("FOO(KC_A)"), None);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::kle::place_keys;
+use rmkit_core::keyboard_toml::{read_keyboard_config, ConfigFormat};
+use rmkit_core::vial::validate_vial_json;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use toml_edit::{Array, DocumentMut};
+
+/// Convert a VIA definition into a `vial.json` for RMK and validate it
+///
+/// VIA v2 and v3 definitions are accepted. Vial doesn't read the `menus` and `keycodes` of VIA v3 and RMK
+/// doesn't control lighting over Vial, so they are dropped with a warning and `lighting` is `none`. The
+/// layout options in `layouts.labels` are kept and checked against the keys of the layout. If keyboard.toml
+/// exists, it's checked against the definition and `unlock_keys` of `[host]` are added for the Vial lock.
+pub(crate) fn import_via_definition(
+    definition_path: &str,
+    keyboard_toml_path: &str,
+    output: &str,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    if Path::new(output).exists() && !force {
+        return Err(format!("{} already exists, use --force to overwrite it", output).into());
+    }
+    let content = fs::read_to_string(definition_path)
+        .map_err(|e| format!("Failed to read {}: {}", definition_path, e))?;
+    let via: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", definition_path, e))?;
+    let mut warnings = Vec::new();
+    let vial = via_to_vial(&via, &mut warnings)
+        .map_err(|e| format!("Invalid VIA definition {}: {}", definition_path, e))?;
+
+    let problems = validate_definition(&vial);
+    for problem in &problems {
+        println!("❌ {}", problem);
+    }
+    if !problems.is_empty() {
+        return Err(format!(
+            "{} isn't a valid definition, {} problem(s) found",
+            definition_path,
+            problems.len()
+        )
+        .into());
+    }
+    for warning in &warnings {
+        println!("⚠️  {}", warning);
+    }
+
+    if Path::new(keyboard_toml_path).exists() {
+        let config = read_keyboard_config(keyboard_toml_path)?;
+        for mismatch in validate_vial_json(&vial, &config)? {
+            println!("⚠️  {}", mismatch);
+        }
+        add_unlock_keys(keyboard_toml_path, &vial)?;
+    } else {
+        println!(
+            "ℹ️  {} not found, set `unlock_keys` in [host] of keyboard.toml for the Vial lock",
+            keyboard_toml_path
+        );
+    }
+    fs::write(output, serde_json::to_string_pretty(&vial)?)?;
+    println!("✅ Converted {} to {}", definition_path, output);
+    Ok(())
+}
+
+/// Convert the fields of a VIA definition to the ones of Vial, other fields are kept as they are
+fn via_to_vial(via: &Value, warnings: &mut Vec<String>) -> Result<Value, Box<dyn Error>> {
+    let mut vial = via.as_object().ok_or("it's not a JSON object")?.clone();
+    for key in ["vendorId", "productId"] {
+        // VIA v3 allows numbers, Vial only reads hex strings
+        if let Some(id) = vial.get(key).and_then(parse_id) {
+            vial.insert(
+                key.to_string(),
+                format!("{:#06X}", id).replace("0X", "0x").into(),
+            );
+        }
+    }
+    for key in ["menus", "keycodes"] {
+        if vial.remove(key).is_some() {
+            warnings.push(format!(
+                "`{}` of VIA v3 isn't supported by Vial, it's dropped",
+                key
+            ));
+        }
+    }
+    match vial.get("lighting") {
+        None => {}
+        Some(Value::String(lighting)) if lighting == "none" => {}
+        Some(lighting) => warnings.push(format!(
+            "RMK doesn't control lighting over Vial, `lighting` {} is set to \"none\"",
+            lighting
+        )),
+    }
+    vial.insert("lighting".to_string(), "none".into());
+    Ok(Value::Object(vial))
+}
+
+/// Parse a USB id of a definition, either a number or a hex string like `0x4C4B`
+fn parse_id(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => {
+            let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))?;
+            i64::from_str_radix(hex, 16).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Check the fields which Vial needs and the keys and layout options of the layout of a definition
+fn validate_definition(vial: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    if vial["name"].as_str().is_none_or(|n| n.is_empty()) {
+        problems.push("name: missing".to_string());
+    }
+    for key in ["vendorId", "productId"] {
+        if !parse_id(&vial[key]).is_some_and(|id| (0..=0xFFFF).contains(&id)) {
+            problems.push(format!("{}: missing or not a 16-bit hex id", key));
+        }
+    }
+    let dimension = |key: &str| vial["matrix"][key].as_i64().filter(|v| *v > 0);
+    let (Some(rows), Some(cols)) = (dimension("rows"), dimension("cols")) else {
+        problems.push("matrix: missing rows or cols".to_string());
+        return problems;
+    };
+    let Some(keymap) = vial["layouts"]["keymap"].as_array() else {
+        problems.push("layouts.keymap: missing".to_string());
+        return problems;
+    };
+
+    // Number of choices of every layout option, a plain label is an on/off option
+    let choices: Vec<usize> = vial["layouts"]["labels"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|label| match label {
+            Value::Array(names) => names.len().saturating_sub(1),
+            _ => 2,
+        })
+        .collect();
+    let mut positions = HashSet::new();
+    for (i, row) in keymap.iter().enumerate() {
+        for label in row
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|k| k.as_str())
+        {
+            let legends: Vec<&str> = label.split('\n').collect();
+            let position = legends[0].split_once(',').and_then(|(r, c)| {
+                Some((r.trim().parse::<i64>().ok()?, c.trim().parse::<i64>().ok()?))
+            });
+            let option = legends.get(8).filter(|o| !o.is_empty()).map(|o| {
+                o.split_once(',').and_then(|(option, choice)| {
+                    Some((
+                        option.trim().parse::<usize>().ok()?,
+                        choice.trim().parse::<usize>().ok()?,
+                    ))
+                })
+            });
+            match position {
+                Some((r, c)) if r < rows && c < cols => {
+                    // Keys of other choices of a layout option may share a matrix position
+                    let default_choice = matches!(option, None | Some(Some((_, 0))));
+                    if default_choice && !positions.insert((r, c)) {
+                        problems.push(format!(
+                            "layouts.keymap[{}]: matrix position \"{}\" is used by more than one key",
+                            i, legends[0]
+                        ));
+                    }
+                }
+                Some(_) => problems.push(format!(
+                    "layouts.keymap[{}]: key \"{}\" is out of the {}x{} matrix",
+                    i, legends[0], rows, cols
+                )),
+                None => problems.push(format!(
+                    "layouts.keymap[{}]: key \"{}\" isn't a `row,col` matrix position",
+                    i, legends[0]
+                )),
+            }
+            match option {
+                None => {}
+                Some(Some((option, choice))) if choices.get(option).is_some_and(|n| choice < *n) => {}
+                Some(Some((option, choice))) => problems.push(format!(
+                    "layouts.keymap[{}]: key \"{}\" is in choice {} of layout option {}, which isn't in layouts.labels",
+                    i, legends[0], choice, option
+                )),
+                Some(None) => problems.push(format!(
+                    "layouts.keymap[{}]: key \"{}\" has an invalid layout option \"{}\", it should be `option,choice`",
+                    i,
+                    legends[0],
+                    legends[8]
+                )),
+            }
+        }
+    }
+    problems
+}
+
+/// Add `unlock_keys` to `[host]` of keyboard.toml if Vial is enabled and they aren't set, the first two keys
+/// of the layout are used
+fn add_unlock_keys(keyboard_toml_path: &str, vial: &Value) -> Result<(), Box<dyn Error>> {
+    let rows = vial["layouts"]["keymap"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let (keys, _) = place_keys(&rows);
+    let unlock_keys: Vec<(usize, usize)> = keys.iter().take(2).map(|k| (k.row, k.col)).collect();
+    let display = unlock_keys
+        .iter()
+        .map(|(r, c)| format!("[{}, {}]", r, c))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if ConfigFormat::from_path(keyboard_toml_path) != ConfigFormat::Toml {
+        println!(
+            "ℹ️  Set `unlock_keys = [{}]` in [host] of {} for the Vial lock",
+            display, keyboard_toml_path
+        );
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(keyboard_toml_path)
+        .map_err(|e| format!("Failed to read {}: {}", keyboard_toml_path, e))?;
+    let mut doc: DocumentMut = content.parse()?;
+    if doc.get("host").is_none() {
+        doc["host"] = toml_edit::table();
+    }
+    let host = doc["host"]
+        .as_table_mut()
+        .ok_or_else(|| format!("[host] of {} isn't a table", keyboard_toml_path))?;
+    let vial_enabled = host.get("vial_enabled").and_then(|v| v.as_bool()) != Some(false);
+    if !vial_enabled || host.contains_key("unlock_keys") || unlock_keys.is_empty() {
+        return Ok(());
+    }
+    let mut array = Array::new();
+    for (r, c) in &unlock_keys {
+        array.push(Array::from_iter([*r as i64, *c as i64]));
+    }
+    host.insert("unlock_keys", toml_edit::value(array));
+    fs::write(keyboard_toml_path, doc.to_string())?;
+    println!(
+        "ℹ️  Added `unlock_keys = [{}]` to [host] of {}, hold these keys to unlock Vial",
+        display, keyboard_toml_path
+    );
+    Ok(())
+}